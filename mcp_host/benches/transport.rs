@@ -0,0 +1,45 @@
+// Request/response latency and throughput of each transport in host::transport_bench.
+// Run with `cargo bench -p mcp_host --features testing`. `cargo test` runs a short version
+// of the same workloads without criterion.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mcp_host::host::transport_bench::{concurrent_calls, connect, sequential_calls, BenchTransport};
+
+/// Calls per measured iteration of the throughput benchmarks
+const BATCH: usize = 100;
+
+/// Outstanding calls to measure throughput at
+const IN_FLIGHT: [usize; 3] = [1, 8, 32];
+
+fn round_trip(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    let mut group = c.benchmark_group("round_trip");
+    for transport in BenchTransport::ALL {
+        let client = runtime.block_on(connect(transport)).expect("failed to connect");
+        group.bench_function(transport.name(), |b| {
+            b.to_async(&runtime).iter(|| async { sequential_calls(&client, 1).await.expect("call failed") })
+        });
+        runtime.block_on(client.cancel()).ok();
+    }
+    group.finish();
+}
+
+fn throughput(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("failed to start tokio runtime");
+    let mut group = c.benchmark_group("throughput");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for transport in BenchTransport::ALL {
+        let client = runtime.block_on(connect(transport)).expect("failed to connect");
+        for in_flight in IN_FLIGHT {
+            group.bench_with_input(BenchmarkId::new(transport.name(), in_flight), &in_flight, |b, &in_flight| {
+                b.to_async(&runtime)
+                    .iter(|| async { concurrent_calls(&client, BATCH, in_flight).await.expect("calls failed") })
+            });
+        }
+        runtime.block_on(client.cancel()).ok();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, round_trip, throughput);
+criterion_main!(benches);
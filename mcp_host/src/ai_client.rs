@@ -1,7 +1,9 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
 use rmcp::model::Role;
 // Removed duplicate imports below
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::path::Path;
 // Use the local Role definition from repl/mod.rs
@@ -33,6 +35,9 @@ pub struct GenerationConfig {
     pub presence_penalty: Option<f32>,
 }
 
+/// A response delivered in chunks of text as the model generates it
+pub type ResponseStream = BoxStream<'static, Result<String>>;
+
 /// Builder for constructing AI requests
 #[async_trait] // Ensure async_trait is applied to the trait definition
 pub trait AIRequestBuilder: Send {
@@ -56,6 +61,70 @@ pub trait AIRequestBuilder: Send {
     
     /// Execute the request and get response as a single string
     async fn execute(self: Box<Self>) -> Result<String>;
+
+    /// Execute the request and get the response in chunks as it is generated, so it can be
+    /// shown incrementally. Builders that can't stream yield the whole response as one chunk.
+    async fn execute_streaming(self: Box<Self>) -> Result<ResponseStream> {
+        let response = self.execute().await?;
+        Ok(futures::stream::once(async move { Ok(response) }).boxed())
+    }
+
+    /// A copy of the request built so far, so it can be sent again (e.g. to retry with a
+    /// follow-up). None if this builder can't be copied.
+    fn try_clone(&self) -> Option<Box<dyn AIRequestBuilder>> {
+        None
+    }
+}
+
+/// Follow-up sent when a response that should have been JSON couldn't be parsed
+const JSON_ONLY_INSTRUCTION: &str = "Your previous response could not be parsed as the requested JSON. \
+    Respond again with ONLY the JSON value: no explanations and no markdown code fences.";
+
+/// Parse the first JSON object or array in `response` that deserializes into `T`, skipping
+/// any prose or markdown fences around it
+pub fn parse_json_response<T: DeserializeOwned>(response: &str) -> Result<T> {
+    let mut last_error = None;
+    for (start, _) in response.match_indices(['{', '[']) {
+        let mut values = serde_json::Deserializer::from_str(&response[start..]).into_iter::<Value>();
+        let Some(Ok(value)) = values.next() else { continue };
+        match serde_json::from_value::<T>(value) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => anyhow!("JSON in the response has the wrong shape: {}. Raw: '{}'", e, response),
+        None => anyhow!("No JSON object or array found in the response: '{}'", response),
+    })
+}
+
+/// Typed JSON responses for any request builder
+#[async_trait]
+pub trait AIRequestBuilderExt {
+    /// Execute the request and deserialize the JSON in the response into `T`. If it can't be
+    /// parsed, the request is sent once more with the bad response and an instruction to
+    /// return only JSON.
+    async fn execute_json<T: DeserializeOwned + Send>(self) -> Result<T>;
+}
+
+#[async_trait]
+impl AIRequestBuilderExt for Box<dyn AIRequestBuilder> {
+    async fn execute_json<T: DeserializeOwned + Send>(self) -> Result<T> {
+        let retry = self.try_clone();
+        let response = self.execute().await?;
+        let error = match parse_json_response(&response) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => e,
+        };
+        let Some(retry) = retry else { return Err(error) };
+        log::warn!("Response was not the expected JSON ({}); asking again for JSON only", error);
+        let response = retry
+            .assistant(response)
+            .user(JSON_ONLY_INSTRUCTION.to_string())
+            .execute()
+            .await?;
+        parse_json_response(&response).context("Response was still not the expected JSON after asking for JSON only")
+    }
 }
 
 /// Core trait for AI model implementations
@@ -110,3 +179,64 @@ pub fn format_message_for_basic_model(role: &Role, content: &str) -> String {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_ai_client::FakeAIClient;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Verdict {
+        passes: bool,
+        feedback: Option<String>,
+    }
+
+    fn verdict(passes: bool, feedback: Option<&str>) -> Verdict {
+        Verdict { passes, feedback: feedback.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_json_extracted_from_clean_fenced_and_prose_responses() {
+        let clean: Verdict = parse_json_response(r#"{"passes": true, "feedback": null}"#).unwrap();
+        assert_eq!(clean, verdict(true, None));
+
+        let fenced: Verdict = parse_json_response("```json\n{\"passes\": false, \"feedback\": \"missing tests\"}\n```").unwrap();
+        assert_eq!(fenced, verdict(false, Some("missing tests")));
+
+        // Braces in the prose and a trailing remark don't confuse the extraction
+        let prose: Verdict = parse_json_response(
+            "Here is my verdict on {the response}:\n{\"passes\": true, \"feedback\": \"uses {braces}\"}\nLet me know!",
+        ).unwrap();
+        assert_eq!(prose, verdict(true, Some("uses {braces}")));
+
+        let list: Vec<u32> = parse_json_response("The ids are [1, 2, 3].").unwrap();
+        assert_eq!(list, vec![1, 2, 3]);
+
+        assert!(parse_json_response::<Verdict>("no json here").unwrap_err().to_string().contains("No JSON"));
+        assert!(parse_json_response::<Verdict>(r#"{"ok": 1}"#).unwrap_err().to_string().contains("wrong shape"));
+    }
+
+    #[tokio::test]
+    async fn test_default_streaming_yields_whole_response_once() {
+        let client = FakeAIClient::new().respond("Hello there, how can I help?");
+
+        let stream = client.raw_builder("").user("hi".to_string()).execute_streaming().await.unwrap();
+        let chunks: Vec<String> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(chunks, vec!["Hello there, how can I help?".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_json_retries_once_asking_for_json_only() {
+        let client = FakeAIClient::new().respond("I think it passes.").respond(r#"{"passes": true}"#);
+        let handle = client.handle();
+
+        let parsed: Verdict = client.raw_builder("").user("Does it pass?".to_string()).execute_json().await.unwrap();
+        assert_eq!(parsed, verdict(true, None));
+
+        let requests = handle.requests();
+        assert_eq!(requests.len(), 2);
+        let messages = &requests[1].messages;
+        assert_eq!(messages[..2], ["user: Does it pass?".to_string(), "assistant: I think it passes.".to_string()]);
+        assert!(messages[2].contains("ONLY the JSON"), "{:?}", messages);
+    }
+}
@@ -0,0 +1,258 @@
+// Non-interactive mode: read prompts from a file or pipe, answer each one, print the
+// final responses and exit. Used by `mcp_host --input <file>` and `... | mcp_host`.
+// With `--json` each turn is printed as one JSON object per line instead.
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use rmcp::model::Role;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::ai_client::AIClient;
+use crate::conversation_logic::{generate_verification_criteria, resolve_assistant_response, ConversationConfig, VerificationOutcome};
+use crate::conversation_state::ConversationState;
+use crate::host::MCPHost;
+
+/// Line that separates prompts in batch input
+pub const PROMPT_SEPARATOR: &str = "---";
+
+/// Options for a batch run, taken from the command line
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Server whose tools are offered; None offers tools from every server
+    pub server: Option<String>,
+    /// Provider to use instead of the configured default
+    pub provider: Option<String>,
+    /// Check each final answer against generated criteria
+    pub verify: bool,
+    /// Print one JSON object per turn instead of styled text
+    pub json: bool,
+}
+
+/// A tool call made while answering a prompt, as printed in JSON mode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnToolCall {
+    pub name: String,
+    pub arguments: Value,
+    pub result: Option<String>,
+}
+
+/// Verification details of a turn, as printed in JSON mode
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnVerification {
+    pub criteria: Option<String>,
+    pub passed: Option<bool>,
+    pub feedback: Option<String>,
+}
+
+/// One answered prompt, as printed in JSON mode
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnOutput {
+    pub prompt: String,
+    pub final_response: String,
+    pub tool_calls: Vec<TurnToolCall>,
+    pub verification: Option<TurnVerification>,
+}
+
+/// Split batch input into prompts on lines containing only `---`, dropping empty prompts
+pub fn split_prompts(input: &str) -> Vec<String> {
+    let mut prompts = Vec::new();
+    let mut current = Vec::new();
+    for line in input.lines() {
+        if line.trim() == PROMPT_SEPARATOR {
+            prompts.push(current.join("\n"));
+            current.clear();
+        } else {
+            current.push(line);
+        }
+    }
+    prompts.push(current.join("\n"));
+    prompts.into_iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()
+}
+
+/// Run every prompt in `input` and print the answers to stdout
+pub async fn run_batch(host: &MCPHost, input: &str, options: &BatchOptions) -> Result<()> {
+    let prompts = split_prompts(input);
+    if prompts.is_empty() {
+        return Err(anyhow!("No prompts in input"));
+    }
+
+    if let Some(provider) = &options.provider {
+        host.set_active_provider(provider).await?;
+    }
+    let client = host.ai_client().await
+        .ok_or_else(|| anyhow!("No AI provider is active. Set an API key or pass --provider."))?;
+
+    let mut stdout = std::io::stdout();
+    let outcomes = run_prompts(host, client, &prompts, options, &mut stdout).await?;
+
+    if options.verify && outcomes.iter().any(|o| o.verification_passed == Some(false)) {
+        return Err(anyhow!("One or more responses failed verification"));
+    }
+    Ok(())
+}
+
+/// Answer `prompts` in order as one conversation, writing each final response to `out`.
+/// Later prompts see the earlier questions and answers.
+pub async fn run_prompts(
+    host: &MCPHost,
+    client: Arc<dyn AIClient>,
+    prompts: &[String],
+    options: &BatchOptions,
+    out: &mut dyn Write,
+) -> Result<Vec<VerificationOutcome>> {
+    let (server_context, mut state) = match options.server.as_deref() {
+        Some(name) => (name, host.enter_chat_mode(name).await?),
+        None => ("*all*", host.enter_multi_server_chat_mode().await?),
+    };
+    let config = ConversationConfig::default();
+
+    let mut outcomes = Vec::with_capacity(prompts.len());
+    for (index, prompt) in prompts.iter().enumerate() {
+        info!("Batch prompt {}/{}", index + 1, prompts.len());
+
+        let criteria = if options.verify {
+            generate_verification_criteria(host, prompt).await.unwrap_or_else(|e| {
+                warn!("Failed to generate verification criteria: {}. Proceeding without verification.", e);
+                String::new()
+            })
+        } else {
+            String::new()
+        };
+        let mut user_input = prompt.clone();
+        if !criteria.is_empty() {
+            user_input.push_str(&format!(
+                "\n\n---\n**Note:** Your response will be evaluated against the following criteria:\n{}\n---",
+                criteria
+            ));
+        }
+        state.begin_turn(prompt);
+        state.add_user_message(&user_input);
+
+        let initial_response = initial_response(client.as_ref(), &state)
+            .await
+            .with_context(|| format!("AI request failed for prompt {}", index + 1))?;
+        let outcome = resolve_assistant_response(
+            host,
+            server_context,
+            &mut state,
+            &initial_response,
+            Arc::clone(&client),
+            &config,
+            &criteria,
+        )
+        .await?;
+
+        if options.json {
+            let turn = TurnOutput {
+                prompt: prompt.clone(),
+                final_response: outcome.final_response.clone(),
+                tool_calls: tool_calls_of_last_turn(&state),
+                verification: outcome.criteria.is_some().then(|| TurnVerification {
+                    criteria: outcome.criteria.clone(),
+                    passed: outcome.verification_passed,
+                    feedback: outcome.verification_feedback.clone(),
+                }),
+            };
+            writeln!(out, "{}", serde_json::to_string(&turn)?)?;
+            outcomes.push(outcome);
+            continue;
+        }
+
+        if prompts.len() > 1 {
+            writeln!(out, "## Prompt {}\n", index + 1)?;
+        }
+        writeln!(out, "{}", outcome.final_response.trim())?;
+        if let Some(passed) = outcome.verification_passed {
+            writeln!(out, "\n[verification {}]", if passed { "passed" } else { "failed" })?;
+        }
+        if index + 1 < prompts.len() {
+            writeln!(out)?;
+        }
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+/// Tool calls made in the conversation's latest turn, with their results
+fn tool_calls_of_last_turn(state: &ConversationState) -> Vec<TurnToolCall> {
+    state
+        .tool_calls()
+        .into_iter()
+        .filter(|call| call.turn == state.turns.len())
+        .map(|call| TurnToolCall { name: call.name, arguments: call.arguments, result: call.result })
+        .collect()
+}
+
+/// First AI response to the conversation so far
+async fn initial_response(client: &dyn AIClient, state: &ConversationState) -> Result<String> {
+    let mut builder = client.raw_builder(&state.system_prompt);
+    for msg in state.messages.iter() {
+        match msg.role {
+            Role::User => builder = builder.user(msg.content.clone()),
+            Role::Assistant => builder = builder.assistant(msg.content.clone()),
+        }
+    }
+    builder.execute().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_ai_client::FakeAIClient;
+    use crate::host::test_support::test_host;
+
+    #[test]
+    fn test_split_prompts() {
+        let input = "What is 2+2?\n---\n\nSummarise\nthe answer.\n---\n---\n";
+        assert_eq!(split_prompts(input), vec!["What is 2+2?", "Summarise\nthe answer."]);
+        assert_eq!(split_prompts("single prompt\n"), vec!["single prompt"]);
+        assert!(split_prompts("\n---\n").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_prompts_prints_final_answers() {
+        let host = test_host().await;
+        let client = FakeAIClient::new().respond("Four.").respond("It was four.");
+        let handle = client.handle();
+
+        let prompts = split_prompts("What is 2+2?\n---\nRepeat that.");
+        let mut out = Vec::new();
+        let outcomes = run_prompts(&host, Arc::new(client), &prompts, &BatchOptions::default(), &mut out).await.unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "## Prompt 1\n\nFour.\n\n## Prompt 2\n\nIt was four.\n");
+        let requests = handle.requests();
+        let last_prompts: Vec<&str> = requests.iter().map(|r| *r.user_messages().last().unwrap()).collect();
+        assert_eq!(last_prompts, vec!["What is 2+2?", "Repeat that."]);
+    }
+
+    #[tokio::test]
+    async fn test_json_output_has_expected_fields() {
+        let host = test_host().await;
+        let tool_call = "Let me check.\n<<<TOOL_CALL>>>\n{\"name\": \"missing_tool\", \"arguments\": {\"path\": \"/tmp\"}}\n<<<END_TOOL_CALL>>>";
+        let client = Arc::new(FakeAIClient::new().respond(tool_call).respond("Nothing there.").respond("Still nothing."));
+
+        let prompts = split_prompts("Look in /tmp\n---\nAnd again?");
+        let options = BatchOptions { json: true, ..Default::default() };
+        let mut out = Vec::new();
+        run_prompts(&host, client, &prompts, &options, &mut out).await.unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["prompt"], "Look in /tmp");
+        assert_eq!(lines[0]["final_response"], "Nothing there.");
+        assert_eq!(lines[0]["tool_calls"][0]["name"], "missing_tool");
+        assert_eq!(lines[0]["tool_calls"][0]["arguments"]["path"], "/tmp");
+        assert!(lines[0]["tool_calls"][0]["result"].as_str().unwrap().contains("not found"));
+        assert!(lines[0]["verification"].is_null());
+        assert_eq!(lines[1]["final_response"], "Still nothing.");
+        assert_eq!(lines[1]["tool_calls"], serde_json::json!([]));
+    }
+}
@@ -112,12 +112,16 @@ async fn main() -> Result<()> {
 
     // Apply initial config to start servers defined in mcp_host_config.json
     let initial_host_config = { host.config.lock().await.clone() };
-    if let Err(e) = host.apply_config(initial_host_config).await {
+    let startup = host.apply_config(initial_host_config).await;
+    if let Err(e) = &startup {
          error!("Failed to apply initial server configuration: {}. Tool servers might not be running.", e);
          // Decide whether to continue or exit
          // return Err(e.into());
     } else {
          info!("Applied initial server configuration.");
+         for (name, e) in startup.iter().flat_map(|report| &report.failed) {
+             error!("Tool server '{}' failed to start: {}", name, e);
+         }
          // Add log to check server count after applying config
          let server_count = host.servers.lock().await.len();
          info!("MCPHost has {} servers configured after applying initial config.", server_count);
@@ -538,6 +542,7 @@ async fn execute_task_simulation(
                 criteria: Some(criteria_for_verification),
                 verification_passed: None,
                 verification_feedback: Some(format!("Error: {}", e)),
+                interrupted: false,
             };
             // Return the error outcome and the state *as it was* when the error occurred
             Ok((error_outcome, state.messages))
@@ -0,0 +1,257 @@
+// Append-only journal of a conversation. Each sync writes only what changed since the last
+// one (new messages, an undone turn, updated metadata), so a long session isn't rewritten in
+// full after every turn and survives the process dying. The journal is periodically
+// compacted into a single snapshot line.
+
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::conversation_state::{ConversationMetadata, ConversationState, Message, TurnStart};
+
+/// One line of a journal
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Entry {
+    /// The whole state; replaces everything before it
+    Snapshot { state: Box<ConversationState> },
+    /// Drop the messages and turns past these counts (an undo or retry)
+    Truncate { messages: usize, turns: usize },
+    Message { message: Message },
+    Turn { turn: TurnStart },
+    Metadata { metadata: ConversationMetadata },
+}
+
+/// Writes a conversation's changes to a `.jsonl` journal as it grows
+#[derive(Debug)]
+pub struct ConversationJournal {
+    path: PathBuf,
+    /// The state as of the last sync, to work out what changed since
+    synced: ConversationState,
+    /// Entries appended since the last snapshot
+    entries: usize,
+    /// Rewrite the journal as a snapshot once this many entries have been appended
+    compact_every: usize,
+}
+
+impl ConversationJournal {
+    /// Start a journal at `path` holding a snapshot of `state`, replacing any existing file
+    pub fn create(path: &Path, state: &ConversationState, compact_every: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create conversation directory {:?}", parent))?;
+        }
+        let mut journal = Self {
+            path: path.to_path_buf(),
+            synced: state.clone(),
+            entries: 0,
+            compact_every: compact_every.max(1),
+        };
+        journal.compact(state)?;
+        Ok(journal)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether `state` is the conversation this journal records (as opposed to one
+    /// started or loaded since)
+    pub fn records(&self, state: &ConversationState) -> bool {
+        self.synced.metadata.created_at == state.metadata.created_at
+    }
+
+    /// Append whatever changed in `state` since the last sync. A changed system prompt, tool
+    /// list or server context is written as a fresh snapshot.
+    pub fn sync(&mut self, state: &ConversationState) -> Result<()> {
+        if state.system_prompt != self.synced.system_prompt
+            || state.tools != self.synced.tools
+            || state.server_context != self.synced.server_context
+        {
+            return self.compact(state);
+        }
+
+        let messages = common_prefix(&self.synced.messages, &state.messages);
+        let turns = common_prefix(&self.synced.turns, &state.turns);
+        let mut entries = Vec::new();
+        if messages < self.synced.messages.len() || turns < self.synced.turns.len() {
+            entries.push(Entry::Truncate { messages, turns });
+        }
+        entries.extend(state.messages[messages..].iter().map(|message| Entry::Message { message: message.clone() }));
+        entries.extend(state.turns[turns..].iter().map(|turn| Entry::Turn { turn: turn.clone() }));
+        if state.metadata != self.synced.metadata {
+            entries.push(Entry::Metadata { metadata: state.metadata.clone() });
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        if self.entries + entries.len() >= self.compact_every {
+            return self.compact(state);
+        }
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open conversation journal {:?}", self.path))?;
+        write_entries(&mut file, &entries)?;
+        debug!("Appended {} entries to conversation journal {:?}", entries.len(), self.path);
+        self.entries += entries.len();
+        self.synced = state.clone();
+        Ok(())
+    }
+
+    /// Rewrite the journal as a single snapshot of `state`
+    pub fn compact(&mut self, state: &ConversationState) -> Result<()> {
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to write conversation journal {:?}", tmp_path))?;
+        write_entries(&mut file, &[Entry::Snapshot { state: Box::new(state.clone()) }])?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace conversation journal {:?}", self.path))?;
+        debug!("Compacted conversation journal {:?} ({} messages)", self.path, state.messages.len());
+        self.entries = 0;
+        self.synced = state.clone();
+        Ok(())
+    }
+}
+
+/// Write `entries` one per line and flush them to disk
+fn write_entries(file: &mut File, entries: &[Entry]) -> Result<()> {
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry).context("Failed to serialize journal entry")?);
+        lines.push('\n');
+    }
+    file.write_all(lines.as_bytes())?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// How many leading items `old` and `new` have in common
+fn common_prefix<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+    old.iter().zip(new).take_while(|(a, b)| a == b).count()
+}
+
+/// Rebuild a conversation from its journal. An incomplete last line (the process died
+/// while writing it) is skipped.
+pub fn replay(path: &Path) -> Result<ConversationState> {
+    let file = File::open(path).with_context(|| format!("Failed to read conversation journal {:?}", path))?;
+    let lines = BufReader::new(file).lines().collect::<std::io::Result<Vec<_>>>()?;
+
+    let mut state: Option<ConversationState> = None;
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(e) if i + 1 == lines.len() => {
+                warn!("Skipping incomplete last line of conversation journal {:?}: {}", path, e);
+                break;
+            }
+            Err(e) => return Err(e).with_context(|| format!("Invalid entry on line {} of {:?}", i + 1, path)),
+        };
+        if let Entry::Snapshot { state: snapshot } = entry {
+            state = Some(*snapshot);
+            continue;
+        }
+        let state = state.as_mut().ok_or_else(|| anyhow!("Conversation journal {:?} does not start with a snapshot", path))?;
+        match entry {
+            Entry::Snapshot { .. } => unreachable!("handled above"),
+            Entry::Truncate { messages, turns } => {
+                state.messages.truncate(messages);
+                state.turns.truncate(turns);
+            }
+            Entry::Message { message } => state.messages.push(message),
+            Entry::Turn { turn } => state.turns.push(turn),
+            Entry::Metadata { metadata } => state.metadata = metadata,
+        }
+    }
+    state.ok_or_else(|| anyhow!("Conversation journal {:?} is empty", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal_path() -> PathBuf {
+        std::env::temp_dir().join(format!("mcp_journal_test_{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    fn as_json(state: &ConversationState) -> serde_json::Value {
+        serde_json::to_value(state).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_journal_matches_snapshot() {
+        let path = journal_path();
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: bash");
+        let mut journal = ConversationJournal::create(&path, &state, 100).unwrap();
+
+        state.begin_turn("list files");
+        state.add_user_message("list files");
+        state.add_assistant_message("a.txt b.txt");
+        journal.sync(&state).unwrap();
+        state.begin_turn("delete them");
+        state.add_user_message("delete them");
+        state.add_assistant_message("Deleted.");
+        journal.sync(&state).unwrap();
+        // Undo the last turn and take it in another direction
+        state.undo_last_turn();
+        state.begin_turn("show a.txt");
+        state.add_user_message("show a.txt");
+        state.add_assistant_message("hello");
+        state.add_tag("files");
+        journal.sync(&state).unwrap();
+        journal.sync(&state).unwrap();
+
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines > 1, "changes should be appended, not rewritten");
+
+        let snapshot_path = path.with_extension("json");
+        state.save_to_json(&snapshot_path).await.unwrap();
+        let from_snapshot = ConversationState::load_from_json(&snapshot_path).await.unwrap();
+        let from_journal = ConversationState::load_from_json(&path).await.unwrap();
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(&snapshot_path).ok();
+
+        assert_eq!(as_json(&from_journal), as_json(&from_snapshot));
+        // The tool instructions, the first turn and its replacement; the undone turn is gone
+        let contents: Vec<&str> = from_journal.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec![
+            "Okay, I have access to the following tools: bash",
+            "list files",
+            "a.txt b.txt",
+            "show a.txt",
+            "hello",
+        ]);
+    }
+
+    #[test]
+    fn test_journal_compacts_and_survives_a_torn_write() {
+        let path = journal_path();
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        let mut journal = ConversationJournal::create(&path, &state, 6).unwrap();
+        for i in 0..3 {
+            state.begin_turn(&format!("question {}", i));
+            state.add_user_message(&format!("question {}", i));
+            state.add_assistant_message(&format!("answer {}", i));
+            journal.sync(&state).unwrap();
+        }
+        // Without compaction this would be a snapshot plus a dozen entries
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines <= 5, "journal was not compacted: {} lines", lines);
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with(r#"{"op":"snapshot""#));
+
+        // A line cut short by a crash is ignored
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"op":"message","message":{"role":"user","con"#).unwrap();
+        let replayed = replay(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(as_json(&replayed), as_json(&state));
+    }
+}
@@ -11,10 +11,15 @@
 use rmcp::model::Tool as RmcpTool;
 
 
-/// Generate a system prompt instructing the AI about tool usage with text delimiters
+/// Generate a system prompt instructing the AI about tool usage with text delimiters.
+/// Tools are listed by name, so the same tools always give a byte-identical prompt
+/// (which keeps provider prompt caching effective and evals reproducible).
 pub fn generate_tool_system_prompt(tools: &[RmcpTool]) -> String { // Use aliased rmcp Tool
+    let mut sorted: Vec<&RmcpTool> = tools.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
     // Format tools information
-    let tools_info = tools.iter()
+    let tools_info = sorted.iter()
         .map(|t| format!(
             "- Name: {}\n  Description: {}\n  Schema: {}",
             t.name.as_ref(),
@@ -55,3 +60,30 @@ pub fn generate_tool_system_prompt(tools: &[RmcpTool]) -> String { // Use aliase
         tools_info // Insert the formatted tool descriptions here
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn tool(name: &str) -> RmcpTool {
+        let schema = serde_json::json!({ "type": "object", "properties": { "path": { "type": "string" }, "all": { "type": "boolean" } } });
+        RmcpTool {
+            name: name.to_string().into(),
+            description: format!("The {} tool", name).into(),
+            input_schema: Arc::new(schema.as_object().unwrap().clone()),
+        }
+    }
+
+    #[test]
+    fn test_tool_order_does_not_change_prompt() {
+        let forward = generate_tool_system_prompt(&[tool("bash"), tool("brave_search"), tool("aider")]);
+        let shuffled = generate_tool_system_prompt(&[tool("aider"), tool("bash"), tool("brave_search")]);
+        assert_eq!(forward.as_bytes(), shuffled.as_bytes());
+        assert_eq!(forward, generate_tool_system_prompt(&[tool("brave_search"), tool("aider"), tool("bash")]));
+
+        let aider = forward.find("- Name: aider").unwrap();
+        let bash = forward.find("- Name: bash").unwrap();
+        assert!(aider < bash);
+    }
+}
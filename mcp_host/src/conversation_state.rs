@@ -6,6 +6,7 @@ use console::style;
 use serde::{Deserialize, Serialize}; // Import Serialize and Deserialize
 use serde_json;
 use anyhow::{Context, Result}; // Import Result and Context
+use chrono::{DateTime, Utc};
 use std::path::Path; // Import Path
 use tokio::fs; // Import tokio::fs
 
@@ -128,18 +129,82 @@ pub fn format_assistant_response_with_tool_calls(raw_response: &str) -> String {
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)] // Add Serialize, Deserialize
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)] // Add Serialize, Deserialize
 pub struct Message {
     pub role: Role,
     pub content: String,
 }
 
+/// Where a turn the user started begins in `ConversationState::messages`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnStart {
+    pub index: usize,
+    /// What the user typed, without anything added for the model (e.g. verification criteria)
+    pub input: String,
+}
+
+/// A tool call the assistant made, with the result recorded for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallRecord {
+    /// The user turn it was made in, counting from 1; 0 for calls before the first turn
+    pub turn: usize,
+    pub name: String,
+    pub arguments: serde_json::Value,
+    /// None if no result was recorded, e.g. the call was interrupted
+    pub result: Option<String>,
+}
+
+/// Longest title taken from a conversation's first message
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Descriptive details for finding a conversation again among the saved ones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationMetadata {
+    /// Taken from the first user turn unless set explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// None for conversations saved before metadata was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Provider and model that answered the latest turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// A short title from the first line of `input`, cut at a word boundary if it is long
+fn title_from(input: &str) -> Option<String> {
+    let line = input.lines().map(str::trim).find(|line| !line.is_empty())?;
+    if line.chars().count() <= MAX_TITLE_CHARS {
+        return Some(line.to_string());
+    }
+    let cut: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut.as_str(),
+    };
+    Some(format!("{}...", cut.trim_end()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)] // Add Serialize, Deserialize
 pub struct ConversationState {
     pub messages: Vec<Message>,
     pub system_prompt: String,
     // Use rmcp::model::Tool here
     pub tools: Vec<RmcpTool>, // Use aliased rmcp Tool
+    /// Start of each user turn, so a turn can be retried or undone as a whole
+    #[serde(default)]
+    pub turns: Vec<TurnStart>,
+    /// The server whose tools this conversation uses; None means all servers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_context: Option<String>,
+    #[serde(default)]
+    pub metadata: ConversationMetadata,
 }
 
 impl ConversationState {
@@ -149,6 +214,13 @@ impl ConversationState {
             system_prompt: system_prompt.clone(),
             messages: Vec::new(),
             tools: tools.clone(), // Store the tools
+            turns: Vec::new(),
+            server_context: None,
+            metadata: ConversationMetadata {
+                created_at: Some(Utc::now()),
+                updated_at: Some(Utc::now()),
+                ..Default::default()
+            },
         };
         // The system prompt is stored but not added as a message here.
         // The REPL will add the initial tool list as a user message.
@@ -173,9 +245,14 @@ impl ConversationState {
         Ok(())
     }
 
-    /// Loads conversation state from a JSON file.
+    /// Loads conversation state from a JSON file. A conversation journal (`.jsonl`) is
+    /// replayed into the state it records.
     pub async fn load_from_json(path: &Path) -> Result<Self> {
         log::info!("Loading conversation state from: {:?}", path);
+        if path.extension().is_some_and(|ext| ext == "jsonl") {
+            let path = path.to_path_buf();
+            return tokio::task::spawn_blocking(move || crate::conversation_journal::replay(&path)).await?;
+        }
         let json_string = fs::read_to_string(path).await
             .with_context(|| format!("Failed to read conversation file {:?}", path))?;
         let state: Self = serde_json::from_str(&json_string)
@@ -189,6 +266,7 @@ impl ConversationState {
             role: Role::User, // Already correct
             content: content.to_string(),
         });
+        self.metadata.updated_at = Some(Utc::now());
     }
 
     pub fn add_assistant_message(&mut self, content: &str) {
@@ -196,6 +274,76 @@ impl ConversationState {
             role: Role::Assistant, // Already correct
             content: content.to_string(),
         });
+        self.metadata.updated_at = Some(Utc::now());
+    }
+
+    /// Add a tag for organizing saved conversations. Returns false if it was already there.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+        if tag.is_empty() || self.metadata.tags.iter().any(|t| t == tag) {
+            return false;
+        }
+        self.metadata.tags.push(tag.to_string());
+        true
+    }
+
+    /// Record the provider and model answering the conversation
+    pub fn set_model(&mut self, provider: Option<String>, model: &str) {
+        self.metadata.provider = provider;
+        self.metadata.model = Some(model.to_string());
+    }
+
+    /// Rough token count of the system prompt and messages (about 4 characters per token)
+    pub fn estimated_tokens(&self) -> usize {
+        let chars = self.system_prompt.len() + self.messages.iter().map(|m| m.content.len()).sum::<usize>();
+        chars.div_ceil(4)
+    }
+
+    /// Server context to resume this conversation in: its server name, or "*all*"
+    pub fn server_context(&self) -> &str {
+        self.server_context.as_deref().unwrap_or("*all*")
+    }
+
+    /// Mark the start of a turn typed by the user. Call just before adding its user message.
+    /// The first turn also names the conversation if it has no title yet.
+    pub fn begin_turn(&mut self, input: &str) {
+        if self.metadata.title.is_none() {
+            self.metadata.title = title_from(input);
+        }
+        self.turns.push(TurnStart { index: self.messages.len(), input: input.to_string() });
+    }
+
+    /// Remove the last user turn - its user message and every response, tool result and
+    /// correction request after it. Returns what the user typed, or None if there is no turn.
+    pub fn undo_last_turn(&mut self) -> Option<String> {
+        while let Some(turn) = self.turns.pop() {
+            // Skip turns whose messages are already gone (e.g. an interrupted request)
+            if turn.index < self.messages.len() {
+                self.messages.truncate(turn.index);
+                return Some(turn.input);
+            }
+        }
+        None
+    }
+
+    /// Drop every turn, keeping the system prompt, tools and server context along with the
+    /// user messages set up before the first turn (the tool instructions). Returns how many
+    /// messages were removed. Conversations saved before turns were recorded keep only their
+    /// first message, which is where the chat puts the tool instructions.
+    pub fn clear(&mut self) -> usize {
+        let first_turn = self.turns.first().map_or(1, |turn| turn.index).min(self.messages.len());
+        let keep = self.messages[..first_turn].iter().take_while(|m| matches!(m.role, Role::User)).count();
+        let removed = self.messages.len() - keep;
+        self.messages.truncate(keep);
+        self.turns.clear();
+        self.metadata.title = None;
+        self.metadata.updated_at = Some(Utc::now());
+        removed
+    }
+
+    /// Replace the system prompt used for the rest of the conversation
+    pub fn set_system_prompt(&mut self, prompt: &str) {
+        self.system_prompt = prompt.to_string();
     }
 
     /// Get the stored system prompt string.
@@ -206,4 +354,144 @@ impl ConversationState {
             Some(&self.system_prompt)
         }
     }
+
+    /// Every tool call in the conversation in the order made, read from the assistant's
+    /// `<<<TOOL_CALL>>>` blocks and matched with the `Tool '<name>' returned: ...` messages
+    /// that follow them
+    pub fn tool_calls(&self) -> Vec<ToolCallRecord> {
+        let mut calls: Vec<ToolCallRecord> = Vec::new();
+        // Calls from the latest response still waiting for their results
+        let mut pending: Vec<usize> = Vec::new();
+        for (index, message) in self.messages.iter().enumerate() {
+            if message.role != Role::Assistant {
+                continue;
+            }
+            let result = message.content.strip_prefix("Tool '").and_then(|rest| rest.split_once("' returned: "));
+            if let Some((name, result)) = result {
+                if let Some(position) = pending.iter().position(|&call| calls[call].name == name) {
+                    calls[pending.remove(position)].result = Some(result.to_string());
+                }
+                continue;
+            }
+            let (parsed, _) = crate::tool_parser::ToolParser::parse_tool_calls(&message.content);
+            if parsed.is_empty() {
+                continue;
+            }
+            let turn = self.turns.iter().filter(|turn| turn.index <= index).count();
+            pending = (calls.len()..calls.len() + parsed.len()).collect();
+            calls.extend(parsed.into_iter().map(|call| ToolCallRecord {
+                turn,
+                name: call.name,
+                arguments: call.arguments,
+                result: None,
+            }));
+        }
+        calls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_removes_whole_tool_turn() {
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: bash");
+        state.begin_turn("list files");
+        state.add_user_message("list files");
+        state.add_assistant_message("Done, there are none.");
+        let before_turn = state.messages.len();
+
+        state.begin_turn("show the readme");
+        state.add_user_message("show the readme\n\n---\n**Note:** criteria");
+        state.add_assistant_message("<<<TOOL_CALL>>>{\"name\": \"bash\"}<<<END_TOOL_CALL>>>");
+        state.add_assistant_message("Tool 'bash' returned: # Readme");
+        state.add_user_message("Correction Request: ...");
+        state.add_assistant_message("Here is the readme.");
+
+        assert_eq!(state.undo_last_turn().as_deref(), Some("show the readme"));
+        assert_eq!(state.messages.len(), before_turn);
+        assert_eq!(state.undo_last_turn().as_deref(), Some("list files"));
+        // The tool list the chat started with is not a turn
+        assert_eq!(state.messages.len(), 1);
+        assert_eq!(state.undo_last_turn(), None);
+        assert_eq!(state.messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_single_server_context_survives_save_and_load() {
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.server_context = Some("filesystem".to_string());
+        state.begin_turn("list files");
+        state.add_user_message("list files");
+
+        let path = std::env::temp_dir().join(format!("mcp_conversation_test_{}.json", uuid::Uuid::new_v4()));
+        state.save_to_json(&path).await.unwrap();
+        let loaded = ConversationState::load_from_json(&path).await.unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.server_context(), "filesystem");
+        assert_eq!(loaded.turns, state.turns);
+        assert_eq!(ConversationState::new(String::new(), Vec::new()).server_context(), "*all*");
+    }
+
+    #[tokio::test]
+    async fn test_metadata_survives_save_and_load() {
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: bash");
+        state.begin_turn("Summarize the open issues\nand group them by label");
+        state.add_user_message("Summarize the open issues");
+        state.set_model(Some("anthropic".to_string()), "claude-3-5-sonnet");
+        assert!(state.add_tag("triage"));
+        assert!(state.add_tag("work"));
+        assert!(!state.add_tag("triage"));
+        // Later turns don't rename the conversation
+        state.begin_turn("thanks");
+
+        let path = std::env::temp_dir().join(format!("mcp_conversation_test_{}.json", uuid::Uuid::new_v4()));
+        state.save_to_json(&path).await.unwrap();
+        let loaded = ConversationState::load_from_json(&path).await.unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.metadata, state.metadata);
+        assert_eq!(loaded.metadata.title.as_deref(), Some("Summarize the open issues"));
+        assert_eq!(loaded.metadata.tags, vec!["triage", "work"]);
+        assert_eq!(loaded.metadata.model.as_deref(), Some("claude-3-5-sonnet"));
+        assert!(loaded.metadata.created_at.unwrap() <= loaded.metadata.updated_at.unwrap());
+    }
+
+    #[test]
+    fn test_clear_without_recorded_turns_keeps_only_tool_instructions() {
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: bash");
+        state.add_user_message("What's in this directory?");
+        state.add_assistant_message("A Cargo project.");
+        assert!(state.turns.is_empty());
+
+        assert_eq!(state.clear(), 2);
+        assert_eq!(state.messages.len(), 1);
+        assert_eq!(state.messages[0].content, "Okay, I have access to the following tools: bash");
+
+        // Nothing to keep in an empty conversation
+        let mut empty = ConversationState::new("system".to_string(), Vec::new());
+        assert_eq!(empty.clear(), 0);
+    }
+
+    #[test]
+    fn test_long_titles_are_cut_at_a_word() {
+        let title = title_from("  \nPlease look through every file in the repository and list the ones that have no tests").unwrap();
+        assert_eq!(title, "Please look through every file in the repository and list...");
+        assert_eq!(title_from(" \n "), None);
+    }
+
+    #[test]
+    fn test_turns_survive_save_format() {
+        // Conversations saved before turns were tracked still load
+        let json = r#"{"messages": [], "system_prompt": "s", "tools": []}"#;
+        let state: ConversationState = serde_json::from_str(json).unwrap();
+        assert!(state.turns.is_empty());
+        assert_eq!(state.server_context(), "*all*");
+        assert_eq!(state.metadata, ConversationMetadata::default());
+    }
 }
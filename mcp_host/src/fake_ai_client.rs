@@ -0,0 +1,218 @@
+// Scripted in-memory AI provider for testing code that talks to a model.
+// Stands in for a real client: answers requests from canned replies and records every
+// request it was sent. Enabled with the `testing` feature.
+
+use crate::ai_client::{AIClient, AIRequestBuilder, GenerationConfig, ModelCapabilities};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Text(String),
+    Error(String),
+    /// Never answer, like a generation that has gone off the rails
+    Hang,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    replies: VecDeque<Reply>,
+    /// Used once `replies` runs out; without one the request fails
+    fallback: Option<Reply>,
+}
+
+/// A request as the fake provider received it
+#[derive(Debug, Clone, Default)]
+pub struct FakeRequest {
+    pub system_prompt: String,
+    /// Messages in order, as `role: content`
+    pub messages: Vec<String>,
+    pub config: Option<GenerationConfig>,
+}
+
+impl FakeRequest {
+    /// Contents of the user messages, in order
+    pub fn user_messages(&self) -> Vec<&str> {
+        self.messages.iter().filter_map(|m| m.strip_prefix("user: ")).collect()
+    }
+}
+
+/// A fake model for `MCPHost::set_ai_client` and anything else taking an `AIClient`.
+///
+/// ```ignore
+/// let client = FakeAIClient::new().respond("Four.").respond_always("I don't know.");
+/// let handle = client.handle();
+/// host.set_ai_client("fake", Arc::new(client)).await;
+/// // ...
+/// assert_eq!(handle.requests()[0].user_messages(), vec!["What is 2+2?"]);
+/// ```
+///
+/// Scripted replies are used in order, then the fallback; with neither left a request fails.
+pub struct FakeAIClient {
+    model: String,
+    capabilities: ModelCapabilities,
+    script: Arc<Mutex<Script>>,
+    handle: FakeAIHandle,
+}
+
+/// Read access to what a `FakeAIClient` was sent, kept after the client is handed over
+#[derive(Debug, Clone, Default)]
+pub struct FakeAIHandle {
+    requests: Arc<Mutex<Vec<FakeRequest>>>,
+}
+
+impl FakeAIHandle {
+    /// Every request executed so far, in order
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Default for FakeAIClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeAIClient {
+    pub fn new() -> Self {
+        Self {
+            model: "fake".to_string(),
+            capabilities: ModelCapabilities::default(),
+            script: Arc::new(Mutex::new(Script::default())),
+            handle: FakeAIHandle::default(),
+        }
+    }
+
+    /// Report `model` as the model name
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Report `capabilities` instead of the defaults
+    pub fn capabilities(mut self, capabilities: ModelCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Answer the next unanswered request with `text`
+    pub fn respond(self, text: impl Into<String>) -> Self {
+        self.push(Reply::Text(text.into()))
+    }
+
+    /// Fail the next unanswered request with `message`
+    pub fn fail(self, message: impl Into<String>) -> Self {
+        self.push(Reply::Error(message.into()))
+    }
+
+    /// Answer with `text` once the scripted replies run out
+    pub fn respond_always(self, text: impl Into<String>) -> Self {
+        self.script.lock().unwrap().fallback = Some(Reply::Text(text.into()));
+        self
+    }
+
+    /// Never answer once the scripted replies run out
+    pub fn hang(self) -> Self {
+        self.script.lock().unwrap().fallback = Some(Reply::Hang);
+        self
+    }
+
+    pub fn handle(&self) -> FakeAIHandle {
+        self.handle.clone()
+    }
+
+    fn push(self, reply: Reply) -> Self {
+        self.script.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    fn new_builder(&self, system_prompt: &str) -> FakeRequestBuilder {
+        FakeRequestBuilder {
+            script: Arc::clone(&self.script),
+            handle: self.handle.clone(),
+            request: FakeRequest { system_prompt: system_prompt.to_string(), ..Default::default() },
+        }
+    }
+}
+
+impl AIClient for FakeAIClient {
+    fn builder(&self, system_prompt: &str) -> Box<dyn AIRequestBuilder> {
+        Box::new(self.new_builder(system_prompt))
+    }
+
+    fn raw_builder(&self, system_prompt: &str) -> Box<dyn AIRequestBuilder> {
+        Box::new(self.new_builder(system_prompt))
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.capabilities.clone()
+    }
+}
+
+#[derive(Clone)]
+struct FakeRequestBuilder {
+    script: Arc<Mutex<Script>>,
+    handle: FakeAIHandle,
+    request: FakeRequest,
+}
+
+impl FakeRequestBuilder {
+    fn message(mut self: Box<Self>, role: &str, content: &str) -> Box<Self> {
+        self.request.messages.push(format!("{}: {}", role, content));
+        self
+    }
+}
+
+#[async_trait]
+impl AIRequestBuilder for FakeRequestBuilder {
+    fn system(self: Box<Self>, content: String) -> Box<dyn AIRequestBuilder> {
+        self.message("system", &content)
+    }
+
+    fn user(self: Box<Self>, content: String) -> Box<dyn AIRequestBuilder> {
+        self.message("user", &content)
+    }
+
+    fn user_with_image(self: Box<Self>, text: String, image_path: &Path) -> Result<Box<dyn AIRequestBuilder>> {
+        Ok(self.message("user", &format!("{} [image: {}]", text, image_path.display())))
+    }
+
+    fn user_with_image_url(self: Box<Self>, text: String, image_url: String) -> Box<dyn AIRequestBuilder> {
+        self.message("user", &format!("{} [image: {}]", text, image_url))
+    }
+
+    fn assistant(self: Box<Self>, content: String) -> Box<dyn AIRequestBuilder> {
+        self.message("assistant", &content)
+    }
+
+    fn config(mut self: Box<Self>, config: GenerationConfig) -> Box<dyn AIRequestBuilder> {
+        self.request.config = Some(config);
+        self
+    }
+
+    async fn execute(self: Box<Self>) -> Result<String> {
+        self.handle.requests.lock().unwrap().push(self.request.clone());
+        let reply = {
+            let mut script = self.script.lock().unwrap();
+            script.replies.pop_front().or_else(|| script.fallback.clone())
+        };
+        match reply {
+            Some(Reply::Text(text)) => Ok(text),
+            Some(Reply::Error(message)) => Err(anyhow!(message)),
+            Some(Reply::Hang) => std::future::pending().await,
+            None => Err(anyhow!("no scripted reply left")),
+        }
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn AIRequestBuilder>> {
+        Some(Box::new(self.clone()))
+    }
+}
@@ -0,0 +1,149 @@
+// Tool annotations (readOnlyHint, destructiveHint, ...) reported by servers in tools/list.
+// rmcp's Tool type drops unknown fields, so the transports hand raw tools/list responses
+// to a ToolAnnotationStore before they are parsed.
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Behaviour hints a server attaches to a tool. All hints are advisory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may perform destructive updates (only meaningful when not read-only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Calling the tool again with the same arguments has no additional effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool interacts with external entities (e.g. the web)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+    /// Annotations this version doesn't know about
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl ToolAnnotations {
+    /// Typed annotations of a tool as it appears in a tools/list result, if it has any
+    pub fn from_tool(tool: &Value) -> Option<Self> {
+        let annotations = tool.get("annotations")?;
+        match serde_json::from_value(annotations.clone()) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                debug!("Ignoring malformed annotations on tool {}: {}", tool["name"], e);
+                None
+            }
+        }
+    }
+}
+
+/// Annotations seen in each server's tools/list responses, keyed by server then tool name
+#[derive(Debug, Clone, Default)]
+pub struct ToolAnnotationStore {
+    by_server: Arc<Mutex<HashMap<String, HashMap<String, ToolAnnotations>>>>,
+}
+
+impl ToolAnnotationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, server: &str, tool: &str) -> Option<ToolAnnotations> {
+        self.by_server.lock().unwrap().get(server)?.get(tool).cloned()
+    }
+
+    /// Look at a raw incoming message and record annotations if it is a tools/list result
+    pub fn observe(&self, server: &str, raw: &[u8]) {
+        // Cheap pre-check so ordinary messages aren't parsed twice
+        if !contains(raw, b"\"tools\"") {
+            return;
+        }
+        let Ok(message) = serde_json::from_slice::<Value>(raw) else { return };
+        let Some(tools) = message.pointer("/result/tools").and_then(Value::as_array) else { return };
+
+        let annotations: HashMap<String, ToolAnnotations> = tools
+            .iter()
+            .filter_map(|tool| Some((tool["name"].as_str()?.to_string(), ToolAnnotations::from_tool(tool)?)))
+            .collect();
+        debug!("Recorded annotations for {} of {} tools on server '{}'", annotations.len(), tools.len(), server);
+
+        // Merge rather than replace, since results may arrive a page at a time
+        self.by_server.lock().unwrap().entry(server.to_string()).or_default().extend(annotations);
+    }
+
+    /// Forget a server's tools, e.g. when it is stopped
+    pub fn remove_server(&self, server: &str) {
+        self.by_server.lock().unwrap().remove(server);
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_known_and_unknown_annotations() {
+        let tool = json!({
+            "name": "delete_file",
+            "description": "Delete a file",
+            "inputSchema": { "type": "object" },
+            "annotations": {
+                "title": "Delete file",
+                "readOnlyHint": false,
+                "destructiveHint": true,
+                "idempotentHint": true,
+                "x-vendor": { "cost": 3 }
+            }
+        });
+        let annotations = ToolAnnotations::from_tool(&tool).expect("annotations present");
+        assert_eq!(annotations.title.as_deref(), Some("Delete file"));
+        assert_eq!(annotations.read_only_hint, Some(false));
+        assert_eq!(annotations.destructive_hint, Some(true));
+        assert_eq!(annotations.idempotent_hint, Some(true));
+        assert_eq!(annotations.open_world_hint, None);
+        assert_eq!(annotations.extra["x-vendor"], json!({ "cost": 3 }));
+    }
+
+    #[test]
+    fn test_tool_without_annotations() {
+        let tool = json!({ "name": "echo", "description": "Echo", "inputSchema": { "type": "object" } });
+        assert_eq!(ToolAnnotations::from_tool(&tool), None);
+        // Malformed hints are ignored rather than failing the whole list
+        let tool = json!({ "name": "echo", "annotations": { "readOnlyHint": "yes" } });
+        assert_eq!(ToolAnnotations::from_tool(&tool), None);
+    }
+
+    #[test]
+    fn test_store_records_tools_list_results() {
+        let store = ToolAnnotationStore::new();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "result": { "tools": [
+                { "name": "read", "inputSchema": {}, "annotations": { "readOnlyHint": true } },
+                { "name": "plain", "inputSchema": {} }
+            ]}
+        });
+        store.observe("files", response.to_string().as_bytes());
+        assert_eq!(store.get("files", "read").unwrap().read_only_hint, Some(true));
+        assert!(store.get("files", "plain").is_none());
+        assert!(store.get("other", "read").is_none());
+
+        // Unrelated messages leave the store alone
+        store.observe("files", br#"{"jsonrpc":"2.0","id":4,"result":{}}"#);
+        assert!(store.get("files", "read").is_some());
+    }
+}
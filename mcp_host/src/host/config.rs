@@ -1,18 +1,45 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path; // Removed PathBuf
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs;
 use anyhow::Result;
 use crate::host::anyhow;
 use log::{debug, info, warn}; // Added log imports
 
-#[derive(Debug, Deserialize, Serialize, Clone)] // Add Clone
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)] // Add Clone
 pub struct ServerConfig {
+    #[serde(default)]
     pub command: String,
+    /// SSE endpoint of a remote server; used instead of `command` when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Extra HTTP headers sent to a remote server
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Environment variable holding a bearer token for a remote server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_env: Option<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Start the server with only `env` and a few basic variables (PATH, HOME, ...) rather
+    /// than everything in the host's environment, which includes its API keys
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub isolate_env: bool,
     #[serde(default)]
     pub args: Option<Vec<String>>, // Add optional args field
+    /// Stop the server after this many minutes without a tool call; it is started again
+    /// the next time one of its tools is called
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    /// Arguments merged into every call to a tool, by tool name, e.g. a fixed `working_dir`
+    /// for `bash`. Values the model gives itself take precedence.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub default_arguments: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+    /// Tools on this server trusted to hand the model its next step. Their results may carry
+    /// a continuation (see `conversation_logic::CONTINUATION_URI`); other tools' never do.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continuation_tools: Vec<String>,
 }
 
 // Removed duplicate imports and struct definition below
@@ -24,6 +51,20 @@ pub struct AIProviderConfig {
     // Removed provider field, key in the map will be the provider name
     #[serde(default)]
     pub model: String,
+    /// API root to use instead of the provider's, e.g. a local OpenAI-compatible server.
+    /// Supported for OpenAI-compatible providers, Anthropic and Ollama.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Service endpoint for providers addressed by host rather than API root
+    /// (e.g. Ollama, or `https://<resource>.openai.azure.com` for Azure)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Azure OpenAI deployment name; requests go to this deployment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+    /// Azure OpenAI API version: "v1" (the default) or a dated version such as "2024-10-21"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize,Clone)]
@@ -53,11 +94,210 @@ impl Default for TimeoutConfig {
 
 
 
+/// When to compact a chat automatically before it outgrows the model's context
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ContextConfig {
+    #[serde(default = "default_auto_compact")]
+    pub auto_compact: bool,
+    /// Fraction of the context window at which to compact
+    #[serde(default = "default_compact_at")]
+    pub compact_at: f32,
+    /// Context window in tokens; defaults to the model's reported max_tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_tokens: Option<u32>,
+}
+
+fn default_auto_compact() -> bool {
+    true
+}
+
+fn default_compact_at() -> f32 {
+    0.8
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            auto_compact: default_auto_compact(),
+            compact_at: default_compact_at(),
+            window_tokens: None,
+        }
+    }
+}
+
+/// How often server processes are sampled for memory and CPU use
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MonitorConfig {
+    /// Seconds between samples; 0 turns sampling off
+    #[serde(default = "default_monitor_interval")]
+    pub interval_secs: u64,
+    /// Log a warning when a server's resident memory goes over this many megabytes
+    /// (null turns the warning off)
+    #[serde(default = "default_memory_warn_mb")]
+    pub memory_warn_mb: Option<u64>,
+}
+
+fn default_monitor_interval() -> u64 {
+    60
+}
+
+fn default_memory_warn_mb() -> Option<u64> {
+    Some(1024)
+}
+
+impl MonitorConfig {
+    /// Time between samples, or None if sampling is off
+    pub fn interval(&self) -> Option<Duration> {
+        (self.interval_secs > 0).then(|| Duration::from_secs(self.interval_secs))
+    }
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_monitor_interval(),
+            memory_warn_mb: default_memory_warn_mb(),
+        }
+    }
+}
+
+/// Append-only journaling of chats, so a long session survives the process dying
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct JournalConfig {
+    /// Write each chat's new messages to `conversations/<name>.jsonl` after every turn
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rewrite a journal as a single snapshot after this many appended entries
+    #[serde(default = "default_journal_compact_every")]
+    pub compact_every: usize,
+}
+
+fn default_journal_compact_every() -> usize {
+    200
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            compact_every: default_journal_compact_every(),
+        }
+    }
+}
+
+/// Masking of secrets in tool results before they are logged or kept in the conversation
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RedactionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Mask the built-in patterns (AWS keys, bearer tokens, `API_KEY=...`, private keys, ...)
+    #[serde(default = "default_true")]
+    pub default_patterns: bool,
+    /// Extra regexes to mask; only a `secret` named group is masked if the pattern has one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// `patterns` compiled on first use, shared by clones of this config
+    #[serde(skip)]
+    pub(crate) compiled: CompiledPatterns,
+}
+
+/// Lazily compiled redaction patterns. Equal whenever the patterns they came from are.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompiledPatterns(pub(crate) std::sync::Arc<once_cell::sync::OnceCell<Vec<regex::Regex>>>);
+
+impl PartialEq for CompiledPatterns {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_patterns: true,
+            patterns: Vec::new(),
+            compiled: CompiledPatterns::default(),
+        }
+    }
+}
+
+/// A prompt template: the text itself, or `{"file": "<path>"}` to read it from a file
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PromptTemplate {
+    Text(String),
+    File { file: PathBuf },
+}
+
+/// Replacements for the prompts the host writes itself. Unset prompts use the built-in ones.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct PromptsConfig {
+    /// Asks the verifier whether a response meets its criteria. Placeholders: `{request}`,
+    /// `{criteria}` and `{conversation}`; the verifier must still answer with the JSON verdict.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<PromptTemplate>,
+    /// Asks for the success criteria of a request. Placeholder: `{request}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub criteria: Option<PromptTemplate>,
+}
+
+/// Which command history the REPL uses
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryScope {
+    /// One history shared by every directory
+    #[default]
+    Global,
+    /// A separate history for each project (found via markers like .git or Cargo.toml)
+    Project,
+}
+
+/// Caps on tool output shown in the terminal (and passed back to the model)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct OutputConfig {
+    #[serde(default = "default_max_output_lines")]
+    pub max_lines: usize,
+    /// Catches single enormous lines (minified JSON, base64 blobs) the line cap lets through
+    #[serde(default = "default_max_output_bytes")]
+    pub max_bytes: usize,
+    /// Cap on the characters of each tool result kept in the conversation. The terminal
+    /// still shows the result under the caps above. None keeps results whole.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_result_chars: Option<usize>,
+}
+
+fn default_max_output_lines() -> usize {
+    150
+}
+
+fn default_max_output_bytes() -> usize {
+    64 * 1024
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            max_lines: default_max_output_lines(),
+            max_bytes: default_max_output_bytes(),
+            max_tool_result_chars: None,
+        }
+    }
+}
+
 impl Default for AIProviderConfig {
     fn default() -> Self {
         Self {
             // provider field removed
             model: "deepseek-chat".to_string(), // Default model
+            base_url: None,
+            endpoint: None,
+            deployment: None,
+            api_version: None,
         }
         // Removed extra closing brace here
     }
@@ -76,6 +316,24 @@ pub struct Config {
 
     #[serde(default)]
     pub timeouts: TimeoutConfig,
+
+    #[serde(default)]
+    pub output: OutputConfig,
+
+    #[serde(default)]
+    pub history: HistoryScope,
+
+    #[serde(default)]
+    pub context: ContextConfig,
+
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub prompts: PromptsConfig,
 }
 
 impl Config {
@@ -143,6 +401,7 @@ impl Config {
                 Ok(content) => {
                     log::debug!("Config file read successfully with std::fs");
                     let config: Self = serde_json::from_str(&content)?;
+                    config.validate()?;
                     return Ok(config);
                 }
                 Err(e) => {
@@ -154,6 +413,7 @@ impl Config {
         match serde_json::from_str::<Self>(&config_str) {
             Ok(config) => {
                 log::debug!("Config parsed successfully");
+                config.validate()?;
                 Ok(config)
             },
             Err(e) => {
@@ -162,6 +422,74 @@ impl Config {
             }
         }
     }
+
+    /// Check what parsing can't: every server needs a `command` to run or a `url` to connect to
+    pub fn validate(&self) -> Result<()> {
+        let mut names: Vec<&String> = self.servers.keys().collect();
+        names.sort();
+        for name in names {
+            let server = &self.servers[name];
+            if server.url.is_none() && server.command.trim().is_empty() {
+                return Err(anyhow!("Server '{}' needs a non-empty 'command' or a 'url'", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Environment variables that configure the host when it has no config file
+pub const DEFAULT_PROVIDER_ENV: &str = "MCP_DEFAULT_PROVIDER";
+pub const DEFAULT_MODEL_ENV: &str = "MCP_DEFAULT_MODEL";
+/// A JSON array of servers, each a server config with a `name`, e.g.
+/// `[{"name": "tools", "command": "mcp_tools"}]`
+pub const SERVERS_ENV: &str = "MCP_SERVERS";
+
+/// An entry of `MCP_SERVERS`
+#[derive(Debug, Deserialize)]
+struct NamedServerConfig {
+    name: String,
+    #[serde(flatten)]
+    config: ServerConfig,
+}
+
+impl Config {
+    /// Config built from `MCP_DEFAULT_PROVIDER`, `MCP_DEFAULT_MODEL` and `MCP_SERVERS` as
+    /// read by `var`, for running without a config file (e.g. in a container). None if none
+    /// of them is set. A provider without a model is left for the host to pick one for.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>> {
+        let var = |name: &str| var(name).map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        let provider = var(DEFAULT_PROVIDER_ENV);
+        let model = var(DEFAULT_MODEL_ENV);
+        let servers = var(SERVERS_ENV);
+        if provider.is_none() && model.is_none() && servers.is_none() {
+            return Ok(None);
+        }
+
+        let mut config = Self::default();
+        if let Some(servers) = servers {
+            let servers: Vec<NamedServerConfig> = serde_json::from_str(&servers)
+                .map_err(|e| anyhow!("{} is not a JSON array of servers: {}", SERVERS_ENV, e))?;
+            for server in servers {
+                if config.servers.insert(server.name.clone(), server.config).is_some() {
+                    return Err(anyhow!("{} lists server '{}' more than once", SERVERS_ENV, server.name));
+                }
+            }
+        }
+        match (provider, model) {
+            (Some(provider), model) => {
+                config.ai_providers.insert(
+                    provider.clone(),
+                    AIProviderConfig { model: model.unwrap_or_default(), ..Default::default() },
+                );
+                config.default_ai_provider = Some(provider);
+            }
+            (None, Some(_)) => warn!("{} is set without {}; ignoring it", DEFAULT_MODEL_ENV, DEFAULT_PROVIDER_ENV),
+            (None, None) => {}
+        }
+        config.validate().map_err(|e| anyhow!("{}: {}", SERVERS_ENV, e))?;
+        info!("Using configuration from environment ({} servers)", config.servers.len());
+        Ok(Some(config))
+    }
 }
 
 impl Default for Config {
@@ -175,10 +503,44 @@ impl Default for Config {
             ai_providers: default_providers, // Use the map with default
             default_ai_provider: None, // No default provider specified by default
             timeouts: TimeoutConfig::default(),
+            output: OutputConfig::default(),
+            history: HistoryScope::default(),
+            context: ContextConfig::default(),
+            monitor: MonitorConfig::default(),
+            redaction: RedactionConfig::default(),
+            journal: JournalConfig::default(),
+            prompts: PromptsConfig::default(),
         }
     }
 }
 
+// --- Profiles ---
+
+/// Path of a named profile's config (`mcp_host_config.<profile>.json`) in `dir`
+pub fn profile_config_path(dir: &Path, profile: &str) -> Result<PathBuf> {
+    if profile.is_empty() || !profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!("Invalid profile name '{}' (use letters, digits, '-' and '_')", profile));
+    }
+    Ok(dir.join(format!("mcp_host_config.{}.json", profile)))
+}
+
+/// The profile a config path belongs to, if it is a profile config
+pub fn profile_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let profile = file_name.strip_prefix("mcp_host_config.")?.strip_suffix(".json")?;
+    (!profile.is_empty()).then(|| profile.to_string())
+}
+
+/// Profiles with a config file in `dir`, sorted by name
+pub fn list_profiles(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut profiles: Vec<String> = entries
+        .filter_map(|entry| profile_name(&entry.ok()?.path()))
+        .collect();
+    profiles.sort();
+    profiles
+}
+
 // --- Provider Models Configuration ---
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
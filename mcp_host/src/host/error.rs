@@ -1,3 +1,5 @@
+use std::io::ErrorKind;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,6 +21,27 @@ pub enum HostError {
     
     #[error("Server not found: {0}")]
     ServerNotFound(String),
+
+    #[error("Message too large: {size} bytes exceeds limit of {limit} bytes")]
+    MessageTooLarge { size: usize, limit: usize },
+
+    #[error("Invalid arguments for tool '{tool}': {reason}")]
+    InvalidToolArguments { tool: String, reason: String },
+
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Tool '{tool}' on server '{server}' timed out after {:.1}s (tool timeout is {}s)", elapsed.as_secs_f64(), timeout.as_secs())]
+    ToolTimeout { tool: String, server: String, elapsed: Duration, timeout: Duration },
+
+    #[error("Server '{server}' failed to connect within {}s", timeout.as_secs_f64())]
+    ConnectTimeout { server: String, timeout: Duration },
+
+    #[error("Connection lost: {0}")]
+    ConnectionLost(String),
+
+    #[error("Request cancelled: {}", reason.as_deref().unwrap_or("no reason given"))]
+    Cancelled { reason: Option<String> },
     
     #[error("I/O error: {0}")]
     IO(#[from] std::io::Error),
@@ -30,4 +53,87 @@ pub enum HostError {
     Other(#[from] anyhow::Error),
 }
 
-pub type Result<T> = std::result::Result<T, HostError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, HostError>;
+
+/// A copy of the error, so a failure shared by several waiters keeps its variant for each.
+/// The wrapped I/O, JSON and `anyhow` errors are copied by kind and message only.
+impl Clone for HostError {
+    fn clone(&self) -> Self {
+        match self {
+            HostError::Server(m) => HostError::Server(m.clone()),
+            HostError::Config(m) => HostError::Config(m.clone()),
+            HostError::AIProvider(m) => HostError::AIProvider(m.clone()),
+            HostError::RPC { code, message } => HostError::RPC { code: *code, message: message.clone() },
+            HostError::Transport(m) => HostError::Transport(m.clone()),
+            HostError::ServerNotFound(m) => HostError::ServerNotFound(m.clone()),
+            HostError::MessageTooLarge { size, limit } => HostError::MessageTooLarge { size: *size, limit: *limit },
+            HostError::InvalidToolArguments { tool, reason } => {
+                HostError::InvalidToolArguments { tool: tool.clone(), reason: reason.clone() }
+            }
+            HostError::Timeout(after) => HostError::Timeout(*after),
+            HostError::ToolTimeout { tool, server, elapsed, timeout } => HostError::ToolTimeout {
+                tool: tool.clone(),
+                server: server.clone(),
+                elapsed: *elapsed,
+                timeout: *timeout,
+            },
+            HostError::ConnectTimeout { server, timeout } => HostError::ConnectTimeout { server: server.clone(), timeout: *timeout },
+            HostError::ConnectionLost(m) => HostError::ConnectionLost(m.clone()),
+            HostError::Cancelled { reason } => HostError::Cancelled { reason: reason.clone() },
+            HostError::IO(e) => HostError::IO(std::io::Error::new(e.kind(), e.to_string())),
+            HostError::JSON(e) => HostError::JSON(serde::de::Error::custom(e)),
+            HostError::Other(e) => HostError::Other(anyhow::anyhow!("{:#}", e)),
+        }
+    }
+}
+
+/// Sort a failed request into the variant callers branch on: JSON-RPC error replies become
+/// `RPC`, a closed connection `ConnectionLost`, and other I/O failures `Transport`.
+impl From<rmcp::ServiceError> for HostError {
+    fn from(error: rmcp::ServiceError) -> Self {
+        match error {
+            rmcp::ServiceError::McpError(e) => HostError::RPC { code: e.code.0 as i64, message: e.message.into_owned() },
+            rmcp::ServiceError::Transport(e) if is_disconnect(&e) => HostError::ConnectionLost(e.to_string()),
+            rmcp::ServiceError::Transport(e) => HostError::Transport(e.to_string()),
+            rmcp::ServiceError::UnexpectedResponse => HostError::Transport("unexpected response type".to_string()),
+            rmcp::ServiceError::Cancelled { reason } => HostError::Cancelled { reason },
+            rmcp::ServiceError::Timeout { timeout } => HostError::Timeout(timeout),
+            other => HostError::Transport(other.to_string()),
+        }
+    }
+}
+
+/// rmcp reports a finished service (the server exited or the connection dropped)
+/// as an "other" I/O error saying "disconnected"
+fn is_disconnect(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::BrokenPipe | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::NotConnected | ErrorKind::UnexpectedEof
+    ) || error.to_string() == "disconnected"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_errors_map_to_variants() {
+        let rpc = HostError::from(rmcp::ServiceError::McpError(rmcp::Error::invalid_params("bad path", None)));
+        assert!(matches!(rpc, HostError::RPC { code: -32602, ref message } if message == "bad path"));
+
+        let timeout = HostError::from(rmcp::ServiceError::Timeout { timeout: Duration::from_secs(3) });
+        assert!(matches!(timeout, HostError::Timeout(after) if after == Duration::from_secs(3)));
+        assert_eq!(timeout.to_string(), "Request timed out after 3s");
+
+        let lost = HostError::from(rmcp::ServiceError::Transport(std::io::Error::other("disconnected")));
+        assert!(matches!(lost, HostError::ConnectionLost(_)));
+        let pipe = HostError::from(rmcp::ServiceError::Transport(ErrorKind::BrokenPipe.into()));
+        assert!(matches!(pipe, HostError::ConnectionLost(_)));
+        let other = HostError::from(rmcp::ServiceError::Transport(std::io::Error::other("bad frame")));
+        assert!(matches!(other, HostError::Transport(ref m) if m == "bad frame"));
+
+        let cancelled = HostError::from(rmcp::ServiceError::Cancelled { reason: None });
+        assert_eq!(cancelled.to_string(), "Request cancelled: no reason given");
+        assert!(matches!(HostError::from(serde_json::from_str::<u32>("x").unwrap_err()), HostError::JSON(_)));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+// Hooks that see (and may rewrite) every JSON-RPC message exchanged with a server,
+// for things like auth tokens in params, tracing and timing metrics.
+// The transports apply them at the point messages are serialized / parsed, so fields
+// a hook adds reach the wire even if rmcp's types don't know about them.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A hook given the server name and the raw JSON-RPC message
+pub type MessageHook = Arc<dyn Fn(&str, &mut Value) + Send + Sync>;
+
+/// Ordered request/response hooks shared by every server connection
+#[derive(Clone, Default)]
+pub struct Interceptors {
+    on_request: Vec<MessageHook>,
+    on_response: Vec<MessageHook>,
+}
+
+impl std::fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interceptors")
+            .field("on_request", &self.on_request.len())
+            .field("on_response", &self.on_response.len())
+            .finish()
+    }
+}
+
+impl Interceptors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `hook` on every message sent to a server (requests, notifications and replies),
+    /// after any hooks registered earlier
+    pub fn on_request(mut self, hook: impl Fn(&str, &mut Value) + Send + Sync + 'static) -> Self {
+        self.on_request.push(Arc::new(hook));
+        self
+    }
+
+    /// Run `hook` on every message received from a server, before it is parsed
+    pub fn on_response(mut self, hook: impl Fn(&str, &mut Value) + Send + Sync + 'static) -> Self {
+        self.on_response.push(Arc::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.on_request.is_empty() && self.on_response.is_empty()
+    }
+
+    /// Serialize an outgoing message, letting the request hooks rewrite it first
+    pub fn encode<T: Serialize>(&self, server_name: &str, message: &T) -> serde_json::Result<Vec<u8>> {
+        if self.on_request.is_empty() {
+            return serde_json::to_vec(message);
+        }
+        let mut value = serde_json::to_value(message)?;
+        for hook in &self.on_request {
+            hook(server_name, &mut value);
+        }
+        serde_json::to_vec(&value)
+    }
+
+    /// Parse an incoming message, letting the response hooks see and rewrite it first
+    pub fn decode<T: DeserializeOwned>(&self, server_name: &str, raw: &[u8]) -> serde_json::Result<T> {
+        if self.on_response.is_empty() {
+            return serde_json::from_slice(raw);
+        }
+        let mut value: Value = serde_json::from_slice(raw)?;
+        for hook in &self.on_response {
+            hook(server_name, &mut value);
+        }
+        serde_json::from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_hooks_run_in_registration_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let first = Arc::clone(&seen);
+        let second = Arc::clone(&seen);
+        let interceptors = Interceptors::new()
+            .on_request(move |server, message| {
+                first.lock().unwrap().push(format!("first:{}", server));
+                message["params"]["token"] = json!("abc");
+            })
+            .on_request(move |_, message| {
+                second.lock().unwrap().push(format!("second:{}", message["params"]["token"]));
+            });
+
+        let encoded = interceptors.encode("remote", &json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })).unwrap();
+        let encoded: Value = serde_json::from_slice(&encoded).unwrap();
+        assert_eq!(encoded["params"]["token"], "abc");
+        assert_eq!(*seen.lock().unwrap(), vec!["first:remote", "second:\"abc\""]);
+    }
+
+    #[test]
+    fn test_response_hooks_rewrite_before_parsing() {
+        let interceptors = Interceptors::new().on_response(|_, message| {
+            message["result"]["checked"] = json!(true);
+        });
+        let decoded: Value = interceptors.decode("remote", br#"{"jsonrpc":"2.0","id":1,"result":{}}"#).unwrap();
+        assert_eq!(decoded["result"]["checked"], true);
+        assert!(Interceptors::new().is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+// Scripted in-memory transport for testing code that talks to MCP servers.
+// Stands in for a server process: answers requests from canned per-method results
+// and records everything the client sends. Enabled with the `testing` feature.
+
+use crate::host::middleware::Interceptors;
+use futures::{Sink, Stream, StreamExt};
+use rmcp::service::{RoleClient, RxJsonRpcMessage, TxJsonRpcMessage};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// JSON-RPC "method not found", returned for requests with no scripted reply
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Clone)]
+enum Reply {
+    Result(Value),
+    Error { code: i64, message: String },
+    /// Never answer, like a server stuck on the request
+    Hang,
+}
+
+/// A fake server connection for `rmcp::serve_client`.
+///
+/// ```ignore
+/// let mock = MockTransport::new()
+///     .respond("tools/list", json!({ "tools": [] }));
+/// let handle = mock.handle();
+/// let client = rmcp::serve_client((), mock.into_transport()).await?;
+/// client.peer().list_tools(None).await?;
+/// assert_eq!(handle.requests("tools/list").len(), 1);
+/// ```
+///
+/// `initialize` is answered with a minimal server info unless scripted otherwise.
+pub struct MockTransport {
+    replies: HashMap<String, Reply>,
+    handle: MockTransportHandle,
+    incoming: mpsc::UnboundedReceiver<Value>,
+    interceptors: (String, Interceptors),
+}
+
+/// Inspect what the client sent, and push server-initiated messages, after the
+/// transport has been handed to the client.
+#[derive(Clone)]
+pub struct MockTransportHandle {
+    sent: Arc<Mutex<Vec<Value>>>,
+    outgoing: mpsc::UnboundedSender<Value>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        let (outgoing, incoming) = mpsc::unbounded_channel();
+        let mut replies = HashMap::new();
+        replies.insert(
+            "initialize".to_string(),
+            Reply::Result(json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "serverInfo": { "name": "mock", "version": "0.0.0" }
+            })),
+        );
+        Self {
+            replies,
+            handle: MockTransportHandle { sent: Arc::new(Mutex::new(Vec::new())), outgoing },
+            incoming,
+            interceptors: (String::new(), Interceptors::new()),
+        }
+    }
+
+    /// Answer every `method` request with `result`
+    pub fn respond(mut self, method: &str, result: Value) -> Self {
+        self.replies.insert(method.to_string(), Reply::Result(result));
+        self
+    }
+
+    /// Answer every `method` request with a JSON-RPC error
+    pub fn respond_error(mut self, method: &str, code: i64, message: &str) -> Self {
+        self.replies.insert(method.to_string(), Reply::Error { code, message: message.to_string() });
+        self
+    }
+
+    /// Record `method` requests but never answer them
+    pub fn hang(mut self, method: &str) -> Self {
+        self.replies.insert(method.to_string(), Reply::Hang);
+        self
+    }
+
+    /// Run `interceptors` on messages from the client as the real transports do, as the
+    /// connection to `server_name`. What they send is what gets recorded.
+    pub fn interceptors(mut self, server_name: &str, interceptors: Interceptors) -> Self {
+        self.interceptors = (server_name.to_string(), interceptors);
+        self
+    }
+
+    /// Handle for inspecting traffic once the transport is in use
+    pub fn handle(&self) -> MockTransportHandle {
+        self.handle.clone()
+    }
+
+    /// The (sink, stream) pair to pass to `rmcp::serve_client`
+    pub fn into_transport(
+        self,
+    ) -> (
+        impl Sink<TxJsonRpcMessage<RoleClient>, Error = std::io::Error> + Send + 'static,
+        impl Stream<Item = RxJsonRpcMessage<RoleClient>> + Send + 'static,
+    ) {
+        let Self { replies, handle, incoming, interceptors } = self;
+
+        let sink = futures::sink::unfold((replies, handle, interceptors), |(replies, handle, interceptors), message: TxJsonRpcMessage<RoleClient>| async move {
+            let (server_name, hooks) = &interceptors;
+            let message: Value = serde_json::from_slice(&hooks.encode(server_name, &message)?)?;
+            handle.sent.lock().unwrap().push(message.clone());
+
+            // Notifications and responses to server requests don't get a reply
+            if let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) {
+                let reply = match replies.get(method) {
+                    Some(Reply::Result(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Some(Reply::Error { code, message }) => {
+                        json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+                    }
+                    Some(Reply::Hang) => return Ok((replies, handle, interceptors)),
+                    None => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": METHOD_NOT_FOUND, "message": format!("No scripted reply for '{}'", method) }
+                    }),
+                };
+                // The client may already have shut down; nothing to deliver to then
+                let _ = handle.outgoing.send(reply);
+            }
+            Ok::<_, std::io::Error>((replies, handle, interceptors))
+        });
+
+        let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(incoming).filter_map(|value| async move {
+            match serde_json::from_value::<RxJsonRpcMessage<RoleClient>>(value) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    log::warn!("MockTransport dropped a message the client can't parse: {}", e);
+                    None
+                }
+            }
+        });
+
+        (sink, stream)
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockTransportHandle {
+    /// Every message the client has sent, in order
+    pub fn sent(&self) -> Vec<Value> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Requests the client sent for `method`
+    pub fn requests(&self, method: &str) -> Vec<Value> {
+        self.sent()
+            .into_iter()
+            .filter(|m| m.get("id").is_some() && m["method"] == method)
+            .collect()
+    }
+
+    /// Notifications the client sent (messages with a method but no id)
+    pub fn notifications(&self) -> Vec<Value> {
+        self.sent()
+            .into_iter()
+            .filter(|m| m.get("id").is_none() && m.get("method").is_some())
+            .collect()
+    }
+
+    /// Deliver a server notification to the client
+    pub fn notify(&self, method: &str, params: Value) {
+        let _ = self.outgoing.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::server_manager::{HostClientHandler, ResourceUpdate};
+    use rmcp::serve_client;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn test_scripted_request_and_response() {
+        let mock = MockTransport::new()
+            .respond("tools/list", json!({ "tools": [
+                { "name": "echo", "description": "Echo input", "inputSchema": { "type": "object" } }
+            ]}))
+            .respond_error("resources/list", -32000, "resources are unavailable");
+        let handle = mock.handle();
+
+        let client = serve_client((), mock.into_transport()).await.expect("handshake failed");
+        let tools = client.peer().list_tools(None).await.unwrap().tools;
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo");
+
+        let err = client.peer().list_resources(None).await.unwrap_err();
+        assert!(err.to_string().contains("resources are unavailable"), "{}", err);
+        assert!(client.peer().list_prompts(None).await.is_err(), "unscripted methods should fail");
+
+        assert_eq!(handle.requests("initialize").len(), 1);
+        assert_eq!(handle.requests("tools/list").len(), 1);
+        assert!(handle.notifications().iter().any(|n| n["method"] == "notifications/initialized"));
+    }
+
+    #[tokio::test]
+    async fn test_server_notifications_reach_the_client() {
+        let (updates, mut updates_rx) = broadcast::channel(4);
+        let handler = HostClientHandler::new("mock", updates, Arc::new(tokio::sync::Mutex::new(HashMap::new())));
+
+        let mock = MockTransport::new();
+        let handle = mock.handle();
+        let _client = serve_client(handler, mock.into_transport()).await.expect("handshake failed");
+
+        handle.notify("notifications/resources/updated", json!({ "uri": "file:///notes.txt" }));
+        let update = tokio::time::timeout(Duration::from_secs(2), updates_rx.recv())
+            .await
+            .expect("notification was not delivered")
+            .unwrap();
+        assert_eq!(update, ResourceUpdate { server: "mock".to_string(), uri: "file:///notes.txt".to_string() });
+    }
+}
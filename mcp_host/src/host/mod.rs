@@ -2,18 +2,33 @@ pub mod server_manager;
 pub mod config;
 // pub mod protocol; // Removed unused module
 pub mod error;
+pub mod transport;
+pub mod sse_transport;
+pub mod annotations;
+pub mod middleware;
+pub mod tool_call;
+pub mod models;
+pub mod monitor;
+pub mod single_flight;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock_transport;
+#[cfg(any(test, feature = "testing"))]
+pub mod transport_bench;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 use std::sync::Arc;
 // Removed duplicate Duration, Result, Mutex, HashMap below
 use anyhow::Result;
 use tokio::sync::Mutex;
 use std::collections::HashMap;
-use std::time::Duration; // Re-add Duration import
+use std::time::{Duration, Instant}; // Re-add Duration import
 
 // Removed duplicate imports below
 use anyhow::{anyhow}; // Keep anyhow, remove duplicate Result
 use log::{debug, error, info, warn};
-use server_manager::ManagedServer;
+use server_manager::{ManagedServer, ResourceUpdate};
+use tokio::sync::broadcast;
 use rmcp::model::Implementation as RmcpImplementation; // Alias Implementation
 use rmcp::model::Tool as RmcpTool; // Alias Tool
 use std::sync::Arc as StdArc; // Add alias import
@@ -23,10 +38,82 @@ use crate::ai_client::{AIClient, AIClientFactory};
 use crate::conversation_service::generate_tool_system_prompt;
 use crate::host::config::{AIProviderConfig, Config as HostConfig, ProviderModelsConfig}; // Removed unused ServerConfig
 use std::path::PathBuf;
+
+/// Providers the host can create clients for without any config entry
+pub const KNOWN_PROVIDERS: &[&str] = &["anthropic", "openai", "deepseek", "gemini", "ollama", "xai", "phind", "groq", "openrouter"];
+
+/// Tool argument through which a tool can be asked for `"image"` or `"text"` output
+pub const CONTENT_TYPE_ARG: &str = "content_type";
+
+/// How long a server gets to start and complete the handshake unless the builder says otherwise
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often servers with an `idle_timeout` are checked for having gone unused
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Setup state of one AI provider, for checking why it is or isn't available
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderStatus {
+    pub name: String,
+    /// Environment variable holding its API key; None if it needs no key
+    pub key_var: Option<&'static str>,
+    /// A masked prefix of the key, if the variable is set
+    pub masked_key: Option<String>,
+    /// Has an entry in the config file's ai_providers
+    pub configured: bool,
+    pub available: bool,
+    pub active: bool,
+}
+
+/// A managed server's process and its latest memory/CPU sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+    pub name: String,
+    /// None for remote (SSE) servers, which have no local process
+    pub pid: Option<u32>,
+    /// None if the process couldn't be read
+    pub sample: Option<monitor::ProcessSample>,
+}
+
+/// Enough of an API key to tell keys apart, never the whole thing
+pub fn mask_api_key(key: &str) -> String {
+    let prefix: String = key.chars().take(4).collect();
+    if key.chars().count() > 8 {
+        format!("{}****", prefix)
+    } else {
+        "****".to_string()
+    }
+}
+
+/// Outcome of starting the servers a config adds
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StartupReport {
+    /// Servers that started, by name
+    pub started: Vec<String>,
+    /// Servers that failed to start, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+impl StartupReport {
+    pub fn all_started(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// One line per failed server, for showing to the user
+    pub fn failure_summary(&self) -> String {
+        self.failed.iter()
+            .map(|(name, error)| format!("Server '{}' failed to start: {}", name, error))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
 pub struct MCPHost {
     pub servers: Arc<Mutex<HashMap<String, ManagedServer>>>,
     pub client_info: RmcpImplementation, // Use aliased type
     pub request_timeout: Duration,
+    pub connect_timeout: Duration, // Limit on starting a server and completing the handshake
+    pub max_message_bytes: usize, // Cap on a single message read from a server
     pub config: Arc<Mutex<HostConfig>>, // Store the whole config
     pub config_path: Arc<Mutex<Option<PathBuf>>>, // Store the config path
     // Removed ai_provider_configs
@@ -34,6 +121,12 @@ pub struct MCPHost {
     ai_client: Arc<Mutex<Option<Arc<dyn AIClient>>>>, // Active client instance, wrapped in Mutex
     pub provider_models: Arc<Mutex<ProviderModelsConfig>>, // Added: Stores suggested models
     provider_models_path: Arc<Mutex<PathBuf>>, // Added: Path to provider_models.toml
+    shared: server_manager::SharedServerState, // Update channels, caches and interceptors used by every ServerManager
+    model_cache: models::ModelCache, // Models fetched from provider APIs this session
+    model_fetches: single_flight::SingleFlight<Vec<String>>, // Model list requests in flight, by provider
+    process_monitor: monitor::ProcessMonitor, // Latest memory/CPU sample of each server process
+    idle_servers: Arc<Mutex<HashMap<String, Vec<RmcpTool>>>>, // Servers stopped for being idle, with the tools they had
+    server_wakes: single_flight::SingleFlight<()>, // Restarts of idle servers in progress, shared by concurrent callers
 }
 
 impl Clone for MCPHost {
@@ -42,12 +135,20 @@ impl Clone for MCPHost {
             servers: Arc::clone(&self.servers),
             client_info: self.client_info.clone(), // Use aliased type
             request_timeout: self.request_timeout,
+            connect_timeout: self.connect_timeout,
+            max_message_bytes: self.max_message_bytes,
             config: Arc::clone(&self.config), // Clone Arc for config
             config_path: Arc::clone(&self.config_path), // Clone Arc for path
             active_provider_name: Arc::clone(&self.active_provider_name),
             ai_client: Arc::clone(&self.ai_client),
             provider_models: Arc::clone(&self.provider_models), // Added clone
             provider_models_path: Arc::clone(&self.provider_models_path), // Added clone
+            shared: self.shared.clone(),
+            model_cache: Arc::clone(&self.model_cache),
+            model_fetches: self.model_fetches.clone(),
+            process_monitor: self.process_monitor.clone(),
+            idle_servers: Arc::clone(&self.idle_servers),
+            server_wakes: self.server_wakes.clone(),
         }
     }
 }
@@ -65,8 +166,10 @@ impl MCPHost {
 
     // Removed load_config method. Use reload_host_config or apply_config.
 
-    /// Apply a new configuration, starting/stopping servers as needed.
-    pub async fn apply_config(&self, new_config: HostConfig) -> Result<()> {
+    /// Apply `new_config`: stop servers it no longer lists, start the ones it adds and
+    /// update the active AI provider. Servers that fail to start are listed in the report
+    /// rather than failing the whole call.
+    pub async fn apply_config(&self, new_config: HostConfig) -> Result<StartupReport> {
         // ---> ADDED LOG <---
         info!("Entered apply_config. Processing {} servers from new config.", new_config.servers.len());
         // ---> END ADDED LOG <---
@@ -76,20 +179,19 @@ impl MCPHost {
         let mut servers_to_start = Vec::new();
         let servers_to_stop: Vec<String>; // Keep String for consistency
 
+        // Servers stopped for being idle still count as running; they restart on their next call
+        let idle_servers: Vec<String> = self.idle_servers.lock().await.keys().cloned().collect();
         { // Scope for the servers lock
             let current_servers = self.servers.lock().await;
             debug!("Servers lock acquired.");
             let mut current_server_names = current_servers.keys().cloned().collect::<std::collections::HashSet<_>>();
+            current_server_names.extend(idle_servers.iter().cloned());
 
             // Determine servers to start
             for (name, server_config) in &new_config.servers {
-                if !current_servers.contains_key(name) {
+                if !current_servers.contains_key(name) && !idle_servers.contains(name) {
                     info!("Server '{}' marked for start.", name);
-                    // Prepare command components for starting later
-                    let program = server_config.command.clone();
-                    let args = server_config.args.clone().unwrap_or_default();
-                    let envs = server_config.env.clone();
-                    servers_to_start.push((name.clone(), program, args, envs));
+                    servers_to_start.push((name.clone(), server_config.clone()));
                 }
                 // Remove from the set of current servers, leaving only those to be stopped
                 current_server_names.remove(name);
@@ -97,6 +199,7 @@ impl MCPHost {
 
             // Servers remaining in current_server_names need to be stopped
             servers_to_stop = current_server_names.into_iter().collect();
+            servers_to_start.sort_by(|(a, _), (b, _)| a.cmp(b));
             debug!("Servers lock released.");
         } // servers lock is released here
 
@@ -105,6 +208,7 @@ impl MCPHost {
             info!("Stopping servers removed from config: {:?}", servers_to_stop);
             for name in servers_to_stop {
                 debug!("Attempting to stop server '{}'", name);
+                self.idle_servers.lock().await.remove(&name);
                 if let Err(e) = server_manager.stop_server(&name).await {
                     error!("Failed to stop server '{}': {}", name, e);
                 } else {
@@ -116,19 +220,19 @@ impl MCPHost {
         }
 
         // Start new servers
+        let mut report = StartupReport::default();
         if !servers_to_start.is_empty() {
-            info!("Starting new servers: {:?}", servers_to_start.iter().map(|(n, _, _, _)| n).collect::<Vec<_>>());
-            for (name, program, args, envs) in servers_to_start {
+            info!("Starting new servers: {:?}", servers_to_start.iter().map(|(n, _)| n).collect::<Vec<_>>());
+            for (name, server_config) in servers_to_start {
                 // ---> ADDED LOG <---
-                info!("apply_config: Preparing to call start_server_with_command for '{}'", name);
+                info!("apply_config: Preparing to start server '{}'", name);
                 // ---> END ADDED LOG <---
-                debug!("Attempting to start server '{}' with program: {}, args: {:?}, envs: {:?}", name, program, args, envs.keys());
-                // Pass components instead of a Command object
-                if let Err(e) = server_manager.start_server_with_components(&name, &program, &args, &envs).await {
+                if let Err(e) = server_manager.start_server_from_config(&name, &server_config).await {
                     error!("Failed to start server '{}': {}", name, e);
-                    // Decide if you want to continue or return error
+                    report.failed.push((name, e.to_string()));
                 } else {
                     info!("Successfully started server '{}'", name);
+                    report.started.push(name);
                 }
             }
         } else {
@@ -178,7 +282,7 @@ impl MCPHost {
             debug!("Config lock released.");
             info!("Configuration applied successfully.");
             info!("Exiting apply_config.");
-            Ok(())
+            Ok(report)
         } // End of apply_config
 
     // Method to save the current in-memory config
@@ -208,7 +312,7 @@ impl MCPHost {
 
 
     // Method to reload config from disk
-    pub async fn reload_host_config(&self) -> Result<()> {
+    pub async fn reload_host_config(&self) -> Result<StartupReport> {
         debug!("Acquiring config_path lock for reloading...");
         let path_to_load: Option<PathBuf>;
         { // Scope for lock
@@ -224,14 +328,53 @@ impl MCPHost {
             debug!("Calling HostConfig::load()...");
             let new_config = HostConfig::load(&path).await?; // Use cloned path
             debug!("Config loaded from disk, now calling apply_config...");
-            self.apply_config(new_config).await?; // apply_config handles its own locks
-            Ok(())
+            self.apply_config(new_config).await // apply_config handles its own locks
         } else {
             error!("No configuration file path set. Cannot reload.");
             Err(anyhow!("No configuration file path set. Cannot reload."))
         }
     }
 
+    /// Switch to the named profile's config (`mcp_host_config.<profile>.json`, next to the
+    /// current config file). Servers the new profile doesn't define identically are stopped,
+    /// its own servers are started, and later saves go to the profile's file.
+    /// Returns the profile's path and which of its servers started.
+    pub async fn switch_profile(&self, profile: &str) -> Result<(PathBuf, StartupReport)> {
+        let dir = self.config_path.lock().await
+            .as_ref()
+            .and_then(|path| path.parent().map(|p| p.to_path_buf()))
+            .or_else(|| dirs::config_dir().map(|p| p.join("mcp")))
+            .ok_or_else(|| anyhow!("Could not determine the config directory"))?;
+        let path = config::profile_config_path(&dir, profile)?;
+        if !path.exists() {
+            return Err(anyhow!("Profile '{}' not found (expected {})", profile, path.display()));
+        }
+
+        info!("Switching to profile '{}' from {:?}", profile, path);
+        let new_config = HostConfig::load(&path).await?;
+
+        // apply_config keeps servers whose name is unchanged, so restart the ones
+        // this profile defines differently
+        let changed: Vec<String> = {
+            let current = self.config.lock().await;
+            current.servers.iter()
+                .filter(|(name, server)| new_config.servers.get(*name).is_some_and(|new| new != *server))
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        let server_manager = self.server_manager();
+        for name in changed {
+            info!("Server '{}' differs in profile '{}'; restarting it.", name, profile);
+            if let Err(e) = server_manager.stop_server(&name).await {
+                error!("Failed to stop server '{}': {}", name, e);
+            }
+        }
+
+        *self.config_path.lock().await = Some(path.clone());
+        let report = self.apply_config(new_config).await?;
+        Ok((path, report))
+    }
+
     /// Reload the provider models configuration from disk.
     pub async fn reload_provider_models(&self) -> Result<()> {
         let path_to_load = { // Scope lock
@@ -247,12 +390,75 @@ impl MCPHost {
         Ok(())
     }
 
+    /// Fetch the models a provider currently offers from its API.
+    /// Successful results are cached for the session; after a failure the next call tries again.
+    pub async fn fetch_available_models(&self, provider_name: &str) -> Result<Vec<String>> {
+        let provider_key = provider_name.to_lowercase();
+        if let Some(models) = self.model_cache.lock().await.get(&provider_key) {
+            return Ok(models.clone());
+        }
+
+        let base_url = models::models_base_url(&provider_key)
+            .ok_or_else(|| anyhow!("Listing models is not supported for provider '{}'", provider_name))?;
+        let api_key = Self::get_api_key_for_provider(&provider_key).ok();
+
+        let cache = Arc::clone(&self.model_cache);
+        let key = provider_key.clone();
+        let result = self.model_fetches.run(&provider_key, move || async move {
+            let models = models::fetch_models(&reqwest::Client::new(), &key, &base_url, api_key.as_deref()).await?;
+            debug!("Fetched {} models for provider '{}'", models.len(), key);
+            cache.lock().await.insert(key, models.clone());
+            Ok(models)
+        }).await;
+        if let Err(e) = &result {
+            warn!("Failed to fetch models for provider '{}': {}", provider_key, e);
+        }
+        result
+    }
+
+    /// Configured models for a provider merged with any fetched from its API
+    pub async fn available_models(&self, provider_name: &str) -> Vec<String> {
+        let configured = self.configured_models(provider_name).await;
+        let fetched = self.fetch_available_models(provider_name).await.unwrap_or_default();
+        models::merge_models(&configured, &fetched)
+    }
+
+    /// Like `available_models`, but without waiting on the provider's API: returns what has
+    /// been fetched so far, and starts a fetch in the background if nothing has been yet
+    pub async fn cached_models(&self, provider_name: &str) -> Vec<String> {
+        let configured = self.configured_models(provider_name).await;
+        let cached = self.model_cache.lock().await.get(&provider_name.to_lowercase()).cloned();
+        let fetched = match cached {
+            Some(models) => models,
+            None => {
+                if models::models_base_url(provider_name).is_some() {
+                    let host = self.clone();
+                    let provider_name = provider_name.to_string();
+                    tokio::spawn(async move {
+                        let _ = host.fetch_available_models(&provider_name).await;
+                    });
+                }
+                Vec::new()
+            }
+        };
+        models::merge_models(&configured, &fetched)
+    }
+
+    async fn configured_models(&self, provider_name: &str) -> Vec<String> {
+        self.provider_models.lock().await
+            .providers
+            .get(&provider_name.to_lowercase())
+            .map(|list| list.models.clone())
+            .unwrap_or_default()
+    }
+
         /// Run the REPL interface
     pub async fn run_repl(&self) -> Result<()> {
         info!("Entering MCPHost::run_repl..."); // Log entry
         // Pass self.clone() directly to Repl::new and remove with_host
         info!("Attempting to create Repl instance..."); // Log before new()
-        let mut repl = match crate::repl::Repl::new(self.clone()) {
+        let history_scope = self.config.lock().await.history;
+        let mut repl = match crate::repl::Repl::new(self.clone(), history_scope) {
             Ok(r) => {
                 info!("Repl instance created successfully."); // Log after new()
                 r
@@ -272,18 +478,130 @@ impl MCPHost {
             StdArc::clone(&self.servers), // Use aliased Arc
             self.client_info.clone(), // Use aliased type
             self.request_timeout,
+            self.connect_timeout,
+            self.max_message_bytes,
+            self.shared.clone(),
         )
     }
 
     /// List the tools available on a server
     // Update return type to use rmcp::model::Tool
     pub async fn list_server_tools(&self, server_name: &str) -> Result<Vec<RmcpTool>> { // Use aliased type
+        // An idle server isn't restarted just to list the tools it had
+        if let Some(tools) = self.idle_servers.lock().await.get(server_name) {
+            return Ok(tools.clone());
+        }
         self.server_manager().list_server_tools(server_name).await
     }
 
+    /// Start building a tool call whose arguments are checked against the tool's `inputSchema`
+    pub fn build_tool_call(&self, server_name: &str, tool_name: &str) -> tool_call::ToolCallBuilder<'_> {
+        tool_call::ToolCallBuilder::new(self, server_name, tool_name)
+    }
+
     /// Call a tool on a server
     pub async fn call_tool(&self, server_name: &str, tool_name: &str, args: serde_json::Value) -> Result<String> {
-        self.server_manager().call_tool(server_name, tool_name, args).await
+        let result = self.call_tool_structured(server_name, tool_name, args).await?;
+        Ok(server_manager::format_tool_result(&result))
+    }
+
+    /// Call a tool with its output suited to the active model. Arguments are first checked
+    /// against the tool's `inputSchema`, so bad calls fail here with a clear message. Tools
+    /// that declare a `content_type` argument are asked for `"image"` only if the model has
+    /// vision, and any image content is replaced with a text placeholder for models without it.
+    /// A call still running after `timeouts.tool` seconds fails with `HostError::ToolTimeout`.
+    /// A server stopped for being idle is started again first. The server's configured
+    /// `default_arguments` for the tool fill in anything the call leaves out.
+    #[cfg_attr(feature = "otel", tracing::instrument(name = "call_tool", skip_all, fields(server = server_name, tool = tool_name)))]
+    pub async fn call_tool_structured(&self, server_name: &str, tool_name: &str, mut args: serde_json::Value) -> Result<rmcp::model::CallToolResult> {
+        self.wake_idle_server(server_name).await?;
+        let defaults = self
+            .config
+            .lock()
+            .await
+            .servers
+            .get(server_name)
+            .and_then(|server| server.default_arguments.get(tool_name).cloned());
+        if let Some(defaults) = defaults {
+            debug!("Applying default arguments {:?} to tool '{}'", defaults.keys().collect::<Vec<_>>(), tool_name);
+            tool_call::apply_default_arguments(&mut args, &defaults);
+        }
+        let tool = self.find_tool(server_name, tool_name).await;
+        if let Some(tool) = &tool {
+            let empty = serde_json::Map::new();
+            let arguments = match &args {
+                serde_json::Value::Object(arguments) => Some(arguments),
+                serde_json::Value::Null => Some(&empty),
+                _ => None,
+            };
+            if let Some(arguments) = arguments {
+                tool_call::validate_arguments(tool_name, &tool.input_schema, arguments)?;
+            }
+        }
+
+        let supports_vision = match self.ai_client().await {
+            Some(client) => client.capabilities().supports_vision,
+            None => false,
+        };
+        let unset = match &args {
+            serde_json::Value::Object(arguments) => !arguments.contains_key(CONTENT_TYPE_ARG),
+            serde_json::Value::Null => true,
+            _ => false,
+        };
+        let takes_content_type = tool
+            .as_ref()
+            .is_some_and(|tool| tool.input_schema.get("properties").and_then(|p| p.get(CONTENT_TYPE_ARG)).is_some());
+        if unset && takes_content_type {
+            let content_type = if supports_vision { "image" } else { "text" };
+            debug!("Asking tool '{}' for {} output", tool_name, content_type);
+            if args.is_null() {
+                args = serde_json::json!({});
+            }
+            args[CONTENT_TYPE_ARG] = content_type.into();
+        }
+
+        let timeout = Duration::from_secs(self.config.lock().await.timeouts.tool);
+        let started = std::time::Instant::now();
+        let manager = self.server_manager();
+        let call = manager.call_tool_structured(server_name, tool_name, args);
+        let result = match tokio::time::timeout(timeout, call).await {
+            Ok(result) => result?,
+            Err(_) => {
+                let error = error::HostError::ToolTimeout {
+                    tool: tool_name.to_string(),
+                    server: server_name.to_string(),
+                    elapsed: started.elapsed(),
+                    timeout,
+                };
+                warn!("{}", error);
+                return Err(error.into());
+            }
+        };
+        Ok(if supports_vision { result } else { server_manager::images_to_placeholders(result) })
+    }
+
+    /// A tool's definition from the server's cached tool list (or the tools it had, if it
+    /// was stopped for being idle). None if the server doesn't have the tool.
+    pub async fn tool_definition(&self, server_name: &str, tool_name: &str) -> Result<Option<RmcpTool>> {
+        if let Some(tools) = self.idle_servers.lock().await.get(server_name) {
+            return Ok(tools.iter().find(|tool| tool.name == tool_name).cloned());
+        }
+        self.server_manager().tool_definition(server_name, tool_name).await
+    }
+
+    /// The tool's definition, if the server lists it. A listing failure is logged as a
+    /// warning and treated as unknown, so the call goes to the server unvalidated.
+    async fn find_tool(&self, server_name: &str, tool_name: &str) -> Option<RmcpTool> {
+        match self.tool_definition(server_name, tool_name).await {
+            Ok(tool) => tool,
+            Err(e) => {
+                warn!(
+                    "Could not look up tool '{}' on server '{}', so its arguments were not validated: {}",
+                    tool_name, server_name, e
+                );
+                None
+            }
+        }
     }
 
     /// Start a server using a command string and optional extra arguments.
@@ -295,9 +613,206 @@ impl MCPHost {
 
     /// Stop a server by name.
     pub async fn stop_server(&self, name: &str) -> Result<()> {
+        self.process_monitor.remove_server(name);
+        self.idle_servers.lock().await.remove(name);
         self.server_manager().stop_server(name).await
     }
 
+    /// Stop every server that has gone longer than its `idle_timeout` without a tool call,
+    /// remembering its tools so it still shows up in tool listings. Returns their names.
+    async fn stop_idle_servers(&self, now: Instant) -> Vec<String> {
+        let timeouts: HashMap<String, Duration> = self.config.lock().await.servers
+            .iter()
+            .filter_map(|(name, config)| Some((name.clone(), Duration::from_secs(config.idle_timeout? * 60))))
+            .collect();
+        if timeouts.is_empty() {
+            return Vec::new();
+        }
+        let mut candidates: Vec<String> = self.servers.lock().await
+            .iter()
+            .filter(|(name, server)| timeouts.get(*name).is_some_and(|timeout| server.is_idle(*timeout, now)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        candidates.sort();
+
+        let mut idle = Vec::new();
+        for name in candidates {
+            let tools = self.server_manager().list_server_tools(&name).await.unwrap_or_else(|e| {
+                warn!("Could not list tools of idle server '{}' before stopping it: {}", name, e);
+                Vec::new()
+            });
+            // A call may have started meanwhile; it is checked again as the server is stopped
+            match self.server_manager().stop_server_if_idle(&name, timeouts[&name], now).await {
+                Ok(false) => continue,
+                Ok(true) => info!("Stopped server '{}' after {:?} without a tool call", name, timeouts[&name]),
+                Err(e) => warn!("Failed to stop idle server '{}': {}", name, e),
+            }
+            self.process_monitor.remove_server(&name);
+            self.idle_servers.lock().await.insert(name.clone(), tools);
+            idle.push(name);
+        }
+        idle
+    }
+
+    /// Start a server again if it was stopped for being idle
+    async fn wake_idle_server(&self, name: &str) -> Result<()> {
+        if !self.idle_servers.lock().await.contains_key(name) {
+            return Ok(());
+        }
+        // Concurrent calls share one restart rather than start the server twice. The
+        // idle_servers lock isn't held across it, so tool listings carry on meanwhile.
+        let host = self.clone();
+        let owned_name = name.to_string();
+        self.server_wakes.run(name, move || async move {
+            let name = owned_name.as_str();
+            if !host.idle_servers.lock().await.contains_key(name) {
+                return Ok(()); // Woken by a restart that finished just before this one began
+            }
+            let server_config = host.config.lock().await.servers.get(name).cloned()
+                .ok_or_else(|| anyhow!("Server '{}' is no longer in the config", name))?;
+            info!("Restarting idle server '{}'", name);
+            host.server_manager().start_server_from_config(name, &server_config).await?;
+            host.idle_servers.lock().await.remove(name);
+            Ok(())
+        }).await
+    }
+
+    /// Check for idle servers in the background. Stops once the host is dropped.
+    fn spawn_idle_monitor(&self) {
+        let servers = StdArc::downgrade(&self.servers);
+        // The task's copy of the host gets the server map only while checking, so it doesn't
+        // keep the host's servers alive
+        let detached = MCPHost { servers: StdArc::new(Mutex::new(HashMap::new())), ..self.clone() };
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+                let Some(servers) = servers.upgrade() else { break };
+                let host = MCPHost { servers, ..detached.clone() };
+                host.stop_idle_servers(Instant::now()).await;
+            }
+        });
+    }
+
+    /// Each server's process with its latest memory/CPU sample, sorted by name.
+    /// Processes the background monitor hasn't sampled yet are sampled now.
+    pub async fn server_status(&self) -> Vec<ServerStatus> {
+        let mut statuses: Vec<ServerStatus> = server_pids(&self.servers).await
+            .into_iter()
+            .map(|(name, pid)| {
+                let sample = pid.and_then(|pid| {
+                    self.process_monitor.latest(&name)
+                        .filter(|sample| sample.pid == pid)
+                        .or_else(|| self.process_monitor.sample(&name, pid))
+                });
+                ServerStatus { name, pid, sample }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Sample every server process in the background at the configured interval,
+    /// warning when one goes over the memory threshold. Stops once the host is dropped.
+    fn spawn_process_monitor(&self) {
+        let servers = StdArc::downgrade(&self.servers);
+        let config = StdArc::clone(&self.config);
+        let monitor = self.process_monitor.clone();
+        tokio::spawn(async move {
+            loop {
+                let settings = config.lock().await.monitor.clone();
+                // Re-read the config each round, so turning sampling back on takes effect
+                tokio::time::sleep(settings.interval().unwrap_or(Duration::from_secs(60))).await;
+                let Some(servers) = servers.upgrade() else { break };
+                if settings.interval().is_none() {
+                    continue;
+                }
+                for (name, pid) in server_pids(&servers).await {
+                    let Some(sample) = pid.and_then(|pid| monitor.sample(&name, pid)) else { continue };
+                    debug!(
+                        "Server '{}' (pid {}): {} resident, CPU {}",
+                        name,
+                        sample.pid,
+                        monitor::format_bytes(sample.rss_bytes),
+                        sample.cpu_percent.map_or("n/a".to_string(), |cpu| format!("{:.1}%", cpu))
+                    );
+                    if let Some(limit_mb) = settings.memory_warn_mb {
+                        monitor.check_threshold(&name, &sample, limit_mb * 1024 * 1024);
+                    }
+                }
+            }
+        });
+    }
+
+    /// The capabilities a running server advertised when it was initialized (tools,
+    /// resources, prompts, logging), for deciding which requests it can answer.
+    pub async fn server_capabilities(&self, server_name: &str) -> Option<rmcp::model::ServerCapabilities> {
+        self.server_manager().server_capabilities(server_name).await
+    }
+
+    /// List the resources a server offers. Servers that don't advertise resources aren't asked.
+    pub async fn list_resources(&self, server_name: &str) -> Result<Vec<rmcp::model::Resource>> {
+        self.server_manager().list_resources(server_name).await
+    }
+
+    /// Read a resource from a server. Subscribed resources are cached until the server reports an update.
+    pub async fn read_resource(&self, server_name: &str, uri: &str) -> Result<rmcp::model::ReadResourceResult> {
+        self.server_manager().read_resource(server_name, uri).await
+    }
+
+    /// Subscribe to update notifications for a resource on a server.
+    /// Updates are delivered to receivers from `resource_updates()`.
+    pub async fn subscribe_resource(&self, server_name: &str, uri: &str) -> Result<()> {
+        self.server_manager().subscribe_resource(server_name, uri).await
+    }
+
+    /// Unsubscribe from update notifications for a resource on a server.
+    pub async fn unsubscribe_resource(&self, server_name: &str, uri: &str) -> Result<()> {
+        self.server_manager().unsubscribe_resource(server_name, uri).await
+    }
+
+    /// Get a receiver for resource update notifications from all servers.
+    pub fn resource_updates(&self) -> broadcast::Receiver<ResourceUpdate> {
+        self.shared.resource_updates.subscribe()
+    }
+
+    /// Get a receiver for log messages (`notifications/message`) from all servers, such as
+    /// output a tool streams while it runs.
+    pub fn server_logs(&self) -> broadcast::Receiver<server_manager::ServerLogMessage> {
+        self.shared.server_logs.subscribe()
+    }
+
+    /// Typed annotations (readOnlyHint, destructiveHint, ...) a server reported for one of its tools.
+    /// Populated whenever the server's tools are listed.
+    pub fn tool_annotations(&self, server_name: &str, tool_name: &str) -> Option<annotations::ToolAnnotations> {
+        self.shared.tool_annotations.get(server_name, tool_name)
+    }
+
+    /// Subscribe to disconnect/reconnect notices from remote (SSE) servers.
+    pub fn connection_notices(&self) -> broadcast::Receiver<sse_transport::ConnectionNotice> {
+        self.shared.connection_notices.subscribe()
+    }
+
+    /// Request argument completions for a prompt or resource reference (`completion/complete`).
+    pub async fn complete(
+        &self,
+        server_name: &str,
+        reference: rmcp::model::Reference,
+        argument_name: &str,
+        value: &str,
+    ) -> Result<rmcp::model::CompleteResult> {
+        self.server_manager().complete(server_name, reference, argument_name, value).await
+    }
+
+    /// Ping a server and return the round-trip latency.
+    pub async fn ping_server(&self, server_name: &str) -> Result<Duration> {
+        self.server_manager().ping_server(server_name).await
+    }
+
+    /// Change a running server's log verbosity (`debug`, `info`, `warning` or `error`).
+    pub async fn set_server_log_level(&self, server_name: &str, level: &str) -> Result<()> {
+        self.server_manager().set_log_level(server_name, level).await
+    }
+
     /// List tools from all currently running servers, removing duplicates by name.
     // Update return type to use rmcp::model::Tool
     pub async fn list_all_tools(&self) -> Result<Vec<RmcpTool>> { // Use aliased type
@@ -305,12 +820,14 @@ impl MCPHost {
         let mut all_tools_map = HashMap::new(); // Use HashMap to deduplicate by name
 
         // --- Step 1: Collect Peers ---
-        let peers_to_query: Vec<(String, rmcp::service::Peer<rmcp::service::RoleClient>)> = {
+        let mut peers_to_query: Vec<(String, rmcp::service::Peer<rmcp::service::RoleClient>)> = {
             let servers_guard = self.servers.lock().await;
             servers_guard.iter()
                 .map(|(name, server)| (name.clone(), server.client.clone())) // Clone the Peer directly
                 .collect()
         }; // Lock released here
+        // Query in name order so the same server wins a name collision every time
+        peers_to_query.sort_by(|a, b| a.0.cmp(&b.0));
         debug!("Collected {} peers to query.", peers_to_query.len());
 
         // --- Step 2: Query Peers Concurrently (or sequentially) ---
@@ -334,8 +851,16 @@ impl MCPHost {
             }
         }
 
-        // --- Step 3: Collect Unique Tools ---
-        let unique_tools: Vec<_> = all_tools_map.into_values().collect();
+        // Servers stopped for being idle still offer the tools they had
+        for tools in self.idle_servers.lock().await.values() {
+            for tool in tools {
+                all_tools_map.entry(tool.name.clone()).or_insert_with(|| tool.clone());
+            }
+        }
+
+        // --- Step 3: Collect Unique Tools (sorted by name, for a stable order) ---
+        let mut unique_tools: Vec<_> = all_tools_map.into_values().collect();
+        unique_tools.sort_by(|a, b| a.name.cmp(&b.name));
         info!("Found {} unique tools across all servers.", unique_tools.len());
         Ok(unique_tools)
     }
@@ -344,10 +869,11 @@ impl MCPHost {
     /// Find the name of the server that provides a specific tool.
     pub async fn get_server_for_tool(&self, tool_name: &str) -> Result<String> {
         debug!("Searching for server providing tool: {}", tool_name);
-        let server_names = { // Scope lock
+        let mut server_names = { // Scope lock
             let servers_guard = self.servers.lock().await;
             servers_guard.keys().cloned().collect::<Vec<_>>()
         }; // Lock released
+        server_names.extend(self.idle_servers.lock().await.keys().cloned());
 
         for server_name in server_names {
             // In the future, we could check cached capabilities here first.
@@ -408,7 +934,8 @@ impl MCPHost {
         log::debug!("Generated full system prompt for single-server chat (length: {})", system_prompt.len());
 
         // Create the conversation state with the full system prompt
-        let state = crate::conversation_state::ConversationState::new(system_prompt, tool_info_list);
+        let mut state = crate::conversation_state::ConversationState::new(system_prompt, tool_info_list);
+        state.server_context = Some(server_name.to_string());
 
         // The ConversationState::new only adds the base system prompt.
         // The tool instructions might be added later or handled by the AI client builder.
@@ -444,6 +971,46 @@ impl MCPHost {
         self.ai_client.lock().await.clone()
     }
 
+    /// Install an AI client directly, bypassing provider config (for tests with scripted models)
+    #[cfg(any(test, feature = "testing"))]
+    pub async fn set_ai_client(&self, provider_name: &str, client: Arc<dyn AIClient>) {
+        *self.ai_client.lock().await = Some(client);
+        *self.active_provider_name.lock().await = Some(provider_name.to_string());
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    pub async fn clear_ai_client(&self) {
+        *self.ai_client.lock().await = None;
+        *self.active_provider_name.lock().await = None;
+    }
+
+    /// The active AI client, or an error explaining how to get one: which providers
+    /// can be selected now and which API key variables would enable the rest
+    pub async fn require_ai_client(&self) -> Result<Arc<dyn AIClient>> {
+        if let Some(client) = self.ai_client().await {
+            return Ok(client);
+        }
+        let available = self.list_available_providers().await;
+        let mut guidance = String::from("No AI provider is active.");
+        if !available.is_empty() {
+            guidance.push_str(&format!(
+                "\nProviders ready to use: {}. Select one with 'provider <name>'.",
+                available.join(", ")
+            ));
+        }
+        let missing: Vec<String> = KNOWN_PROVIDERS.iter()
+            .filter(|name| !available.iter().any(|a| a == *name))
+            .filter_map(|name| Self::get_api_key_var(name).map(|var| format!("{} ({})", var, name)))
+            .collect();
+        if !missing.is_empty() {
+            guidance.push_str(&format!(
+                "\nTo enable another provider, set its API key and restart: {}.",
+                missing.join(", ")
+            ));
+        }
+        Err(anyhow!(guidance))
+    }
+
     /// Get the name of the currently active AI provider
     pub async fn get_active_provider_name(&self) -> Option<String> {
         self.active_provider_name.lock().await.clone()
@@ -461,7 +1028,7 @@ impl MCPHost {
         }
         drop(config_guard); // Release lock
         // Check standard environment variables for providers not explicitly configured
-        for provider in ["anthropic", "openai", "deepseek", "gemini", "ollama", "xai", "phind", "groq", "openrouter"] {
+        for provider in KNOWN_PROVIDERS {
             if !available.contains(&provider.to_string()) && Self::get_api_key_for_provider(provider).is_ok() {
                  available.push(provider.to_string());
             }
@@ -471,6 +1038,32 @@ impl MCPHost {
         available
     }
 
+    /// Key, config and active state of every known or configured provider
+    pub async fn provider_status(&self) -> Vec<ProviderStatus> {
+        let available = self.list_available_providers().await;
+        let active = self.get_active_provider_name().await;
+        let configured: Vec<String> = self.config.lock().await.ai_providers.keys().cloned().collect();
+
+        let mut names: Vec<String> = KNOWN_PROVIDERS.iter().map(|p| p.to_string()).chain(configured.iter().cloned()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let key_var = Self::get_api_key_var(&name);
+                ProviderStatus {
+                    masked_key: key_var.and_then(|var| std::env::var(var).ok()).map(|key| mask_api_key(&key)),
+                    key_var,
+                    configured: configured.contains(&name),
+                    available: available.contains(&name),
+                    active: active.as_deref() == Some(name.as_str()),
+                    name,
+                }
+            })
+            .collect()
+    }
+
     /// Set the active AI provider by name.
     pub async fn set_active_provider(&self, provider_name: &str) -> Result<()> {
         info!("Attempting to set active AI provider to: {}", provider_name);
@@ -510,7 +1103,7 @@ impl MCPHost {
                         Self::get_default_model_for_provider(provider_name, &models_guard) // Pass the locked guard
                     }
                 };
-                AIProviderConfig { model: determined_model }
+                AIProviderConfig { model: determined_model, ..Default::default() }
             }
         };
 
@@ -552,10 +1145,9 @@ impl MCPHost {
             ));
         }
 
-        // Create a temporary config with the new model name
-        let temp_config = AIProviderConfig {
-            model: model_name.to_string(),
-        };
+        // Keep the provider's configured endpoint settings, with the new model name
+        let mut temp_config = self.config.lock().await.ai_providers.get(provider_name).cloned().unwrap_or_default();
+        temp_config.model = model_name.to_string();
 
         // Try to create the client with the new model
         match Self::create_ai_client_internal(provider_name, &temp_config).await {
@@ -590,6 +1182,7 @@ impl MCPHost {
             "phind" => Some("PHIND_API_KEY"),
             "groq" => Some("GROQ_API_KEY"),
             "openrouter" => Some("OPENROUTER_API_KEY"),
+            "azure" => Some("AZURE_OPENAI_API_KEY"),
             "ollama" => None, // Ollama doesn't use an API key
             _ => None,
         }
@@ -609,7 +1202,7 @@ impl MCPHost {
 
     /// Helper to get a default model name for a given provider.
     /// Prioritizes the first model listed in provider_models config, then falls back to hardcoded defaults.
-    fn get_default_model_for_provider(
+    pub(crate) fn get_default_model_for_provider(
         provider_name: &str,
         provider_models_config: &ProviderModelsConfig, // Accept models config
     ) -> String {
@@ -641,6 +1234,14 @@ impl MCPHost {
     }
 
 
+    /// What `AIClientFactory` receives for a provider: the API key plus every field
+    /// of its config entry that is set (model, base_url, ...)
+    fn factory_config(api_key: &str, config: &AIProviderConfig) -> serde_json::Value {
+        let mut factory_config = serde_json::to_value(config).unwrap_or_else(|_| serde_json::json!({}));
+        factory_config["api_key"] = api_key.into();
+        factory_config
+    }
+
      /// Internal helper to create an AI client instance.
      /// Refactored from the original builder logic.
      async fn create_ai_client_internal(provider_name: &str, config: &AIProviderConfig) -> Result<Option<Box<dyn AIClient>>> {
@@ -658,10 +1259,7 @@ impl MCPHost {
                 }
 
                 // Use AIClientFactory to create the client
-                let factory_config = serde_json::json!({
-                    "api_key": api_key,
-                    "model": model // Pass the model name from config
-                });
+                let factory_config = Self::factory_config(&api_key, config);
 
                 match AIClientFactory::create(&provider_lower, factory_config) {
                     Ok(client) => {
@@ -695,7 +1293,11 @@ pub struct MCPHostBuilder {
     provider_models_path: Option<PathBuf>, // Added path for provider models config
     // Removed ai_provider_configs and default_ai_provider
     request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    max_message_bytes: Option<usize>,
     client_info: Option<RmcpImplementation>, // Use aliased type
+    interceptors: middleware::Interceptors,
+    env: fn(&str) -> Option<String>, // Reads the environment; replaced in tests
 }
 
 impl MCPHostBuilder {
@@ -705,7 +1307,11 @@ impl MCPHostBuilder {
             config_path: None,
             provider_models_path: None, // Initialize new path
             request_timeout: None,
+            connect_timeout: None,
+            max_message_bytes: None,
             client_info: None,
+            interceptors: middleware::Interceptors::new(),
+            env: |name| std::env::var(name).ok(),
         }
     }
 
@@ -729,6 +1335,20 @@ impl MCPHostBuilder {
         self
     }
 
+    /// Set how long a server may take to start and finish the MCP handshake before it is
+    /// given up on. Separate from the request timeout, so a server that never comes up
+    /// fails quickly while slow requests still get their full time.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum size of a single message read from a server
+    pub fn max_message_bytes(mut self, bytes: usize) -> Self {
+        self.max_message_bytes = Some(bytes);
+        self
+    }
+
     /// Set the client info
     pub fn client_info(mut self, name: &str, version: &str) -> Self {
         self.client_info = Some(RmcpImplementation { // Use aliased type
@@ -740,6 +1360,19 @@ impl MCPHostBuilder {
         self
     }
 
+    /// Run `hook` on every message sent to a server, e.g. to add an auth token to params.
+    /// Hooks run in the order they were added.
+    pub fn on_request(mut self, hook: impl Fn(&str, &mut serde_json::Value) + Send + Sync + 'static) -> Self {
+        self.interceptors = self.interceptors.on_request(hook);
+        self
+    }
+
+    /// Run `hook` on every message received from a server, before it is parsed
+    pub fn on_response(mut self, hook: impl Fn(&str, &mut serde_json::Value) + Send + Sync + 'static) -> Self {
+        self.interceptors = self.interceptors.on_response(hook);
+        self
+    }
+
     /// Build the MCPHost
     pub async fn build(self) -> Result<MCPHost> {
         // --- Configuration Loading ---
@@ -752,16 +1385,27 @@ impl MCPHostBuilder {
         });
         info!("Using main config path: {:?}", config_path); // Log the determined main config path
 
+        // Without a config file the environment may configure the host instead
+        let env_config = if config_path.exists() {
+            None
+        } else {
+            HostConfig::from_vars(self.env)?
+        };
+        let from_env = env_config.is_some();
+
         // Load initial config or create default
-        let initial_config = match HostConfig::load(&config_path).await {
-             Ok(cfg) => {
-                 info!("Loaded initial main config from {:?}", config_path);
-                 cfg
-             },
-             Err(e) => {
-                 warn!("Failed to load config from {:?}: {}. Using default.", config_path, e);
-                 HostConfig::default()
-             }
+        let mut initial_config = match env_config {
+            Some(cfg) => cfg,
+            None => match HostConfig::load(&config_path).await {
+                Ok(cfg) => {
+                    info!("Loaded initial main config from {:?}", config_path);
+                    cfg
+                },
+                Err(e) => {
+                    warn!("Failed to load config from {:?}: {}. Using default.", config_path, e);
+                    HostConfig::default()
+                }
+            },
         };
 
         // Determine provider models config path
@@ -776,6 +1420,16 @@ impl MCPHostBuilder {
         // Load provider models config
         let provider_models_config = ProviderModelsConfig::load(&provider_models_path).await;
 
+        // A provider named in the environment without a model gets its default one
+        if from_env {
+            if let Some(provider) = initial_config.default_ai_provider.clone() {
+                let provider_config = initial_config.ai_providers.entry(provider.clone()).or_default();
+                if provider_config.model.is_empty() {
+                    provider_config.model = MCPHost::get_default_model_for_provider(&provider, &provider_models_config);
+                }
+            }
+        }
+
         // --- Client Info ---
         let client_info = self.client_info.unwrap_or_else(|| RmcpImplementation { // Use aliased type
             name: "mcp-host".to_string().into(), // Convert to Cow
@@ -785,6 +1439,8 @@ impl MCPHostBuilder {
 
         // --- Timeouts ---
         let request_timeout = self.request_timeout.unwrap_or(Duration::from_secs(120));
+        let connect_timeout = self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+        let max_message_bytes = self.max_message_bytes.unwrap_or(transport::DEFAULT_MAX_MESSAGE_BYTES);
 
         // --- Initialize Core Host Structure (without servers started yet) ---
         let host_servers_map = StdArc::new(Mutex::new(HashMap::new()));
@@ -792,24 +1448,27 @@ impl MCPHostBuilder {
             servers: StdArc::clone(&host_servers_map),
             client_info: client_info.clone(), // Clone for the host instance
             request_timeout,
+            connect_timeout,
+            max_message_bytes,
             config: StdArc::new(Mutex::new(initial_config.clone())), // Store loaded config
             config_path: StdArc::new(Mutex::new(Some(config_path))),
             provider_models: StdArc::new(Mutex::new(provider_models_config.clone())), // Store loaded models
             provider_models_path: StdArc::new(Mutex::new(provider_models_path)),
             active_provider_name: StdArc::new(Mutex::new(None)), // Start with no active provider name
             ai_client: StdArc::new(Mutex::new(None)), // Start with no active client
+            shared: server_manager::SharedServerState::new(self.interceptors),
+            model_cache: StdArc::new(Mutex::new(HashMap::new())),
+            model_fetches: single_flight::SingleFlight::new(),
+            process_monitor: monitor::ProcessMonitor::new(),
+            idle_servers: StdArc::new(Mutex::new(HashMap::new())),
+            server_wakes: single_flight::SingleFlight::new(),
         };
 
-        // --- Start Initial Servers Defined in Config ---
         // --- Start Initial Servers Defined in Config ---
         // We need to call start_server_with_components directly on the host instance
         // after it's fully constructed but before returning it.
         // We'll do this after initializing the AI client.
 
-        // --- Determine Initial AI Provider ---
-        // (This logic remains largely the same, but operates on the created host instance's fields)
-
-
         // --- Determine Initial AI Provider ---
         // (This logic remains largely the same, but operates on the created host instance's fields)
         let mut initial_ai_client: Option<Arc<dyn AIClient>> = None;
@@ -846,7 +1505,7 @@ impl MCPHostBuilder {
                          // If not in main config, get default model from provider_models_config
                          let default_model = MCPHost::get_default_model_for_provider(provider_name, &provider_models_config);
                          debug!("Using default model '{}' from provider_models for initial check of provider '{}'", default_model, provider_name);
-                         AIProviderConfig { model: default_model }
+                         AIProviderConfig { model: default_model, ..Default::default() }
                      });
 
                  // Try creating the client with the determined config
@@ -880,11 +1539,8 @@ impl MCPHostBuilder {
         let mut servers_started_successfully = 0;
         for (name, server_config) in &config_for_startup.servers {
             info!("Attempting initial start for server '{}'", name);
-            let program = &server_config.command;
-            let args = server_config.args.as_deref().unwrap_or(&[]); // Get args slice
-            let envs = &server_config.env;
             // Call the method on the host instance itself
-            match host.server_manager().start_server_with_components(name, program, args, envs).await {
+            match host.server_manager().start_server_from_config(name, server_config).await {
                  Ok(_) => {
                      info!("Successfully started initial server '{}'", name);
                      servers_started_successfully += 1;
@@ -899,7 +1555,532 @@ impl MCPHostBuilder {
                servers_started_successfully, config_for_startup.servers.len());
 
 
+        host.spawn_process_monitor();
+        host.spawn_idle_monitor();
+
         info!("MCPHost build complete.");
         Ok(host) // Return the fully initialized host
     }
 }
+
+/// Each server's name and process id (None for remote servers)
+async fn server_pids(servers: &Mutex<HashMap<String, ManagedServer>>) -> Vec<(String, Option<u32>)> {
+    let processes: Vec<(String, Option<Arc<Mutex<tokio::process::Child>>>)> = servers.lock().await
+        .iter()
+        .map(|(name, server)| (name.clone(), server.process.clone()))
+        .collect();
+    let mut pids = Vec::with_capacity(processes.len());
+    for (name, process) in processes {
+        let pid = match process {
+            Some(process) => process.lock().await.id(),
+            None => None,
+        };
+        pids.push((name, pid));
+    }
+    pids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_ai_client::FakeAIClient;
+    use crate::host::config::ServerConfig;
+    use crate::host::test_support::{insert_mock_server, test_dir, test_host, test_host_in};
+
+    /// Answers `initialize` (echoing its id) and then idles, standing in for a stdio server
+    const FAKE_SERVER: &str = r#"read -r line
+id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{},"serverInfo":{"name":"fake","version":"0"}}}\n' "$id"
+exec cat >/dev/null"#;
+
+    /// Answers `initialize`, `tools/list` (one `whoami` tool) and `tools/call` (with its pid)
+    const FAKE_TOOL_SERVER: &str = r#"while read -r line; do
+id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+case "$line" in
+*'"initialize"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"fake","version":"0"}}}\n' "$id" ;;
+*'"tools/list"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"whoami","description":"Report the pid","inputSchema":{"type":"object"}}]}}\n' "$id" ;;
+*'"tools/call"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"%s"}]}}\n' "$id" "$$" ;;
+esac
+done"#;
+
+    fn profile(servers: &[&str]) -> HostConfig {
+        let mut config = HostConfig::default();
+        for name in servers {
+            config.servers.insert(name.to_string(), ServerConfig {
+                command: "sh".to_string(),
+                url: None,
+                headers: HashMap::new(),
+                bearer_token_env: None,
+                env: HashMap::new(),
+                args: Some(vec!["-c".to_string(), FAKE_SERVER.to_string()]),
+                idle_timeout: None,
+                default_arguments: HashMap::new(),
+                continuation_tools: Vec::new(),
+                isolate_env: false,
+            });
+        }
+        config
+    }
+
+    async fn running(host: &MCPHost) -> Vec<String> {
+        let mut names: Vec<String> = host.servers.lock().await.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_switching_profiles_swaps_servers() {
+        let dir = test_dir();
+        let path_a = config::profile_config_path(&dir, "a").unwrap();
+        profile(&["alpha", "shared"]).save(&path_a).await.unwrap();
+        profile(&["beta", "shared"]).save(config::profile_config_path(&dir, "b").unwrap()).await.unwrap();
+        assert_eq!(config::list_profiles(&dir), vec!["a", "b"]);
+
+        let host = MCPHost::builder()
+            .config_path(path_a)
+            .provider_models_path(dir.join("provider_models.toml"))
+            .build()
+            .await
+            .expect("failed to build host");
+        assert_eq!(running(&host).await, vec!["alpha", "shared"]);
+        let shared_before = host.servers.lock().await["shared"].process.clone().unwrap();
+
+        let (path_b, report) = host.switch_profile("b").await.unwrap();
+        assert_eq!(report.started, vec!["beta"]);
+        assert_eq!(running(&host).await, vec!["beta", "shared"]);
+        assert_eq!(config::profile_name(&path_b).as_deref(), Some("b"));
+        assert_eq!(host.config_path.lock().await.as_deref(), Some(path_b.as_path()));
+        // A server defined the same way in both profiles keeps running
+        let shared_after = host.servers.lock().await["shared"].process.clone().unwrap();
+        assert!(Arc::ptr_eq(&shared_before, &shared_after));
+
+        assert!(host.switch_profile("missing").await.is_err());
+        assert!(host.switch_profile("../a").await.is_err());
+        assert_eq!(running(&host).await, vec!["beta", "shared"]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_content_follows_model_vision() {
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        let mock = MockTransport::new()
+            .respond("tools/list", json!({ "tools": [{
+                "name": "mermaid_chart",
+                "description": "Draw a chart of the given files",
+                "inputSchema": { "type": "object", "properties": {
+                    "files": { "type": "string" },
+                    "content_type": { "type": "string" }
+                }}
+            }]}))
+            .respond("tools/call", json!({ "content": [
+                { "type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png" },
+                { "type": "text", "text": "graph TD; A-->B" }
+            ]}));
+        let handle = insert_mock_server(&host, "charts", mock).await;
+
+        let vision = crate::ai_client::ModelCapabilities { supports_vision: true, ..Default::default() };
+        host.set_ai_client("vision", Arc::new(FakeAIClient::new().capabilities(vision))).await;
+        let result = host.call_tool_structured("charts", "mermaid_chart", json!({ "files": "a.rs" })).await.unwrap();
+        assert!(matches!(result.content[0].raw, rmcp::model::RawContent::Image(_)));
+
+        host.set_ai_client("text", Arc::new(FakeAIClient::new())).await;
+        let output = host.call_tool("charts", "mermaid_chart", json!({ "files": "a.rs" })).await.unwrap();
+        assert!(output.starts_with("[image/png image omitted"), "{}", output);
+        assert!(output.contains("graph TD; A-->B"));
+
+        let requested: Vec<_> = handle.requests("tools/call").iter().map(|r| r["params"]["arguments"]["content_type"].clone()).collect();
+        assert_eq!(requested, vec![json!("image"), json!("text")]);
+        // The tool's definition was listed once, not once per call
+        assert_eq!(handle.requests("tools/list").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_rejected_before_call() {
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        let mock = MockTransport::new()
+            .respond("tools/list", json!({ "tools": [{
+                "name": "bash",
+                "description": "Run a command",
+                "inputSchema": { "type": "object", "required": ["command"], "properties": {
+                    "command": { "type": "string" },
+                    "shell": { "type": "string", "enum": ["sh", "bash"] }
+                }}
+            }]}))
+            .respond("tools/call", json!({ "content": [{ "type": "text", "text": "ok" }] }));
+        let handle = insert_mock_server(&host, "shell", mock).await;
+
+        let err = host.call_tool("shell", "bash", json!({ "shell": "zsh" })).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid arguments for tool 'bash': missing required field 'command'; field 'shell' must be one of \"sh\", \"bash\" but got \"zsh\""
+        );
+        assert!(host.call_tool("shell", "bash", serde_json::Value::Null).await.is_err());
+        assert!(handle.requests("tools/call").is_empty());
+
+        assert_eq!(host.call_tool("shell", "bash", json!({ "command": "ls" })).await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_default_arguments_merged_into_calls() {
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        host.config.lock().await.servers.insert("shell".to_string(), ServerConfig {
+            command: "unused".to_string(),
+            url: None,
+            headers: HashMap::new(),
+            bearer_token_env: None,
+            env: HashMap::new(),
+            args: None,
+            idle_timeout: None,
+            default_arguments: HashMap::from([(
+                "bash".to_string(),
+                json!({ "working_dir": "/srv/app", "timeout": 30 }).as_object().cloned().unwrap(),
+            )]),
+            continuation_tools: Vec::new(),
+            isolate_env: false,
+        });
+        let mock = MockTransport::new().respond("tools/call", json!({ "content": [{ "type": "text", "text": "ok" }] }));
+        let handle = insert_mock_server(&host, "shell", mock).await;
+
+        host.call_tool("shell", "bash", json!({ "command": "ls" })).await.unwrap();
+        host.call_tool("shell", "bash", json!({ "command": "ls", "working_dir": "/tmp" })).await.unwrap();
+        // Other tools on the server get none
+        host.call_tool("shell", "python", json!({ "code": "1" })).await.unwrap();
+
+        let sent: Vec<_> = handle.requests("tools/call").iter().map(|r| r["params"]["arguments"].clone()).collect();
+        assert_eq!(sent, vec![
+            json!({ "command": "ls", "working_dir": "/srv/app", "timeout": 30 }),
+            json!({ "command": "ls", "working_dir": "/tmp", "timeout": 30 }),
+            json!({ "code": "1" }),
+        ]);
+    }
+
+    #[cfg(feature = "otel")]
+    #[tokio::test]
+    async fn test_tool_call_emits_spans() {
+        use crate::host::mock_transport::MockTransport;
+        use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+        use serde_json::json;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry().with(crate::telemetry::layer(&provider)));
+
+        let host = test_host().await;
+        let mock = MockTransport::new()
+            .interceptors("tools", host.server_manager().interceptors.clone())
+            .respond("tools/call", json!({ "content": [{ "type": "text", "text": "hi" }] }));
+        let handle = insert_mock_server(&host, "tools", mock).await;
+
+        host.call_tool("tools", "echo", json!({ "text": "hi" })).await.unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let call_tool = spans.iter().find(|span| span.name == "call_tool").expect("no call_tool span");
+        let call = spans.iter().find(|span| span.name == "call").expect("no call span");
+        assert_eq!(call.parent_span_id, call_tool.span_context.span_id());
+        let trace_id = call_tool.span_context.trace_id();
+        assert_eq!(call.span_context.trace_id(), trace_id);
+
+        // The request reaches the server with the call's trace context in `_meta`, and its
+        // arguments as given
+        let request = handle.requests("tools/call").remove(0);
+        assert_eq!(request["params"]["arguments"], json!({ "text": "hi" }));
+        let traceparent = request["params"]["_meta"]["traceparent"].as_str().unwrap();
+        assert_eq!(traceparent, format!("00-{}-{}-01", trace_id, call.span_context.span_id()));
+    }
+
+    #[tokio::test]
+    async fn test_slow_tool_call_times_out() {
+        use crate::host::error::HostError;
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        host.config.lock().await.timeouts.tool = 1;
+        let mock = MockTransport::new()
+            .respond("tools/list", json!({ "tools": [] }))
+            .hang("tools/call");
+        let handle = insert_mock_server(&host, "slow", mock).await;
+
+        let err = host.call_tool("slow", "sleep", json!({})).await.unwrap_err();
+        match err.downcast_ref::<HostError>() {
+            Some(HostError::ToolTimeout { tool, server, elapsed, timeout }) => {
+                assert_eq!((tool.as_str(), server.as_str()), ("sleep", "slow"));
+                assert_eq!(*timeout, Duration::from_secs(1));
+                assert!(*elapsed >= *timeout, "{:?}", elapsed);
+            }
+            other => panic!("expected a tool timeout, got {:?}", other),
+        }
+        assert!(err.to_string().starts_with("Tool 'sleep' on server 'slow' timed out after 1."), "{}", err);
+        assert!(err.to_string().ends_with("(tool timeout is 1s)"), "{}", err);
+        assert_eq!(handle.requests("tools/call").len(), 1);
+
+        // The server is told to stop the call it was abandoned on
+        let request_id = handle.requests("tools/call")[0]["id"].clone();
+        let cancelled = async {
+            loop {
+                let sent = handle.notifications();
+                if let Some(cancel) = sent.iter().find(|n| n["method"] == "notifications/cancelled") {
+                    return cancel.clone();
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let cancel = tokio::time::timeout(Duration::from_secs(2), cancelled).await.expect("no cancellation sent");
+        assert_eq!(cancel["params"]["requestId"], request_id);
+        assert_eq!(cancel["params"]["reason"], server_manager::CALL_ABANDONED_REASON);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_tool_lists_share_one_request() {
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        let mock = MockTransport::new().respond(
+            "tools/list",
+            json!({ "tools": [{ "name": "bash", "description": "Run a command", "inputSchema": { "type": "object" } }] }),
+        );
+        let handle = insert_mock_server(&host, "shell", mock).await;
+
+        let (first, second) = tokio::join!(host.list_server_tools("shell"), host.list_server_tools("shell"));
+        assert_eq!(first.unwrap(), second.unwrap());
+        assert_eq!(handle.requests("tools/list").len(), 1);
+
+        // Once it has finished, the next call asks the server again
+        assert_eq!(host.list_server_tools("shell").await.unwrap().len(), 1);
+        assert_eq!(handle.requests("tools/list").len(), 2);
+    }
+
+    #[test]
+    fn test_configured_base_url_reaches_factory() {
+        let config: AIProviderConfig = serde_json::from_value(serde_json::json!({
+            "model": "Qwen/Qwen2.5-7B-Instruct",
+            "base_url": "http://localhost:8000/v1"
+        })).unwrap();
+        let factory_config = MCPHost::factory_config("local-key", &config);
+        assert_eq!(factory_config["base_url"], "http://localhost:8000/v1");
+        assert_eq!(factory_config["model"], "Qwen/Qwen2.5-7B-Instruct");
+        assert_eq!(factory_config["api_key"], "local-key");
+        assert!(factory_config.get("endpoint").is_none());
+
+        // Providers without one keep their default API root
+        let factory_config = MCPHost::factory_config("key", &AIProviderConfig::default());
+        assert!(factory_config.get("base_url").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_build_activates_default_provider_once() {
+        let dir = test_dir();
+        let mut config = HostConfig { default_ai_provider: Some("ollama".to_string()), ..Default::default() };
+        config.ai_providers.insert("ollama".to_string(), AIProviderConfig { model: "llama3".to_string(), ..Default::default() });
+        config.save(dir.join("config.json")).await.unwrap();
+
+        let host = test_host_in(&dir).await;
+
+        assert_eq!(host.get_active_provider_name().await.as_deref(), Some("ollama"));
+        let client = host.ai_client().await.expect("default provider should be active");
+        assert_eq!(client.model_name(), "llama3");
+        // The builder hands its client to the host and keeps no other reference
+        assert_eq!(Arc::strong_count(&client), 2);
+    }
+
+    #[tokio::test]
+    async fn test_env_configures_host_without_config_file() {
+        let dir = test_dir();
+        let mut builder = MCPHost::builder()
+            .config_path(dir.join("config.json"))
+            .provider_models_path(dir.join("provider_models.toml"));
+        builder.env = |name| match name {
+            config::DEFAULT_PROVIDER_ENV => Some("ollama".to_string()),
+            config::SERVERS_ENV => Some(
+                serde_json::json!([{ "name": "env-server", "command": "sh", "args": ["-c", FAKE_SERVER] }]).to_string(),
+            ),
+            _ => None,
+        };
+        let host = builder.build().await.expect("failed to build host");
+
+        assert_eq!(running(&host).await, vec!["env-server"]);
+        assert_eq!(host.get_active_provider_name().await.as_deref(), Some("ollama"));
+        // No model given, so the provider's default is used
+        assert_eq!(host.ai_client().await.unwrap().model_name(), "llama3");
+        assert!(!dir.join("config.json").exists(), "an env-only host shouldn't write a config file");
+
+        let err = HostConfig::from_vars(|name| (name == config::SERVERS_ENV).then(|| "{\"name\": \"x\"}".to_string())).unwrap_err();
+        assert!(err.to_string().starts_with("MCP_SERVERS is not a JSON array of servers"), "{}", err);
+        assert!(HostConfig::from_vars(|_| None).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_server_without_command_or_url_is_rejected() {
+        let dir = test_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{ "mcpServers": { "tools": { "command": " " } } }"#).unwrap();
+        let err = HostConfig::load(&path).await.unwrap_err();
+        assert_eq!(err.to_string(), "Server 'tools' needs a non-empty 'command' or a 'url'");
+
+        // A remote server needs no command
+        std::fs::write(&path, r#"{ "mcpServers": { "remote": { "url": "http://localhost:8080/sse" } } }"#).unwrap();
+        assert!(HostConfig::load(&path).await.is_ok());
+
+        let err = HostConfig::from_vars(|name| (name == config::SERVERS_ENV).then(|| r#"[{ "name": "x" }]"#.to_string())).unwrap_err();
+        assert_eq!(err.to_string(), "MCP_SERVERS: Server 'x' needs a non-empty 'command' or a 'url'");
+    }
+
+    #[tokio::test]
+    async fn test_isolated_server_does_not_see_host_env() {
+        let dir = test_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        // Stands in for a secret in the host's environment; cargo sets it for every test run
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR for tests");
+        let host = test_host_in(&dir).await;
+
+        // Each server writes its environment to a file before answering the handshake
+        let mut config = profile(&["isolated", "inheriting"]);
+        for (name, server) in config.servers.iter_mut() {
+            server.args = Some(vec!["-c".to_string(), format!("env > \"$ENV_DUMP\"\n{}", FAKE_SERVER)]);
+            server.env.insert("ENV_DUMP".to_string(), dir.join(format!("{}.env", name)).display().to_string());
+            server.isolate_env = name == "isolated";
+        }
+        host.apply_config(config).await.unwrap();
+
+        let isolated = std::fs::read_to_string(dir.join("isolated.env")).unwrap();
+        assert!(!isolated.contains("CARGO_MANIFEST_DIR"), "{}", isolated);
+        assert!(isolated.lines().any(|line| line.starts_with("ENV_DUMP=")), "{}", isolated);
+        assert!(isolated.lines().any(|line| line.starts_with("PATH=")), "{}", isolated);
+        let inheriting = std::fs::read_to_string(dir.join("inheriting.env")).unwrap();
+        assert!(inheriting.contains(&format!("CARGO_MANIFEST_DIR={}", manifest_dir)), "{}", inheriting);
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_reports_failed_servers() {
+        let dir = test_dir();
+        let host = test_host_in(&dir).await;
+
+        let mut config = profile(&["good"]);
+        config.servers.insert("broken".to_string(), ServerConfig {
+            command: dir.join("no-such-server").display().to_string(),
+            url: None,
+            headers: HashMap::new(),
+            bearer_token_env: None,
+            env: HashMap::new(),
+            args: None,
+            idle_timeout: None,
+            default_arguments: HashMap::new(),
+            continuation_tools: Vec::new(),
+            isolate_env: false,
+        });
+        let report = host.apply_config(config).await.expect("apply_config itself succeeds");
+
+        assert_eq!(report.started, vec!["good"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "broken");
+        assert!(!report.failed[0].1.is_empty());
+        assert!(!report.all_started());
+        assert!(report.failure_summary().starts_with("Server 'broken' failed to start: "), "{}", report.failure_summary());
+        assert_eq!(running(&host).await, vec!["good"]);
+    }
+
+    #[tokio::test]
+    async fn test_idle_server_is_stopped_and_restarted_on_next_call() {
+        let host = test_host().await;
+        let mut config = profile(&["busy"]);
+        config.servers.insert("rare".to_string(), ServerConfig {
+            args: Some(vec!["-c".to_string(), FAKE_TOOL_SERVER.to_string()]),
+            idle_timeout: Some(5),
+            default_arguments: HashMap::new(),
+            continuation_tools: Vec::new(),
+            ..config.servers["busy"].clone()
+        });
+        host.apply_config(config).await.unwrap();
+        let first_pid = host.call_tool("rare", "whoami", serde_json::json!({})).await.unwrap();
+
+        // Not idle long enough yet
+        assert!(host.stop_idle_servers(Instant::now() + Duration::from_secs(60)).await.is_empty());
+        assert_eq!(running(&host).await, vec!["busy", "rare"]);
+
+        // Servers without an idle timeout keep running
+        let later = Instant::now() + Duration::from_secs(6 * 60);
+        assert_eq!(host.stop_idle_servers(later).await, vec!["rare"]);
+        assert_eq!(running(&host).await, vec!["busy"]);
+        // Its tools are still offered while it is stopped
+        let tools = host.list_server_tools("rare").await.unwrap();
+        assert_eq!(tools.iter().map(|tool| tool.name.as_ref()).collect::<Vec<_>>(), vec!["whoami"]);
+        assert_eq!(host.get_server_for_tool("whoami").await.unwrap(), "rare");
+
+        // The next call starts it again
+        let second_pid = host.call_tool("rare", "whoami", serde_json::json!({})).await.unwrap();
+        assert_ne!(first_pid, second_pid);
+        assert_eq!(running(&host).await, vec!["busy", "rare"]);
+        assert!(host.stop_idle_servers(Instant::now() + Duration::from_secs(60)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_server_with_a_call_in_flight_is_not_stopped() {
+        use crate::host::mock_transport::MockTransport;
+        use serde_json::json;
+
+        let host = test_host().await;
+        host.config.lock().await.servers.insert("slow".to_string(), ServerConfig {
+            command: "unused".to_string(),
+            url: None,
+            headers: HashMap::new(),
+            bearer_token_env: None,
+            env: HashMap::new(),
+            args: None,
+            idle_timeout: Some(5),
+            default_arguments: HashMap::new(),
+            continuation_tools: Vec::new(),
+            isolate_env: false,
+        });
+        let mock = MockTransport::new()
+            .respond("tools/list", json!({ "tools": [] }))
+            .hang("tools/call");
+        let handle = insert_mock_server(&host, "slow", mock).await;
+
+        let call = tokio::spawn({
+            let host = host.clone();
+            async move { host.call_tool("slow", "sleep", json!({})).await }
+        });
+        let sent = async {
+            while handle.requests("tools/call").is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(2), sent).await.expect("the call was never sent");
+
+        // Long past the idle timeout, but the call is still running
+        let later = Instant::now() + Duration::from_secs(6 * 60);
+        assert!(host.stop_idle_servers(later).await.is_empty());
+        assert_eq!(running(&host).await, vec!["slow"]);
+        call.abort();
+    }
+
+    #[tokio::test]
+    async fn test_server_status_reports_child_memory() {
+        let host = test_host().await;
+        host.apply_config(profile(&["worker"])).await.unwrap();
+
+        let status = host.server_status().await;
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].name, "worker");
+        let pid = status[0].pid.expect("stdio servers have a pid");
+        let sample = status[0].sample.clone().expect("a running child can be sampled");
+        assert_eq!(sample.pid, pid);
+        assert!(sample.rss_bytes > 0);
+
+        // Later calls reuse the latest sample rather than reading the process again
+        assert_eq!(host.server_status().await[0].sample, Some(sample));
+        host.stop_server("worker").await.unwrap();
+        assert!(host.server_status().await.is_empty());
+    }
+}
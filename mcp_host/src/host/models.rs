@@ -0,0 +1,149 @@
+// Live model listing from provider APIs, used alongside the static provider_models.toml
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Models fetched from provider APIs this session, keyed by lowercase provider name
+pub type ModelCache = Arc<Mutex<HashMap<String, Vec<String>>>>;
+
+/// How long to wait on a provider's models endpoint before giving up
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Base URL of a provider's API, if we know how to list its models.
+/// Ollama honours `OLLAMA_HOST`; everything else uses the public endpoint.
+pub fn models_base_url(provider: &str) -> Option<String> {
+    let url = match provider.to_lowercase().as_str() {
+        "openai" => "https://api.openai.com/v1".to_string(),
+        "groq" => "https://api.groq.com/openai/v1".to_string(),
+        "deepseek" => "https://api.deepseek.com/v1".to_string(),
+        "xai" | "grok" => "https://api.x.ai/v1".to_string(),
+        "openrouter" => "https://openrouter.ai/api/v1".to_string(),
+        "ollama" => std::env::var("OLLAMA_HOST")
+            .ok()
+            .filter(|host| !host.is_empty())
+            .map(|host| if host.starts_with("http") { host } else { format!("http://{}", host) })
+            .unwrap_or_else(|| "http://localhost:11434".to_string()),
+        _ => return None,
+    };
+    Some(url)
+}
+
+/// Query a provider's models endpoint and return the model ids, sorted.
+/// Ollama uses `GET /api/tags`; the rest speak the OpenAI-style `GET /models`.
+pub async fn fetch_models(
+    http: &reqwest::Client,
+    provider: &str,
+    base_url: &str,
+    api_key: Option<&str>,
+) -> Result<Vec<String>> {
+    let base_url = base_url.trim_end_matches('/');
+    let is_ollama = provider.eq_ignore_ascii_case("ollama");
+    let url = if is_ollama { format!("{}/api/tags", base_url) } else { format!("{}/models", base_url) };
+
+    let mut request = http.get(&url).timeout(FETCH_TIMEOUT);
+    if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+        request = request.bearer_auth(key);
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned HTTP {}", url, response.status()));
+    }
+    let body: Value = response.json().await?;
+
+    // Ollama: {"models": [{"name": ...}]}, OpenAI-style: {"data": [{"id": ...}]}
+    let (list_key, name_key) = if is_ollama { ("models", "name") } else { ("data", "id") };
+    let entries = body[list_key]
+        .as_array()
+        .ok_or_else(|| anyhow!("Unexpected response from {}: missing '{}' array", url, list_key))?;
+
+    let mut models: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| entry[name_key].as_str().map(str::to_string))
+        .collect();
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// Configured models first (in config order), followed by any fetched models not already listed
+pub fn merge_models(configured: &[String], fetched: &[String]) -> Vec<String> {
+    let mut merged = configured.to_vec();
+    for model in fetched {
+        if !merged.contains(model) {
+            merged.push(model.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fetch_openai_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("authorization", "Bearer sk-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [
+                    { "id": "gpt-4o", "object": "model" },
+                    { "id": "gpt-4.1", "object": "model" }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let base_url = format!("{}/v1", server.uri());
+        let models = fetch_models(&reqwest::Client::new(), "openai", &base_url, Some("sk-test")).await.unwrap();
+        assert_eq!(models, vec!["gpt-4.1", "gpt-4o"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_ollama_models() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/tags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [
+                    { "name": "llama3:latest", "size": 1 },
+                    { "name": "qwen2.5-coder:7b", "size": 2 }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let models = fetch_models(&reqwest::Client::new(), "ollama", &server.uri(), None).await.unwrap();
+        assert_eq!(models, vec!["llama3:latest", "qwen2.5-coder:7b"]);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_models_error_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let err = fetch_models(&reqwest::Client::new(), "openai", &server.uri(), Some("bad")).await.unwrap_err();
+        assert!(err.to_string().contains("401"), "{}", err);
+    }
+
+    #[test]
+    fn test_merge_models_keeps_config_order() {
+        let configured = vec!["gpt-4o-mini".to_string(), "gpt-4o".to_string()];
+        let fetched = vec!["gpt-4.1".to_string(), "gpt-4o".to_string()];
+        assert_eq!(merge_models(&configured, &fetched), vec!["gpt-4o-mini", "gpt-4o", "gpt-4.1"]);
+    }
+}
@@ -0,0 +1,185 @@
+// Periodic sampling of each stdio server's memory and CPU use, so a server that leaks over a
+// long session shows up in `server_status()` and the log. Remote (SSE) servers have no local
+// process to sample.
+
+use log::{info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Memory and CPU use of a server process at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessSample {
+    pub pid: u32,
+    /// Resident set size
+    pub rss_bytes: u64,
+    /// Percent of one CPU used since the previous sample; None for a process's first sample
+    pub cpu_percent: Option<f64>,
+    /// CPU time (user + system) the process has used in total
+    pub cpu_time: Duration,
+    pub taken_at: Instant,
+}
+
+/// The latest sample of each server's process
+#[derive(Debug, Clone, Default)]
+pub struct ProcessMonitor {
+    samples: Arc<Mutex<HashMap<String, ProcessSample>>>,
+    /// Servers currently over the memory threshold, so the warning is logged once per crossing
+    over_threshold: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn latest(&self, server: &str) -> Option<ProcessSample> {
+        self.samples.lock().unwrap().get(server).cloned()
+    }
+
+    /// Sample `server`'s process now, working out CPU use since its previous sample.
+    /// None if the process can't be read (e.g. it has exited).
+    pub fn sample(&self, server: &str, pid: u32) -> Option<ProcessSample> {
+        let (rss_bytes, cpu_time) = read_usage(pid)?;
+        let taken_at = Instant::now();
+        let mut samples = self.samples.lock().unwrap();
+        // A restarted server has a new pid; its CPU time starts over
+        let cpu_percent = samples.get(server).filter(|previous| previous.pid == pid).and_then(|previous| {
+            let wall = taken_at.duration_since(previous.taken_at).as_secs_f64();
+            (wall > 0.0).then(|| cpu_time.saturating_sub(previous.cpu_time).as_secs_f64() / wall * 100.0)
+        });
+        let sample = ProcessSample { pid, rss_bytes, cpu_percent, cpu_time, taken_at };
+        samples.insert(server.to_string(), sample.clone());
+        Some(sample)
+    }
+
+    /// Warn when `server`'s memory goes over `limit_bytes`, and note when it drops back.
+    /// Returns true only for the sample that crosses the limit.
+    pub fn check_threshold(&self, server: &str, sample: &ProcessSample, limit_bytes: u64) -> bool {
+        let mut over = self.over_threshold.lock().unwrap();
+        if sample.rss_bytes > limit_bytes {
+            if over.insert(server.to_string()) {
+                warn!(
+                    "Server '{}' (pid {}) is using {} of memory, over the {} threshold",
+                    server, sample.pid, format_bytes(sample.rss_bytes), format_bytes(limit_bytes)
+                );
+                return true;
+            }
+        } else if over.remove(server) {
+            info!("Server '{}' memory back under the threshold: {}", server, format_bytes(sample.rss_bytes));
+        }
+        false
+    }
+
+    /// Forget a server's samples, e.g. when it is stopped
+    pub fn remove_server(&self, server: &str) {
+        self.samples.lock().unwrap().remove(server);
+        self.over_threshold.lock().unwrap().remove(server);
+    }
+}
+
+/// Bytes as a short human-readable size, e.g. "1.5 GB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Resident memory and total CPU time of `pid`, read from /proc
+#[cfg(target_os = "linux")]
+fn read_usage(pid: u32) -> Option<(u64, Duration)> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let rss_kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Skip past the command name, which is in parentheses and may contain spaces;
+    // utime and stime (fields 14 and 15) are then at indexes 11 and 12
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let ticks = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    // Clock ticks are USER_HZ, which is 100 on every Linux platform
+    Some((rss_kb * 1024, Duration::from_millis(ticks * 10)))
+}
+
+/// Resident memory and total CPU time of `pid`, as reported by ps
+#[cfg(not(target_os = "linux"))]
+fn read_usage(pid: u32) -> Option<(u64, Duration)> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=,time=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut fields = output.split_whitespace();
+    let rss_kb: u64 = fields.next()?.parse().ok()?;
+    Some((rss_kb * 1024, parse_cpu_time(fields.next()?)?))
+}
+
+/// ps's cumulative CPU time: `[[dd-]hh:]mm:ss[.ff]`
+#[cfg(not(target_os = "linux"))]
+fn parse_cpu_time(value: &str) -> Option<Duration> {
+    let (days, clock) = match value.split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, value),
+    };
+    let seconds = clock
+        .split(':')
+        .try_fold(0.0, |total, part| part.parse::<f64>().ok().map(|p| total * 60.0 + p))?;
+    Some(Duration::from_secs_f64(days * 86_400.0 + seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_track_cpu_between_readings() {
+        let monitor = ProcessMonitor::new();
+        let pid = std::process::id();
+        let first = monitor.sample("self", pid).expect("own process is readable");
+        assert!(first.rss_bytes > 0);
+        assert_eq!(first.cpu_percent, None);
+
+        std::thread::sleep(Duration::from_millis(20));
+        let second = monitor.sample("self", pid).unwrap();
+        assert!(second.cpu_percent.is_some());
+        assert_eq!(monitor.latest("self"), Some(second));
+
+        monitor.remove_server("self");
+        assert!(monitor.latest("self").is_none());
+        assert!(monitor.sample("gone", u32::MAX).is_none());
+    }
+
+    #[test]
+    fn test_threshold_warns_once_per_crossing() {
+        let monitor = ProcessMonitor::new();
+        let mut sample = ProcessSample {
+            pid: 1,
+            rss_bytes: 2 << 30,
+            cpu_percent: None,
+            cpu_time: Duration::ZERO,
+            taken_at: Instant::now(),
+        };
+        assert!(monitor.check_threshold("leaky", &sample, 1 << 30));
+        assert!(!monitor.check_threshold("leaky", &sample, 1 << 30));
+
+        sample.rss_bytes = 1 << 20;
+        assert!(!monitor.check_threshold("leaky", &sample, 1 << 30));
+        sample.rss_bytes = 3 << 30;
+        assert!(monitor.check_threshold("leaky", &sample, 1 << 30));
+        assert_eq!(format_bytes(sample.rss_bytes), "3.0 GB");
+    }
+}
@@ -12,10 +12,38 @@ use rmcp::model::{
     CallToolRequestParam as RmcpCallToolRequestParam, // Alias CallToolRequestParam
     // Removed unused import: Content as RmcpContent,
     RawContent as RmcpRawContent, // Alias RawContent
+    LoggingLevel as RmcpLoggingLevel, // Alias LoggingLevel
+    SetLevelRequestParam as RmcpSetLevelRequestParam, // Alias SetLevelRequestParam
+    SubscribeRequestParam as RmcpSubscribeRequestParam, // Alias SubscribeRequestParam
+    UnsubscribeRequestParam as RmcpUnsubscribeRequestParam, // Alias UnsubscribeRequestParam
+    ReadResourceRequestParam as RmcpReadResourceRequestParam, // Alias ReadResourceRequestParam
+    ReadResourceResult as RmcpReadResourceResult, // Alias ReadResourceResult
+    Resource as RmcpResource, // Alias Resource
+    ResourceUpdatedNotificationParam as RmcpResourceUpdatedNotificationParam, // Alias ResourceUpdatedNotificationParam
+    LoggingMessageNotificationParam as RmcpLoggingMessageNotificationParam, // Alias LoggingMessageNotificationParam
+    ClientRequest as RmcpClientRequest, // Alias ClientRequest
+    PingRequest as RmcpPingRequest, // Alias PingRequest
+    CompleteRequestParam as RmcpCompleteRequestParam, // Alias CompleteRequestParam
+    CompleteResult as RmcpCompleteResult, // Alias CompleteResult
+    Reference as RmcpReference, // Alias Reference
+    ArgumentInfo as RmcpArgumentInfo, // Alias ArgumentInfo
+    CallToolRequest as RmcpCallToolRequest, // Alias CallToolRequest
+    CancelledNotificationParam as RmcpCancelledNotificationParam, // Alias CancelledNotificationParam
+    RequestId as RmcpRequestId, // Alias RequestId
+    ServerResult as RmcpServerResult, // Alias ServerResult
     // Removed unused import: RawTextContent as RmcpRawTextContent,
 };
-use rmcp::service::{serve_client, Peer, RoleClient as RmcpRoleClient}; // Import Peer, RoleClient alias
-use rmcp::transport::TokioChildProcess;
+use rmcp::service::{serve_client_with_ct, Peer, PeerRequestOptions, RoleClient as RmcpRoleClient}; // Import Peer, RoleClient alias
+use rmcp::ClientHandler;
+use tokio::sync::broadcast;
+use crate::host::transport::{line_transport, ReadFailure};
+use crate::host::sse_transport::{ConnectionNotice, SseTransportBuilder};
+use crate::host::config::ServerConfig;
+use crate::host::annotations::ToolAnnotationStore;
+use crate::host::middleware::Interceptors;
+use crate::host::single_flight::SingleFlight;
+use crate::host::error::HostError;
+use tokio_util::sync::CancellationToken;
 use std::collections::HashMap;
 // Use TokioCommand explicitly, remove unused StdCommand alias
 use tokio::process::Command as TokioCommand;
@@ -23,8 +51,9 @@ use tokio::process::Command as TokioCommand;
 use tokio::process::Child as TokioChild;
 use std::process::Stdio;
 use std::sync::Arc; // Re-add top-level Arc import
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 // Removed imports related to ManualTransport: ChildStdin, ChildStdout, rmcp::{TransportStream, TransportSink, TransportError}, bytes::Bytes, futures::{SinkExt, StreamExt}, tokio_util::codec
 
 
@@ -102,6 +131,11 @@ pub mod production {
             Ok(())
         }
 
+        /// Send an MCP `ping` and return the round-trip latency
+        pub async fn ping(&self) -> anyhow::Result<std::time::Duration> {
+            crate::host::server_manager::ping_peer(&self.inner).await
+        }
+
         pub fn capabilities(&self) -> Option<&RmcpServerCapabilities> { // Use aliased type
             log::warn!("McpClient::capabilities called. Capabilities should be accessed from ManagedServer after initialization.");
             None // Or retrieve from InitializeResult if stored within McpClient after init
@@ -130,16 +164,229 @@ pub use self::production::McpClient; // Only export McpClient for production
 #[derive(Debug)]
 pub struct ManagedServer {
     pub name: String,
-    pub process: Arc<Mutex<TokioChild>>, // Wrap process in Arc<Mutex> for killing
+    pub process: Option<Arc<Mutex<TokioChild>>>, // Wrap process in Arc<Mutex> for killing; None for remote (SSE) servers
     pub client: Peer<RmcpRoleClient>, // Store the Peer directly
+    pub cancel: CancellationToken, // Stops the client service (and any reconnect loop) on shutdown
     pub capabilities: Option<RmcpServerCapabilities>, // Use aliased type
+    pub last_used: Instant, // Start, or start or end of the most recent tool call, for stopping idle servers
+    pub calls_in_flight: Arc<AtomicUsize>, // Tool calls awaiting a response; a server with any is never idle
+    pub read_failure: ReadFailure, // Why the host stopped reading from the server, if it did
+}
+
+impl ManagedServer {
+    /// Whether the server has no calls outstanding and none started or finished for `timeout`
+    pub fn is_idle(&self, timeout: Duration, now: Instant) -> bool {
+        self.calls_in_flight.load(Ordering::SeqCst) == 0 && now.saturating_duration_since(self.last_used) >= timeout
+    }
+}
+
+/// Counts a tool call as in flight on its server until dropped, however the call ends
+struct InFlightCall(Arc<AtomicUsize>);
+
+impl InFlightCall {
+    fn start(count: &Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Self(Arc::clone(count))
+    }
+}
+
+impl Drop for InFlightCall {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 
 
+/// Host environment variables an isolated server still gets: what programs need to run,
+/// but nothing like an API key
+pub const ISOLATED_ENV_ALLOWLIST: &[&str] = &[
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_ALL", "TZ", "TMPDIR",
+    // Windows can't start most programs without these
+    "SYSTEMROOT", "WINDIR", "TEMP", "TMP",
+];
+
 // Define the concrete type for the servers map using the production McpClient
 type ServerMap = HashMap<String, ManagedServer>;
 
+/// Subscribed resources, keyed by (server name, resource URI), with their latest
+/// `resources/read` result if they have been read since the last update. Only subscribed
+/// resources are cached, since only they are reported as updated.
+pub type ResourceCache = Arc<Mutex<HashMap<(String, String), Option<RmcpReadResourceResult>>>>;
+
+/// Each server's tools from its latest tools/list, for looking up a tool's definition
+/// without listing again. Dropped when the server reconnects, stops or reports a change.
+pub type ToolCache = Arc<Mutex<HashMap<String, Vec<RmcpTool>>>>;
+
+/// A `notifications/resources/updated` message received from a server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceUpdate {
+    pub server: String,
+    pub uri: String,
+}
+
+/// A `notifications/message` log message received from a server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerLogMessage {
+    pub server: String,
+    pub level: RmcpLoggingLevel,
+    pub logger: Option<String>,
+    pub data: Value,
+}
+
+impl ServerLogMessage {
+    /// The message as one line of text: the data itself if it is a string, its `message`
+    /// field if it has one, else the data as JSON
+    pub fn text(&self) -> String {
+        match &self.data {
+            Value::String(text) => text.clone(),
+            data => match data.get("message").and_then(Value::as_str) {
+                Some(message) => message.to_string(),
+                None => data.to_string(),
+            },
+        }
+    }
+}
+
+/// Client-side handler for a single server connection.
+/// Invalidates cached resources and forwards update notifications to the host.
+#[derive(Clone)]
+pub struct HostClientHandler {
+    server_name: String,
+    resource_updates: broadcast::Sender<ResourceUpdate>,
+    resource_cache: ResourceCache,
+    tool_cache: Option<ToolCache>,
+    server_logs: Option<broadcast::Sender<ServerLogMessage>>,
+    peer: Option<Peer<RmcpRoleClient>>,
+    info: rmcp::model::ClientInfo, // Sent as the initialize request's params
+}
+
+impl HostClientHandler {
+    pub fn new(server_name: &str, resource_updates: broadcast::Sender<ResourceUpdate>, resource_cache: ResourceCache) -> Self {
+        Self {
+            server_name: server_name.to_string(),
+            resource_updates,
+            resource_cache,
+            tool_cache: None,
+            server_logs: None,
+            peer: None,
+            info: rmcp::model::ClientInfo {
+                capabilities: host_client_capabilities(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Identify as `client_info` during initialize instead of rmcp's build defaults
+    pub fn with_client_info(mut self, client_info: RmcpImplementation) -> Self {
+        self.info.client_info = client_info;
+        self
+    }
+
+    /// Forward the server's log messages to `server_logs`
+    pub fn with_server_logs(mut self, server_logs: broadcast::Sender<ServerLogMessage>) -> Self {
+        self.server_logs = Some(server_logs);
+        self
+    }
+
+    /// Drop the server's entry in `tool_cache` when it says its tools changed
+    pub fn with_tool_cache(mut self, tool_cache: ToolCache) -> Self {
+        self.tool_cache = Some(tool_cache);
+        self
+    }
+}
+
+/// Capabilities the host declares in `initialize`. It answers neither `roots/list` nor
+/// `sampling/createMessage`, so it declares neither and the object serializes as `{}`.
+pub fn host_client_capabilities() -> rmcp::model::ClientCapabilities {
+    rmcp::model::ClientCapabilities::default()
+}
+
+impl ClientHandler for HostClientHandler {
+    async fn on_resource_updated(&self, params: RmcpResourceUpdatedNotificationParam) {
+        info!("Resource '{}' updated on server '{}'", params.uri, self.server_name);
+        // Invalidate before notifying so listeners re-read fresh content
+        if let Some(cached) = self.resource_cache.lock().await.get_mut(&(self.server_name.clone(), params.uri.clone())) {
+            *cached = None;
+        }
+
+        // Sending only fails when nobody is listening, which is fine
+        let _ = self.resource_updates.send(ResourceUpdate {
+            server: self.server_name.clone(),
+            uri: params.uri,
+        });
+    }
+
+    async fn on_tool_list_changed(&self) {
+        info!("Tools changed on server '{}'", self.server_name);
+        if let Some(tool_cache) = &self.tool_cache {
+            tool_cache.lock().await.remove(&self.server_name);
+        }
+    }
+
+    /// Servers report things like a background task finishing this way; surface them in the log
+    async fn on_logging_message(&self, params: RmcpLoggingMessageNotificationParam) {
+        let logger = params.logger.as_deref().unwrap_or("server");
+        match params.level {
+            RmcpLoggingLevel::Debug => debug!("[{}/{}] {}", self.server_name, logger, params.data),
+            RmcpLoggingLevel::Info | RmcpLoggingLevel::Notice => info!("[{}/{}] {}", self.server_name, logger, params.data),
+            RmcpLoggingLevel::Warning => warn!("[{}/{}] {}", self.server_name, logger, params.data),
+            _ => error!("[{}/{}] {}", self.server_name, logger, params.data),
+        }
+        if let Some(server_logs) = &self.server_logs {
+            // Sending only fails when nobody is listening, which is fine
+            let _ = server_logs.send(ServerLogMessage {
+                server: self.server_name.clone(),
+                level: params.level,
+                logger: params.logger,
+                data: params.data,
+            });
+        }
+    }
+
+    fn get_peer(&self) -> Option<Peer<RmcpRoleClient>> {
+        self.peer.clone()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RmcpRoleClient>) {
+        self.peer = Some(peer);
+    }
+
+    fn get_info(&self) -> rmcp::model::ClientInfo {
+        self.info.clone()
+    }
+}
+
+/// Channels and caches every `ServerManager` of a host shares, so that what one learns
+/// about a server (its tools, subscriptions, ...) is seen by the next
+#[derive(Clone)]
+pub struct SharedServerState {
+    pub resource_updates: broadcast::Sender<ResourceUpdate>,
+    pub resource_cache: ResourceCache,
+    pub connection_notices: broadcast::Sender<ConnectionNotice>,
+    pub tool_annotations: ToolAnnotationStore,
+    pub interceptors: Interceptors,
+    pub tool_lists: SingleFlight<Vec<RmcpTool>>, // tools/list requests in flight, shared by concurrent callers
+    pub tool_cache: ToolCache,
+    pub server_logs: broadcast::Sender<ServerLogMessage>,
+}
+
+impl SharedServerState {
+    /// Empty caches and fresh channels, with `interceptors` applied to every message
+    pub fn new(interceptors: Interceptors) -> Self {
+        Self {
+            resource_updates: broadcast::channel(64).0,
+            resource_cache: Arc::new(Mutex::new(HashMap::new())),
+            connection_notices: broadcast::channel(64).0,
+            tool_annotations: ToolAnnotationStore::new(),
+            interceptors,
+            tool_lists: SingleFlight::new(),
+            tool_cache: Arc::new(Mutex::new(HashMap::new())),
+            server_logs: broadcast::channel(256).0,
+        }
+    }
+}
+
 /// Manager for MCP-compatible tool servers
 ///
 /// The ServerManager handles communication with tool servers using the shared protocol
@@ -149,6 +396,16 @@ pub struct ServerManager {
     pub servers: Arc<Mutex<ServerMap>>,
     pub client_info: RmcpImplementation, // Use aliased type
     pub request_timeout: Duration,
+    pub connect_timeout: Duration, // Limit on starting a server and completing the handshake
+    pub max_message_bytes: usize,
+    pub resource_updates: broadcast::Sender<ResourceUpdate>,
+    pub resource_cache: ResourceCache,
+    pub connection_notices: broadcast::Sender<ConnectionNotice>,
+    pub tool_annotations: ToolAnnotationStore,
+    pub interceptors: Interceptors,
+    pub tool_lists: SingleFlight<Vec<RmcpTool>>, // tools/list requests in flight, shared by concurrent callers
+    pub tool_cache: ToolCache,
+    pub server_logs: broadcast::Sender<ServerLogMessage>,
 }
 
 impl ServerManager {
@@ -157,22 +414,134 @@ impl ServerManager {
         servers: Arc<Mutex<ServerMap>>, // Use ServerMap
         client_info: RmcpImplementation, // Use aliased type
         request_timeout: Duration,
+        connect_timeout: Duration,
+        max_message_bytes: usize,
+        shared: SharedServerState,
     ) -> Self {
+        let SharedServerState {
+            resource_updates,
+            resource_cache,
+            connection_notices,
+            tool_annotations,
+            interceptors,
+            tool_lists,
+            tool_cache,
+            server_logs,
+        } = shared;
+        // Tool calls get the trace context held for them as they are sent; see telemetry.rs
+        #[cfg(feature = "otel")]
+        let interceptors = interceptors.on_request(crate::telemetry::attach_trace_context);
         Self {
             servers,
             client_info,
             request_timeout,
+            connect_timeout,
+            max_message_bytes,
+            resource_updates,
+            resource_cache,
+            connection_notices,
+            tool_annotations,
+            interceptors,
+            tool_lists,
+            tool_cache,
+            server_logs,
         }
     }
 
+    /// Start a server from its config: connect over SSE if it has a `url`, otherwise spawn `command`.
+    pub async fn start_server_from_config(&self, name: &str, config: &ServerConfig) -> Result<()> {
+        match &config.url {
+            Some(url) => self.start_sse_server(name, url, config).await,
+            None => {
+                let args = config.args.as_deref().unwrap_or(&[]);
+                self.start_server_with_components(name, &config.command, args, &config.env, config.isolate_env).await
+            }
+        }
+    }
+
+    /// Connect to a remote server over SSE, sending the config's headers and bearer token.
+    /// Dropped streams are resumed automatically; disconnects and reconnects are reported
+    /// on `connection_notices`.
+    pub async fn start_sse_server(&self, name: &str, url: &str, config: &ServerConfig) -> Result<()> {
+        info!("Attempting to connect to SSE server '{}' at {}", name, url);
+        if self.servers.lock().await.contains_key(name) {
+            warn!("Server '{}' is already running.", name);
+            return Ok(());
+        }
+
+        let mut builder = SseTransportBuilder::new(url, name)
+            .notices(self.connection_notices.clone())
+            .annotations(self.tool_annotations.clone())
+            .interceptors(self.interceptors.clone())
+            .with_headers(&config.headers)
+            .with_context(|| format!("Invalid headers for server '{}'", name))?;
+        if let Some(var) = &config.bearer_token_env {
+            builder = builder.with_bearer_token_env(var);
+        }
+        // A server that accepts the connection but never sends its endpoint event counts as hung
+        let transport = tokio::time::timeout(self.connect_timeout, builder.connect())
+            .await
+            .map_err(|_| HostError::ConnectTimeout { server: name.to_string(), timeout: self.connect_timeout })?
+            .with_context(|| format!("Failed to connect to SSE server '{}' at {}", name, url))?;
+
+        let cancel = CancellationToken::new();
+        let (client, capabilities) = self.handshake(name, transport, cancel.clone()).await?;
+
+        let managed_server = ManagedServer {
+            name: name.to_string(),
+            process: None,
+            client,
+            cancel,
+            capabilities: Some(capabilities),
+            last_used: Instant::now(),
+            calls_in_flight: Default::default(),
+            read_failure: Default::default(),
+        };
+        self.servers.lock().await.insert(name.to_string(), managed_server);
+        info!("Connected to SSE server '{}'.", name);
+        Ok(())
+    }
+
+    /// Run the MCP handshake with server `name` over `transport`: `initialize` carrying the
+    /// host's client info, then the `notifications/initialized` notification. Afterwards the
+    /// returned peer is ready for requests; server notifications are routed back to the host.
+    /// A server that hasn't answered within `connect_timeout` fails with `HostError::ConnectTimeout`.
+    pub async fn handshake<T, E, A>(
+        &self,
+        name: &str,
+        transport: T,
+        cancel: CancellationToken,
+    ) -> Result<(Peer<RmcpRoleClient>, RmcpServerCapabilities)>
+    where
+        T: rmcp::transport::IntoTransport<RmcpRoleClient, E, A>,
+        E: std::error::Error + From<std::io::Error> + Send + Sync + 'static,
+    {
+        let handler = HostClientHandler::new(name, self.resource_updates.clone(), Arc::clone(&self.resource_cache))
+            .with_client_info(self.client_info.clone())
+            .with_server_logs(self.server_logs.clone())
+            .with_tool_cache(Arc::clone(&self.tool_cache));
+        let running_service = tokio::time::timeout(self.connect_timeout, serve_client_with_ct(handler, transport, cancel))
+            .await
+            .map_err(|_| HostError::ConnectTimeout { server: name.to_string(), timeout: self.connect_timeout })?
+            .map_err(|e| anyhow!("MCP handshake with server '{}' failed: {}", name, e))?;
+        // A new connection may serve different tools than the last one, and starts with no subscriptions
+        self.tool_cache.lock().await.remove(name);
+        self.forget_resources(name).await;
+        let capabilities = running_service.peer_info().capabilities.clone();
+        Ok((running_service.peer().clone(), capabilities))
+    }
+
     /// Start a server process using detailed components.
     /// This is the core function for launching and connecting to a server.
+    /// With `isolate_env` the process gets only `envs` and `ISOLATED_ENV_ALLOWLIST`
+    /// from the host's environment.
     pub async fn start_server_with_components(
         &self,
         name: &str,
         program: &str,
         args: &[String],
         envs: &HashMap<String, String>,
+        isolate_env: bool,
     ) -> Result<()> {
         info!("Attempting to start server '{}' with program: {}, args: {:?}, envs: {:?}", name, program, args, envs.keys());
 
@@ -187,13 +556,22 @@ impl ServerManager {
 
         // --- Spawn Process ---
         let mut tokio_command_spawn = TokioCommand::new(program);
+        if isolate_env {
+            tokio_command_spawn.env_clear();
+            for var in ISOLATED_ENV_ALLOWLIST {
+                if let Some(value) = std::env::var_os(var) {
+                    tokio_command_spawn.env(var, value);
+                }
+            }
+        }
         tokio_command_spawn.args(args)
                            .envs(envs)
                            .stdin(Stdio::piped())
                            .stdout(Stdio::piped())
-                           .stderr(Stdio::piped()); // Capture stderr
+                           .stderr(Stdio::piped()) // Capture stderr
+                           .kill_on_drop(true);
 
-        let process = match tokio_command_spawn.spawn() {
+        let mut process = match tokio_command_spawn.spawn() {
             Ok(p) => p,
             Err(e) => {
                 error!("Failed to spawn process for server '{}': {}", name, e);
@@ -204,57 +582,53 @@ impl ServerManager {
         info!("Process spawned successfully for server '{}', PID: {:?}", name, process_id);
 
         // --- Create Transport and Client using rmcp ---
-        // Create transport using the *original* command components
-        let mut transport_cmd = TokioCommand::new(program); // Use original program path
-        transport_cmd.args(args).envs(envs)
-                     .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
-
-        let transport = match TokioChildProcess::new(&mut transport_cmd) {
-            Ok(t) => t,
-            // Removed duplicate Ok(t) arm
-            Err(e) => {
-                error!("Failed to create TokioChildProcess transport for server '{}': {}", name, e);
-                // Attempt to kill the spawned process if transport creation fails
-                let process_guard = Arc::new(Mutex::new(process)); // Removed mut
-                if let Err(kill_err) = process_guard.lock().await.kill().await {
+        // Talk to the process we just spawned over its stdin/stdout
+        let (stdout, stdin) = match (process.stdout.take(), process.stdin.take()) {
+            (Some(stdout), Some(stdin)) => (stdout, stdin),
+            _ => {
+                error!("Failed to capture stdin/stdout for server '{}'", name);
+                if let Err(kill_err) = process.kill().await {
                      error!("Also failed to kill process for server '{}' after transport error: {}", name, kill_err);
                 }
-                return Err(anyhow!("Failed to create TokioChildProcess transport for server '{}': {}", name, e));
+                return Err(anyhow!("Failed to capture stdin/stdout for server '{}'", name));
             }
         };
-        info!("TokioChildProcess transport created for server '{}'.", name);
-
-        // Serve the client handler (use `()` for default behavior)
-        // Provide client info and capabilities during the serve call
-        let running_service = match serve_client(
-            (), // Default client handler
-            transport
-        ).await {
-           Ok(rs) => rs,
+        let read_failure = ReadFailure::default();
+        let transport = line_transport(
+            stdout,
+            stdin,
+            self.max_message_bytes,
+            name,
+            Some(self.tool_annotations.clone()),
+            self.interceptors.clone(),
+            read_failure.clone(),
+        );
+        info!("Transport created for server '{}' (max message size: {} bytes).", name, self.max_message_bytes);
+
+        let cancel = CancellationToken::new();
+        let (client, capabilities) = match self.handshake(name, transport, cancel.clone()).await {
+           Ok(connected) => connected,
            Err(e) => {
-               error!("Failed to serve client and create Peer for server '{}': {}", name, e);
-                // Attempt to kill the spawned process if serve_client fails
-                let process_guard = Arc::new(Mutex::new(process)); // Removed mut
-                if let Err(kill_err) = process_guard.lock().await.kill().await {
-                     error!("Also failed to kill process for server '{}' after serve_client error: {}", name, kill_err);
+               error!("{}", e);
+                // Attempt to kill the spawned process if the handshake fails
+                if let Err(kill_err) = process.kill().await {
+                     error!("Also failed to kill process for server '{}' after handshake error: {}", name, kill_err);
                 }
-                return Err(anyhow!("Failed to serve client and create Peer for server '{}': {}", name, e));
+                return Err(e);
             }
         };
-        info!("RunningService (including Peer) created for server '{}'.", name);
-
-        let client = running_service.peer().clone(); // Get the Peer
-        let capabilities = running_service.peer_info()
-        .capabilities.clone();
-
         info!("Peer<RoleClient> obtained for server '{}'.", name);
 
         // --- Store Managed Server ---
         let managed_server = ManagedServer {
             name: name.to_string(),
-            process: Arc::new(Mutex::new(process)), // Wrap process in Arc<Mutex>
+            process: Some(Arc::new(Mutex::new(process))), // Wrap process in Arc<Mutex>
             client, // Store the Peer
+            cancel,
             capabilities: Some(capabilities),
+            last_used: Instant::now(),
+            calls_in_flight: Default::default(),
+            read_failure,
         };
 
         { // Scope for servers lock
@@ -288,71 +662,126 @@ impl ServerManager {
         // Use empty environment map for now. Could inherit or load from config if needed.
         let envs = HashMap::new();
 
-        self.start_server_with_components(name, program, &args, &envs).await
+        self.start_server_with_components(name, program, &args, &envs, false).await
     }
 
     /// Stop a running server process and remove it from management.
     pub async fn stop_server(&self, name: &str) -> Result<()> {
         info!("Attempting to stop server '{}'", name);
-        let mut servers_guard = self.servers.lock().await;
-
-        if let Some(server) = servers_guard.remove(name) {
-            info!("Removed server '{}' from map. Attempting to kill process...", name);
-            let mut process_guard = server.process.lock().await; // Lock the Mutex around the Child
-            match process_guard.kill().await {
-                Ok(_) => {
-                    info!("Successfully killed process for server '{}'", name);
-                    // Optionally wait for the process to ensure it's fully terminated
-                    // let _ = process_guard.wait().await;
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Failed to kill process for server '{}': {}", name, e);
-                    // Even if killing fails, it's removed from the map.
-                    // Return an error to indicate the potential zombie process.
-                    Err(anyhow!("Failed to kill process for server '{}': {}", name, e))
-                }
+        let server = self.servers.lock().await.remove(name);
+        match server {
+            Some(server) => self.shut_down(name, server).await,
+            None => {
+                warn!("Server '{}' not found or already stopped.", name);
+                Ok(()) // Not an error if it wasn't running
+            }
+        }
+    }
+
+    /// Stop a server only if it is still idle (see `ManagedServer::is_idle`), checked under
+    /// the same lock that a tool call takes to start. Returns whether it was stopped.
+    pub async fn stop_server_if_idle(&self, name: &str, timeout: Duration, now: Instant) -> Result<bool> {
+        let server = {
+            let mut servers = self.servers.lock().await;
+            if !servers.get(name).is_some_and(|server| server.is_idle(timeout, now)) {
+                return Ok(false);
+            }
+            servers.remove(name)
+        };
+        match server {
+            Some(server) => self.shut_down(name, server).await.map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Disconnect from a server already removed from the map and kill its process
+    async fn shut_down(&self, name: &str, server: ManagedServer) -> Result<()> {
+        server.cancel.cancel(); // Stop the client service
+        self.tool_annotations.remove_server(name);
+        self.tool_cache.lock().await.remove(name);
+        self.forget_resources(name).await;
+        let Some(process) = server.process else {
+            info!("Disconnected from remote server '{}'", name);
+            return Ok(());
+        };
+        info!("Removed server '{}' from map. Attempting to kill process...", name);
+        let mut process_guard = process.lock().await; // Lock the Mutex around the Child
+        match process_guard.kill().await {
+            Ok(_) => {
+                info!("Successfully killed process for server '{}'", name);
+                // Optionally wait for the process to ensure it's fully terminated
+                // let _ = process_guard.wait().await;
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to kill process for server '{}': {}", name, e);
+                // Even if killing fails, it's removed from the map.
+                // Return an error to indicate the potential zombie process.
+                Err(anyhow!("Failed to kill process for server '{}': {}", name, e))
             }
-        } else {
-            warn!("Server '{}' not found or already stopped.", name);
-            Ok(()) // Not an error if it wasn't running
         }
     }
 
     /// List all available tools on the specified server
     pub async fn list_server_tools(&self, server_name: &str) -> Result<Vec<RmcpTool>> { // Use aliased type
-        let servers = self.servers.lock().await;
-        let server = servers.get(server_name)
-            .ok_or_else(|| anyhow!("Server not found: {}", server_name))?;
+        // Take the peer and release the lock; the request itself may take a while
+        let (client, read_failure) = self.peer_for(server_name).await?;
 
-        info!("Sending tool list request to server {}", server_name);
+        // Concurrent callers asking for the same server's tools share one request
+        let name = server_name.to_string();
+        let tool_cache = Arc::clone(&self.tool_cache);
+        self.tool_lists.run(server_name, move || async move {
+            info!("Sending tool list request to server {}", name);
 
-        // Call list_tools directly on the Peer stored in ManagedServer
-        match server.client.list_tools(None).await { // Pass None for default params
-            Ok(list_tools_result) => {
-                let tools_vec = list_tools_result.tools; // Extract Vec<Tool>
-                info!("Successfully received tools list: {} tools", tools_vec.len());
-                debug!("Tools list details: {:?}", tools_vec);
-                Ok(tools_vec)
-            },
-            Err(e) => {
-                error!("Error listing tools from {}: {:?}", server_name, e);
-                // Use context method from anyhow::Context trait
-                Err(anyhow!("Failed to list tools from {}: {}", server_name, e)).context(format!("Listing tools failed for {}", server_name))
+            // Call list_tools directly on the Peer stored in ManagedServer
+            match client.list_tools(None).await { // Pass None for default params
+                Ok(list_tools_result) => {
+                    let tools_vec = list_tools_result.tools; // Extract Vec<Tool>
+                    info!("Successfully received tools list: {} tools", tools_vec.len());
+                    debug!("Tools list details: {:?}", tools_vec);
+                    tool_cache.lock().await.insert(name.clone(), tools_vec.clone());
+                    Ok(tools_vec)
+                },
+                Err(e) => {
+                    error!("Error listing tools from {}: {:?}", name, e);
+                    Err(request_failed_on(&read_failure, e, format!("Failed to list tools from {}", name)))
+                }
             }
-        }
+        }).await
+    }
+
+    /// A tool's definition from the server's cached tool list, listing its tools only if
+    /// nothing is cached. None if the server doesn't have the tool.
+    pub async fn tool_definition(&self, server_name: &str, tool_name: &str) -> Result<Option<RmcpTool>> {
+        let cached = self.tool_cache.lock().await.get(server_name).cloned();
+        let tools = match cached {
+            Some(tools) => tools,
+            None => self.list_server_tools(server_name).await?,
+        };
+        Ok(tools.into_iter().find(|tool| tool.name == tool_name))
     }
 
     /// Call a tool on the specified server with the given arguments
     pub async fn call_tool(&self, server_name: &str, tool_name: &str, args: Value) -> Result<String> {
+        let result = self.call_tool_structured(server_name, tool_name, args).await?;
+        // Format the tool response content using rmcp::model::CallToolResult
+        Ok(format_tool_result(&result))
+    }
+
+    /// Call a tool and return its result unformatted, content items and all. Dropping the
+    /// future before it finishes cancels the call on the server.
+    #[cfg_attr(feature = "otel", tracing::instrument(name = "call", skip_all, fields(server = server_name, tool = tool_name)))]
+    pub async fn call_tool_structured(&self, server_name: &str, tool_name: &str, args: Value) -> Result<RmcpCallToolResult> {
         debug!("call_tool started");
         debug!("Server: {}", server_name);
         debug!("Tool: {}", tool_name);
         debug!("Arguments: {}", serde_json::to_string_pretty(&args).unwrap_or_default());
 
-        let servers = self.servers.lock().await;
-        let server = servers.get(server_name)
+        let mut servers = self.servers.lock().await;
+        let server = servers.get_mut(server_name)
             .ok_or_else(|| anyhow!("Server not found: {}", server_name))?;
+        server.last_used = Instant::now();
+        let in_flight = InFlightCall::start(&server.calls_in_flight);
 
         // Prepare parameters for the Peer's call_tool method
         let arguments_map = match args {
@@ -364,17 +793,260 @@ impl ServerManager {
             name: tool_name.to_string().into(),
             arguments: arguments_map,
         };
+        #[cfg(feature = "otel")]
+        let _trace_context = crate::telemetry::hold_trace_context(server_name, &params);
 
-        // Call call_tool directly on the Peer stored in ManagedServer
-        let result = server.client.call_tool(params).await
-            .map_err(|e| anyhow!("Failed to call tool '{}' on server '{}': {}", tool_name, server_name, e))?;
+        let peer = server.client.clone();
+        let read_failure = server.read_failure.clone();
+        drop(servers);
 
-        // Format the tool response content using rmcp::model::CallToolResult
-        let output = format_tool_result(&result); // Use aliased type
-        Ok(output)
+        // Sent as a cancellable request so that if the caller gives up on it (a timeout, an
+        // interrupt) the server is told to stop the tool rather than leave it running
+        let failed = |e| request_failed_on(&read_failure, e, format!("Failed to call tool '{}' on server '{}'", tool_name, server_name));
+        let request = RmcpClientRequest::CallToolRequest(RmcpCallToolRequest { method: Default::default(), params });
+        let handle = peer.send_cancellable_request(request, PeerRequestOptions::no_options()).await.map_err(failed)?;
+        let guard = CancelOnDrop { peer: Some(peer.clone()), request_id: handle.id.clone(), server: server_name.to_string() };
+        let response = handle.await_response().await;
+        guard.finish();
+        // A long call counts as use until it ends, not only when it started
+        if let Some(server) = self.servers.lock().await.get_mut(server_name) {
+            server.last_used = Instant::now();
+        }
+        drop(in_flight);
+        match response.map_err(failed)? {
+            RmcpServerResult::CallToolResult(result) => Ok(result),
+            _ => Err(failed(rmcp::ServiceError::UnexpectedResponse)),
+        }
     }
 
+    /// Get a clone of a server's Peer, and where its transport records why it stopped reading,
+    /// without holding the servers lock across requests
+    async fn peer_for(&self, server_name: &str) -> Result<(Peer<RmcpRoleClient>, ReadFailure)> {
+        let servers = self.servers.lock().await;
+        servers.get(server_name)
+            .map(|server| (server.client.clone(), server.read_failure.clone()))
+            .ok_or_else(|| anyhow!("Server not found: {}", server_name))
+    }
+
+    /// The capabilities a server advertised in its `initialize` result. None if the server
+    /// isn't running or its capabilities aren't known.
+    pub async fn server_capabilities(&self, server_name: &str) -> Option<RmcpServerCapabilities> {
+        self.servers.lock().await.get(server_name).and_then(|server| server.capabilities.clone())
+    }
+
+    /// Whether a server advertised the capability `has` looks for. A server whose
+    /// capabilities aren't known is assumed to have it.
+    async fn has_capability(&self, server_name: &str, has: fn(&RmcpServerCapabilities) -> bool) -> Result<bool> {
+        let servers = self.servers.lock().await;
+        let server = servers.get(server_name)
+            .ok_or_else(|| anyhow!("Server not found: {}", server_name))?;
+        Ok(server.capabilities.as_ref().is_none_or(has))
+    }
 
+    /// Fail without sending anything if a server didn't advertise `capability`, rather than
+    /// have it answer "method not found"
+    async fn require_capability(&self, server_name: &str, capability: &str, has: fn(&RmcpServerCapabilities) -> bool) -> Result<()> {
+        if !self.has_capability(server_name, has).await? {
+            return Err(anyhow!("Server '{}' does not advertise the '{}' capability", server_name, capability));
+        }
+        Ok(())
+    }
+
+    /// List every resource a server offers; empty, without asking, if it doesn't advertise resources.
+    pub async fn list_resources(&self, server_name: &str) -> Result<Vec<RmcpResource>> {
+        if !self.has_capability(server_name, |caps| caps.resources.is_some()).await? {
+            debug!("Server '{}' does not advertise resources; not listing them", server_name);
+            return Ok(Vec::new());
+        }
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+        peer.list_all_resources().await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to list resources on server '{}'", server_name)))
+    }
+
+    /// Read a resource from a server. Repeated reads of a subscribed resource are served
+    /// from the cache until the server reports it as updated; others are always re-read.
+    pub async fn read_resource(&self, server_name: &str, uri: &str) -> Result<RmcpReadResourceResult> {
+        let key = (server_name.to_string(), uri.to_string());
+        if let Some(Some(cached)) = self.resource_cache.lock().await.get(&key) {
+            debug!("Resource cache hit for '{}' on server '{}'", uri, server_name);
+            return Ok(cached.clone());
+        }
+
+        self.require_capability(server_name, "resources", |caps| caps.resources.is_some()).await?;
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+        let result = peer.read_resource(RmcpReadResourceRequestParam { uri: uri.to_string() }).await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to read resource '{}' from server '{}'", uri, server_name)))?;
+        if let Some(cached) = self.resource_cache.lock().await.get_mut(&key) {
+            *cached = Some(result.clone());
+        }
+        Ok(result)
+    }
+
+    /// Subscribe to `notifications/resources/updated` for a resource URI.
+    pub async fn subscribe_resource(&self, server_name: &str, uri: &str) -> Result<()> {
+        {
+            let servers = self.servers.lock().await;
+            let server = servers.get(server_name)
+                .ok_or_else(|| anyhow!("Server not found: {}", server_name))?;
+            let supports_subscribe = server.capabilities.as_ref()
+                .and_then(|caps| caps.resources.as_ref())
+                .and_then(|resources| resources.subscribe)
+                .unwrap_or(false);
+            if !supports_subscribe {
+                return Err(anyhow!("Server '{}' does not support resource subscriptions", server_name));
+            }
+        } // Lock released
+
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+        info!("Subscribing to resource '{}' on server '{}'", uri, server_name);
+        peer.subscribe(RmcpSubscribeRequestParam { uri: uri.to_string() }).await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to subscribe to resource '{}' on server '{}'", uri, server_name)))?;
+        // Updates will be reported from now on, so reads can be cached
+        self.resource_cache.lock().await.entry((server_name.to_string(), uri.to_string())).or_insert(None);
+        Ok(())
+    }
+
+    /// Stop receiving update notifications for a resource URI.
+    pub async fn unsubscribe_resource(&self, server_name: &str, uri: &str) -> Result<()> {
+        self.require_capability(server_name, "resources", |caps| caps.resources.is_some()).await?;
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+        info!("Unsubscribing from resource '{}' on server '{}'", uri, server_name);
+        // Updates may stop at any point from here, so stop trusting the cache first
+        self.resource_cache.lock().await.remove(&(server_name.to_string(), uri.to_string()));
+        peer.unsubscribe(RmcpUnsubscribeRequestParam { uri: uri.to_string() }).await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to unsubscribe from resource '{}' on server '{}'", uri, server_name)))
+    }
+
+    /// Drop a server's subscriptions and cached resources
+    async fn forget_resources(&self, name: &str) {
+        self.resource_cache.lock().await.retain(|(server, _), _| server != name);
+    }
+
+    /// Ask a server for `completion/complete` suggestions for a prompt or resource argument.
+    pub async fn complete(&self, server_name: &str, reference: RmcpReference, argument_name: &str, value: &str) -> Result<RmcpCompleteResult> {
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+        let params = RmcpCompleteRequestParam {
+            r#ref: reference,
+            argument: RmcpArgumentInfo { name: argument_name.to_string(), value: value.to_string() },
+        };
+        debug!("Requesting completions from server '{}': {:?}", server_name, params);
+        peer.complete(params).await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to get completions from server '{}'", server_name)))
+    }
+
+    /// Ping a server and return the round-trip latency.
+    pub async fn ping_server(&self, server_name: &str) -> Result<Duration> {
+        let (peer, _) = self.peer_for(server_name).await?;
+        ping_peer(&peer).await
+            .with_context(|| format!("Ping failed for server '{}'", server_name))
+    }
+
+    /// Ask a server to change its log verbosity via `logging/setLevel`.
+    pub async fn set_log_level(&self, server_name: &str, level: &str) -> Result<()> {
+        let level = parse_log_level(level)?;
+        self.require_capability(server_name, "logging", |caps| caps.logging.is_some()).await?;
+        let (peer, read_failure) = self.peer_for(server_name).await?;
+
+        info!("Setting log level for server '{}' to {:?}", server_name, level);
+        peer.set_level(RmcpSetLevelRequestParam { level }).await
+            .map_err(|e| request_failed_on(&read_failure, e, format!("Failed to set log level on server '{}'", server_name)))
+    }
+
+}
+
+/// Reason given to a server for a tool call the host stopped waiting for
+pub const CALL_ABANDONED_REASON: &str = "The host stopped waiting for this tool call";
+
+/// Sends `notifications/cancelled` for a request if dropped before `finish`, so a server
+/// can stop a tool (and kill any process it started) once nobody is waiting for the result
+struct CancelOnDrop {
+    peer: Option<Peer<RmcpRoleClient>>,
+    request_id: RmcpRequestId,
+    server: String,
+}
+
+impl CancelOnDrop {
+    /// The request completed; nothing to cancel
+    fn finish(mut self) {
+        self.peer = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(peer) = self.peer.take() else { return };
+        // Drop can't wait on the notification, and may run as the runtime shuts down
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else { return };
+        debug!("Cancelling request {} on server '{}'", self.request_id, self.server);
+        let params = RmcpCancelledNotificationParam {
+            request_id: self.request_id.clone(),
+            reason: Some(CALL_ABANDONED_REASON.to_string()),
+        };
+        let server = self.server.clone();
+        runtime.spawn(async move {
+            if let Err(e) = peer.notify_cancelled(params).await {
+                debug!("Could not send cancellation to server '{}': {}", server, e);
+            }
+        });
+    }
+}
+
+/// Wrap a failed request as a `HostError` (so callers can `downcast_ref` and branch on
+/// timeouts, lost connections and so on) under a message saying what was attempted.
+pub fn request_failed(error: rmcp::ServiceError, action: String) -> anyhow::Error {
+    with_action(HostError::from(error), action)
+}
+
+/// `request_failed` for a request to a server whose transport records read failures: a
+/// connection lost because the host stopped reading (e.g. `MessageTooLarge`) reports why
+pub fn request_failed_on(read_failure: &ReadFailure, error: rmcp::ServiceError, action: String) -> anyhow::Error {
+    let error = match (HostError::from(error), read_failure.get()) {
+        (HostError::ConnectionLost(_), Some(cause)) => cause,
+        (error, _) => error,
+    };
+    with_action(error, action)
+}
+
+fn with_action(error: HostError, action: String) -> anyhow::Error {
+    let message = format!("{}: {}", action, error);
+    anyhow::Error::new(error).context(message)
+}
+
+/// Send the MCP `ping` utility request over a Peer and time the round trip.
+pub async fn ping_peer(peer: &Peer<RmcpRoleClient>) -> Result<Duration> {
+    let started = Instant::now();
+    peer.send_request(RmcpClientRequest::PingRequest(RmcpPingRequest { method: Default::default() })).await
+        .map_err(|e| request_failed(e, "Ping request failed".to_string()))?;
+    let latency = started.elapsed();
+    debug!("Ping round trip took {:?}", latency);
+    Ok(latency)
+}
+
+/// Parse a user-supplied log level into an rmcp `LoggingLevel`.
+/// Only the standard `debug`/`info`/`warning`/`error` levels are accepted.
+pub fn parse_log_level(level: &str) -> Result<RmcpLoggingLevel> {
+    match level.trim().to_lowercase().as_str() {
+        "debug" => Ok(RmcpLoggingLevel::Debug),
+        "info" => Ok(RmcpLoggingLevel::Info),
+        "warning" => Ok(RmcpLoggingLevel::Warning),
+        "error" => Ok(RmcpLoggingLevel::Error),
+        other => Err(anyhow!("Invalid log level '{}'. Use one of: debug, info, warning, error", other)),
+    }
+}
+
+/// Replace image content with a short text placeholder, for models that can't take images
+pub fn images_to_placeholders(mut result: RmcpCallToolResult) -> RmcpCallToolResult {
+    for content in &mut result.content {
+        if let RmcpRawContent::Image(image) = &content.raw {
+            let placeholder = format!(
+                "[{} image omitted ({} bytes base64): the active model cannot view images]",
+                image.mime_type,
+                image.data.len()
+            );
+            *content = rmcp::model::Content::text(placeholder);
+        }
+    }
+    result
 }
 
 /// Format a tool result (rmcp::model::CallToolResult) into a string for display
@@ -432,3 +1104,408 @@ pub fn format_tool_result(result: &RmcpCallToolResult) -> String { // Make publi
     output.trim_end().to_string()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::test_support::register_mock_server;
+    use rmcp::model::{CompletionInfo, PromptReference, ResourceContents, ServerCapabilities, ServerInfo};
+    use rmcp::service::{serve_client, RequestContext, RoleServer};
+    use rmcp::{Error as McpError, ServerHandler, ServiceExt};
+
+    /// Minimal in-process MCP server used to exercise the client-side requests.
+    #[derive(Clone, Default)]
+    struct MockServer {
+        levels: Arc<std::sync::Mutex<Vec<RmcpLoggingLevel>>>,
+    }
+
+    impl ServerHandler for MockServer {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder()
+                    .enable_logging()
+                    .enable_resources()
+                    .enable_resources_subscribe()
+                    .build(),
+                ..Default::default()
+            }
+        }
+
+        async fn set_level(&self, request: RmcpSetLevelRequestParam, _context: RequestContext<RoleServer>) -> Result<(), McpError> {
+            self.levels.lock().unwrap().push(request.level);
+            Ok(())
+        }
+
+        async fn complete(&self, request: RmcpCompleteRequestParam, _context: RequestContext<RoleServer>) -> Result<RmcpCompleteResult, McpError> {
+            let values = ["python", "pytorch", "pyside"].iter()
+                .filter(|v| v.starts_with(&request.argument.value))
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>();
+            Ok(RmcpCompleteResult {
+                completion: CompletionInfo { total: Some(values.len() as u32), values, has_more: Some(false) },
+            })
+        }
+
+        async fn read_resource(&self, request: RmcpReadResourceRequestParam, _context: RequestContext<RoleServer>) -> Result<RmcpReadResourceResult, McpError> {
+            Ok(RmcpReadResourceResult {
+                contents: vec![ResourceContents::text("contents", request.uri)],
+            })
+        }
+
+        async fn subscribe(&self, request: RmcpSubscribeRequestParam, context: RequestContext<RoleServer>) -> Result<(), McpError> {
+            // Push an update straight away, as if the resource changed after subscribing
+            let peer = context.peer.clone();
+            tokio::spawn(async move {
+                let _ = peer.notify_resource_updated(RmcpResourceUpdatedNotificationParam { uri: request.uri }).await;
+            });
+            Ok(())
+        }
+
+        async fn unsubscribe(&self, _request: RmcpUnsubscribeRequestParam, _context: RequestContext<RoleServer>) -> Result<(), McpError> {
+            Ok(())
+        }
+    }
+
+    /// Connect `mock` over an in-memory duplex pipe and register it as `name`.
+    /// A placeholder `sleep` process stands in for the real child process.
+    async fn connect_mock_server(manager: &ServerManager, name: &str, mock: MockServer) {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            if let Ok(server) = mock.serve(server_io).await {
+                let _ = server.waiting().await;
+            }
+        });
+
+        let handler = HostClientHandler::new(name, manager.resource_updates.clone(), Arc::clone(&manager.resource_cache));
+        let running_service = serve_client(handler, client_io).await.expect("client handshake failed");
+        let process = TokioCommand::new("sleep").arg("60").kill_on_drop(true).spawn().expect("failed to spawn placeholder process");
+        let managed_server = ManagedServer {
+            name: name.to_string(),
+            process: Some(Arc::new(Mutex::new(process))),
+            cancel: CancellationToken::new(),
+            client: running_service.peer().clone(),
+            capabilities: Some(running_service.peer_info().capabilities.clone()),
+            last_used: Instant::now(),
+            calls_in_flight: Default::default(),
+            read_failure: Default::default(),
+        };
+        manager.servers.lock().await.insert(name.to_string(), managed_server);
+    }
+
+    fn test_manager() -> ServerManager {
+        ServerManager::new(
+            Arc::new(Mutex::new(HashMap::new())),
+            RmcpImplementation { name: "test-host".to_string(), version: "0.0.0".to_string() },
+            Duration::from_secs(5),
+            Duration::from_secs(5),
+            crate::host::transport::DEFAULT_MAX_MESSAGE_BYTES,
+            SharedServerState::new(Interceptors::new()),
+        )
+    }
+
+    #[test]
+    fn test_client_capabilities_match_spec_shape() {
+        // The spec's initialize example
+        let spec = serde_json::json!({ "roots": { "listChanged": true }, "sampling": {} });
+        let capabilities = rmcp::model::ClientCapabilities {
+            experimental: None,
+            roots: Some(rmcp::model::RootsCapabilities { list_changed: Some(true) }),
+            sampling: Some(Default::default()),
+        };
+        assert_eq!(serde_json::to_value(&capabilities).unwrap(), spec);
+        assert_eq!(serde_json::from_value::<rmcp::model::ClientCapabilities>(spec).unwrap(), capabilities);
+
+        // Undeclared capabilities are omitted rather than sent as null
+        assert_eq!(serde_json::to_value(host_client_capabilities()).unwrap(), serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_initialize_sends_host_client_info() {
+        let mock = crate::host::mock_transport::MockTransport::new();
+        let handle = mock.handle();
+        let handler = HostClientHandler::new("mock", broadcast::channel(4).0, Arc::new(Mutex::new(HashMap::new())))
+            .with_client_info(RmcpImplementation { name: "test-host".to_string(), version: "1.2.3".to_string() });
+        let _client = serve_client(handler, mock.into_transport()).await.expect("handshake failed");
+
+        let initialize = &handle.requests("initialize")[0]["params"];
+        assert_eq!(initialize["clientInfo"], serde_json::json!({ "name": "test-host", "version": "1.2.3" }));
+        assert_eq!(initialize["capabilities"], serde_json::json!({}));
+        assert!(initialize["protocolVersion"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_initializes_before_first_request() {
+        let manager = test_manager();
+        let mock = crate::host::mock_transport::MockTransport::new()
+            .respond("initialize", serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": { "tools": { "listChanged": true } },
+                "serverInfo": { "name": "mock", "version": "0.0.0" }
+            }))
+            .respond("tools/list", serde_json::json!({ "tools": [] }));
+        let handle = mock.handle();
+
+        let (peer, capabilities) = manager.handshake("mock", mock.into_transport(), CancellationToken::new()).await.unwrap();
+        assert_eq!(capabilities.tools.and_then(|tools| tools.list_changed), Some(true));
+        peer.list_tools(None).await.unwrap();
+
+        let methods: Vec<String> = handle.sent().iter().filter_map(|m| m["method"].as_str().map(String::from)).collect();
+        assert_eq!(methods, vec!["initialize", "notifications/initialized", "tools/list"]);
+        assert_eq!(handle.requests("initialize")[0]["params"]["clientInfo"]["name"], "test-host");
+    }
+
+    #[tokio::test]
+    async fn test_server_hanging_before_initialize_fails_fast() {
+        let manager = ServerManager { connect_timeout: Duration::from_millis(300), ..test_manager() };
+        let start = Instant::now();
+        let err = manager
+            .start_server("hung", "sh", &["-c".to_string(), "read line; sleep 30".to_string()])
+            .await
+            .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5), "took {:?}", start.elapsed());
+        assert!(
+            matches!(err.downcast_ref::<HostError>(), Some(HostError::ConnectTimeout { server, .. }) if server == "hung"),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.to_string(), "Server 'hung' failed to connect within 0.3s");
+        assert!(!manager.servers.lock().await.contains_key("hung"));
+
+        // A missing binary fails at spawn, without waiting for the timeout
+        let err = manager.start_server("missing", "/nonexistent/mcp-server", &[]).await.unwrap_err();
+        assert!(err.to_string().starts_with("Failed to spawn process for server 'missing'"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_sse_server_hanging_before_endpoint_fails_fast() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+            .mount(&server)
+            .await;
+        let manager = ServerManager { connect_timeout: Duration::from_millis(300), ..test_manager() };
+        let config = ServerConfig {
+            command: String::new(),
+            url: Some(format!("{}/sse", server.uri())),
+            headers: HashMap::new(),
+            bearer_token_env: None,
+            env: HashMap::new(),
+            args: None,
+            idle_timeout: None,
+            default_arguments: HashMap::new(),
+            continuation_tools: Vec::new(),
+            isolate_env: false,
+        };
+
+        let start = Instant::now();
+        let err = manager.start_sse_server("remote", config.url.as_deref().unwrap(), &config).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5), "took {:?}", start.elapsed());
+        assert!(
+            matches!(err.downcast_ref::<HostError>(), Some(HostError::ConnectTimeout { server, .. }) if server == "remote"),
+            "{:?}",
+            err
+        );
+        assert!(!manager.servers.lock().await.contains_key("remote"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_reaches_the_caller() {
+        // Answers the handshake, then replies to a tool call with a 5000-byte line
+        let script = r#"while read -r line; do
+id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+case "$line" in
+*'"initialize"'*) printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"2024-11-05","capabilities":{"tools":{}},"serverInfo":{"name":"big","version":"0"}}}\n' "$id" ;;
+*'"tools/call"'*) head -c 5000 /dev/zero | tr '\0' x; echo ;;
+esac
+done"#;
+        let manager = ServerManager { max_message_bytes: 1024, ..test_manager() };
+        manager.start_server("big", "sh", &["-c".to_string(), script.to_string()]).await.unwrap();
+
+        let err = manager.call_tool("big", "dump", serde_json::json!({})).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<HostError>(), Some(HostError::MessageTooLarge { limit: 1024, .. })),
+            "{:?}",
+            err
+        );
+        assert!(err.to_string().starts_with("Failed to call tool 'dump' on server 'big': Message too large"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_request_failures_keep_their_kind() {
+        let manager = test_manager();
+        let mock = crate::host::mock_transport::MockTransport::new()
+            .respond_error("tools/call", -32602, "unknown tool: nope")
+            .respond("ping", serde_json::json!({}));
+        register_mock_server(&manager, "mock", mock).await;
+
+        let err = manager.call_tool("mock", "nope", Value::Null).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<HostError>(), Some(HostError::RPC { code: -32602, .. })), "{:?}", err);
+        assert_eq!(err.to_string(), "Failed to call tool 'nope' on server 'mock': JSON-RPC error -32602: unknown tool: nope");
+
+        // Once the connection is gone, requests fail as ConnectionLost rather than an opaque string
+        manager.servers.lock().await["mock"].cancel.cancel();
+        let mut last = None;
+        for _ in 0..100 {
+            match manager.ping_server("mock").await {
+                Ok(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                Err(e) => {
+                    last = Some(e);
+                    break;
+                }
+            }
+        }
+        let err = last.expect("requests should fail after the connection closes");
+        assert!(matches!(err.downcast_ref::<HostError>(), Some(HostError::ConnectionLost(_))), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_tool_definitions_come_from_the_cached_list() {
+        let manager = test_manager();
+        let mock = crate::host::mock_transport::MockTransport::new()
+            .respond("tools/list", serde_json::json!({ "tools": [{ "name": "echo", "description": "Echo the input", "inputSchema": { "type": "object" } }] }));
+        let handle = register_mock_server(&manager, "mock", mock).await;
+
+        assert_eq!(manager.tool_definition("mock", "echo").await.unwrap().unwrap().name, "echo");
+        assert!(manager.tool_definition("mock", "missing").await.unwrap().is_none());
+        assert_eq!(handle.requests("tools/list").len(), 1);
+
+        // A change notice drops the cached list, so the next lookup lists again
+        handle.notify("notifications/tools/list_changed", serde_json::json!({}));
+        for _ in 0..100 {
+            if !manager.tool_cache.lock().await.contains_key("mock") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(manager.tool_definition("mock", "echo").await.unwrap().is_some());
+        assert_eq!(handle.requests("tools/list").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tools_only_server_is_not_asked_for_resources() {
+        let manager = test_manager();
+        let mock = crate::host::mock_transport::MockTransport::new()
+            .respond("initialize", serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "tools-only", "version": "0.0.0" }
+            }))
+            .respond("resources/list", serde_json::json!({ "resources": [] }));
+        let handle = register_mock_server(&manager, "tools-only", mock).await;
+
+        let capabilities = manager.server_capabilities("tools-only").await.expect("capabilities are kept");
+        assert!(capabilities.tools.is_some());
+        assert!(capabilities.resources.is_none());
+        assert!(capabilities.logging.is_none());
+
+        assert!(manager.list_resources("tools-only").await.unwrap().is_empty());
+        let err = manager.read_resource("tools-only", "file:///notes.txt").await.unwrap_err();
+        assert_eq!(err.to_string(), "Server 'tools-only' does not advertise the 'resources' capability");
+        assert!(manager.set_log_level("tools-only", "debug").await.is_err());
+        // None of it reached the server
+        assert!(handle.requests("resources/list").is_empty());
+        assert!(handle.requests("resources/read").is_empty());
+        assert!(handle.requests("logging/setLevel").is_empty());
+        assert!(manager.server_capabilities("missing").await.is_none());
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        assert_eq!(parse_log_level("debug").unwrap(), RmcpLoggingLevel::Debug);
+        assert_eq!(parse_log_level("INFO").unwrap(), RmcpLoggingLevel::Info);
+        assert_eq!(parse_log_level("warning").unwrap(), RmcpLoggingLevel::Warning);
+        assert_eq!(parse_log_level(" error ").unwrap(), RmcpLoggingLevel::Error);
+        assert!(parse_log_level("verbose").is_err());
+        assert!(parse_log_level("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_log_level_reaches_server() {
+        let manager = test_manager();
+        let mock = MockServer::default();
+        connect_mock_server(&manager, "mock", mock.clone()).await;
+
+        manager.set_log_level("mock", "debug").await.unwrap();
+        assert_eq!(*mock.levels.lock().unwrap(), vec![RmcpLoggingLevel::Debug]);
+
+        // Invalid levels are rejected before anything is sent
+        assert!(manager.set_log_level("mock", "loud").await.is_err());
+        assert_eq!(mock.levels.lock().unwrap().len(), 1);
+
+        assert!(manager.set_log_level("missing", "info").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resource_update_notification_reaches_handler() {
+        let manager = test_manager();
+        let mut updates = manager.resource_updates.subscribe();
+        connect_mock_server(&manager, "mock", MockServer::default()).await;
+
+        // Nothing reports changes to an unsubscribed resource, so its reads aren't cached
+        manager.read_resource("mock", "file:///config.json").await.unwrap();
+        let key = ("mock".to_string(), "file:///config.json".to_string());
+        assert!(!manager.resource_cache.lock().await.contains_key(&key));
+
+        // The mock pushes an update right after the subscription
+        manager.subscribe_resource("mock", "file:///config.json").await.unwrap();
+        let update = tokio::time::timeout(Duration::from_secs(5), updates.recv()).await
+            .expect("timed out waiting for resource update")
+            .unwrap();
+        assert_eq!(update, ResourceUpdate { server: "mock".to_string(), uri: "file:///config.json".to_string() });
+        manager.read_resource("mock", "file:///config.json").await.unwrap();
+        assert!(matches!(manager.resource_cache.lock().await.get(&key), Some(Some(_))));
+
+        manager.unsubscribe_resource("mock", "file:///config.json").await.unwrap();
+        assert!(!manager.resource_cache.lock().await.contains_key(&key));
+
+        // Subscriptions and cached reads go with the server
+        manager.subscribe_resource("mock", "file:///config.json").await.unwrap();
+        manager.read_resource("mock", "file:///config.json").await.unwrap();
+        manager.stop_server("mock").await.unwrap();
+        assert!(manager.resource_cache.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ping_server_reports_latency() {
+        let manager = test_manager();
+        connect_mock_server(&manager, "mock", MockServer::default()).await;
+
+        let latency = manager.ping_server("mock").await.unwrap();
+        assert!(latency < Duration::from_secs(5));
+        assert!(manager.ping_server("missing").await.is_err());
+    }
+
+    #[test]
+    fn test_complete_request_serialization() {
+        let params = RmcpCompleteRequestParam {
+            r#ref: RmcpReference::Prompt(PromptReference { name: "code_review".to_string() }),
+            argument: RmcpArgumentInfo { name: "language".to_string(), value: "py".to_string() },
+        };
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!({
+                "ref": { "type": "ref/prompt", "name": "code_review" },
+                "argument": { "name": "language", "value": "py" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_complete_result_parsing() {
+        let result: RmcpCompleteResult = serde_json::from_value(serde_json::json!({
+            "completion": { "values": ["python", "pytorch", "pyside"], "total": 10, "hasMore": true }
+        })).unwrap();
+        assert_eq!(result.completion.values, vec!["python", "pytorch", "pyside"]);
+        assert_eq!(result.completion.total, Some(10));
+        assert_eq!(result.completion.has_more, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_complete_returns_suggestions() {
+        let manager = test_manager();
+        connect_mock_server(&manager, "mock", MockServer::default()).await;
+
+        let reference = RmcpReference::Prompt(PromptReference { name: "code_review".to_string() });
+        let result = manager.complete("mock", reference, "language", "py").await.unwrap();
+        assert_eq!(result.completion.values, vec!["python", "pytorch", "pyside"]);
+    }
+}
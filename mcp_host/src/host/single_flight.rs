@@ -0,0 +1,137 @@
+// Deduplication of identical in-flight requests: while one caller is waiting on a request
+// for a key, later callers for the same key wait on that request instead of sending their
+// own. Only for idempotent requests such as tools/list.
+
+use crate::host::error::HostError;
+use anyhow::{anyhow, Result};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use log::debug;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+type SharedResult<T> = Shared<BoxFuture<'static, std::result::Result<T, Arc<anyhow::Error>>>>;
+
+/// In-flight requests by key, each shared by everyone asking for that key until it finishes
+pub struct SingleFlight<T: Clone> {
+    in_flight: Arc<Mutex<HashMap<String, SharedResult<T>>>>,
+}
+
+impl<T: Clone> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self { in_flight: Arc::clone(&self.in_flight) }
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self { in_flight: Arc::new(Mutex::new(HashMap::new())) }
+    }
+}
+
+impl<T: Clone> std::fmt::Debug for SingleFlight<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SingleFlight").field("in_flight", &self.in_flight.lock().unwrap().len()).finish()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Result of the request in flight for `key`, or of a new one made by `request` if there
+    /// is none. The entry is dropped once the request finishes, so a later call sends again.
+    pub async fn run<F, Fut>(&self, key: &str, request: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let shared = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(key) {
+                Some(shared) => {
+                    debug!("Joining in-flight request for '{}'", key);
+                    shared.clone()
+                }
+                None => {
+                    let map = Arc::clone(&self.in_flight);
+                    let owned_key = key.to_string();
+                    let fut = request();
+                    let shared = async move {
+                        let result = fut.await.map_err(Arc::new);
+                        map.lock().unwrap().remove(&owned_key);
+                        result
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.to_string(), shared.clone());
+                    shared
+                }
+            }
+        };
+        shared.await.map_err(unshare)
+    }
+}
+
+/// An owned copy of a shared failure: the error itself for the last waiter, otherwise a copy
+/// under the same message that keeps the `HostError` (if any) for callers to `downcast_ref`
+fn unshare(error: Arc<anyhow::Error>) -> anyhow::Error {
+    let error = match Arc::try_unwrap(error) {
+        Ok(error) => return error,
+        Err(error) => error,
+    };
+    match error.downcast_ref::<HostError>() {
+        Some(host_error) if host_error.to_string() == error.to_string() => anyhow::Error::new(host_error.clone()),
+        Some(host_error) => anyhow::Error::new(host_error.clone()).context(error.to_string()),
+        None => anyhow!("{:#}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_request() {
+        let flights: SingleFlight<usize> = SingleFlight::new();
+        let sent = Arc::new(AtomicUsize::new(0));
+        let call = || {
+            let sent = Arc::clone(&sent);
+            flights.run("key", move || async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok(sent.fetch_add(1, Ordering::SeqCst) + 1)
+            })
+        };
+        let (a, b) = tokio::join!(call(), call());
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 1));
+        assert_eq!(sent.load(Ordering::SeqCst), 1);
+
+        // Finished requests aren't cached
+        assert_eq!(call().await.unwrap(), 2);
+
+        // Errors reach every waiter
+        let fail = || flights.run("bad", || async { Err::<usize, _>(anyhow!("boom")) });
+        let (a, b) = tokio::join!(fail(), fail());
+        assert_eq!(a.unwrap_err().to_string(), "boom");
+        assert_eq!(b.unwrap_err().to_string(), "boom");
+    }
+
+    #[tokio::test]
+    async fn test_every_waiter_keeps_the_error_variant() {
+        let flights: SingleFlight<usize> = SingleFlight::new();
+        let fail = || {
+            flights.run("lost", || async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Err::<usize, _>(anyhow::Error::new(HostError::ConnectionLost("disconnected".to_string())).context("Listing tools failed"))
+            })
+        };
+        let (a, b) = tokio::join!(fail(), fail());
+        for err in [a.unwrap_err(), b.unwrap_err()] {
+            assert_eq!(err.to_string(), "Listing tools failed");
+            assert!(matches!(err.downcast_ref::<HostError>(), Some(HostError::ConnectionLost(_))), "{:?}", err);
+        }
+    }
+}
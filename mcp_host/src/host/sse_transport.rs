@@ -0,0 +1,632 @@
+// JSON-RPC over Server-Sent Events for remote MCP servers.
+// Server messages arrive on a long-lived GET stream; client messages are POSTed to the
+// endpoint the server announces. Dropped streams are resumed with Last-Event-ID.
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, Sink, Stream, StreamExt};
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::{RequestBuilder, Response, StatusCode, Url};
+use rmcp::service::{RoleClient, RxJsonRpcMessage, TxJsonRpcMessage};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::host::annotations::ToolAnnotationStore;
+use crate::host::middleware::Interceptors;
+
+const EVENT_STREAM: &str = "text/event-stream";
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+
+/// How long to wait between reconnection attempts
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Give up after this many failed attempts in a row (None retries forever)
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the given attempt (1-based), doubling each time up to `max_delay`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Change in an SSE server's connection state
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEvent {
+    Disconnected { reason: String },
+    Reconnected { attempts: u32 },
+}
+
+/// A connection state change, broadcast so the REPL can tell the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionNotice {
+    pub server: String,
+    pub event: ConnectionEvent,
+}
+
+/// One dispatched server-sent event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for the `text/event-stream` format
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    data_lines: Vec<String>,
+    /// Id of the last event seen, sent back as Last-Event-ID when reconnecting
+    pub last_event_id: Option<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of the response body and return any events it completes
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Drop any half-received event, e.g. after the connection broke mid-event
+    pub fn discard_partial(&mut self) {
+        self.buffer.clear();
+        self.current = SseEvent::default();
+        self.data_lines.clear();
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            // Blank line dispatches the event
+            if let Some(id) = &self.current.id {
+                self.last_event_id = Some(id.clone());
+            }
+            let mut event = std::mem::take(&mut self.current);
+            if self.data_lines.is_empty() {
+                return None;
+            }
+            event.data = std::mem::take(&mut self.data_lines).join("\n");
+            return Some(event);
+        }
+        if line.starts_with(':') {
+            return None; // Comment / keep-alive
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data_lines.push(value.to_string()),
+            "event" => self.current.event = Some(value.to_string()),
+            "id" if !value.contains('\0') => self.current.id = Some(value.to_string()),
+            _ => {} // "retry" and unknown fields are ignored
+        }
+        None
+    }
+}
+
+/// Fetches a fresh bearer token after the server answers 401
+pub type TokenRefresh = Arc<dyn Fn() -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// Headers and bearer token sent with every request to the server
+#[derive(Clone, Default)]
+pub struct HttpAuth {
+    headers: HeaderMap,
+    bearer_token: Arc<Mutex<Option<String>>>,
+    refresh: Option<TokenRefresh>,
+}
+
+impl HttpAuth {
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        request = request.headers(self.headers.clone());
+        if let Some(token) = self.bearer_token.lock().unwrap().as_deref() {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    /// Get a new token from the refresh callback; false if there is no callback or it failed
+    async fn refresh(&self, server_name: &str) -> bool {
+        let Some(refresh) = &self.refresh else { return false };
+        match refresh().await {
+            Ok(token) => {
+                info!("Refreshed bearer token for server '{}'", server_name);
+                *self.bearer_token.lock().unwrap() = Some(token);
+                true
+            }
+            Err(e) => {
+                warn!("Refreshing the bearer token for server '{}' failed: {}", server_name, e);
+                false
+            }
+        }
+    }
+
+    /// Send a request built by `build`, refreshing the token and retrying once on 401
+    async fn send(&self, server_name: &str, build: impl Fn() -> RequestBuilder) -> reqwest::Result<Response> {
+        let response = self.apply(build()).send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.refresh(server_name).await {
+            return self.apply(build()).send().await?.error_for_status();
+        }
+        response.error_for_status()
+    }
+}
+
+/// Open the event stream, resuming from `last_event_id` if given
+async fn open_stream(
+    http: &reqwest::Client,
+    url: &Url,
+    server_name: &str,
+    auth: &HttpAuth,
+    last_event_id: Option<&str>,
+) -> Result<BoxStream<'static, reqwest::Result<Bytes>>> {
+    let response = auth
+        .send(server_name, || {
+            let request = http.get(url.clone()).header(ACCEPT, EVENT_STREAM);
+            match last_event_id {
+                Some(id) => request.header(LAST_EVENT_ID, id),
+                None => request,
+            }
+        })
+        .await?;
+    let content_type = response.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !content_type.starts_with(EVENT_STREAM) {
+        return Err(anyhow!("Expected {} from {}, got '{}'", EVENT_STREAM, url, content_type));
+    }
+    Ok(response.bytes_stream().boxed())
+}
+
+/// Reads events from the server, reconnecting when the stream drops
+struct SseReader {
+    http: reqwest::Client,
+    url: Url,
+    server_name: String,
+    body: BoxStream<'static, reqwest::Result<Bytes>>,
+    parser: SseParser,
+    pending: VecDeque<SseEvent>,
+    post_url: Arc<Mutex<Url>>,
+    policy: ReconnectPolicy,
+    notices: broadcast::Sender<ConnectionNotice>,
+    annotations: Option<ToolAnnotationStore>,
+    interceptors: Interceptors,
+    auth: HttpAuth,
+}
+
+impl SseReader {
+    async fn next_event(&mut self) -> Option<SseEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let reason = match self.body.next().await {
+                Some(Ok(chunk)) => {
+                    let events = self.parser.feed(&chunk);
+                    self.pending.extend(events);
+                    continue;
+                }
+                Some(Err(e)) => e.to_string(),
+                None => "stream closed by server".to_string(),
+            };
+            if !self.reconnect(reason).await {
+                return None;
+            }
+        }
+    }
+
+    fn notify(&self, event: ConnectionEvent) {
+        // Nobody listening is fine
+        let _ = self.notices.send(ConnectionNotice { server: self.server_name.clone(), event });
+    }
+
+    /// Returns false once the policy's attempts are used up
+    async fn reconnect(&mut self, reason: String) -> bool {
+        warn!("SSE stream for server '{}' dropped: {}", self.server_name, reason);
+        self.notify(ConnectionEvent::Disconnected { reason });
+        self.parser.discard_partial();
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if self.policy.max_attempts.is_some_and(|max| attempt > max) {
+                warn!("Giving up reconnecting to server '{}' after {} attempts", self.server_name, attempt - 1);
+                return false;
+            }
+            tokio::time::sleep(self.policy.delay(attempt)).await;
+
+            let last_event_id = self.parser.last_event_id.as_deref();
+            match open_stream(&self.http, &self.url, &self.server_name, &self.auth, last_event_id).await {
+                Ok(body) => {
+                    info!(
+                        "Reconnected to server '{}' (attempt {}, Last-Event-ID {:?})",
+                        self.server_name, attempt, self.parser.last_event_id
+                    );
+                    self.body = body;
+                    self.notify(ConnectionEvent::Reconnected { attempts: attempt });
+                    return true;
+                }
+                Err(e) => debug!("Reconnect attempt {} to server '{}' failed: {}", attempt, self.server_name, e),
+            }
+        }
+    }
+
+    fn set_endpoint(&self, endpoint: &str) {
+        match self.url.join(endpoint) {
+            Ok(url) => *self.post_url.lock().unwrap() = url,
+            Err(e) => warn!("Server '{}' announced an invalid endpoint '{}': {}", self.server_name, endpoint, e),
+        }
+    }
+}
+
+/// Builds an rmcp-compatible (sink, stream) pair for an SSE MCP server.
+///
+/// ```ignore
+/// let transport = SseTransportBuilder::new("https://example.com/sse", "remote")
+///     .with_bearer_token_env("REMOTE_MCP_TOKEN")
+///     .with_headers([("X-Team", "tools")])?
+///     .connect()
+///     .await?;
+/// ```
+pub struct SseTransportBuilder {
+    url: String,
+    server_name: String,
+    http: reqwest::Client,
+    policy: ReconnectPolicy,
+    notices: Option<broadcast::Sender<ConnectionNotice>>,
+    annotations: Option<ToolAnnotationStore>,
+    interceptors: Interceptors,
+    auth: HttpAuth,
+    bearer_token_env: Option<String>,
+}
+
+impl SseTransportBuilder {
+    pub fn new(url: &str, server_name: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            server_name: server_name.to_string(),
+            http: reqwest::Client::new(),
+            policy: ReconnectPolicy::default(),
+            notices: None,
+            annotations: None,
+            interceptors: Interceptors::new(),
+            auth: HttpAuth::default(),
+            bearer_token_env: None,
+        }
+    }
+
+    /// Use this HTTP client (e.g. one with custom TLS or proxy settings)
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Report disconnects and reconnects on this channel
+    pub fn notices(mut self, notices: broadcast::Sender<ConnectionNotice>) -> Self {
+        self.notices = Some(notices);
+        self
+    }
+
+    /// Record tool annotations from tools/list results
+    pub fn annotations(mut self, annotations: ToolAnnotationStore) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Pass every message through these hooks on its way in or out
+    pub fn interceptors(mut self, interceptors: Interceptors) -> Self {
+        self.interceptors = interceptors;
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request
+    pub fn with_bearer_token(self, token: &str) -> Self {
+        *self.auth.bearer_token.lock().unwrap() = Some(token.to_string());
+        self
+    }
+
+    /// Read the bearer token from environment variable `var` when connecting
+    pub fn with_bearer_token_env(mut self, var: &str) -> Self {
+        self.bearer_token_env = Some(var.to_string());
+        self
+    }
+
+    /// Send these headers with every request. Fails on invalid header names or values.
+    pub fn with_headers<K: AsRef<str>, V: AsRef<str>>(
+        mut self,
+        headers: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self> {
+        for (name, value) in headers {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            let header_name = HeaderName::try_from(name).with_context(|| format!("Invalid header name '{}'", name))?;
+            let header_value = HeaderValue::try_from(value).with_context(|| format!("Invalid value for header '{}'", name))?;
+            self.auth.headers.insert(header_name, header_value);
+        }
+        Ok(self)
+    }
+
+    /// When the server answers 401, call `refresh` for a new bearer token and retry once
+    pub fn on_unauthorized<F, Fut>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<String>> + Send + 'static,
+    {
+        self.auth.refresh = Some(Arc::new(move || refresh().boxed()));
+        self
+    }
+
+    /// Connect and wait for the server's `endpoint` event before returning
+    pub async fn connect(
+        self,
+    ) -> Result<(
+        impl Sink<TxJsonRpcMessage<RoleClient>, Error = std::io::Error> + Send + 'static,
+        impl Stream<Item = RxJsonRpcMessage<RoleClient>> + Send + 'static,
+    )> {
+        let Self { url, server_name, http, policy, notices, annotations, interceptors, auth, bearer_token_env } = self;
+        if let Some(var) = bearer_token_env {
+            let token = std::env::var(&var)
+                .map_err(|_| anyhow!("Environment variable {} (bearer token for server '{}') is not set", var, server_name))?;
+            *auth.bearer_token.lock().unwrap() = Some(token);
+        }
+
+        let url = Url::parse(&url)?;
+        let body = open_stream(&http, &url, &server_name, &auth, None).await?;
+        let mut reader = SseReader {
+            http: http.clone(),
+            post_url: Arc::new(Mutex::new(url.clone())),
+            url,
+            server_name: server_name.clone(),
+            body,
+            parser: SseParser::new(),
+            pending: VecDeque::new(),
+            policy,
+            // Nobody listening is fine
+            notices: notices.unwrap_or_else(|| broadcast::channel(1).0),
+            annotations,
+            interceptors: interceptors.clone(),
+            auth: auth.clone(),
+        };
+
+        // The first event tells us where to POST our messages
+        loop {
+            let event = reader.next_event().await
+                .ok_or_else(|| anyhow!("Server '{}' closed the SSE stream before sending its endpoint", server_name))?;
+            if event.event.as_deref() == Some("endpoint") {
+                reader.set_endpoint(&event.data);
+                break;
+            }
+        }
+        info!("Server '{}' accepts messages at {}", server_name, reader.post_url.lock().unwrap());
+
+        let sink = futures::sink::unfold(
+            (http, Arc::clone(&reader.post_url), server_name, interceptors, auth),
+            |(http, post_url, server_name, interceptors, auth), message: TxJsonRpcMessage<RoleClient>| async move {
+                let body = interceptors.encode(&server_name, &message)?;
+                let url = post_url.lock().unwrap().clone();
+                auth.send(&server_name, || {
+                    http.post(url.clone()).header(CONTENT_TYPE, "application/json").body(body.clone())
+                })
+                .await
+                .map_err(std::io::Error::other)?;
+                Ok::<_, std::io::Error>((http, post_url, server_name, interceptors, auth))
+            },
+        );
+
+        let stream = futures::stream::unfold(reader, |mut reader| async move {
+            loop {
+                let event = reader.next_event().await?;
+                match event.event.as_deref() {
+                    // Servers may hand out a new endpoint after a reconnect
+                    Some("endpoint") => reader.set_endpoint(&event.data),
+                    None | Some("message") => {
+                        if let Some(annotations) = &reader.annotations {
+                            annotations.observe(&reader.server_name, event.data.as_bytes());
+                        }
+                        match reader.interceptors.decode::<RxJsonRpcMessage<RoleClient>>(&reader.server_name, event.data.as_bytes()) {
+                            Ok(message) => return Some((message, reader)),
+                            Err(e) => warn!("Skipping invalid message from server '{}': {}", reader.server_name, e),
+                        }
+                    }
+                    Some(other) => debug!("Ignoring '{}' event from server '{}'", other, reader.server_name),
+                }
+            }
+        });
+
+        Ok((sink, stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::SinkExt;
+    use serde_json::Value;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn sse_body(body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(body.as_bytes().to_vec(), EVENT_STREAM)
+    }
+
+    fn method_of(message: &RxJsonRpcMessage<RoleClient>) -> String {
+        let value: Value = serde_json::to_value(message).unwrap();
+        value["method"].as_str().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn test_parser_handles_split_chunks_and_comments() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b": keep-alive\nid: 7\nevent: mess").is_empty());
+        let events = parser.feed(b"age\r\ndata: {\"a\":\ndata: 1}\n\n");
+        assert_eq!(events, vec![SseEvent {
+            id: Some("7".to_string()),
+            event: Some("message".to_string()),
+            data: "{\"a\":\n1}".to_string(),
+        }]);
+        assert_eq!(parser.last_event_id.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            max_attempts: None,
+        };
+        let delays: Vec<_> = (1..=5).map(|n| policy.delay(n).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_stream_resumes_with_last_event_id() {
+        let server = MockServer::start().await;
+        // First connection: endpoint + one message, then the stream ends
+        Mock::given(method("GET"))
+            .and(path("/sse"))
+            .respond_with(sse_body(
+                "event: endpoint\ndata: /message?session=1\n\n\
+                 id: 41\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/tools/list_changed\"}\n\n",
+            ))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        // The reconnect must say where it left off
+        Mock::given(method("GET"))
+            .and(path("/sse"))
+            .and(header(LAST_EVENT_ID, "41"))
+            .respond_with(sse_body(
+                "id: 42\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/resources/list_changed\"}\n\n",
+            ))
+            .expect(1..)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/message"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (notices_tx, mut notices) = broadcast::channel(16);
+        let policy = ReconnectPolicy { initial_delay: Duration::from_millis(10), max_attempts: Some(3), ..Default::default() };
+        let (sink, stream) = SseTransportBuilder::new(&format!("{}/sse", server.uri()), "remote")
+            .reconnect_policy(policy)
+            .notices(notices_tx)
+            .connect()
+            .await
+            .expect("failed to connect");
+        let (mut sink, mut stream) = (Box::pin(sink), Box::pin(stream));
+
+        let first = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.unwrap().unwrap();
+        assert_eq!(method_of(&first), "notifications/tools/list_changed");
+        let second = tokio::time::timeout(Duration::from_secs(5), stream.next()).await.unwrap().unwrap();
+        assert_eq!(method_of(&second), "notifications/resources/list_changed");
+
+        assert!(matches!(notices.recv().await.unwrap().event, ConnectionEvent::Disconnected { .. }));
+        assert_eq!(notices.recv().await.unwrap().event, ConnectionEvent::Reconnected { attempts: 1 });
+
+        // Outgoing messages still go to the announced endpoint
+        let ping: TxJsonRpcMessage<RoleClient> =
+            serde_json::from_value(serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).unwrap();
+        sink.send(ping).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_auth_headers_sent_and_token_refreshed_on_401() {
+        let server = MockServer::start().await;
+        // The first token is rejected; the refreshed one is accepted
+        Mock::given(method("GET"))
+            .and(path("/sse"))
+            .and(header("authorization", "Bearer stale"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/sse"))
+            .and(header("authorization", "Bearer fresh"))
+            .and(header("x-team", "tools"))
+            .respond_with(sse_body("event: endpoint\ndata: /message\n\n"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/message"))
+            .and(header("authorization", "Bearer fresh"))
+            .and(header("x-team", "tools"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (sink, _stream) = SseTransportBuilder::new(&format!("{}/sse", server.uri()), "remote")
+            .with_bearer_token("stale")
+            .with_headers([("X-Team", "tools")])
+            .unwrap()
+            .on_unauthorized(|| async { Ok("fresh".to_string()) })
+            .connect()
+            .await
+            .expect("failed to connect");
+
+        let ping: TxJsonRpcMessage<RoleClient> =
+            serde_json::from_value(serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).unwrap();
+        Box::pin(sink).send(ping).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unset_bearer_token_env_is_an_error() {
+        let result = SseTransportBuilder::new("http://localhost/sse", "remote")
+            .with_bearer_token_env("SSE_TRANSPORT_TEST_UNSET_TOKEN")
+            .connect()
+            .await;
+        let err = result.err().expect("connect should fail");
+        assert_eq!(
+            err.to_string(),
+            "Environment variable SSE_TRANSPORT_TEST_UNSET_TOKEN (bearer token for server 'remote') is not set"
+        );
+    }
+
+    #[test]
+    fn test_invalid_header_is_rejected() {
+        assert!(SseTransportBuilder::new("http://localhost/sse", "remote").with_headers([("bad header", "x")]).is_err());
+    }
+}
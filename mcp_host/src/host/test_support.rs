@@ -0,0 +1,56 @@
+// Setup shared by the host's tests: hosts with a throwaway config directory, and servers
+// backed by a MockTransport instead of a process.
+
+use crate::host::mock_transport::{MockTransport, MockTransportHandle};
+use crate::host::server_manager::{ManagedServer, ServerManager};
+use crate::host::MCPHost;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// A fresh directory path under the system temp dir; not created until something writes to it
+pub fn test_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("mcp_host_test_{}", uuid::Uuid::new_v4()))
+}
+
+/// A host with no servers or provider, its config files in a fresh directory
+pub async fn test_host() -> MCPHost {
+    test_host_in(&test_dir()).await
+}
+
+/// A host reading `config.json` (if any) and `provider_models.toml` from `dir`
+pub async fn test_host_in(dir: &Path) -> MCPHost {
+    MCPHost::builder()
+        .config_path(dir.join("config.json"))
+        .provider_models_path(dir.join("provider_models.toml"))
+        .build()
+        .await
+        .expect("failed to build host")
+}
+
+/// Connect to `mock` and register it as server `name` of `host`, as if it had been started.
+/// Returns the handle for inspecting what the server was sent.
+pub async fn insert_mock_server(host: &MCPHost, name: &str, mock: MockTransport) -> MockTransportHandle {
+    register_mock_server(&host.server_manager(), name, mock).await
+}
+
+/// `insert_mock_server` for a bare `ServerManager`
+pub async fn register_mock_server(manager: &ServerManager, name: &str, mock: MockTransport) -> MockTransportHandle {
+    let handle = mock.handle();
+    let cancel = CancellationToken::new();
+    let (client, capabilities) = manager
+        .handshake(name, mock.into_transport(), cancel.clone())
+        .await
+        .expect("handshake failed");
+    manager.servers.lock().await.insert(name.to_string(), ManagedServer {
+        name: name.to_string(),
+        process: None,
+        client,
+        cancel,
+        capabilities: Some(capabilities),
+        last_used: Instant::now(),
+        calls_in_flight: Default::default(),
+        read_failure: Default::default(),
+    });
+    handle
+}
@@ -0,0 +1,276 @@
+// Typed construction of tool calls, validated client-side against the tool's input schema.
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use rmcp::model::CallToolRequestParam as RmcpCallToolRequestParam; // Alias CallToolRequestParam
+use rmcp::model::JsonObject;
+use serde_json::Value;
+
+use crate::host::error::HostError;
+use crate::host::MCPHost;
+
+/// Builder returned by `MCPHost::build_tool_call`.
+pub struct ToolCallBuilder<'a> {
+    host: &'a MCPHost,
+    server_name: String,
+    tool_name: String,
+    arguments: JsonObject,
+}
+
+impl<'a> ToolCallBuilder<'a> {
+    pub(crate) fn new(host: &'a MCPHost, server_name: &str, tool_name: &str) -> Self {
+        Self {
+            host,
+            server_name: server_name.to_string(),
+            tool_name: tool_name.to_string(),
+            arguments: JsonObject::new(),
+        }
+    }
+
+    /// Set a single argument, replacing any previous value for `key`
+    pub fn arg(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.arguments.insert(key.into(), value.into());
+        self
+    }
+
+    /// Look up the tool's `inputSchema` in the server's tool list and validate the arguments against it
+    pub async fn build(self) -> Result<RmcpCallToolRequestParam> {
+        let tool = self
+            .host
+            .tool_definition(&self.server_name, &self.tool_name)
+            .await?
+            .ok_or_else(|| anyhow!("Tool '{}' not found on server '{}'", self.tool_name, self.server_name))?;
+
+        validate_arguments(&self.tool_name, &tool.input_schema, &self.arguments)?;
+        debug!("Arguments for tool '{}' passed schema validation", self.tool_name);
+
+        Ok(RmcpCallToolRequestParam {
+            name: self.tool_name.into(),
+            arguments: Some(self.arguments),
+        })
+    }
+
+    /// Validate and send the call, returning the tool's output
+    pub async fn call(self) -> Result<String> {
+        let host = self.host;
+        let server_name = self.server_name.clone();
+        let params = self.build().await?;
+        let args = params.arguments.map(Value::Object).unwrap_or(Value::Null);
+        host.call_tool(&server_name, &params.name, args).await
+    }
+}
+
+/// Check `arguments` against a tool's JSON Schema: required fields must be present and
+/// declared properties must match their `type` and `enum`. Other schema keywords are not
+/// checked. Every problem found is listed in the error, separated by `; `.
+pub fn validate_arguments(tool_name: &str, schema: &JsonObject, arguments: &JsonObject) -> std::result::Result<(), HostError> {
+    let problems = schema_violations(schema, arguments);
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(HostError::InvalidToolArguments {
+            tool: tool_name.to_string(),
+            reason: problems.join("; "),
+        })
+    }
+}
+
+/// Fill in `defaults` for any argument the call doesn't set. Arguments of `null` become an
+/// object; arguments that aren't an object are left alone for the server to reject.
+pub fn apply_default_arguments(arguments: &mut Value, defaults: &JsonObject) {
+    if defaults.is_empty() {
+        return;
+    }
+    if arguments.is_null() {
+        *arguments = Value::Object(JsonObject::new());
+    }
+    if let Value::Object(arguments) = arguments {
+        for (key, value) in defaults {
+            arguments.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// Everything wrong with `arguments` according to `schema`, in a stable order
+pub fn schema_violations(schema: &JsonObject, arguments: &JsonObject) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for field in required.iter().filter_map(Value::as_str) {
+            if !arguments.contains_key(field) {
+                problems.push(format!("missing required field '{}'", field));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let mut keys: Vec<&String> = arguments.keys().collect();
+    keys.sort();
+    for key in keys {
+        let value = &arguments[key];
+        match properties.and_then(|p| p.get(key)) {
+            Some(property) => {
+                if let Some(expected) = property.get("type") {
+                    if !matches_type(expected, value) {
+                        problems.push(format!(
+                            "field '{}' should be {} but got {}",
+                            key,
+                            describe_type(expected),
+                            json_type_name(value)
+                        ));
+                        continue;
+                    }
+                }
+                if let Some(allowed) = property.get("enum").and_then(Value::as_array) {
+                    if !allowed.contains(value) {
+                        let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                        problems.push(format!("field '{}' must be one of {} but got {}", key, allowed.join(", "), value));
+                    }
+                }
+            }
+            None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                problems.push(format!("unknown field '{}'", key));
+            }
+            None => {}
+        }
+    }
+
+    problems
+}
+
+/// `type` may be a single name or a list of names (e.g. `["string", "null"]` for optional fields)
+fn matches_type(expected: &Value, value: &Value) -> bool {
+    match expected {
+        Value::String(name) => matches_type_name(name, value),
+        Value::Array(names) => names.iter().filter_map(Value::as_str).any(|name| matches_type_name(name, value)),
+        _ => true, // Malformed schema; let the server decide
+    }
+}
+
+fn matches_type_name(name: &str, value: &Value) -> bool {
+    match name {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn describe_type(expected: &Value) -> String {
+    match expected {
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or "),
+        other => other.as_str().unwrap_or("unknown").to_string(),
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> JsonObject {
+        json!({
+            "type": "object",
+            "properties": {
+                "command": { "type": "string" },
+                "timeout": { "type": ["integer", "null"] },
+                "verbose": { "type": "boolean" },
+                "shell": { "type": "string", "enum": ["sh", "bash"] }
+            },
+            "required": ["command"]
+        })
+        .as_object()
+        .cloned()
+        .unwrap()
+    }
+
+    fn args(value: Value) -> JsonObject {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_valid_arguments_pass() {
+        let arguments = args(json!({ "command": "ls", "timeout": null, "verbose": true }));
+        assert!(validate_arguments("bash", &schema(), &arguments).is_ok());
+    }
+
+    #[test]
+    fn test_missing_required_field() {
+        let err = validate_arguments("bash", &schema(), &args(json!({ "verbose": true }))).unwrap_err();
+        assert!(matches!(err, HostError::InvalidToolArguments { .. }));
+        assert_eq!(err.to_string(), "Invalid arguments for tool 'bash': missing required field 'command'");
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let err = validate_arguments("bash", &schema(), &args(json!({ "command": "ls", "timeout": "soon" }))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid arguments for tool 'bash': field 'timeout' should be integer or null but got string"
+        );
+    }
+
+    #[test]
+    fn test_enum_violation() {
+        assert!(validate_arguments("bash", &schema(), &args(json!({ "command": "ls", "shell": "bash" }))).is_ok());
+        let err = validate_arguments("bash", &schema(), &args(json!({ "command": "ls", "shell": "zsh" }))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid arguments for tool 'bash': field 'shell' must be one of \"sh\", \"bash\" but got \"zsh\""
+        );
+    }
+
+    #[test]
+    fn test_all_problems_reported() {
+        let problems = schema_violations(&schema(), &args(json!({ "shell": 1, "verbose": "yes" })));
+        assert_eq!(problems, vec![
+            "missing required field 'command'",
+            "field 'shell' should be string but got integer",
+            "field 'verbose' should be boolean but got string",
+        ]);
+    }
+
+    #[test]
+    fn test_unknown_field_rejected_only_when_closed() {
+        let mut closed = schema();
+        closed.insert("additionalProperties".to_string(), Value::Bool(false));
+        let arguments = args(json!({ "command": "ls", "extra": 1 }));
+
+        assert!(validate_arguments("bash", &schema(), &arguments).is_ok());
+        assert!(validate_arguments("bash", &closed, &arguments).is_err());
+    }
+
+    #[test]
+    fn test_default_arguments_fill_gaps_only() {
+        let defaults = args(json!({ "working_dir": "/srv/app", "shell": "bash" }));
+
+        let mut arguments = json!({ "command": "ls", "shell": "sh" });
+        apply_default_arguments(&mut arguments, &defaults);
+        assert_eq!(arguments, json!({ "command": "ls", "shell": "sh", "working_dir": "/srv/app" }));
+
+        let mut arguments = Value::Null;
+        apply_default_arguments(&mut arguments, &defaults);
+        assert_eq!(arguments, json!({ "working_dir": "/srv/app", "shell": "bash" }));
+
+        // Without defaults a null call stays null
+        let mut arguments = Value::Null;
+        apply_default_arguments(&mut arguments, &JsonObject::new());
+        assert_eq!(arguments, Value::Null);
+    }
+}
@@ -0,0 +1,285 @@
+// Line-delimited JSON-RPC transport over a child process's stdin/stdout.
+// Replaces rmcp's TokioChildProcess so we control how incoming lines are read.
+
+use futures::{Sink, Stream};
+use log::{debug, error, warn};
+use rmcp::service::{RoleClient, RxJsonRpcMessage, TxJsonRpcMessage};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::host::annotations::ToolAnnotationStore;
+use crate::host::error::HostError;
+use crate::host::middleware::Interceptors;
+
+/// Default cap on a single incoming message (16 MiB)
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Why a transport stopped reading, if it stopped on an error rather than at EOF. rmcp only
+/// tells waiting requests that the connection is gone; this keeps the reason for them.
+#[derive(Debug, Clone, Default)]
+pub struct ReadFailure(Arc<Mutex<Option<HostError>>>);
+
+impl ReadFailure {
+    fn set(&self, error: HostError) {
+        *self.0.lock().unwrap() = Some(error);
+    }
+
+    pub fn get(&self) -> Option<HostError> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Read one newline-terminated message without buffering more than `max_message_bytes`.
+/// Returns `Ok(None)` at EOF. The trailing newline (and any `\r`) is stripped.
+/// Bytes are accumulated until the newline arrives, so a message may be written in any
+/// number of pieces and may be larger than the reader's buffer. Not cancel-safe: dropping
+/// the future part way through a message loses what was read of it.
+pub async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_message_bytes: usize,
+) -> std::result::Result<Option<Vec<u8>>, HostError> {
+    let mut message = Vec::new();
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            // EOF - return whatever was read without a terminating newline
+            return Ok(if message.is_empty() { None } else { Some(message) });
+        }
+
+        let (chunk_len, found_newline) = match available.iter().position(|b| *b == b'\n') {
+            Some(pos) => (pos, true),
+            None => (available.len(), false),
+        };
+
+        if message.len() + chunk_len > max_message_bytes {
+            return Err(HostError::MessageTooLarge {
+                size: message.len() + chunk_len,
+                limit: max_message_bytes,
+            });
+        }
+
+        message.extend_from_slice(&available[..chunk_len]);
+        reader.consume(if found_newline { chunk_len + 1 } else { chunk_len });
+
+        if found_newline {
+            if message.last() == Some(&b'\r') {
+                message.pop();
+            }
+            return Ok(Some(message));
+        }
+    }
+}
+
+/// Build an rmcp-compatible (sink, stream) pair from a server's stdout and stdin.
+/// Lines that aren't valid JSON-RPC (e.g. stray log output) are skipped with a warning.
+/// Reading stops (closing the connection) if a message exceeds `max_message_bytes`; the
+/// error is kept in `read_failure`.
+/// Tool annotations in tools/list results are recorded in `annotations`, if given.
+/// Every message passes through `interceptors` on its way in or out.
+pub fn line_transport<R, W>(
+    reader: R,
+    writer: W,
+    max_message_bytes: usize,
+    server_name: &str,
+    annotations: Option<ToolAnnotationStore>,
+    interceptors: Interceptors,
+    read_failure: ReadFailure,
+) -> (
+    impl Sink<TxJsonRpcMessage<RoleClient>, Error = std::io::Error> + Send + 'static,
+    impl Stream<Item = RxJsonRpcMessage<RoleClient>> + Send + 'static,
+)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let sink = futures::sink::unfold(
+        (writer, server_name.to_string(), interceptors.clone()),
+        |(mut writer, server_name, interceptors), message: TxJsonRpcMessage<RoleClient>| async move {
+            let mut bytes = interceptors.encode(&server_name, &message)?;
+            bytes.push(b'\n');
+            writer.write_all(&bytes).await?;
+            writer.flush().await?;
+            Ok::<_, std::io::Error>((writer, server_name, interceptors))
+        },
+    );
+
+    let server_name = server_name.to_string();
+    let stream = futures::stream::unfold(
+        (BufReader::new(reader), server_name, annotations, interceptors, read_failure),
+        move |(mut reader, server_name, annotations, interceptors, read_failure)| async move {
+            loop {
+                match read_message(&mut reader, max_message_bytes).await {
+                    Ok(Some(line)) => {
+                        if line.iter().all(|b| b.is_ascii_whitespace()) {
+                            continue; // Ignore blank lines
+                        }
+                        if let Some(annotations) = &annotations {
+                            annotations.observe(&server_name, &line);
+                        }
+                        match interceptors.decode::<RxJsonRpcMessage<RoleClient>>(&server_name, &line) {
+                            Ok(message) => return Some((message, (reader, server_name, annotations, interceptors, read_failure))),
+                            Err(e) => {
+                                // Servers often leak log lines onto stdout; don't drop the connection over it
+                                warn!(
+                                    "Skipping non-JSON-RPC line from server '{}' ({}): {}",
+                                    server_name,
+                                    e,
+                                    String::from_utf8_lossy(&line)
+                                );
+                                continue;
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        debug!("Server '{}' closed its stdout", server_name);
+                        return None;
+                    }
+                    Err(e) => {
+                        error!("Stopped reading from server '{}': {}", server_name, e);
+                        read_failure.set(e);
+                        return None;
+                    }
+                }
+            }
+        },
+    );
+
+    (sink, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::serve_client;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn test_read_message_splits_lines() {
+        let mut reader = BufReader::new(&b"{\"a\":1}\r\n{\"b\":2}\n"[..]);
+        assert_eq!(read_message(&mut reader, 64).await.unwrap().unwrap(), b"{\"a\":1}");
+        assert_eq!(read_message(&mut reader, 64).await.unwrap().unwrap(), b"{\"b\":2}");
+        assert!(read_message(&mut reader, 64).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_message_split_across_writes_is_read_once_complete() {
+        let (mut server_end, client_end) = tokio::io::duplex(64 * 1024);
+        // A buffer smaller than the message, so it is also read in several pieces
+        let mut reader = BufReader::with_capacity(16, client_end);
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "tools": [{ "name": "bash", "description": "Run a command", "inputSchema": { "type": "object" } }] }
+        })
+        .to_string();
+        let (first, second) = message.split_at(message.len() / 2);
+
+        server_end.write_all(first.as_bytes()).await.unwrap();
+        let read = tokio::spawn(async move { read_message(&mut reader, DEFAULT_MAX_MESSAGE_BYTES).await });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!read.is_finished(), "returned a message before its newline arrived");
+
+        server_end.write_all(format!("{}\n", second).as_bytes()).await.unwrap();
+        let line = read.await.unwrap().unwrap().expect("a complete message");
+        assert_eq!(line, message.as_bytes());
+        let parsed: Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(parsed.pointer("/result/tools/0/name"), Some(&json!("bash")));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_is_rejected() {
+        // A 1 MiB line with no newline, read through a small buffer
+        let oversized = vec![b'x'; 1024 * 1024];
+        let mut reader = BufReader::with_capacity(1024, &oversized[..]);
+
+        match read_message(&mut reader, 4096).await {
+            Err(HostError::MessageTooLarge { size, limit }) => {
+                assert_eq!(limit, 4096);
+                assert!(size > limit && size <= limit + 1024, "read {} bytes past the limit", size);
+            }
+            other => panic!("expected MessageTooLarge, got {:?}", other),
+        }
+    }
+
+    /// Hand-rolled server that prints a junk line before every tools/list response
+    async fn run_noisy_server(stream: tokio::io::DuplexStream) {
+        let (read_half, mut write_half) = tokio::io::split(stream);
+        let mut reader = BufReader::new(read_half);
+        while let Ok(Some(line)) = read_message(&mut reader, DEFAULT_MAX_MESSAGE_BYTES).await {
+            let request: Value = serde_json::from_slice(&line).unwrap();
+            let Some(id) = request.get("id").cloned() else { continue }; // Skip notifications
+            let result = match request["method"].as_str() {
+                Some("initialize") => json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": { "name": "noisy", "version": "0.0.0" }
+                }),
+                Some("tools/list") => {
+                    write_half.write_all(b"INFO server is doing things\n").await.unwrap();
+                    json!({ "tools": [] })
+                }
+                _ => json!({}),
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            write_half.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_junk_lines_are_skipped() {
+        let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(run_noisy_server(server_end));
+
+        let (read_half, write_half) = tokio::io::split(client_end);
+        let transport = line_transport(read_half, write_half, DEFAULT_MAX_MESSAGE_BYTES, "noisy", None, Interceptors::new(), ReadFailure::default());
+        let client = serve_client((), transport).await.expect("handshake failed");
+
+        // Both calls succeed even though each response is preceded by a junk line
+        assert!(client.peer().list_tools(None).await.unwrap().tools.is_empty());
+        assert!(client.peer().list_tools(None).await.unwrap().tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_fields_reach_the_server() {
+        let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+        let (server_read, mut server_write) = tokio::io::split(server_end);
+        // Server that records each request's params and answers tools/list
+        let server = tokio::spawn(async move {
+            let mut reader = BufReader::new(server_read);
+            let mut tools_list_params = None;
+            while let Ok(Some(line)) = read_message(&mut reader, DEFAULT_MAX_MESSAGE_BYTES).await {
+                let request: Value = serde_json::from_slice(&line).unwrap();
+                let Some(id) = request.get("id").cloned() else { continue };
+                let result = match request["method"].as_str() {
+                    Some("initialize") => json!({
+                        "protocolVersion": "2024-11-05",
+                        "capabilities": {},
+                        "serverInfo": { "name": "auth", "version": "0.0.0" }
+                    }),
+                    Some("tools/list") => {
+                        tools_list_params = Some(request["params"].clone());
+                        json!({ "tools": [] })
+                    }
+                    _ => json!({}),
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                server_write.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+            }
+            tools_list_params
+        });
+
+        let interceptors = Interceptors::new().on_request(|server, message| {
+            if message.get("id").is_some() {
+                message["params"]["_meta"]["authToken"] = json!(format!("token-for-{}", server));
+            }
+        });
+        let (read_half, write_half) = tokio::io::split(client_end);
+        let transport = line_transport(read_half, write_half, DEFAULT_MAX_MESSAGE_BYTES, "auth", None, interceptors, ReadFailure::default());
+        let client = serve_client((), transport).await.expect("handshake failed");
+        client.peer().list_tools(None).await.unwrap();
+        client.cancel().await.unwrap();
+
+        let params = server.await.unwrap().expect("tools/list was not received");
+        assert_eq!(params["_meta"]["authToken"], "token-for-auth");
+    }
+}
@@ -0,0 +1,141 @@
+// Workloads for measuring the request/response throughput and latency of the transports the
+// host talks to servers over, against in-memory servers so only the transport and rmcp's
+// request handling are timed. Used by the criterion benchmarks in `benches/transport.rs`;
+// the unit tests run a short version of them. Enabled with the `testing` feature.
+
+use anyhow::{anyhow, Result};
+use futures::stream::{self, StreamExt};
+use rmcp::model::CallToolRequestParam;
+use rmcp::service::{RoleClient, RunningService};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWriteExt, BufReader, DuplexStream};
+
+use crate::host::middleware::Interceptors;
+use crate::host::mock_transport::{MockTransport, METHOD_NOT_FOUND};
+use crate::host::transport::{line_transport, read_message, ReadFailure, DEFAULT_MAX_MESSAGE_BYTES};
+
+/// The one tool the benchmark servers offer. It takes no arguments and answers "ok".
+pub const BENCH_TOOL: &str = "noop";
+
+/// Buffer size of the in-memory pipe standing in for a server's stdin/stdout
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// A transport to benchmark. New transports get a variant here so they are measured
+/// against the same workloads and baselines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchTransport {
+    /// `MockTransport`: messages are passed as values and never serialized to bytes, so
+    /// this is the cost of rmcp's request handling alone
+    Mock,
+    /// `line_transport`, which server processes are spoken to over, on an in-memory pipe
+    Line,
+}
+
+impl BenchTransport {
+    pub const ALL: [BenchTransport; 2] = [BenchTransport::Mock, BenchTransport::Line];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BenchTransport::Mock => "mock",
+            BenchTransport::Line => "line",
+        }
+    }
+}
+
+pub type BenchClient = RunningService<RoleClient, ()>;
+
+fn noop_result() -> Value {
+    json!({ "content": [{ "type": "text", "text": "ok" }] })
+}
+
+/// A client connected over `transport` to a server offering `BENCH_TOOL`
+pub async fn connect(transport: BenchTransport) -> Result<BenchClient> {
+    let client = match transport {
+        BenchTransport::Mock => {
+            let mock = MockTransport::new().respond("tools/call", noop_result());
+            rmcp::serve_client((), mock.into_transport()).await
+        }
+        BenchTransport::Line => {
+            let (client_end, server_end) = tokio::io::duplex(PIPE_CAPACITY);
+            tokio::spawn(serve_noop(server_end));
+            let (reader, writer) = tokio::io::split(client_end);
+            let transport = line_transport(reader, writer, DEFAULT_MAX_MESSAGE_BYTES, "bench", None, Interceptors::new(), ReadFailure::default());
+            rmcp::serve_client((), transport).await
+        }
+    };
+    client.map_err(|e| anyhow!("Benchmark handshake over the {} transport failed: {}", transport.name(), e))
+}
+
+/// Line-delimited server answering `initialize` and `tools/call` until the pipe closes
+async fn serve_noop(stream: DuplexStream) {
+    let (read_half, mut write_half) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read_half);
+    while let Ok(Some(line)) = read_message(&mut reader, DEFAULT_MAX_MESSAGE_BYTES).await {
+        let Ok(request) = serde_json::from_slice::<Value>(&line) else { continue };
+        let Some(id) = request.get("id").cloned() else { continue }; // Notifications get no reply
+        let reply = match request["method"].as_str() {
+            Some("initialize") => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "bench", "version": "0.0.0" }
+                }
+            }),
+            Some("tools/call") => json!({ "jsonrpc": "2.0", "id": id, "result": noop_result() }),
+            _ => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": METHOD_NOT_FOUND, "message": "not supported" } }),
+        };
+        let mut bytes = reply.to_string().into_bytes();
+        bytes.push(b'\n');
+        if write_half.write_all(&bytes).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn call_noop(client: &BenchClient) -> Result<()> {
+    let params = CallToolRequestParam { name: BENCH_TOOL.into(), arguments: None };
+    client.peer().call_tool(params).await.map_err(|e| anyhow!("Benchmark call failed: {}", e))?;
+    Ok(())
+}
+
+/// Make `requests` calls one after another, each waiting for the last; the mean round trip
+pub async fn sequential_calls(client: &BenchClient, requests: usize) -> Result<Duration> {
+    let start = Instant::now();
+    for _ in 0..requests {
+        call_noop(client).await?;
+    }
+    Ok(start.elapsed() / requests.max(1) as u32)
+}
+
+/// Make `requests` calls with up to `in_flight` outstanding at once; the time for all of them
+pub async fn concurrent_calls(client: &BenchClient, requests: usize, in_flight: usize) -> Result<Duration> {
+    let start = Instant::now();
+    let results: Vec<Result<()>> = stream::iter(0..requests)
+        .map(|_| call_noop(client))
+        .buffer_unordered(in_flight.max(1))
+        .collect()
+        .await;
+    results.into_iter().collect::<Result<Vec<_>>>()?;
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A short run of the benchmark workloads, so they keep working without criterion
+    #[tokio::test]
+    async fn test_short_benchmark_run() {
+        for transport in BenchTransport::ALL {
+            let client = connect(transport).await.unwrap();
+            sequential_calls(&client, 50).await.unwrap();
+            let total = concurrent_calls(&client, 200, 16).await.unwrap();
+            // Only a sanity bound; the benchmarks are for real numbers
+            assert!(total < Duration::from_secs(10), "{} transport took {:?} for 200 calls", transport.name(), total);
+            client.cancel().await.unwrap();
+        }
+    }
+}
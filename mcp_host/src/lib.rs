@@ -3,12 +3,20 @@ pub mod ai_client;
 pub mod conversation_service;
 pub mod repl;
 pub mod main_repl;
+pub mod batch;
 pub mod conversation_state;
+pub mod conversation_journal;
 pub mod conversation_logic; // Add this line
 pub mod host;
 pub mod tool_parser;
+pub mod redaction;
+pub mod prompt_templates;
+#[cfg(feature = "otel")]
+pub mod telemetry;
 pub mod rllm_adapter;
 pub mod openrouter;
+#[cfg(any(test, feature = "testing"))]
+pub mod fake_ai_client;
 
 // Re-export key components 
 pub use crate::host::MCPHost;
@@ -5,9 +5,10 @@ use mcp_host::main_repl; // Use the main_repl module from the library crate
 async fn main() -> Result<()> {
     // Load environment variables from .env file if it exists
     // This should be one of the first things the application does.
+    // Reported on stderr: logging isn't set up yet, and in batch mode stdout carries only results.
     match dotenvy::dotenv() {
-        Ok(path) => println!("Loaded .env file from: {}", path.display()),
-        Err(_) => println!("No .env file found or failed to load."), // It's okay if it doesn't exist
+        Ok(path) => eprintln!("Loaded .env file from: {}", path.display()),
+        Err(_) => eprintln!("No .env file found or failed to load."), // It's okay if it doesn't exist
     }
 
     // Simply forward to the REPL implementation defined in the library
@@ -1,4 +1,4 @@
-use anyhow::{Result}; // Removed anyhow function/macro import
+use anyhow::{anyhow, Result};
 use tracing_subscriber::{fmt, EnvFilter}; // Import EnvFilter
 use tracing_appender;
 use std::time::Duration;
@@ -6,6 +6,62 @@ use log::{info, error};
 use console::style;
 use tracing_appender::non_blocking::WorkerGuard; // Import the guard type
 use std::path::PathBuf; // Add PathBuf
+use std::io::{IsTerminal, Read};
+use crate::batch::BatchOptions;
+
+/// Where batch input comes from, if running non-interactively
+enum BatchInput {
+    File(PathBuf),
+    Stdin,
+}
+
+/// Parse `--input <file>`, `--server <name>`, `--provider <name>`, `--verify` and `--json`.
+/// (`--profile <name>` is handled with the config path.)
+/// Batch mode is used when `--input` is given or stdin is not a terminal.
+fn parse_batch_args(args: &[String]) -> Result<Option<(BatchInput, BatchOptions)>> {
+    let mut input = None;
+    let mut options = BatchOptions::default();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| iter.next().cloned().ok_or_else(|| anyhow!("{} needs a value", flag));
+        match arg.as_str() {
+            "--input" => input = Some(BatchInput::File(PathBuf::from(value("--input")?))),
+            "--server" => options.server = Some(value("--server")?),
+            "--provider" => options.provider = Some(value("--provider")?),
+            "--verify" => options.verify = true,
+            "--json" => options.json = true,
+            "--profile" => {
+                value("--profile")?;
+            }
+            _ => {}
+        }
+    }
+    if options.json && input.is_none() && std::io::stdin().is_terminal() {
+        return Err(anyhow!("--json needs prompts from --input <file> or stdin"));
+    }
+    if input.is_none() && !std::io::stdin().is_terminal() {
+        input = Some(BatchInput::Stdin);
+    }
+    Ok(input.map(|input| (input, options)))
+}
+
+/// Resolve `--profile <name>` to `mcp_host_config.<name>.json`, next to the
+/// `load_config` path if one was given, otherwise in the default config directory.
+fn profile_config_path(args: &[String], config_path: Option<&str>) -> Result<Option<PathBuf>> {
+    let Some(index) = args.iter().position(|a| a == "--profile") else { return Ok(None) };
+    let profile = args.get(index + 1).ok_or_else(|| anyhow!("--profile needs a value"))?;
+    let dir = match config_path {
+        Some(path) => PathBuf::from(path).parent().map(|p| p.to_path_buf()).unwrap_or_default(),
+        None => dirs::config_dir()
+            .map(|p| p.join("mcp"))
+            .ok_or_else(|| anyhow!("Could not determine the config directory for --profile"))?,
+    };
+    let path = crate::host::config::profile_config_path(&dir, profile)?;
+    if !path.exists() {
+        return Err(anyhow!("Profile '{}' not found (expected {})", profile, path.display()));
+    }
+    Ok(Some(path))
+}
 
 /// Main entry point for the MCP host REPL
 pub async fn main() -> Result<()> {
@@ -16,27 +72,36 @@ pub async fn main() -> Result<()> {
     log::debug!("Logging setup complete in main_repl::main.");
     // --- End added log line ---
 
+    let args: Vec<String> = std::env::args().collect();
+    let batch = parse_batch_args(&args)?;
+
     // Print startup info - More structured
-    println!("\n{}", style("--- MCP Host REPL ---").cyan().bold());
+    if batch.is_none() {
+        println!("\n{}", style("--- MCP Host REPL ---").cyan().bold());
+    }
     // println!("Current directory: {:?}", std::env::current_dir().unwrap_or_default()); // Less verbose startup
     // println!("Command line args: {:?}", std::env::args().collect::<Vec<_>>()); // Less verbose startup
 
     // Parse command line arguments
-    let args: Vec<String> = std::env::args().collect();
     let mut config_path_opt: Option<&str> = None;
     
     // Check for config file argument
-    if args.len() > 2 && args[1] == "load_config" {
+    let profile_path = profile_config_path(&args, (args.len() > 2 && args[1] == "load_config").then(|| args[2].as_str()))?;
+    if let Some(path) = &profile_path {
+        info!("Using profile config: {}", path.display());
+    } else if args.len() > 2 && args[1] == "load_config" {
         config_path_opt = Some(&args[2]);
         info!("Config path specified: {}", args[2]);
     } else {
+        // In batch mode stdout carries only the results, so these go to the log instead
+        let notice = |message: String| if batch.is_none() { println!("{}", message) } else { info!("{}", message) };
         // --- Suggestion: Add Default Path ---
         let default_path_buf = dirs::config_dir()
             .map(|p| p.join("mcp/mcp_host_config.json"));
 
         if let Some(ref path_buf) = default_path_buf {
              if path_buf.exists() {
-                 println!("No config path specified, attempting to load default: {}", path_buf.display());
+                 notice(format!("No config path specified, attempting to load default: {}", path_buf.display()));
                  // Need to store the path string to pass its slice later
                  let path_str = path_buf.to_str().map(|s| s.to_string());
                  if let Some(_s) = path_str { // Prefix with underscore
@@ -44,13 +109,13 @@ pub async fn main() -> Result<()> {
                      // For simplicity, let's just load it here if it exists
                      // Or better, pass the PathBuf to the builder
                  } else {
-                      println!("Could not convert default path to string.");
+                      notice("Could not convert default path to string.".to_string());
                  }
              } else {
-                 println!("No config file specified and default not found ({}). Use 'load_config <path>' or create the default file.", path_buf.display());
+                 notice(format!("No config file specified and default not found ({}). Use 'load_config <path>' or create the default file.", path_buf.display()));
              }
         } else {
-             println!("No config file specified and could not determine default config path.");
+             notice("No config file specified and could not determine default config path.".to_string());
         }
         // --- End Suggestion ---
     }
@@ -62,7 +127,9 @@ pub async fn main() -> Result<()> {
         .client_info("mcp-host-repl", "1.0.0");
 
     // Pass config path to builder if specified or default exists
-    if let Some(path_str) = config_path_opt {
+    if let Some(path) = profile_path {
+        host_builder = host_builder.config_path(path);
+    } else if let Some(path_str) = config_path_opt {
         host_builder = host_builder.config_path(PathBuf::from(path_str));
     } else if let Some(default_path_buf) = dirs::config_dir().map(|p| p.join("mcp/mcp_host_config.json")) {
          if default_path_buf.exists() {
@@ -82,11 +149,42 @@ pub async fn main() -> Result<()> {
         }
     };
 
+    // --- Batch Mode: answer the input's prompts and exit ---
+    if let Some((input, options)) = batch {
+        if options.json {
+            // Keep stdout parseable
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+        let input = match input {
+            BatchInput::File(path) => std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("Failed to read input file {}: {}", path.display(), e))?,
+            BatchInput::Stdin => {
+                let mut buf = String::new();
+                std::io::stdin().read_to_string(&mut buf)?;
+                buf
+            }
+        };
+        let initial_config = host.config.lock().await.clone();
+        match host.apply_config(initial_config).await {
+            Ok(report) if !report.all_started() => eprintln!("Warning: {}", report.failure_summary()),
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to apply initial server configuration: {}", e);
+                eprintln!("Warning: Failed to start servers from initial config: {}", e);
+            }
+        }
+        let result = crate::batch::run_batch(&host, &input, &options).await;
+        #[cfg(feature = "otel")]
+        crate::telemetry::shutdown();
+        return result;
+    }
+
     // --- Print API Key Status ---
     println!("\n{}", style("AI Provider Key Status:").bold());
     let known_providers = [
         "openai", "anthropic", "deepseek", "gemini", "google",
-        "ollama", "xai", "grok", "phind", "groq", "openrouter"
+        "ollama", "xai", "grok", "phind", "groq", "openrouter", "azure"
     ];
     let mut found_keys = Vec::new();
     let mut missing_keys = Vec::new();
@@ -149,17 +247,26 @@ pub async fn main() -> Result<()> {
         let config_guard = host.config.lock().await;
         (*config_guard).clone() // Clone the Config inside the guard
     };
-    if let Err(e) = host.apply_config(initial_config).await {
-         error!("Failed to apply initial server configuration: {}", e);
-         println!("{}", style(format!("Warning: Failed to start servers from initial config: {}", e)).yellow());
-         // Decide how to handle this - maybe exit or continue without servers?
-    } else {
-         info!("Initial server configuration applied.");
+    match host.apply_config(initial_config).await {
+        Ok(report) => {
+            info!("Initial server configuration applied.");
+            if !report.all_started() {
+                println!("{}", style(report.failure_summary()).red());
+            }
+        }
+        Err(e) => {
+            error!("Failed to apply initial server configuration: {}", e);
+            println!("{}", style(format!("Warning: Failed to start servers from initial config: {}", e)).yellow());
+            // Decide how to handle this - maybe exit or continue without servers?
+        }
     }
     info!("Returned from apply_config in main_repl."); // <-- Add log here
 
     // Run the REPL interface
-    host.run_repl().await
+    let result = host.run_repl().await;
+    #[cfg(feature = "otel")]
+    crate::telemetry::shutdown();
+    result
 }
 
 // Return the WorkerGuard to keep it alive
@@ -200,6 +307,7 @@ pub fn setup_logging() -> Option<WorkerGuard> {
         let env_filter = EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| EnvFilter::new("warn"));
 
+        #[cfg(not(feature = "otel"))]
         let subscriber = fmt() // Use fmt directly
             .with_env_filter(env_filter) // Apply the filter
             .with_writer(non_blocking) // Log to file
@@ -208,7 +316,26 @@ pub fn setup_logging() -> Option<WorkerGuard> {
             .with_file(true)
             .with_line_number(true)
             .with_target(true);
-            
+
+        // The same log output, plus spans sent to OpenTelemetry if an exporter is configured.
+        // RUST_LOG filters only the log output; spans are exported at INFO and above.
+        #[cfg(feature = "otel")]
+        let subscriber = {
+            use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, Layer};
+            let log_layer = fmt::layer()
+                .with_writer(non_blocking)
+                .with_writer(std::io::stderr)
+                .with_thread_ids(true)
+                .with_file(true)
+                .with_line_number(true)
+                .with_target(true)
+                .with_filter(env_filter);
+            let otel_layer = crate::telemetry::layer_from_env().with_filter(LevelFilter::INFO);
+            tracing_subscriber::registry().with(log_layer).with(otel_layer)
+        };
+        #[cfg(feature = "otel")]
+        use tracing_subscriber::util::SubscriberInitExt;
+
         // Try to initialize, but don't panic if it fails
         match subscriber.try_init() {
             Ok(_) => {
@@ -157,6 +157,7 @@ impl AIClient for OpenRouterClient {
     }
 }
 
+#[derive(Clone)]
 struct OpenRouterRequestBuilder {
     api_key: String,
     model_name: String,
@@ -216,6 +217,11 @@ impl AIRequestBuilder for OpenRouterRequestBuilder {
         self
     }
 
+    fn try_clone(&self) -> Option<Box<dyn AIRequestBuilder>> {
+        Some(Box::new(self.clone()))
+    }
+
+    #[cfg_attr(feature = "otel", tracing::instrument(name = "execute", skip_all, fields(model = %self.model_name)))]
     async fn execute(self: Box<Self>) -> Result<String> {
         info!("Executing OpenRouter request for model: {}", self.model_name);
 
@@ -0,0 +1,129 @@
+// Templates for the prompts the host writes on its own behalf: generating success criteria
+// for a request and verifying a response against them. The built-in English prompts are the
+// defaults; the `prompts` config section can replace either, inline or from a file.
+
+use anyhow::{Context, Result};
+use log::warn;
+
+use crate::host::config::{PromptTemplate, PromptsConfig};
+
+/// Built-in prompt asking for the success criteria of `{request}`
+pub const DEFAULT_CRITERIA_TEMPLATE: &str = "Based on the following user request, list concise, verifiable criteria for a successful response. \
+Focus on key actions, information requested, and constraints mentioned. \
+Output ONLY the criteria list, one criterion per line, starting with '- '. Do not include any other text.\n\n\
+User Request:\n```\n{request}\n```\n\nCriteria:";
+
+/// Built-in prompt asking whether `{conversation}` meets `{criteria}` for `{request}`
+pub const DEFAULT_VERIFICATION_TEMPLATE: &str = "You are a strict evaluator. Verify if the 'Relevant Conversation Sequence' below meets ALL the 'Success Criteria' based on the 'Original User Request'.\n\n\
+Original User Request:\n```\n{request}\n```\n\n\
+Success Criteria:\n```\n{criteria}\n```\n\n\
+Relevant Conversation Sequence (User messages, Assistant actions/responses, Tool results):\n```\n{conversation}\n```\n\n\
+Instructions:\n\
+1. Carefully review the *entire sequence* including user feedback, assistant actions (tool calls/results shown), and the final response.\n\
+2. Compare this sequence against each point in the 'Success Criteria'.\n\
+3. Determine if the *outcome* of the assistant's actions and the final response *fully and accurately* satisfy *all* criteria.\n\
+4. Output ONLY the raw JSON object. Your entire response must start with `{` and end with `}`.\n\
+5. The JSON object must have the following structure: `{\"passes\": boolean, \"feedback\": \"string (provide concise feedback ONLY if passes is false, explaining which criteria failed and why, referencing the assistant's actions/responses if relevant)\"}`\n\
+6. ABSOLUTELY DO NOT include any other text, explanations, apologies, introductory phrases, or markdown formatting like ```json or ```.";
+
+impl PromptTemplate {
+    /// The template's text, reading it from its file if it names one
+    pub fn text(&self) -> Result<String> {
+        match self {
+            PromptTemplate::Text(text) => Ok(text.clone()),
+            PromptTemplate::File { file } => {
+                let path = shellexpand::tilde(&file.to_string_lossy()).into_owned();
+                std::fs::read_to_string(&path).with_context(|| format!("Failed to read prompt template {}", path))
+            }
+        }
+    }
+}
+
+/// Fill in `{name}` placeholders in one pass, so placeholder-like text in a value (a request
+/// that mentions `{criteria}`, say) is left as it is. Other braces are kept verbatim.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = values.iter().find(|(name, _)| {
+            rest[1..].starts_with(name) && rest[1 + name.len()..].starts_with('}')
+        });
+        match placeholder {
+            Some((name, value)) => {
+                rendered.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Render the configured template, or `default` if there is none, warning about any
+/// placeholder a custom template leaves out
+fn render_prompt(kind: &str, template: Option<&PromptTemplate>, default: &str, values: &[(&str, &str)]) -> Result<String> {
+    let Some(template) = template else {
+        return Ok(render(default, values));
+    };
+    let text = template.text()?;
+    for (name, _) in values {
+        if !text.contains(&format!("{{{}}}", name)) {
+            warn!("Custom {} prompt template has no {{{}}} placeholder", kind, name);
+        }
+    }
+    Ok(render(&text, values))
+}
+
+/// The prompt asking for the success criteria of `request`
+pub fn criteria_prompt(prompts: &PromptsConfig, request: &str) -> Result<String> {
+    render_prompt("criteria", prompts.criteria.as_ref(), DEFAULT_CRITERIA_TEMPLATE, &[("request", request)])
+}
+
+/// The prompt asking whether `conversation` meets `criteria` for `request`
+pub fn verification_prompt(prompts: &PromptsConfig, request: &str, criteria: &str, conversation: &str) -> Result<String> {
+    render_prompt(
+        "verification",
+        prompts.verification.as_ref(),
+        DEFAULT_VERIFICATION_TEMPLATE,
+        &[("request", request), ("criteria", criteria), ("conversation", conversation)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_placeholders_once() {
+        let values = [("request", "print {criteria}"), ("criteria", "- prints it")];
+        assert_eq!(
+            render("Request: {request}\nCriteria: {criteria}\nVerdict: {\"passes\": true} {unknown}", &values),
+            "Request: print {criteria}\nCriteria: - prints it\nVerdict: {\"passes\": true} {unknown}"
+        );
+        // The defaults render with the JSON braces of the verdict format intact
+        let prompt = verification_prompt(&PromptsConfig::default(), "list files", "- lists files", "a.txt").unwrap();
+        assert!(prompt.contains("Original User Request:\n```\nlist files\n```"), "{}", prompt);
+        assert!(prompt.contains("`{\"passes\": boolean, "), "{}", prompt);
+    }
+
+    #[test]
+    fn test_template_from_file() {
+        let path = std::env::temp_dir().join(format!("mcp_prompt_test_{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "Criteria for: {request}").unwrap();
+        let prompts = PromptsConfig { criteria: Some(PromptTemplate::File { file: path.clone() }), ..Default::default() };
+        let prompt = criteria_prompt(&prompts, "deploy");
+        std::fs::remove_file(&path).ok();
+        assert_eq!(prompt.unwrap(), "Criteria for: deploy");
+
+        // The config accepts either form
+        let prompts: PromptsConfig = serde_json::from_str(r#"{"verification": "Judge: {conversation}", "criteria": {"file": "/nonexistent"}}"#).unwrap();
+        assert_eq!(prompts.verification, Some(PromptTemplate::Text("Judge: {conversation}".to_string())));
+        assert!(criteria_prompt(&prompts, "deploy").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+// Named snapshots of a conversation, so an alternative path can be tried and rolled back
+
+use std::collections::BTreeMap;
+
+use crate::conversation_state::ConversationState;
+
+/// Conversation snapshots held by the REPL for the lifetime of the session
+#[derive(Debug, Default)]
+pub struct Checkpoints {
+    snapshots: BTreeMap<String, ConversationState>,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot `state` under `name`, replacing any earlier checkpoint with that name.
+    /// Returns true if an existing checkpoint was overwritten.
+    pub fn save(&mut self, name: &str, state: &ConversationState) -> bool {
+        self.snapshots.insert(name.to_string(), state.clone()).is_some()
+    }
+
+    /// A copy of the named snapshot; the checkpoint itself is kept so it can be restored again
+    pub fn restore(&self, name: &str) -> Option<ConversationState> {
+        self.snapshots.get(name).cloned()
+    }
+
+    /// Checkpoint names in sorted order
+    pub fn names(&self) -> Vec<String> {
+        self.snapshots.keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Role;
+
+    fn contents(state: &ConversationState) -> Vec<String> {
+        state.messages.iter().map(|m| m.content.clone()).collect()
+    }
+
+    #[test]
+    fn test_restore_brings_back_exact_messages() {
+        let mut state = ConversationState::new("system prompt".to_string(), Vec::new());
+        state.add_user_message("first");
+        state.add_assistant_message("reply");
+
+        let mut checkpoints = Checkpoints::new();
+        assert!(!checkpoints.save("before", &state));
+        let expected = contents(&state);
+
+        // Keep going, and change the prompt, after taking the checkpoint
+        state.add_user_message("second");
+        state.add_assistant_message("another reply");
+        state.system_prompt = "changed".to_string();
+
+        let restored = checkpoints.restore("before").expect("checkpoint exists");
+        assert_eq!(contents(&restored), expected);
+        assert_eq!(restored.system_prompt, "system prompt");
+        assert_eq!(restored.messages.len(), 2);
+        assert!(matches!(restored.messages[1].role, Role::Assistant));
+
+        // Restoring doesn't consume the checkpoint
+        assert_eq!(contents(&checkpoints.restore("before").unwrap()), expected);
+        assert!(checkpoints.restore("missing").is_none());
+    }
+
+    #[test]
+    fn test_save_overwrites_and_lists_names() {
+        let state = ConversationState::new(String::new(), Vec::new());
+        let mut checkpoints = Checkpoints::new();
+        checkpoints.save("b", &state);
+        checkpoints.save("a", &state);
+        assert!(checkpoints.save("b", &state));
+        assert_eq!(checkpoints.names(), vec!["a", "b"]);
+    }
+}
@@ -51,18 +51,35 @@ impl ReplHelper {
                 "chat".to_string(),
                 "provider".to_string(),
                 "providers".to_string(),
+                "provider-info".to_string(),
+                "capabilities".to_string(),
                 "model".to_string(),
+                "models".to_string(),
                 "add_server".to_string(),
                 "edit_server".to_string(),
                 "remove_server".to_string(),
                 "save_config".to_string(),
                 "reload_config".to_string(),
                 "show_config".to_string(),
+                "profile".to_string(),
                 "verify".to_string(),
                 "save_chat".to_string(), // Added
                 "load_chat".to_string(), // Added
                 "new_chat".to_string(), // Added
+                "replay".to_string(),
+                "dryrun".to_string(),
+                "checkpoint".to_string(),
+                "restore".to_string(),
+                "undo".to_string(),
+                "tag".to_string(),
+                "conversations".to_string(),
+                "tools-log".to_string(),
+                "whoami".to_string(),
                 "compact".to_string(), // Added compact command (chat mode only)
+                "ping".to_string(),
+                "loglevel".to_string(),
+                "subscribe".to_string(),
+                "unsubscribe".to_string(),
                 "exit".to_string(),
                 "quit".to_string(),
             ],
@@ -121,8 +138,9 @@ impl Completer for ReplHelper {
             let word = line_parts[1];
             let start = line.rfind(word).unwrap_or(pos);
 
-            if command == "use" || command == "tools" || command == "chat" {
-                // Complete server names for 'use', 'tools', 'chat'
+            if command == "use" || command == "tools" || command == "chat" || command == "ping" || command == "loglevel"
+                || command == "subscribe" || command == "unsubscribe" {
+                // Complete server names for 'use', 'tools', 'chat', 'ping', 'loglevel', 'subscribe', 'unsubscribe'
                 let matches: Vec<Pair> = self.server_names.iter()
                     .filter(|name| name.starts_with(word))
                     .map(|name| Pair { display: name.clone(), replacement: name.clone() })
@@ -136,7 +154,7 @@ impl Completer for ReplHelper {
                     .map(|tool| Pair { display: tool.name.to_string(), replacement: tool.name.to_string() }) // Already correct
                     .collect();
                 return Ok((start, matches));
-            } else if command == "provider" {
+            } else if command == "provider" || command == "models" {
                  // Complete provider names for 'provider' command
                  let matches: Vec<Pair> = self.available_providers.iter()
                      .filter(|name| name.starts_with(word))
@@ -157,6 +175,25 @@ impl Completer for ReplHelper {
                     .collect();
                 return Ok((start, matches));
             }
+        } else if line_parts.len() == 3 && line_parts[0] == "loglevel" {
+             // Complete log levels after the server name for 'loglevel'
+             let word = line_parts[2];
+             let start = line.rfind(word).unwrap_or(pos);
+             let levels = ["debug", "info", "warning", "error"];
+             let matches: Vec<Pair> = levels.iter()
+                 .filter(|level| level.starts_with(word))
+                 .map(|level| Pair { display: (*level).to_string(), replacement: (*level).to_string() })
+                 .collect();
+             return Ok((start, matches));
+        } else if line_parts.len() == 3 && line_parts[0] == "tools" {
+             // Complete tool names after the server name for 'tools'
+             let word = line_parts[2];
+             let start = line.rfind(word).unwrap_or(pos);
+             let matches: Vec<Pair> = self.current_tools.iter()
+                 .filter(|tool| tool.name.starts_with(word))
+                 .map(|tool| Pair { display: tool.name.to_string(), replacement: tool.name.to_string() })
+                 .collect();
+             return Ok((start, matches));
         } else if line_parts.len() == 3 && line_parts[0] == "call" {
              // Complete server names after the tool name for 'call' command
              let word = line_parts[2];
@@ -190,17 +227,28 @@ impl Hinter for ReplHelper {
         // Use more descriptive placeholders
         match line_parts[0] {
             "use" if line_parts.len() == 1 => Some(" [server_name]".to_string()),
-            "tools" if line_parts.len() == 1 => Some(" [server_name]".to_string()),
+            "tools" if line_parts.len() == 1 => Some(" [server_name] [tool_name]".to_string()),
             "call" if line_parts.len() == 1 => Some(" <tool_name> [server_name] [json_args]".to_string()),
             "chat" if line_parts.len() == 1 => Some(" <server_name>".to_string()),
             "provider" if line_parts.len() == 1 => Some(" [provider_name]".to_string()), // Added hint
             "model" if line_parts.len() == 1 => Some(" [model_name]".to_string()), // Added hint
+            "models" if line_parts.len() == 1 => Some(" [provider_name]".to_string()),
+            "profile" if line_parts.len() == 1 => Some(" [profile_name]".to_string()),
             "edit_server" if line_parts.len() == 1 => Some(" <server_name>".to_string()),
             "remove_server" if line_parts.len() == 1 => Some(" <server_name>".to_string()),
             "show_config" if line_parts.len() == 1 => Some(" [server_name]".to_string()),
             "verify" if line_parts.len() == 1 => Some(" [on|off]".to_string()),
             "save_chat" if line_parts.len() == 1 => Some(" [filename]".to_string()), // Added hint
             "load_chat" if line_parts.len() == 1 => Some(" <filename>".to_string()), // Added hint
+            "replay" if line_parts.len() == 1 => Some(" <file>".to_string()),
+            "dryrun" if line_parts.len() == 1 => Some(" [on|off]".to_string()),
+            "checkpoint" if line_parts.len() == 1 => Some(" [name]".to_string()),
+            "restore" if line_parts.len() == 1 => Some(" <name>".to_string()),
+            "tag" if line_parts.len() == 1 => Some(" [name...]".to_string()),
+            "conversations" if line_parts.len() == 1 => Some(" [tag]".to_string()),
+            "ping" if line_parts.len() == 1 => Some(" [server_name]".to_string()),
+            "loglevel" if line_parts.len() == 1 => Some(" <server_name> <debug|info|warning|error>".to_string()),
+            "subscribe" | "unsubscribe" if line_parts.len() == 1 => Some(" <server_name> <uri>".to_string()),
             // "new_chat" needs no arguments
             _ => None,
         }
@@ -0,0 +1,80 @@
+// Where REPL command history is kept: one global file, or one file per project so
+// commands typed in unrelated projects don't mix.
+
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::host::config::HistoryScope;
+
+/// Files and directories that mark the root of a project
+const PROJECT_MARKERS: &[&str] = &[".git", "Cargo.toml", "package.json", "pyproject.toml", "go.mod"];
+
+/// Nearest ancestor of `dir` (or `dir` itself) containing a project marker
+pub fn project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| PROJECT_MARKERS.iter().any(|marker| ancestor.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
+/// History file to use when the REPL is started in `cwd`.
+/// Per-project history lives in `<config_dir>/history/<project>-<hash>.txt`; without a
+/// detectable project (or with the global scope) it is `<config_dir>/history.txt`.
+pub fn history_path(config_dir: &Path, scope: HistoryScope, cwd: &Path) -> PathBuf {
+    let global = config_dir.join("history.txt");
+    if scope == HistoryScope::Global {
+        return global;
+    }
+    let Some(root) = project_root(cwd) else { return global };
+
+    // The hash keeps same-named projects apart; the name keeps the files recognizable
+    let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+    let hash: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    let name: String = root
+        .file_name()
+        .map(|name| {
+            name.to_string_lossy()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect()
+        })
+        .unwrap_or_else(|| "root".to_string());
+    config_dir.join("history").join(format!("{}-{}.txt", name, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::{DefaultHistory, History};
+
+    #[test]
+    fn test_history_is_kept_per_project() {
+        let base = std::env::temp_dir().join(format!("mcp_history_test_{}", uuid::Uuid::new_v4()));
+        let config_dir = base.join("config");
+        let project_a = base.join("a");
+        let project_b = base.join("b");
+        for project in [&project_a, &project_b] {
+            std::fs::create_dir_all(project.join(".git")).unwrap();
+        }
+        std::fs::create_dir_all(project_a.join("src")).unwrap();
+
+        // Subdirectories share their project's history
+        let path_a = history_path(&config_dir, HistoryScope::Project, &project_a.join("src"));
+        assert_eq!(path_a, history_path(&config_dir, HistoryScope::Project, &project_a));
+        let path_b = history_path(&config_dir, HistoryScope::Project, &project_b);
+        assert_ne!(path_a, path_b);
+
+        std::fs::create_dir_all(path_a.parent().unwrap()).unwrap();
+        let mut history = DefaultHistory::new();
+        history.add("call bash").unwrap();
+        history.save(&path_a).unwrap();
+
+        let mut reloaded = DefaultHistory::new();
+        reloaded.load(&path_a).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert!(!path_b.exists(), "project b must not see project a's history");
+
+        // Global scope ignores the project
+        assert_eq!(history_path(&config_dir, HistoryScope::Global, &project_a), config_dir.join("history.txt"));
+        std::fs::remove_dir_all(&base).ok();
+    }
+}
@@ -1,10 +1,14 @@
 // Enhanced MCP Host REPL Implementation
 // Merges REPL simplicity with CLI prompt enhancements
 // connections module removed as MCPHost handles server management
+mod checkpoint;
 mod command;
 mod helper;
+mod history;
+mod replay;
 
 
+pub use checkpoint::Checkpoints;
 pub use command::CommandProcessor;
 pub use helper::ReplHelper;
 // Remove ServerConnections from public API
@@ -21,15 +25,20 @@ use std::path::PathBuf;
 // Removed unused import: use std::sync::Arc;
 // Removed unused import: use tokio::process::Command as TokioCommand;
 // Removed unused import: use tokio::sync::Mutex;
+use tokio::sync::broadcast;
 use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 // Removed unused import: use crate::conversation_service::handle_assistant_response;
 use crate::host::MCPHost;
+use crate::host::server_manager::ResourceUpdate;
+use crate::host::sse_transport::{ConnectionEvent, ConnectionNotice};
 // Define Role locally if not directly available from rllm 1.1.7
 
 use crate::conversation_logic::{generate_verification_criteria}; // Removed VerificationOutcome import
 use crate::conversation_service::generate_tool_system_prompt; // Import tool prompt generator
 use crate::conversation_state::ConversationState; // Import ConversationState
+use crate::conversation_journal::ConversationJournal;
 
 /// Main REPL implementation with enhanced CLI features
 // Remove lifetime 'a from Repl struct definition
@@ -44,19 +53,34 @@ pub struct Repl {
     loaded_conversation: Option<ConversationState>, // Holds state when not actively chatting
     current_conversation_path: Option<PathBuf>, // Path for save/load
     verify_responses: bool, // Added flag for verification
+    dry_run: bool, // Show the AI's tool calls in chat without running them
+    resource_updates: broadcast::Receiver<ResourceUpdate>, // Notices for subscribed resources
+    connection_notices: broadcast::Receiver<ConnectionNotice>, // Remote server disconnects/reconnects
+    journal: Option<ConversationJournal>, // Append-only record of the conversation, if journaling is on
 }
 
 // Remove lifetime 'a here
 impl Repl {
-    /// Create a new REPL, requires an initialized MCPHost
-    pub fn new(host: MCPHost) -> Result<Self> {
+    /// Create a new REPL, requires an initialized MCPHost.
+    /// `history_scope` selects global or per-project command history.
+    pub fn new(host: MCPHost, history_scope: crate::host::config::HistoryScope) -> Result<Self> {
         // Set up config directory
         let config_dir = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("mcp");
 
         std::fs::create_dir_all(&config_dir)?;
-        let history_path = config_dir.join("history.txt");
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let history_path = history::history_path(&config_dir, history_scope, &cwd);
+        Self::with_history_path(host, history_path)
+    }
+
+    /// Create a REPL keeping its command history in `history_path`
+    pub fn with_history_path(host: MCPHost, history_path: PathBuf) -> Result<Self> {
+        if let Some(parent) = history_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        log::debug!("Using command history at {}", history_path.display());
 
         // Initialize the editor with the ReplHelper and DefaultHistory types.
         let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
@@ -84,6 +108,10 @@ impl Repl {
             loaded_conversation: None,
             current_conversation_path: None,
             verify_responses: false,
+            dry_run: false,
+            resource_updates: host.resource_updates(),
+            connection_notices: host.connection_notices(),
+            journal: None,
         };
 
         // Remove the problematic assignment and extra creation step that caused borrow errors
@@ -115,6 +143,89 @@ impl Repl {
         Ok(conversations_dir)
     }
 
+    /// Append the active (or loaded) conversation's changes to its journal when journaling is
+    /// on. A journal is started at the conversation's first turn, named after its save file
+    /// if it has one.
+    async fn sync_journal(&mut self) {
+        let config = self.host.config.lock().await.journal.clone();
+        if !config.enabled {
+            return;
+        }
+        let Some(state) = self.chat_state.as_ref().map(|(_, state)| state).or(self.loaded_conversation.as_ref()) else {
+            return;
+        };
+        if state.turns.is_empty() {
+            return;
+        }
+
+        if let Some(journal) = self.journal.as_mut().filter(|journal| journal.records(state)) {
+            if let Err(e) = journal.sync(state) {
+                log::warn!("Failed to update conversation journal {}: {}", journal.path().display(), e);
+            }
+            return;
+        }
+        let name = self.current_conversation_path.as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("chat_{}", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+        let path = match self.get_conversations_dir() {
+            Ok(dir) => dir.join(format!("{}.jsonl", name)),
+            Err(e) => {
+                log::warn!("Not journaling the conversation: {}", e);
+                return;
+            }
+        };
+        match ConversationJournal::create(&path, state, config.compact_every) {
+            Ok(journal) => {
+                println!("{}", style(format!("Journaling this conversation to {}", path.display())).dim());
+                self.journal = Some(journal);
+            }
+            Err(e) => log::warn!("Failed to start conversation journal {}: {}", path.display(), e),
+        }
+    }
+
+    /// Print a notice for each resource update received since the last prompt.
+    fn print_resource_updates(&mut self) {
+        loop {
+            match self.resource_updates.try_recv() {
+                Ok(update) => println!(
+                    "{} Resource {} changed on server {}",
+                    style("[notice]").cyan(),
+                    style(&update.uri).yellow(),
+                    style(&update.server).green()
+                ),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    log::warn!("Missed {} resource update notifications", skipped);
+                }
+                Err(_) => break, // Empty or closed
+            }
+        }
+    }
+
+    /// Print a notice for each remote server disconnect or reconnect since the last prompt.
+    fn print_connection_notices(&mut self) {
+        loop {
+            match self.connection_notices.try_recv() {
+                Ok(ConnectionNotice { server, event: ConnectionEvent::Disconnected { reason } }) => println!(
+                    "{} Lost connection to server {} ({}), reconnecting...",
+                    style("[notice]").yellow(),
+                    style(&server).green(),
+                    reason
+                ),
+                Ok(ConnectionNotice { server, event: ConnectionEvent::Reconnected { attempts } }) => println!(
+                    "{} Reconnected to server {} after {} attempt(s)",
+                    style("[notice]").cyan(),
+                    style(&server).green(),
+                    attempts
+                ),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    log::warn!("Missed {} connection notices", skipped);
+                }
+                Err(_) => break, // Empty or closed
+            }
+        }
+    }
+
     // with_host method removed as host is now required in new()
 
     /// Run the REPL
@@ -137,6 +248,9 @@ impl Repl {
         }
 
         loop {
+            self.print_resource_updates();
+            self.print_connection_notices();
+
             // Dynamically set the prompt based on the current server and AI provider
             let server_part = match self.command_processor.current_server_name() {
                 Some(server) => style(server).green().to_string(),
@@ -252,6 +366,59 @@ impl Repl {
                     log::debug!("User requested exit from chat mode, moving state to loaded_conversation.");
                     self.loaded_conversation = Some(state); // Keep the state
                     // Keep self.current_conversation_path as is
+                } else if line == "/retry" || line.starts_with("/retry ") {
+                    // --- Re-run the last turn ---
+                    let temperature = match line["/retry".len()..].trim() {
+                        "" => Ok(None),
+                        value => value.parse::<f32>().map(Some).map_err(|_| value.to_string()),
+                    };
+                    match temperature {
+                        Err(value) => {
+                            println!("{}: '{}' is not a temperature. Usage: /retry [temperature]", style("Error").red().bold(), value);
+                            self.chat_state = Some((server_context, state));
+                        }
+                        Ok(temperature) => match self.retry_last_turn(&server_context, &mut state, temperature).await {
+                            Ok(true) => self.chat_state = Some((server_context, state)),
+                            Ok(false) => {
+                                println!("{}", style("Nothing to retry yet.").yellow());
+                                self.chat_state = Some((server_context, state));
+                            }
+                            Err(e) => {
+                                log::error!("Error retrying turn for context '{}': {}", server_context, e);
+                                println!("{}: {}", style("Chat Error").red().bold(), e);
+                                println!("{}", style("Exiting chat input due to error. Conversation loaded.").yellow());
+                                self.loaded_conversation = Some(state);
+                            }
+                        },
+                    }
+                } else if line == "/system" || line.starts_with("/system ") {
+                    // --- Show or replace the system prompt (raw text, so no shell-style splitting) ---
+                    match system_prompt_command(&mut state, &line["/system".len()..]) {
+                        Ok(output) => println!("{}", output),
+                        Err(e) => println!("{}: {}", style("Error").red().bold(), e),
+                    }
+                    self.chat_state = Some((server_context, state));
+                } else if line == "/clear" {
+                    println!("{}", clear_command(&mut state));
+                    // A fresh start; saving it shouldn't overwrite the conversation it came from
+                    self.current_conversation_path = None;
+                    self.chat_state = Some((server_context, state));
+                } else if line == "/dryrun" || line.starts_with("/dryrun ") {
+                    match dry_run_command(self.dry_run, &line["/dryrun".len()..]) {
+                        Ok((output, dry_run)) => {
+                            self.dry_run = dry_run;
+                            println!("{}", output);
+                        }
+                        Err(e) => println!("{}: {}", style("Error").red().bold(), e),
+                    }
+                    self.chat_state = Some((server_context, state));
+                } else if line == "/replay" || line.starts_with("/replay ") {
+                    // --- Replay runs on a fresh state and puts the current chat back when done ---
+                    self.chat_state = Some((server_context, state));
+                    match self.replay_command(line["/replay".len()..].trim()).await {
+                        Ok(report) => println!("{}", report),
+                        Err(e) => println!("{}: {}", style("Error").red().bold(), e),
+                    }
                 } else if line.starts_with('/') {
                     // --- Process REPL Command While in Chat Mode ---
                     let command_line = line[1..].trim(); // Remove leading '/'
@@ -331,7 +498,23 @@ impl Repl {
                 log::debug!("Processing input in command mode: '{}'", line);
 
                 // Check if it's a command or potentially a chat message to resume
-                if line.starts_with('/') || self.command_processor.is_known_command(line) {
+                let command_line = line.strip_prefix('/').unwrap_or(line).trim();
+                if command_line == "replay" || command_line.starts_with("replay ") {
+                    // --- Replay a saved conversation (needs the chat machinery, so handled here) ---
+                    match self.replay_command(command_line["replay".len()..].trim()).await {
+                        Ok(report) => println!("{}", report),
+                        Err(e) => println!("{}: {}", style("Error").red().bold(), e),
+                    }
+                } else if command_line == "dryrun" || command_line.starts_with("dryrun ") {
+                    // --- Dry-run mode belongs to the REPL's chat settings, so handled here ---
+                    match dry_run_command(self.dry_run, &command_line["dryrun".len()..]) {
+                        Ok((output, dry_run)) => {
+                            self.dry_run = dry_run;
+                            println!("{}", output);
+                        }
+                        Err(e) => println!("{}: {}", style("Error").red().bold(), e),
+                    }
+                } else if line.starts_with('/') || self.command_processor.is_known_command(line) {
                     // --- Process REPL Command ---
                     let command_line = if line.starts_with('/') {
                         line[1..].trim()
@@ -382,15 +565,15 @@ impl Repl {
                     if let Some(state) = self.loaded_conversation.take() {
                         // --- Resume Loaded Conversation ---
                         log::info!("Resuming loaded conversation.");
-                        // Determine server context (might need adjustment if loaded state doesn't store it)
-                        // For now, assume multi-server context if loaded without active chat
-                        let server_context = "*all*".to_string(); // Or retrieve from state if stored
+                        // Resume with the tools the conversation was started with
+                        let server_context = state.server_context().to_string();
                         let active_provider = self.host.get_active_provider_name().await.unwrap_or("none".to_string());
                         let active_model = self.host.ai_client().await.map(|c| c.model_name()).unwrap_or("?".to_string());
                         println!(
                             "\n{}",
                             style(format!(
-                                "Resuming chat using provider '{}' (model: {}).",
+                                "Resuming chat with {} using provider '{}' (model: {}).",
+                                if server_context == "*all*" { "all servers".to_string() } else { format!("server '{}'", style(&server_context).green()) },
                                 style(&active_provider).cyan(),
                                 style(&active_model).green()
                             )).italic()
@@ -400,7 +583,7 @@ impl Repl {
                     } else {
                         // --- Start New Chat (Multi-server default) ---
                         log::info!("Starting new multi-server chat session.");
-                        match self.host.enter_multi_server_chat_mode().await {
+                        match self.start_chat(None).await {
                             Ok(mut initial_state) => { // Add mut here
                                 let active_provider = self.host.get_active_provider_name().await.unwrap_or("none".to_string());
                                 let active_model = self.host.ai_client().await.map(|c| c.model_name()).unwrap_or("?".to_string());
@@ -437,8 +620,8 @@ impl Repl {
                      // Treat non-command input as a chat message if a conversation is loaded
                      log::debug!("Non-command input received while conversation loaded. Resuming chat.");
                      let state = self.loaded_conversation.take().unwrap(); // Take the loaded state
-                     // Determine server context (default to *all* for now)
-                     let server_context = "*all*".to_string();
+                     // Resume with the tools the conversation was started with
+                     let server_context = state.server_context().to_string();
                      println!("{}", style("(Resuming chat...)").dim()); // Indicate resumption
                      self.chat_state = Some((server_context.clone(), state)); // Put it into active chat_state
                      // Re-process the line as a chat turn
@@ -498,7 +681,7 @@ impl Repl {
                         // --- Specific Server Chat ---
                         log::info!("'chat' command detected for specific server: '{}'", target_server);
                         log::debug!("Attempting to enter single-server chat mode with '{}'", target_server);
-                        match self.host.enter_chat_mode(target_server).await {
+                        match self.start_chat(Some(target_server)).await {
                             Ok(mut initial_state) => { // Add mut here
                                 let active_provider = self.host.get_active_provider_name().await.unwrap_or("none".to_string());
                                 let active_model = self.host.ai_client().await.map(|c| c.model_name()).unwrap_or("?".to_string());
@@ -534,7 +717,7 @@ impl Repl {
                     } else {
                         // --- Multi-Server Chat ---
                         log::info!("'chat' command detected with no server specified. Entering multi-server mode.");
-                        match self.host.enter_multi_server_chat_mode().await {
+                        match self.start_chat(None).await {
                             Ok(mut initial_state) => { // Add mut here
                                 let active_provider = self.host.get_active_provider_name().await.unwrap_or("none".to_string());
                                 let active_model = self.host.ai_client().await.map(|c| c.model_name()).unwrap_or("?".to_string());
@@ -598,6 +781,8 @@ impl Repl {
                 }
             }
 
+            self.sync_journal().await;
+
             // --- Update Helper State (Runs regardless of mode) ---
             log::debug!("Updating REPL helper state.");
             // Update server names for completion
@@ -633,22 +818,8 @@ impl Repl {
 
                     // Update available models for the current provider
                     if let Some(active_provider) = self.host.get_active_provider_name().await {
-                        let models = { // Scope lock
-                            let models_config_guard = self.host.provider_models.lock().await; // Lock the models config
-                            let provider_key = active_provider.to_lowercase();
-                            // --- Add detailed logging ---
-                            let available_keys: Vec<_> = models_config_guard.providers.keys().cloned().collect();
-                            log::debug!(
-                                "Helper Update: Looking for key '{}'. Available keys: {:?}",
-                                provider_key,
-                                available_keys
-                            );
-                            // --- End detailed logging ---
-                            models_config_guard.providers // Access the inner HashMap
-                                .get(&provider_key) // Use lowercase key
-                                .map(|list| list.models.clone()) // Clone the Vec<String> if found
-                                .unwrap_or_default() // Return empty Vec if not found
-                        };
+                        // Configured models plus any the provider's API has reported; fetched in the background
+                        let models = self.host.cached_models(&active_provider).await;
                         log::debug!("Updating helper with {} suggested models for provider '{}'", models.len(), active_provider); // Log the count
                         if let Some(h) = self.editor.helper_mut() { h.update_current_provider_models(models); } // Update the helper
                     } else {
@@ -732,6 +903,8 @@ impl Repl {
         // 5. Create new state with original system prompt and tools
         // Use the *original* system prompt and tools from the *input* state
         let mut new_state = ConversationState::new(state.system_prompt.clone(), state.tools.clone());
+        new_state.server_context = state.server_context.clone();
+        new_state.metadata = state.metadata.clone();
 
         // 6. Add summary message to the new state
         let summary_message = format!(
@@ -746,6 +919,48 @@ impl Repl {
     }
 
 
+    /// Fresh conversation state for `chat [server]`. Fails with setup guidance
+    /// rather than a broken chat if no AI provider is active.
+    async fn start_chat(&self, server_name: Option<&str>) -> Result<ConversationState> {
+        self.host.require_ai_client().await?;
+        match server_name {
+            Some(name) => self.host.enter_chat_mode(name).await,
+            None => self.host.enter_multi_server_chat_mode().await,
+        }
+    }
+
+    /// Compact `state` in place if it has grown past the configured share of the context window.
+    /// Returns whether it was compacted; a failed compaction leaves the state as it was.
+    async fn auto_compact_if_needed(&self, server_name: &str, state: &mut ConversationState) -> bool {
+        let context = self.host.config.lock().await.context.clone();
+        let window = match (context.window_tokens, self.host.ai_client().await) {
+            (Some(window), _) => Some(window),
+            (None, Some(client)) => client.capabilities().max_tokens,
+            (None, None) => None,
+        };
+        let estimated = state.estimated_tokens();
+        if !needs_compaction(&context, window, estimated) {
+            return false;
+        }
+
+        println!(
+            "{}",
+            style(format!("Conversation is ~{} tokens (window {}); compacting before continuing...", estimated, window.unwrap_or_default())).dim()
+        );
+        match self.execute_compact_conversation(server_name, state).await {
+            Ok(compacted) => {
+                log::info!("Auto-compacted conversation from ~{} to ~{} tokens.", estimated, compacted.estimated_tokens());
+                *state = compacted;
+                true
+            }
+            Err(e) => {
+                log::warn!("Auto-compaction failed: {}", e);
+                println!("{}: Automatic compaction failed: {}", style("Warning").yellow(), e);
+                false
+            }
+        }
+    }
+
     /// Executes one turn of the chat interaction.
     /// Takes user input, calls the AI, and handles the response (including tool calls).
     async fn execute_chat_turn(
@@ -753,8 +968,99 @@ impl Repl {
         server_name: &str,
         state: &mut crate::conversation_state::ConversationState,
         user_input: &str,
+    ) -> Result<()> {
+        self.execute_chat_turn_with(server_name, state, user_input, None).await
+    }
+
+    /// Discard the last turn and run the same user input again, optionally at a different
+    /// temperature. Returns false if there is no turn to retry.
+    async fn retry_last_turn(
+        &mut self,
+        server_name: &str,
+        state: &mut crate::conversation_state::ConversationState,
+        temperature: Option<f32>,
+    ) -> Result<bool> {
+        let Some(input) = state.undo_last_turn() else {
+            return Ok(false);
+        };
+        log::info!("Retrying last turn ({} messages kept): '{}'", state.messages.len(), input);
+        println!("{} {}", style("Retrying:").dim(), style(&input).italic());
+        self.execute_chat_turn_with(server_name, state, &input, temperature).await?;
+        Ok(true)
+    }
+
+    /// `replay <file>`: load a saved conversation (a path, or a name in the conversations
+    /// directory) and replay it
+    async fn replay_command(&mut self, arg: &str) -> Result<String> {
+        if arg.is_empty() {
+            return Err(anyhow!("Usage: replay <file>"));
+        }
+        let mut path = PathBuf::from(shellexpand::tilde(arg).as_ref());
+        if !path.exists() {
+            let file_name = if arg.ends_with(".json") { arg.to_string() } else { format!("{}.json", arg) };
+            path = self.get_conversations_dir()?.join(file_name);
+        }
+        if !path.exists() {
+            return Err(anyhow!("Conversation file not found: {}", path.display()));
+        }
+        let saved = ConversationState::load_from_json(&path).await?;
+        self.replay_conversation(&saved).await
+    }
+
+    /// Start `saved` over from the messages before its first turn and send each of its user
+    /// turns again with the current provider and tools. Returns a report comparing each new
+    /// final answer with the saved one.
+    async fn replay_conversation(&mut self, saved: &ConversationState) -> Result<String> {
+        let (preamble, turns) = replay::saved_turns(saved)?;
+        let server_context = saved.server_context().to_string();
+        let mut state = ConversationState::new(saved.system_prompt.clone(), saved.tools.clone());
+        state.server_context = saved.server_context.clone();
+        state.messages = preamble;
+
+        // Chat turns leave their state in chat_state; keep whatever the user had there
+        let previous_chat = self.chat_state.take();
+        let mut report = Vec::new();
+        let mut changed = 0;
+        for (i, turn) in turns.iter().enumerate() {
+            println!("\n{} {}", style(format!("Replaying turn {}/{}:", i + 1, turns.len())).cyan().bold(), style(&turn.input).italic());
+            let start = state.messages.len();
+            let result = self.execute_chat_turn(&server_context, &mut state, &turn.input).await;
+            self.chat_state = None;
+            if let Err(e) = result {
+                self.chat_state = previous_chat;
+                return Err(anyhow!("Replay stopped at turn {}: {}", i + 1, e));
+            }
+
+            let label = style(format!("Turn {}", i + 1)).bold();
+            let response = replay::last_assistant_message(state.messages.get(start..).unwrap_or_default());
+            match (&turn.response, &response) {
+                (_, None) => {
+                    changed += 1;
+                    report.push(format!("{}: {}", label, style("no response this time (see errors above)").red()));
+                }
+                (Some(old), Some(new)) if old == new => report.push(format!("{}: {}", label, style("unchanged").green())),
+                (old, Some(new)) => {
+                    changed += 1;
+                    report.push(format!("{}: {}\n{}", label, style("changed").yellow(), replay::diff_lines(old.as_deref().unwrap_or(""), new)));
+                }
+            }
+        }
+        self.chat_state = previous_chat;
+
+        report.insert(0, style(format!("Replayed {} turns: {} changed.", turns.len(), changed)).cyan().bold().to_string());
+        Ok(report.join("\n"))
+    }
+
+    /// A chat turn; `temperature` overrides the model's default for the first response
+    async fn execute_chat_turn_with(
+        &mut self,
+        server_name: &str,
+        state: &mut crate::conversation_state::ConversationState,
+        user_input: &str,
+        temperature: Option<f32>,
     ) -> Result<()> {
         log::debug!("Executing chat turn for server '{}'. Original user input: '{}'", server_name, user_input);
+        self.auto_compact_if_needed(server_name, state).await;
 
         let mut final_user_input = user_input.to_string();
         let mut criteria_for_verification = String::new(); // Initialize empty
@@ -796,6 +1102,7 @@ impl Repl {
 
 
         // 1. Add potentially modified user message to state
+        state.begin_turn(user_input);
         state.add_user_message(&final_user_input); // Use the input (with or without appended criteria)
         log::debug!("Added user message to state. Total messages: {}", state.messages.len());
 
@@ -807,18 +1114,47 @@ impl Repl {
             })?;
         let model_name = client.model_name(); // Get model name for logging
         log::debug!("Using AI client for model: {}", model_name);
+        state.set_model(self.host.get_active_provider_name().await, &model_name);
 
         // 3. Print model info (optional, kept for consistency)
         println!("{}", style(format!("Using AI model: {}", model_name)).dim());
 
+        // Ctrl+C while the turn runs cancels it instead of killing the REPL
+        let cancel_token = CancellationToken::new();
+        let ctrl_c_listener = {
+            let cancel_token = cancel_token.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    cancel_token.cancel();
+                }
+            })
+        };
+        let result = self.run_chat_turn(server_name, state, &criteria_for_verification, client, cancel_token, temperature).await;
+        ctrl_c_listener.abort();
+        result
+    }
+
+    /// The cancellable part of a chat turn: the initial AI call and the tool loop
+    async fn run_chat_turn(
+        &mut self,
+        server_name: &str,
+        state: &mut crate::conversation_state::ConversationState,
+        criteria_for_verification: &str,
+        client: std::sync::Arc<dyn crate::ai_client::AIClient>,
+        cancel_token: CancellationToken,
+        temperature: Option<f32>,
+    ) -> Result<()> {
         // 4. Build *initial* request and call AI (using with_progress for the first call)
-        println!("{}", style("Analyzing your request...").dim());
-        let initial_response_result: Result<String> = crate::repl::with_progress( // Use with_progress for the *first* call
+        println!("{}", style("Analyzing your request... (Ctrl+C to interrupt)").dim());
+        let initial_response_result: Option<Result<String>> = crate::repl::with_progress( // Use with_progress for the *first* call
             "Getting initial response".to_string(),
             async {
                 // Get system prompt from state helper method
                 let system_prompt = state.get_system_prompt().unwrap_or(""); // Use empty if not found
                 let mut builder = client.raw_builder(system_prompt);
+                if temperature.is_some() {
+                    builder = builder.config(crate::ai_client::GenerationConfig { temperature, ..Default::default() });
+                }
                 log::trace!("Building raw AI request for initial chat turn.");
                 // Add all messages *up to this point*. System prompt is handled by the builder.
                 for msg in state.messages.iter() {
@@ -831,13 +1167,25 @@ impl Repl {
                 // Tool prompt is already included in state via ConversationState::new
 
                 log::debug!("Executing initial AI request...");
-                builder.execute().await.map_err(|e| {
-                    log::error!("Initial AI execution failed: {}", e);
-                    anyhow!("Initial AI request failed: {}", e)
-                })
+                tokio::select! {
+                    _ = cancel_token.cancelled() => None,
+                    result = builder.execute() => Some(result.map_err(|e| {
+                        log::error!("Initial AI execution failed: {}", e);
+                        anyhow!("Initial AI request failed: {}", e)
+                    })),
+                }
             }
         ).await;
 
+        let Some(initial_response_result) = initial_response_result else {
+            // Nothing was answered yet - drop the unanswered user message
+            state.messages.pop();
+            state.turns.pop();
+            println!("{}", style("Interrupted. The request was discarded.").yellow());
+            self.chat_state = Some((server_name.to_string(), state.clone()));
+            return Ok(());
+        };
+
         // 5. Process initial AI response using the new shared logic
         match initial_response_result {
             Ok(initial_response) => {
@@ -847,6 +1195,9 @@ impl Repl {
                 // Use default config which now has max_tool_iterations = 3
                 let config = crate::conversation_logic::ConversationConfig {
                     interactive_output: true,
+                    cancel_token: Some(cancel_token.clone()),
+                    confirm_tool: Some(std::sync::Arc::new(confirm_tool_call)),
+                    dry_run: self.dry_run,
                     ..Default::default() // Use default for max_tool_iterations
                 };
 
@@ -859,10 +1210,14 @@ impl Repl {
                     &initial_response, // Pass the first response
                     client, // Pass the client Arc
                     &config,
-                    &criteria_for_verification, // Pass the clean criteria string
+                    criteria_for_verification, // Pass the clean criteria string
                 )
                 .await
                 {
+                    Ok(outcome) if outcome.interrupted => {
+                        println!("{}", style("\nInterrupted. The conversation so far is kept; continue when ready.").yellow());
+                        self.chat_state = Some((server_name.to_string(), state.clone()));
+                    }
                     Ok(outcome) => {
                         // The final response was already printed by resolve_assistant_response
                         // The state has been mutated in place.
@@ -896,6 +1251,68 @@ impl Repl {
 
 }
 
+/// Ask on the terminal before running a tool that may change things. Anything but "y"/"yes" declines.
+fn confirm_tool_call(server_name: &str, tool_name: &str, args: &serde_json::Value) -> bool {
+    // Tools can run concurrently; keep their prompts from interleaving
+    static PROMPT_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = PROMPT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let term = console::Term::stdout();
+    let _ = term.write_line(&format!(
+        "\n{} '{}' on server '{}' may modify your system. Arguments:\n{}",
+        style("Confirm:").yellow().bold(),
+        style(tool_name).yellow(),
+        style(server_name).green(),
+        crate::conversation_state::format_json_output(&serde_json::to_string_pretty(args).unwrap_or_default())
+    ));
+    let _ = term.write_str(&style("Run it? [y/N] ").cyan().to_string());
+    // Reading the terminal blocks; let the runtime move other tasks off this thread
+    let answer = tokio::task::block_in_place(|| term.read_line()).unwrap_or_default();
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// `/system` in chat: with no arguments show the conversation's system prompt;
+/// `set <text>` replaces it for every following turn
+fn system_prompt_command(state: &mut ConversationState, args: &str) -> Result<String> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Ok(match state.get_system_prompt() {
+            Some(prompt) => format!("{}\n{}", style(format!("System prompt ({} characters):", prompt.len())).cyan(), prompt),
+            None => style("This conversation has no system prompt.").yellow().to_string(),
+        });
+    }
+    match args.split_once(char::is_whitespace).map(|(sub, text)| (sub, text.trim())).unwrap_or((args, "")) {
+        ("set", "") => Err(anyhow!("Usage: /system set <text>")),
+        ("set", text) => {
+            state.set_system_prompt(text);
+            Ok(format!("System prompt replaced ({} characters); it applies from the next turn.", text.len()))
+        }
+        _ => Err(anyhow!("Usage: /system [set <text>]")),
+    }
+}
+
+/// `/clear` in chat: drop the conversation so far but keep its tools and system prompt
+fn clear_command(state: &mut ConversationState) -> String {
+    let removed = state.clear();
+    style(format!("Cleared {} messages; the tools and system prompt are unchanged.", removed)).green().to_string()
+}
+
+/// `dryrun [on|off]`: show the dry-run setting, or the message and new setting after changing it
+fn dry_run_command(current: bool, args: &str) -> Result<(String, bool)> {
+    match args.trim().to_lowercase().as_str() {
+        "" => {
+            let status = if current { style("on").green() } else { style("off").yellow() };
+            Ok((format!("Dry-run mode is currently {}.", status), current))
+        }
+        "on" | "true" | "yes" | "enable" => Ok((
+            style("Dry-run mode enabled: tool calls will be shown but not executed.").green().to_string(),
+            true,
+        )),
+        "off" | "false" | "no" | "disable" => Ok((style("Dry-run mode disabled.").yellow().to_string(), false)),
+        other => Err(anyhow!("Invalid argument '{}'. Usage: dryrun [on|off]", other)),
+    }
+}
+
 /// Truncate a string to a maximum number of lines.
 pub fn truncate_lines(text: &str, max_lines: usize) -> String { // Make this function public
     let lines: Vec<&str> = text.lines().collect();
@@ -907,8 +1324,103 @@ pub fn truncate_lines(text: &str, max_lines: usize) -> String { // Make this fun
     }
 }
 
-/// Helper function for progress spinner
+/// The longest prefix of `text` that fits in `max_bytes` without splitting a character
+fn byte_prefix(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// The first `max_lines` lines of `text`, as a slice of the original
+fn line_prefix(text: &str, max_lines: usize) -> &str {
+    match text.match_indices('\n').nth(max_lines.saturating_sub(1)) {
+        Some((index, _)) if max_lines > 0 => &text[..index],
+        _ if max_lines == 0 => "",
+        _ => text,
+    }
+}
+
+/// Truncate a string to at most `max_bytes` bytes, cutting at a character boundary.
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> String {
+    let kept = byte_prefix(text, max_bytes);
+    if kept.len() == text.len() {
+        text.to_string()
+    } else {
+        format!("{}\n\n{}", kept, style(format!("... (output truncated: {} more bytes)", text.len() - kept.len())).dim())
+    }
+}
+
+/// Truncate tool output to the configured line and byte caps, saying how much was hidden.
+pub fn truncate_output(text: &str, limits: &crate::host::config::OutputConfig) -> String {
+    let kept = byte_prefix(line_prefix(text, limits.max_lines), limits.max_bytes);
+    if kept.len() == text.len() {
+        return text.to_string();
+    }
+    let marker = format!(
+        "... (output truncated: showing {} of {} lines, {} of {} bytes; raise output.max_lines / output.max_bytes in the config to see more)",
+        kept.lines().count(),
+        text.lines().count(),
+        kept.len(),
+        text.len()
+    );
+    format!("{}\n\n{}", kept, style(marker).dim())
+}
+
+/// Whether a conversation of `estimated_tokens` should be compacted under `context`,
+/// given a context window of `window_tokens` (unknown windows never trigger compaction)
+fn needs_compaction(context: &crate::host::config::ContextConfig, window_tokens: Option<u32>, estimated_tokens: usize) -> bool {
+    match window_tokens {
+        Some(window) if context.auto_compact && window > 0 => estimated_tokens as f64 >= window as f64 * context.compact_at as f64,
+        _ => false,
+    }
+}
+
+/// How long an operation runs before its progress line is shown as a warning
+pub const SLOW_OPERATION_WARNING: Duration = Duration::from_secs(30);
+
+/// Elapsed time as "12s" or "2m 05s"
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// One frame of the progress line: spinner, message and elapsed time, styled as a
+/// warning once `warn_after` has passed
+fn progress_line(spinner: &str, msg: &str, elapsed: Duration, warn_after: Option<Duration>) -> String {
+    let elapsed_text = format_elapsed(elapsed);
+    if warn_after.is_some_and(|limit| elapsed >= limit) {
+        format!(
+            "\r{} {} {}",
+            style(spinner).yellow(),
+            style(msg).dim(),
+            style(format!("{} (still running)", elapsed_text)).yellow()
+        )
+    } else {
+        format!("\r{} {} {}", style(spinner).cyan(), style(msg).dim(), style(elapsed_text).dim())
+    }
+}
+
+/// Helper function for progress spinner.
+/// Shows how long the operation has run, turning yellow after `SLOW_OPERATION_WARNING`.
 pub async fn with_progress<F, T>(msg: String, future: F) -> T
+where
+    F: std::future::Future<Output = T>,
+{
+    with_progress_timed(msg, Some(SLOW_OPERATION_WARNING), future).await
+}
+
+/// Progress spinner with an elapsed-time counter; `warn_after` (if any) is when the
+/// line switches to a warning style
+pub async fn with_progress_timed<F, T>(msg: String, warn_after: Option<Duration>, future: F) -> T
 where
     F: std::future::Future<Output = T>,
 {
@@ -918,15 +1430,17 @@ where
     let spinner = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
     let mut i = 0;
 
-    // Clone the message and term for the spawned task
-    let progress_msg = style(msg).dim().to_string(); // Style the progress message
+    // Clone the term for the spawned task
     let progress_term = term.clone();
+    let started = tokio::time::Instant::now();
 
     let handle = tokio::spawn(async move {
         loop {
             // Write the spinner and message, staying on same line
-            progress_term.write_str(&format!("\r{} {}", style(spinner[i]).cyan(), progress_msg)) // Style spinner cyan
-                .unwrap_or_default();
+            let line = progress_line(spinner[i], &msg, started.elapsed(), warn_after);
+            // Clear first so a shorter line doesn't leave stale characters behind
+            progress_term.clear_line().unwrap_or_default();
+            progress_term.write_str(&line).unwrap_or_default();
             // Ensure the line is flushed
             progress_term.flush().unwrap_or_default();
 
@@ -941,3 +1455,189 @@ where
     term.clear_line().unwrap_or_default();
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_ai_client::{FakeAIClient, FakeAIHandle};
+    use crate::host::test_support::{test_dir, test_host};
+    use std::sync::Arc;
+
+    /// A REPL keeping its history in a throwaway directory rather than the user's config
+    fn repl_for(host: MCPHost) -> Repl {
+        Repl::with_history_path(host, test_dir().join("history.txt")).expect("failed to create REPL")
+    }
+
+    /// A REPL whose model answers "answer N" to its Nth request
+    async fn test_repl() -> (Repl, FakeAIHandle) {
+        let host = test_host().await;
+        let client = (1..=10).fold(FakeAIClient::new(), |client, n| client.respond(format!("answer {}", n)));
+        let handle = client.handle();
+        host.set_ai_client("fake", Arc::new(client)).await;
+        (repl_for(host), handle)
+    }
+
+    #[test]
+    fn test_system_command_replaces_prompt() {
+        let mut state = ConversationState::new("You are helpful.".to_string(), Vec::new());
+        let shown = system_prompt_command(&mut state, "").unwrap();
+        assert!(shown.ends_with("You are helpful."), "{}", shown);
+
+        system_prompt_command(&mut state, " set Don't use tools; answer in one line.").unwrap();
+        assert_eq!(state.get_system_prompt(), Some("Don't use tools; answer in one line."));
+
+        assert!(system_prompt_command(&mut state, "set").is_err());
+        assert!(system_prompt_command(&mut state, "reset").is_err());
+        assert_eq!(state.get_system_prompt(), Some("Don't use tools; answer in one line."));
+    }
+
+    #[tokio::test]
+    async fn test_clear_keeps_only_tool_instructions() {
+        let (mut repl, _) = test_repl().await;
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools from all servers:\n- bash");
+        repl.execute_chat_turn("*all*", &mut state, "first").await.unwrap();
+        repl.execute_chat_turn("*all*", &mut state, "second").await.unwrap();
+        assert_eq!(state.messages.len(), 5);
+
+        let output = clear_command(&mut state);
+        assert!(output.contains("Cleared 4 messages"), "{}", output);
+        assert_eq!(state.messages.len(), 1);
+        assert!(state.messages[0].content.starts_with("Okay, I have access to the following tools"));
+        assert_eq!(state.get_system_prompt(), Some("system"));
+        assert!(state.undo_last_turn().is_none());
+
+        // The chat carries on from the cleared state
+        repl.execute_chat_turn("*all*", &mut state, "third").await.unwrap();
+        assert_eq!(state.messages.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_chat_without_provider_explains_setup() {
+        let host = test_host().await;
+        // Whatever the environment offered, start with nothing active
+        host.clear_ai_client().await;
+        let repl = repl_for(host);
+
+        let err = repl.start_chat(None).await.unwrap_err().to_string();
+        assert!(err.starts_with("No AI provider is active"), "{}", err);
+        assert!(err.contains("provider <name>"), "{}", err);
+        assert!(err.contains("ollama"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_tiny_window_forces_auto_compaction() {
+        let (mut repl, model) = test_repl().await;
+        repl.host.config.lock().await.context = crate::host::config::ContextConfig {
+            auto_compact: true,
+            compact_at: 0.5,
+            window_tokens: Some(40),
+        };
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+
+        // ~20 tokens of history: under half of the 40-token window, so no compaction yet
+        repl.execute_chat_turn("*all*", &mut state, &"a".repeat(60)).await.unwrap();
+        assert_eq!(state.messages.len(), 2);
+        repl.execute_chat_turn("*all*", &mut state, &"b".repeat(60)).await.unwrap();
+        assert_eq!(state.messages.len(), 4);
+        assert_eq!(model.requests().len(), 2);
+
+        // Now over the threshold: the next turn starts from a summary
+        repl.execute_chat_turn("*all*", &mut state, "next").await.unwrap();
+        assert_eq!(model.requests().len(), 4, "one summary call plus the turn itself");
+        assert!(state.messages[0].content.starts_with("Conversation history compacted"), "{:?}", state.messages[0]);
+        assert_eq!(state.messages.len(), 3);
+
+        // Switched off, the same history is left alone
+        repl.host.config.lock().await.context.auto_compact = false;
+        let before = state.messages.len();
+        state.add_user_message(&"c".repeat(400));
+        repl.execute_chat_turn("*all*", &mut state, "again").await.unwrap();
+        assert_eq!(state.messages.len(), before + 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_replaces_last_response() {
+        let (mut repl, model) = test_repl().await;
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: none");
+
+        repl.execute_chat_turn("*all*", &mut state, "first question").await.unwrap();
+        repl.execute_chat_turn("*all*", &mut state, "second question").await.unwrap();
+        assert_eq!(state.messages.len(), 5);
+        assert_eq!(state.messages[4].content, "answer 2");
+
+        assert!(repl.retry_last_turn("*all*", &mut state, Some(1.2)).await.unwrap());
+        // Same request, a fresh answer, and nothing left over from the discarded one
+        assert_eq!(state.messages.len(), 5);
+        assert_eq!(state.messages[3].content, "second question");
+        assert_eq!(state.messages[4].content, "answer 3");
+        let temperatures: Vec<Option<f32>> = model.requests().iter().map(|r| r.config.as_ref().and_then(|c| c.temperature)).collect();
+        assert_eq!(temperatures, vec![None, None, Some(1.2)]);
+        assert_eq!(state.turns.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reruns_each_saved_turn() {
+        let (mut repl, model) = test_repl().await;
+        let mut saved = ConversationState::new("system".to_string(), Vec::new());
+        saved.add_user_message("Okay, I have access to the following tools: none");
+        for (input, answer) in [("first question", "answer 1"), ("second question", "an older answer")] {
+            saved.begin_turn(input);
+            saved.add_user_message(input);
+            saved.add_assistant_message(answer);
+        }
+
+        let report = console::strip_ansi_codes(&repl.replay_conversation(&saved).await.unwrap()).to_string();
+        assert_eq!(model.requests().len(), 2, "one AI request per saved turn");
+        assert!(report.starts_with("Replayed 2 turns: 1 changed."), "{}", report);
+        assert!(report.contains("Turn 1: unchanged"), "{}", report);
+        assert!(report.contains("Turn 2: changed\n- an older answer\n+ answer 2"), "{}", report);
+        // Replaying doesn't leave the REPL in chat mode
+        assert!(repl.chat_state.is_none());
+    }
+
+    #[test]
+    fn test_byte_cap_cuts_mid_line() {
+        let line = "x".repeat(100);
+        let output = console::strip_ansi_codes(&truncate_bytes(&line, 40)).to_string();
+        assert_eq!(output, format!("{}\n\n... (output truncated: 60 more bytes)", "x".repeat(40)));
+        assert_eq!(truncate_bytes("short", 40), "short");
+
+        // A single huge line gets through the line cap but not the byte cap
+        let limits = crate::host::config::OutputConfig { max_lines: 150, max_bytes: 10, max_tool_result_chars: None };
+        let output = console::strip_ansi_codes(&truncate_output(&format!("ok\n{}", line), &limits)).to_string();
+        assert!(output.starts_with("ok\nxxxxxxx\n\n... (output truncated: showing 2 of 2 lines, 10 of 103 bytes"), "{}", output);
+
+        let limits = crate::host::config::OutputConfig { max_lines: 2, max_bytes: 1000, max_tool_result_chars: None };
+        let output = console::strip_ansi_codes(&truncate_output("a\nb\nc\nd", &limits)).to_string();
+        assert!(output.starts_with("a\nb\n\n... (output truncated: showing 2 of 4 lines, 3 of 7 bytes"), "{}", output);
+    }
+
+    #[test]
+    fn test_byte_cap_keeps_utf8_boundaries() {
+        // "é" is two bytes and "€" three; a cut inside either backs off to the character start
+        let text = "é€é€";
+        assert_eq!(byte_prefix(text, 1), "");
+        assert_eq!(byte_prefix(text, 3), "é");
+        assert_eq!(byte_prefix(text, 4), "é");
+        assert_eq!(byte_prefix(text, 5), "é€");
+        let output = console::strip_ansi_codes(&truncate_bytes(text, 6)).to_string();
+        assert_eq!(output, "é€\n\n... (output truncated: 5 more bytes)");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_progress_line_counts_elapsed_time() {
+        let started = tokio::time::Instant::now();
+        let line = |warn_after| console::strip_ansi_codes(&progress_line("⠋", "Getting response...", started.elapsed(), warn_after)).to_string();
+        assert_eq!(line(None), "\r⠋ Getting response... 0s");
+
+        tokio::time::advance(Duration::from_secs(12)).await;
+        assert_eq!(line(Some(Duration::from_secs(30))), "\r⠋ Getting response... 12s");
+
+        tokio::time::advance(Duration::from_secs(113)).await;
+        assert_eq!(line(Some(Duration::from_secs(30))), "\r⠋ Getting response... 2m 05s (still running)");
+        // Without a threshold there is never a warning
+        assert_eq!(line(None), "\r⠋ Getting response... 2m 05s");
+    }
+}
@@ -0,0 +1,105 @@
+// Replaying a saved conversation turn by turn against the current provider and tools,
+// to spot how responses change after a prompt or model switch
+
+use anyhow::{anyhow, Result};
+use console::style;
+use rmcp::model::Role;
+
+use crate::conversation_state::{ConversationState, Message};
+
+/// One user turn of a saved conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedTurn {
+    /// What the user typed
+    pub input: String,
+    /// The last assistant message of the turn (its final answer), if it got one
+    pub response: Option<String>,
+}
+
+/// Split a saved conversation into the messages before its first turn (e.g. the tool
+/// instructions) and its user turns
+pub fn saved_turns(state: &ConversationState) -> Result<(Vec<Message>, Vec<SavedTurn>)> {
+    let first = state.turns.first()
+        .ok_or_else(|| anyhow!("The conversation has no recorded turns to replay"))?;
+    let preamble = state.messages[..first.index.min(state.messages.len())].to_vec();
+
+    let turns = state.turns.iter().enumerate().map(|(i, turn)| {
+        let end = state.turns.get(i + 1).map_or(state.messages.len(), |next| next.index);
+        SavedTurn {
+            input: turn.input.clone(),
+            response: last_assistant_message(state.messages.get(turn.index..end).unwrap_or_default()),
+        }
+    }).collect();
+    Ok((preamble, turns))
+}
+
+/// Content of the last assistant message in `messages`
+pub fn last_assistant_message(messages: &[Message]) -> Option<String> {
+    messages.iter().rev().find(|m| matches!(m.role, Role::Assistant)).map(|m| m.content.clone())
+}
+
+/// Line diff of two responses: removed lines prefixed with "-", added lines with "+"
+pub fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(style(format!("- {}", old[i])).red().to_string());
+            i += 1;
+        } else {
+            lines.push(style(format!("+ {}", new[j])).green().to_string());
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_turns_pair_inputs_with_final_answers() {
+        let mut state = ConversationState::new("system".to_string(), Vec::new());
+        state.add_user_message("Okay, I have access to the following tools: none");
+        state.begin_turn("list files");
+        state.add_user_message("list files");
+        state.add_assistant_message("calling a tool");
+        state.add_user_message("tool result");
+        state.add_assistant_message("here are the files");
+        state.begin_turn("thanks");
+        state.add_user_message("thanks");
+
+        let (preamble, turns) = saved_turns(&state).unwrap();
+        assert_eq!(preamble.len(), 1);
+        assert_eq!(turns, vec![
+            SavedTurn { input: "list files".to_string(), response: Some("here are the files".to_string()) },
+            SavedTurn { input: "thanks".to_string(), response: None },
+        ]);
+
+        let empty = ConversationState::new("system".to_string(), Vec::new());
+        assert!(saved_turns(&empty).is_err());
+    }
+
+    #[test]
+    fn test_diff_marks_changed_lines() {
+        let diff = console::strip_ansi_codes(&diff_lines("a\nb\nc", "a\nB\nc\nd")).to_string();
+        assert_eq!(diff, "  a\n- b\n+ B\n  c\n+ d");
+        assert_eq!(diff_lines("same", "same"), "  same");
+    }
+}
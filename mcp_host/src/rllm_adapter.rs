@@ -5,7 +5,7 @@ use rmcp::model::Role;
 use rllm::error::LLMError;
 use tracing::info;
 use crate::ai_client::{AIClient, AIRequestBuilder, GenerationConfig, ModelCapabilities};
-use serde_json::Value;
+use serde_json::{json, Value};
 // Use the local Role definition from repl/mod.rs
 use rllm::builder::{LLMBackend, LLMBuilder};
 // Import necessary types from rllm::chat
@@ -14,6 +14,19 @@ use std::path::Path;
 use log;
 use regex::Regex;
 use once_cell::sync::Lazy; // For static regex compilation
+use std::time::Duration;
+
+/// Limit on a request sent without rllm (Anthropic, Azure, OpenAI-compatible servers),
+/// matching the other providers' HTTP clients
+const DIRECT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// HTTP client for requests sent without rllm
+fn direct_http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(DIRECT_REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))
+}
 
 /// Client adapter for the rllm crate to interface with the MCP system
 pub struct RLLMClient {
@@ -21,6 +34,10 @@ pub struct RLLMClient {
     backend: LLMBackend,
     // Store API key for recreating the client if needed
     api_key: String,
+    // API root to send requests to instead of the backend's default
+    base_url: Option<String>,
+    // Set for Azure OpenAI, which is called directly rather than through rllm
+    azure: Option<AzureTarget>,
 }
 
 impl RLLMClient {
@@ -43,6 +60,12 @@ impl RLLMClient {
         if key_required && api_key.is_empty() {
             return Err(anyhow!("API key is required for {:?} backend", backend));
         }
+        // rllm only sends Ollama requests to a base URL; Anthropic and OpenAI-compatible
+        // requests to one are sent directly, and other backends have fixed endpoints
+        if base_url.is_some() && !matches!(backend, LLMBackend::Ollama | LLMBackend::Anthropic) && !is_openai_compatible(&backend) {
+            return Err(anyhow!("A base_url is not supported for the {:?} backend", backend));
+        }
+        let base_url = base_url.map(|url| normalize_base_url(&backend, url));
         
         // Build with appropriate options
         let mut builder = LLMBuilder::new()
@@ -100,6 +123,8 @@ impl RLLMClient {
                     model_name: model.clone(),
                     backend,
                     api_key,  // Move api_key into the struct
+                    base_url,
+                    azure: None,
                 })
             },
             Err(e) => {
@@ -138,10 +163,153 @@ impl std::fmt::Debug for RLLMClient {
          .field("model_name", &self.model_name)
          .field("backend", &self.backend)
          .field("api_key", &format!("{}****", &self.api_key.chars().take(4).collect::<String>()))
+         .field("base_url", &self.base_url)
          .finish()
     }
 }
 
+/// The `base_url` from a provider's config, if one is set
+fn configured_base_url(config: &Value) -> Option<String> {
+    config["base_url"].as_str()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Backends speaking the OpenAI chat completions API, whose requests go straight to a
+/// configured base URL
+fn is_openai_compatible(backend: &LLMBackend) -> bool {
+    matches!(backend, LLMBackend::OpenAI | LLMBackend::DeepSeek | LLMBackend::XAI | LLMBackend::Groq)
+}
+
+/// OpenAI-style request paths are resolved against the base URL with `Url::join`, which
+/// replaces a last segment that doesn't end in `/` (`https://host/v1` would send to
+/// `https://host/chat/completions`), so such base URLs get a trailing slash
+fn normalize_base_url(backend: &LLMBackend, url: String) -> String {
+    if is_openai_compatible(backend) && !url.ends_with('/') {
+        format!("{}/", url)
+    } else {
+        url
+    }
+}
+
+/// `chat/completions` under an OpenAI-style base URL
+fn chat_completions_url(base_url: &str) -> Result<reqwest::Url> {
+    reqwest::Url::parse(base_url)
+        .and_then(|base| base.join("chat/completions"))
+        .map_err(|e| anyhow!("Invalid base URL '{}': {}", base_url, e))
+}
+
+/// Where to send Azure OpenAI requests, and the model name to send: the deployment
+#[derive(Debug, Clone, PartialEq)]
+struct AzureTarget {
+    base_url: String,
+    deployment: String,
+    api_version: Option<String>, // None for the `v1` API, which takes no `api-version`
+}
+
+impl AzureTarget {
+    /// The chat completions URL, with the `api-version` query parameter if there is one.
+    /// It is added after joining, since `Url::join` drops any query on the base URL.
+    fn chat_completions_url(&self) -> Result<reqwest::Url> {
+        let mut url = chat_completions_url(&self.base_url)?;
+        if let Some(version) = &self.api_version {
+            url.query_pairs_mut().append_pair("api-version", version);
+        }
+        Ok(url)
+    }
+}
+
+/// The Azure target a provider config describes. API version "v1" (the default) uses the
+/// resource's OpenAI-compatible `/openai/v1/` API; a dated version addresses the
+/// deployment's own URL.
+fn azure_target(config: &Value) -> Result<AzureTarget> {
+    let non_empty = |key: &str| config[key].as_str().map(str::trim).filter(|s| !s.is_empty());
+    let endpoint = non_empty("endpoint")
+        .ok_or_else(|| anyhow!("Azure OpenAI needs an 'endpoint' (https://<resource>.openai.azure.com) in its provider config"))?
+        .trim_end_matches('/');
+    let deployment = non_empty("deployment")
+        .ok_or_else(|| anyhow!("Azure OpenAI needs a 'deployment' name in its provider config"))?;
+    let (base_url, api_version) = match non_empty("api_version").unwrap_or("v1") {
+        "v1" => (format!("{}/openai/v1/", endpoint), None),
+        version => (format!("{}/openai/deployments/{}/", endpoint, deployment), Some(version.to_string())),
+    };
+    Ok(AzureTarget {
+        base_url: configured_base_url(config).map(|url| normalize_base_url(&LLMBackend::OpenAI, url)).unwrap_or(base_url),
+        deployment: deployment.to_string(),
+        api_version,
+    })
+}
+
+/// Cache breakpoint to put after the system prompt. The tool instructions in it are long and
+/// identical every turn, so Anthropic can serve them from its prompt cache instead of
+/// re-reading them. Other backends have no such marker.
+fn system_cache_control(backend: &LLMBackend) -> Option<Value> {
+    matches!(backend, LLMBackend::Anthropic).then(|| json!({ "type": "ephemeral" }))
+}
+
+/// Body of an Anthropic Messages API request with the system prompt as a single cacheable block
+fn anthropic_messages_body(
+    model: &str,
+    system_prompt: &str,
+    cache_control: Value,
+    turns: &[(Role, String)],
+    config: Option<&GenerationConfig>,
+) -> Value {
+    let messages: Vec<Value> = turns
+        .iter()
+        .map(|(role, content)| {
+            let role = match role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            json!({ "role": role, "content": content })
+        })
+        .collect();
+    let mut body = json!({
+        "model": model,
+        "max_tokens": config.and_then(|c| c.max_tokens).unwrap_or(50000),
+        "messages": messages,
+    });
+    // Anthropic rejects empty text blocks
+    if !system_prompt.is_empty() {
+        body["system"] = json!([{ "type": "text", "text": system_prompt, "cache_control": cache_control }]);
+    }
+    if let Some(temperature) = config.and_then(|c| c.temperature) {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = config.and_then(|c| c.top_p) {
+        body["top_p"] = json!(top_p);
+    }
+    body
+}
+
+/// Body of an OpenAI-style chat completions request, the system prompt first
+fn openai_chat_body(model: &str, system_prompt: &str, turns: &[(Role, String)], config: Option<&GenerationConfig>) -> Value {
+    let system = (!system_prompt.is_empty()).then(|| json!({ "role": "system", "content": system_prompt }));
+    let messages: Vec<Value> = system
+        .into_iter()
+        .chain(turns.iter().map(|(role, content)| {
+            let role = match role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            json!({ "role": role, "content": content })
+        }))
+        .collect();
+    let mut body = json!({
+        "model": model,
+        "max_tokens": config.and_then(|c| c.max_tokens).unwrap_or(50000),
+        "messages": messages,
+    });
+    if let Some(temperature) = config.and_then(|c| c.temperature) {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = config.and_then(|c| c.top_p) {
+        body["top_p"] = json!(top_p);
+    }
+    body
+}
+
 /// Create an RLLM client for the given provider
 pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<Box<dyn AIClient>> {
     // Match against lowercase provider name for consistency
@@ -154,8 +322,7 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
             let model = config["model"].as_str()
                 .filter(|s| !s.is_empty())
                 .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'google/gemini'"))?;
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Google, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Google, configured_base_url(&config))?;
             Ok(Box::new(client))
         }
         "anthropic" => {
@@ -167,12 +334,12 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'anthropic'"))?;
 
             log::info!("Using RLLM adapter for Anthropic provider");
-            // Explicitly set the base URL for Anthropic
+            // Explicitly set the base URL for Anthropic unless the config overrides it
             let client = RLLMClient::new_with_base_url(
                 api_key.to_string(),
                 model.to_string(),
                 LLMBackend::Anthropic,
-                Some("https://api.anthropic.com/v1".to_string()) // Standard Anthropic base URL
+                configured_base_url(&config).or_else(|| Some("https://api.anthropic.com/v1".to_string())) // Standard Anthropic base URL
             )?;
             Ok(Box::new(client))
         }
@@ -185,16 +352,17 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'openai'"))?;
 
             log::info!("Using RLLM adapter for OpenAI provider");
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::OpenAI, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::OpenAI, configured_base_url(&config))?;
             Ok(Box::new(client))
         }
         "ollama" => {
             log::info!("Using RLLM adapter for Ollama provider");
             // Ollama endpoint can be configured, default to localhost
-            let base_url = config["endpoint"].as_str()
-                .filter(|s| !s.is_empty())
-                .map(|s| s.to_string()); // Store as Option<String>
+            let base_url = configured_base_url(&config).or_else(|| {
+                config["endpoint"].as_str()
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+            });
             // Model name MUST be provided by the caller now
             let model = config["model"].as_str()
                 .filter(|s| !s.is_empty())
@@ -214,8 +382,7 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                 .filter(|s| !s.is_empty())
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'deepseek'"))?;
 
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::DeepSeek, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::DeepSeek, configured_base_url(&config))?;
             Ok(Box::new(client))
         }
         "xai" => {
@@ -227,8 +394,7 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                 .filter(|s| !s.is_empty())
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'xai'"))?;
 
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::XAI, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::XAI, configured_base_url(&config))?;
             Ok(Box::new(client))
         }
         "phind" => {
@@ -240,8 +406,7 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                 .filter(|s| !s.is_empty())
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'phind'"))?;
 
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Phind, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Phind, configured_base_url(&config))?;
             Ok(Box::new(client))
         }
         "groq" => {
@@ -253,8 +418,21 @@ pub fn create_rllm_client_for_provider(provider: &str, config: Value) -> Result<
                 .filter(|s| !s.is_empty())
                  .ok_or_else(|| anyhow!("Model name missing or empty in config for provider 'groq'"))?;
 
-            // Use new_with_base_url, passing None for base_url
-            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Groq, None)?;
+            let client = RLLMClient::new_with_base_url(api_key.to_string(), model.to_string(), LLMBackend::Groq, configured_base_url(&config))?;
+            Ok(Box::new(client))
+        }
+        "azure" => {
+            log::info!("Using RLLM adapter (OpenAI backend) for Azure OpenAI provider");
+            let api_key = config["api_key"].as_str()
+                .ok_or_else(|| anyhow!("Azure OpenAI API key not provided (AZURE_OPENAI_API_KEY)"))?;
+            let target = azure_target(&config)?;
+            let mut client = RLLMClient::new_with_base_url(
+                api_key.to_string(),
+                target.deployment.clone(),
+                LLMBackend::OpenAI,
+                Some(target.base_url.clone()),
+            )?;
+            client.azure = Some(target);
             Ok(Box::new(client))
         }
         _ => Err(anyhow!("Unknown or unsupported AI provider: {}", provider))
@@ -273,6 +451,8 @@ impl AIClient for RLLMClient {
             api_key: self.api_key.clone(),
             model_name: self.model_name.clone(),
             backend: self.backend.clone(),
+            base_url: self.base_url.clone(),
+            azure: self.azure.clone(),
             system_prompt: system_prompt.to_string(), // Store system prompt
             messages: Vec::new(),
             config: None,
@@ -454,12 +634,125 @@ struct RLLMRequestBuilder {
     api_key: String,
     model_name: String,
     backend: LLMBackend,
+    base_url: Option<String>,
+    azure: Option<AzureTarget>,
     // Store messages and configuration
     messages: Vec<(Role, String)>,
     config: Option<GenerationConfig>,
     system_prompt: String, // Renamed from 'system'
 }
 
+impl RLLMRequestBuilder {
+    /// The user/assistant messages to send, without a leading copy of the system prompt
+    fn turns(&self) -> &[(Role, String)] {
+        // Skip the first message if a system prompt was set via the builder
+        if !self.system_prompt.is_empty() && !self.messages.is_empty() {
+            // Check if the first message content actually matches the system prompt
+            // This is a safety check in case the state management changes
+            if self.messages[0].1 == self.system_prompt {
+                 log::debug!("Skipping first message in adapter loop as it matches the system prompt.");
+                 return &self.messages[1..];
+            }
+            log::warn!("System prompt set, but first message content doesn't match. Adding all messages.");
+        }
+        &self.messages
+    }
+
+    /// Send the request straight to the Anthropic Messages API, since rllm has no way to
+    /// mark the system prompt as cacheable and ignores the base URL
+    async fn execute_anthropic(&self, cache_control: Value) -> Result<String> {
+        let base_url = self.base_url.as_deref().unwrap_or("https://api.anthropic.com/v1").trim_end_matches('/');
+        let body = anthropic_messages_body(&self.model_name, &self.system_prompt, cache_control, self.turns(), self.config.as_ref());
+        log::debug!("Sending Anthropic request with {} messages and a cached system prompt", body["messages"].as_array().map_or(0, Vec::len));
+
+        let start_time = std::time::Instant::now();
+        let response = direct_http_client()?
+            .post(format!("{}/messages", base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to send request to Anthropic API: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await
+                .unwrap_or_else(|_| "Could not read error response".to_string());
+            return Err(anyhow!("Anthropic API error ({}): {}", status, error_text));
+        }
+        let response: Value = response.json().await
+            .map_err(|e| anyhow!("Failed to parse Anthropic API response: {}", e))?;
+        info!("time elapsed: {:.2}s", start_time.elapsed().as_secs_f64());
+        log::debug!(
+            "Anthropic prompt cache: {} tokens read, {} tokens written",
+            response["usage"]["cache_read_input_tokens"].as_u64().unwrap_or(0),
+            response["usage"]["cache_creation_input_tokens"].as_u64().unwrap_or(0)
+        );
+
+        let text: Vec<&str> = response["content"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Anthropic API response has no content: {}", response))?
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        Ok(text.join(""))
+    }
+
+    /// Send the request straight to Azure OpenAI, which wants an `api-key` header and an
+    /// `api-version` query parameter that rllm's OpenAI backend can't send
+    async fn execute_azure(&self, target: &AzureTarget) -> Result<String> {
+        let url = target.chat_completions_url()?;
+        let body = openai_chat_body(&target.deployment, &self.system_prompt, self.turns(), self.config.as_ref());
+        log::debug!("Sending Azure OpenAI request with {} messages to {}", body["messages"].as_array().map_or(0, Vec::len), url);
+
+        let request = direct_http_client()?.post(url).header("api-key", &self.api_key);
+        send_chat_completion("Azure OpenAI", request, &body).await
+    }
+
+    /// Send the request straight to an OpenAI-compatible server at `base_url` (vLLM, a
+    /// proxy), since rllm always uses the backend's own endpoint
+    async fn execute_openai_compatible(&self, base_url: &str) -> Result<String> {
+        let url = chat_completions_url(base_url)?;
+        let body = openai_chat_body(&self.model_name, &self.system_prompt, self.turns(), self.config.as_ref());
+        log::debug!("Sending OpenAI-compatible request with {} messages to {}", body["messages"].as_array().map_or(0, Vec::len), url);
+
+        let mut request = direct_http_client()?.post(url);
+        // Local servers often run without a key
+        if !self.api_key.is_empty() {
+            request = request.bearer_auth(&self.api_key);
+        }
+        send_chat_completion("OpenAI-compatible server", request, &body).await
+    }
+}
+
+/// Send an OpenAI-style chat completions request and return the reply's text. `service`
+/// names the other end in errors.
+async fn send_chat_completion(service: &str, request: reqwest::RequestBuilder, body: &Value) -> Result<String> {
+    let start_time = std::time::Instant::now();
+    let response = request
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| anyhow!("Failed to send request to {}: {}", service, e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await
+            .unwrap_or_else(|_| "Could not read error response".to_string());
+        return Err(anyhow!("{} error ({}): {}", service, status, error_text));
+    }
+    let response: Value = response.json().await
+        .map_err(|e| anyhow!("Failed to parse {} response: {}", service, e))?;
+    info!("time elapsed: {:.2}s", start_time.elapsed().as_secs_f64());
+
+    response["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("{} response has no message content: {}", service, response))
+}
+
 #[async_trait] // Ensure async_trait is applied to the impl block
 impl AIRequestBuilder for RLLMRequestBuilder {
     fn system(mut self: Box<Self>, content: String) -> Box<dyn AIRequestBuilder> {
@@ -562,14 +855,32 @@ impl AIRequestBuilder for RLLMRequestBuilder {
         self
     }
 
+    fn try_clone(&self) -> Option<Box<dyn AIRequestBuilder>> {
+        Some(Box::new(self.clone()))
+    }
+
+    #[cfg_attr(feature = "otel", tracing::instrument(name = "execute", skip_all, fields(model = %self.model_name)))]
     async fn execute(self: Box<Self>) -> Result<String> {
         log::info!("Executing RLLM request with model {}", self.model_name);
+
+        if let Some(target) = &self.azure {
+            return self.execute_azure(target).await;
+        }
+        if let Some(cache_control) = system_cache_control(&self.backend) {
+            return self.execute_anthropic(cache_control).await;
+        }
+        if let Some(base_url) = self.base_url.as_deref().filter(|_| is_openai_compatible(&self.backend)) {
+            return self.execute_openai_compatible(base_url).await;
+        }
         
         // Create a new LLMBuilder with our stored configuration
         let mut builder = LLMBuilder::new()
             .backend(self.backend.clone())
             .model(&self.model_name)
             .api_key(&self.api_key);
+        if let Some(url) = &self.base_url {
+            builder = builder.base_url(url);
+        }
         
         // Apply configuration options if provided
         if let Some(cfg) = &self.config {
@@ -616,22 +927,7 @@ impl AIRequestBuilder for RLLMRequestBuilder {
         // --- System Prompt is handled by the builder now ---
 
         // --- Process User/Assistant Messages ---
-        // Skip the first message if a system prompt was set via the builder
-        let messages_to_add = if !self.system_prompt.is_empty() && !self.messages.is_empty() {
-            // Check if the first message content actually matches the system prompt
-            // This is a safety check in case the state management changes
-            if self.messages[0].1 == self.system_prompt {
-                 log::debug!("Skipping first message in adapter loop as it matches the system prompt.");
-                 self.messages.iter().skip(1)
-            } else {
-                 log::warn!("System prompt set, but first message content doesn't match. Adding all messages.");
-                 self.messages.iter().skip(0) // Add all if mismatch
-            }
-        } else {
-            self.messages.iter().skip(0) // Add all messages if no system prompt was set
-        };
-
-        for (role, content) in messages_to_add {
+        for (role, content) in self.turns() {
             let (rllm_role, message_type) = match role {
                 Role::User => {
                     // Determine message type based on content prefix
@@ -728,3 +1024,140 @@ impl AIRequestBuilder for RLLMRequestBuilder {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_azure_config_targets_deployment() {
+        let config = json!({
+            "api_key": "key",
+            "endpoint": "https://contoso.openai.azure.com/",
+            "deployment": "gpt-4o-prod",
+            "api_version": "2024-10-21"
+        });
+        let target = azure_target(&config).unwrap();
+        assert_eq!(target.base_url, "https://contoso.openai.azure.com/openai/deployments/gpt-4o-prod/");
+        assert_eq!(target.deployment, "gpt-4o-prod");
+        assert_eq!(
+            target.chat_completions_url().unwrap().as_str(),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o-prod/chat/completions?api-version=2024-10-21"
+        );
+
+        let target = azure_target(&json!({ "endpoint": "https://contoso.openai.azure.com", "deployment": "gpt-4o-prod" })).unwrap();
+        assert_eq!(target.api_version, None);
+        assert_eq!(target.chat_completions_url().unwrap().as_str(), "https://contoso.openai.azure.com/openai/v1/chat/completions");
+
+        let err = azure_target(&json!({ "endpoint": "https://contoso.openai.azure.com" })).unwrap_err();
+        assert!(err.to_string().contains("'deployment'"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_azure_request_sends_api_key_and_version() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt-4o-prod/chat/completions"))
+            .and(query_param("api-version", "2024-10-21"))
+            .and(header("api-key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "hello" }, "finish_reason": "stop" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let config = json!({ "api_key": "key", "endpoint": server.uri(), "deployment": "gpt-4o-prod", "api_version": "2024-10-21" });
+        let client = create_rllm_client_for_provider("azure", config).unwrap();
+        let reply = client.builder("Be brief").user("hi".to_string()).execute().await.unwrap();
+        assert_eq!(reply, "hello");
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url.path(), "/openai/deployments/gpt-4o-prod/chat/completions");
+        assert_eq!(requests[0].url.query(), Some("api-version=2024-10-21"));
+        assert_eq!(requests[0].headers.get("api-key").unwrap(), "key");
+        assert!(requests[0].headers.get("authorization").is_none());
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["model"], "gpt-4o-prod");
+        assert_eq!(body["messages"], json!([
+            { "role": "system", "content": "Be brief" },
+            { "role": "user", "content": "hi" }
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_base_url_keeps_its_path() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "model": "gpt-4o",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "hello" }, "finish_reason": "stop" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let base_url = format!("{}/v1", server.uri());
+        let client = RLLMClient::new_with_base_url("key".to_string(), "gpt-4o".to_string(), LLMBackend::OpenAI, Some(base_url)).unwrap();
+        assert_eq!(client.base_url, Some(format!("{}/v1/", server.uri())));
+        let reply = client.builder("").user("hi".to_string()).execute().await.unwrap();
+        assert_eq!(reply, "hello");
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url.path(), "/v1/chat/completions");
+        assert_eq!(requests[0].headers.get("authorization").unwrap(), "Bearer key");
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["model"], "gpt-4o");
+        assert_eq!(body["messages"], json!([{ "role": "user", "content": "hi" }]));
+
+        // Backends that append their paths themselves are left alone
+        assert_eq!(normalize_base_url(&LLMBackend::Ollama, "http://localhost:11434".to_string()), "http://localhost:11434");
+        // and ones with fixed endpoints can't take a base URL at all
+        let err = RLLMClient::new_with_base_url("key".to_string(), "gemini-2.0-flash".to_string(), LLMBackend::Google, Some(server.uri())).unwrap_err();
+        assert!(err.to_string().contains("base_url"), "{}", err);
+    }
+
+    #[test]
+    fn test_system_prompt_cacheable_only_for_anthropic() {
+        assert_eq!(system_cache_control(&LLMBackend::Anthropic), Some(json!({ "type": "ephemeral" })));
+        assert_eq!(system_cache_control(&LLMBackend::OpenAI), None);
+        assert_eq!(system_cache_control(&LLMBackend::Google), None);
+        assert_eq!(system_cache_control(&LLMBackend::Ollama), None);
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_request_marks_system_prompt_cacheable() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .and(header("x-api-key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [{ "type": "text", "text": "hello" }],
+                "usage": { "cache_read_input_tokens": 1200 }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = RLLMClient {
+            model_name: "claude-sonnet-4-5".to_string(),
+            backend: LLMBackend::Anthropic,
+            api_key: "key".to_string(),
+            base_url: Some(server.uri()),
+            azure: None,
+        };
+        let reply = client.builder("Use tools wisely").user("hi".to_string()).execute().await.unwrap();
+        assert_eq!(reply, "hello");
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["system"], json!([
+            { "type": "text", "text": "Use tools wisely", "cache_control": { "type": "ephemeral" } }
+        ]));
+        assert_eq!(body["messages"], json!([{ "role": "user", "content": "hi" }]));
+    }
+}
@@ -0,0 +1,191 @@
+// OpenTelemetry export of the host's tracing spans: `call_tool` (a tool call as the host
+// handles it), `call` (the request sent to the server) and `execute` (an AI request).
+// Enabled with the `otel` feature. Tool calls carry the W3C trace context to the server in
+// `params._meta`, so a server that reads it can continue the same trace.
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider as SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use once_cell::sync::Lazy;
+use rmcp::model::{CallToolRequestParam, JsonObject};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Spans are exported only if this is set; the exporter reads the other standard
+/// `OTEL_EXPORTER_OTLP_*` variables itself
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// `service.name` the host's spans are reported under
+pub const SERVICE_NAME: &str = "mcp_host";
+
+/// A tracing layer sending spans to `provider`
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+/// A layer exporting spans over OTLP if `OTEL_EXPORTER_OTLP_ENDPOINT` is set. None if it
+/// isn't, or if the exporter can't be set up. Must be called within the tokio runtime.
+pub fn layer_from_env<S>() -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    std::env::var_os(OTLP_ENDPOINT_ENV)?;
+    let exporter = match opentelemetry_otlp::SpanExporter::builder().with_tonic().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            // Logging isn't set up yet; this runs while building the subscriber
+            eprintln!("Warning: Could not set up OpenTelemetry export: {}", e);
+            return None;
+        }
+    };
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+    // Kept globally so `shutdown` can flush it
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Some(layer(&provider))
+}
+
+/// Export any spans still buffered; call before exiting
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// W3C trace context (`traceparent`, and `tracestate` if set) of the current span. Empty if
+/// the span isn't part of an exported trace.
+pub fn current_trace_context() -> JsonObject {
+    let mut carrier: HashMap<String, String> = HashMap::new();
+    TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut carrier);
+    carrier.into_iter().map(|(key, value)| (key, Value::String(value))).collect()
+}
+
+/// Trace contexts of tool calls on their way to a server, until `attach_trace_context`
+/// puts them in `params._meta`. rmcp's request types have no `_meta`, and the call is sent
+/// from rmcp's own task, so the context waits here rather than travel with the request.
+static OUTGOING: Lazy<Mutex<Vec<OutgoingContext>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_OUTGOING_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A held context and the call it belongs to, recognised by server, tool and arguments
+struct OutgoingContext {
+    id: u64,
+    server: String,
+    tool: String,
+    arguments: Option<JsonObject>,
+    context: JsonObject,
+}
+
+/// A trace context held for a tool call; dropping it forgets the context if it wasn't sent
+#[must_use]
+pub struct HeldTraceContext(u64);
+
+impl Drop for HeldTraceContext {
+    fn drop(&mut self) {
+        OUTGOING.lock().unwrap().retain(|held| held.id != self.0);
+    }
+}
+
+/// Hold the current span's trace context for a `tools/call` about to be sent to `server`.
+/// None outside an exported trace. Keep the guard until the call is answered.
+pub fn hold_trace_context(server: &str, params: &CallToolRequestParam) -> Option<HeldTraceContext> {
+    let context = current_trace_context();
+    if context.is_empty() {
+        return None;
+    }
+    Some(hold(server, &params.name, params.arguments.clone(), context))
+}
+
+fn hold(server: &str, tool: &str, arguments: Option<JsonObject>, context: JsonObject) -> HeldTraceContext {
+    let id = NEXT_OUTGOING_ID.fetch_add(1, Ordering::Relaxed);
+    OUTGOING.lock().unwrap().push(OutgoingContext {
+        id,
+        server: server.to_string(),
+        tool: tool.to_string(),
+        arguments,
+        context,
+    });
+    HeldTraceContext(id)
+}
+
+/// Interceptor hook putting the trace context held for a tool call into its `params._meta`,
+/// where MCP servers look for it. The arguments are sent as they are.
+pub fn attach_trace_context(server: &str, message: &mut Value) {
+    if message.get("method").and_then(Value::as_str) != Some("tools/call") {
+        return;
+    }
+    let Some(params) = message.get_mut("params").and_then(Value::as_object_mut) else { return };
+    let tool = params.get("name").and_then(Value::as_str);
+    let arguments = params.get("arguments").and_then(Value::as_object);
+    let context = {
+        let mut outgoing = OUTGOING.lock().unwrap();
+        let Some(index) = outgoing
+            .iter()
+            .position(|held| held.server == server && Some(held.tool.as_str()) == tool && held.arguments.as_ref() == arguments)
+        else {
+            return;
+        };
+        outgoing.remove(index).context
+    };
+    if let Some(meta) = params.entry("_meta").or_insert_with(|| Value::Object(JsonObject::new())).as_object_mut() {
+        meta.extend(context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool_call(arguments: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "bash", "arguments": arguments } })
+    }
+
+    fn context() -> JsonObject {
+        json!({ "traceparent": "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01" }).as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_held_trace_context_goes_in_meta() {
+        let arguments = json!({ "command": "ls" }).as_object().cloned();
+        let held = hold("meta-test", "bash", arguments, context());
+
+        // Another call's message is left alone
+        let mut other = tool_call(json!({ "command": "pwd" }));
+        attach_trace_context("meta-test", &mut other);
+        assert_eq!(other["params"].get("_meta"), None);
+
+        let mut message = tool_call(json!({ "command": "ls" }));
+        attach_trace_context("meta-test", &mut message);
+        assert_eq!(message["params"], json!({
+            "name": "bash",
+            "arguments": { "command": "ls" },
+            "_meta": { "traceparent": "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01" }
+        }));
+        drop(held);
+
+        // Outside a trace nothing is held
+        let params = CallToolRequestParam { name: "bash".into(), arguments: None };
+        assert!(hold_trace_context("meta-test", &params).is_none());
+    }
+
+    #[test]
+    fn test_unsent_trace_context_is_forgotten() {
+        let arguments = json!({ "command": "ls" }).as_object().cloned();
+        drop(hold("forget-test", "bash", arguments, context()));
+
+        let mut message = tool_call(json!({ "command": "ls" }));
+        attach_trace_context("forget-test", &mut message);
+        assert_eq!(message["params"].get("_meta"), None);
+    }
+}
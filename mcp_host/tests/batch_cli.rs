@@ -0,0 +1,94 @@
+//! End-to-end tests that run the `mcp_host` binary in batch mode against a mock provider.
+
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::process::Command;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A throwaway directory with a config that points the Anthropic provider at `provider`
+fn session_dir(provider: &MockServer) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mcp_host_batch_test_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = json!({
+        "mcpServers": {},
+        "ai_providers": { "anthropic": { "model": "claude-test", "base_url": provider.uri() } },
+        "default_ai_provider": "anthropic"
+    });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    dir
+}
+
+/// Mock Anthropic Messages API answering every request with `answer`
+async fn mock_provider(answer: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(header("x-api-key", "test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "content": [{ "type": "text", "text": answer }],
+            "usage": { "input_tokens": 10, "output_tokens": 2 }
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Run `mcp_host load_config <dir>/config.json --input <prompts> <args>` with a clean
+/// environment, from `dir` so no `.env` is picked up
+async fn run_batch(dir: &PathBuf, prompts: &str, args: &[&str]) -> Output {
+    std::fs::write(dir.join("prompts.txt"), prompts).unwrap();
+    let run = Command::new(env!("CARGO_BIN_EXE_mcp_host"))
+        .arg("load_config")
+        .arg(dir.join("config.json"))
+        .arg("--input")
+        .arg(dir.join("prompts.txt"))
+        .args(args)
+        .current_dir(dir)
+        .env_clear()
+        .env("PATH", std::env::var_os("PATH").unwrap_or_default())
+        .env("HOME", dir)
+        .env("LOG_DIR", dir.join("logs"))
+        .env("ANTHROPIC_API_KEY", "test-key")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    tokio::time::timeout(Duration::from_secs(60), run)
+        .await
+        .expect("mcp_host did not finish")
+        .expect("failed to run mcp_host")
+}
+
+#[tokio::test]
+async fn test_json_batch_stdout_is_only_json() {
+    let provider = mock_provider("Paris.").await;
+    let dir = session_dir(&provider);
+    let output = run_batch(&dir, "What is the capital of France?", &["--json", "--provider", "anthropic"]).await;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout: {}\nstderr: {}", stdout, String::from_utf8_lossy(&output.stderr));
+    let turn: Value = serde_json::from_str(&stdout).unwrap_or_else(|e| panic!("stdout is not JSON ({}): {}", e, stdout));
+    assert_eq!(turn["prompt"], "What is the capital of France?");
+    assert_eq!(turn["final_response"], "Paris.");
+    assert_eq!(turn["tool_calls"], json!([]));
+}
+
+#[tokio::test]
+async fn test_batch_prints_only_the_answers() {
+    let provider = mock_provider("Paris.").await;
+    let dir = session_dir(&provider);
+    let output = run_batch(&dir, "Capital of France?\n---\nAnd its population?\n", &[]).await;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stdout: {}\nstderr: {}", stdout, String::from_utf8_lossy(&output.stderr));
+    assert_eq!(stdout, "## Prompt 1\n\nParis.\n\n## Prompt 2\n\nParis.\n");
+
+    // The prompts are one conversation: the second request carries the first exchange
+    let requests = provider.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+    let contents: Vec<&str> = body["messages"].as_array().unwrap().iter().filter_map(|m| m["content"].as_str()).collect();
+    assert_eq!(contents, vec!["Capital of France?", "Paris.", "And its population?"]);
+}
@@ -1,12 +1,20 @@
 use anyhow::Result;
+use nix::errno::Errno;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema; // Added
-use std::process::Command;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
 
-use tracing::{debug, error}; // Added tracing
+use tracing::{debug, error, warn}; // Added tracing
 // Import specific items from rmcp instead of prelude
 use rmcp::tool;
 
+use crate::command_allowlist::CommandAllowlist;
+use crate::progress::{stream_output, ProgressTracker};
+
 // Removed unused shared_protocol_objects::ToolInfo import
 
 
@@ -17,6 +25,9 @@ pub struct BashParams {
     #[serde(default = "default_cwd")]
     #[schemars(description = "The working directory for the command (defaults to current dir)")] // Added
     pub cwd: String,
+    #[serde(default)]
+    #[schemars(description = "Optional text written to the command's stdin (stdin is closed afterwards)")]
+    pub stdin: Option<String>,
 }
 
 fn default_cwd() -> String {
@@ -36,6 +47,29 @@ pub struct BashResult {
 
 pub struct BashExecutor;
 
+/// Kills a command's process group if dropped while the command runs, as happens when the
+/// tool call is cancelled. `kill_on_drop` alone would only reach `bash`, not what it started.
+struct ProcessGroupGuard(Option<u32>);
+
+impl ProcessGroupGuard {
+    /// The command finished; anything it left running in the background is its own business
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0.take() {
+            debug!("Killing process group {} of an abandoned bash command", pid);
+            match killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+                Ok(()) | Err(Errno::ESRCH) => {}
+                Err(e) => warn!("Failed to kill process group {}: {}", pid, e),
+            }
+        }
+    }
+}
+
 impl BashExecutor {
     pub fn new() -> Self {
         BashExecutor
@@ -50,25 +84,60 @@ impl BashExecutor {
             std::fs::create_dir_all(&cwd)?;
         }
 
-        let output = Command::new("bash")
+        let mut child = Command::new("bash")
             .arg("-c")
             .arg(&params.command)
             .current_dir(&cwd)
-            .output()?;
+            .stdin(if params.stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()?;
+        let group = ProcessGroupGuard(child.id());
 
-        // Check if there were permission issues
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("permission denied") {
-                return Err(anyhow::anyhow!("Permission denied. Try running with appropriate permissions or in a different directory."));
+        // Feed stdin from a separate task so a command producing lots of output
+        // can't deadlock against us while we're still writing its input
+        let stdin_writer = match (child.stdin.take(), params.stdin) {
+            (Some(mut pipe), Some(input)) => Some(tokio::spawn(async move {
+                let result = pipe.write_all(input.as_bytes()).await;
+                drop(pipe); // Close stdin so the command sees EOF
+                result
+            })),
+            _ => None,
+        };
+
+        // Pass output on line by line while the command runs
+        let tracker = ProgressTracker::current();
+        let (stdout, stderr, status) = tokio::join!(
+            stream_output(child.stdout.take(), "bash stdout", tracker.clone()),
+            stream_output(child.stderr.take(), "bash stderr", tracker),
+            child.wait(),
+        );
+        let status = status?;
+        group.disarm();
+
+        if let Some(writer) = stdin_writer {
+            match writer.await {
+                // The command may exit without reading all of its input
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                    debug!("Command exited before consuming all stdin");
+                }
+                Ok(result) => result?,
+                Err(e) => return Err(anyhow::anyhow!("stdin writer task failed: {}", e)),
             }
         }
 
+        // Check if there were permission issues
+        if !status.success() && stderr.contains("permission denied") {
+            return Err(anyhow::anyhow!("Permission denied. Try running with appropriate permissions or in a different directory."));
+        }
+
         Ok(BashResult {
-            success: output.status.success(),
-            status: output.status.code().unwrap_or(-1),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            success: status.success(),
+            status: status.code().unwrap_or(-1),
+            stdout,
+            stderr,
         })
     }
 }
@@ -76,12 +145,19 @@ impl BashExecutor {
 // --- New SDK Implementation ---
 
 #[derive(Debug, Clone)] // Added Clone
-pub struct BashTool;
+pub struct BashTool {
+    /// Commands allowed to run (from MCP_TOOLS_BASH_ALLOWLIST); None allows any command
+    allowlist: Option<CommandAllowlist>,
+}
 
 impl BashTool {
     // Add a constructor
     pub fn new() -> Self {
-        Self
+        Self::with_allowlist(CommandAllowlist::from_env())
+    }
+
+    pub fn with_allowlist(allowlist: Option<CommandAllowlist>) -> Self {
+        Self { allowlist }
     }
 }
 
@@ -94,6 +170,12 @@ impl BashTool {
         #[tool(aggr)] params: BashParams // Automatically aggregates JSON args into BashParams
     ) -> String { // Return String directly
         debug!("Executing bash tool with params: {:?}", params);
+        if let Some(allowlist) = &self.allowlist {
+            if let Err(reason) = allowlist.check(&params.command) {
+                warn!("Rejected bash command '{}': {}", params.command, reason);
+                return format!("TOOL EXECUTION ERROR: Command not allowed: {}", reason);
+            }
+        }
         let executor = BashExecutor::new();
 
         // Execute the command and handle the Result explicitly
@@ -117,3 +199,84 @@ impl BashTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(command: &str, stdin: Option<String>) -> BashParams {
+        BashParams {
+            command: command.to_string(),
+            cwd: default_cwd(),
+            stdin,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stdin_is_piped_to_command() {
+        let result = BashExecutor::new()
+            .execute(params("cat", Some("hello\nworld\n".to_string())))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.stdout, "hello\nworld\n");
+    }
+
+    #[tokio::test]
+    async fn test_large_stdin_does_not_deadlock() {
+        // Well beyond the pipe buffer size in both directions
+        let input = "x".repeat(4 * 1024 * 1024);
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(30),
+            BashExecutor::new().execute(params("cat", Some(input.clone()))),
+        )
+        .await
+        .expect("command deadlocked")
+        .unwrap();
+        assert_eq!(result.stdout.len(), input.len());
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_limits_commands() {
+        let tool = BashTool::with_allowlist(CommandAllowlist::parse(Some("echo,cat")));
+        let output = tool.bash(params("echo allowed | cat", None)).await;
+        assert!(output.starts_with("Command completed with status 0"), "{}", output);
+        assert!(output.contains("allowed"), "{}", output);
+
+        let output = tool.bash(params("echo hi; touch should-not-exist", None)).await;
+        assert_eq!(output, "TOOL EXECUTION ERROR: Command not allowed: 'touch' is not an allowed command (allowed: cat, echo)");
+        assert!(!std::path::Path::new(&default_cwd()).join("should-not-exist").exists());
+    }
+
+    /// Whether `pid` is alive; a zombie waiting to be reaped counts as gone
+    fn is_running(pid: i32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.rsplit(')').next().unwrap_or_default().trim_start().starts_with('Z'),
+            Err(_) => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_command_is_killed() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("sleep.pid");
+        // The sleep is bash's child, not bash itself, as with any multi-step command
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let executor = BashExecutor::new();
+        let run = executor.execute(params(&command, None));
+        assert!(tokio::time::timeout(std::time::Duration::from_secs(1), run).await.is_err());
+
+        let pid: i32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while is_running(pid) && std::time::Instant::now() < deadline {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(!is_running(pid), "sleep {} outlived its cancelled tool call", pid);
+    }
+
+    #[tokio::test]
+    async fn test_without_stdin_reads_eof() {
+        let result = BashExecutor::new().execute(params("wc -c", None)).await.unwrap();
+        assert_eq!(result.stdout.trim(), "0");
+    }
+}
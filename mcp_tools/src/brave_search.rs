@@ -67,6 +67,34 @@ pub struct Query {
     pub reddit_cluster: Option<String>,
 }
 
+/// One web result, as returned to the caller alongside the readable summary so a result
+/// can be picked (e.g. to scrape) by its URL without re-parsing the text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StructuredResult {
+    pub title: String,
+    pub url: String,
+    pub description: Option<String>,
+}
+
+/// The first `count` web results of a response
+pub fn structured_results(response: &SearchResponse, count: u8) -> Vec<StructuredResult> {
+    response
+        .web
+        .as_ref()
+        .map(|web| {
+            web.results
+                .iter()
+                .take(count as usize)
+                .map(|result| StructuredResult {
+                    title: result.title.clone(),
+                    url: result.url.clone(),
+                    description: result.description.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 // Tool Parameters
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct BraveSearchParams {
@@ -82,6 +110,29 @@ fn default_count() -> u8 {
     10
 }
 
+/// Parameters for `google_search`, which is an alias backed by Brave Search.
+/// Mirrors the Google Programmable Search parameter names so existing configs keep working.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GoogleSearchParams {
+    #[schemars(description = "The search query - be specific and include relevant keywords")]
+    pub query: String,
+
+    #[serde(default = "default_count")]
+    #[schemars(description = "Number of results to return (max 20)")]
+    pub num: u8,
+
+    #[serde(default)]
+    #[schemars(description = "1-based index of the first result, for paging (e.g. 11 for the second page of 10)")]
+    pub start: Option<u32>,
+}
+
+/// Brave pages by result-page offset (0-9), Google by 1-based result index
+fn start_to_offset(start: Option<u32>, count: u8) -> Option<u8> {
+    let start = start?;
+    let page = start.saturating_sub(1) / u32::from(count.max(1));
+    (page > 0).then(|| page.min(9) as u8)
+}
+
 // Request Parameters
 #[derive(Debug, Serialize)]
 struct SearchParams {
@@ -97,30 +148,44 @@ struct SearchParams {
 // Define the BraveSearch tool
 #[derive(Debug, Clone)]
 pub struct BraveSearchTool {
-    // We'll get the API key from env in execute_search
+    // Unless one is given, the API key is read from the env in execute_search
+    base_url: String,
+    api_key: Option<String>,
 }
 
 impl BraveSearchTool {
     pub fn new() -> Self {
-        Self {}
+        Self::with_base_url("https://api.search.brave.com/res/v1/web/search")
+    }
+
+    /// Point the tool at a different endpoint (e.g. a mock server)
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self { base_url: base_url.into(), api_key: None }
+    }
+
+    /// Use `api_key` instead of reading `BRAVE_API_KEY`
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
     }
     
     // Helper method to create a properly configured client and execute search
-    async fn execute_search(&self, query: &str, count: u8) -> Result<String> {
+    async fn execute_search(&self, query: &str, count: u8, offset: Option<u8>) -> Result<String> {
         info!("Starting Brave Search request for query: {}", query);
         
-        // Get API key from environment
-        let api_key = env::var("BRAVE_API_KEY")
-            .map_err(|_| anyhow!("BRAVE_API_KEY environment variable must be set"))?;
+        // Get API key from environment unless one was given
+        let api_key = match &self.api_key {
+            Some(key) => key.clone(),
+            None => env::var("BRAVE_API_KEY")
+                .map_err(|_| anyhow!("BRAVE_API_KEY environment variable must be set"))?,
+        };
         
         // Create client
         let client = reqwest::Client::new();
-        let base_url = "https://api.search.brave.com/res/v1/web/search";
-        
         let params = SearchParams {
             q: query.to_string(),
             count: Some(count.min(20)),  // maximum 20 results
-            offset: None,
+            offset,
             safesearch: Some("moderate".to_string()),
         };
 
@@ -136,7 +201,7 @@ impl BraveSearchTool {
         
         // Make the request
         let response = client
-            .get(base_url)
+            .get(&self.base_url)
             .headers(headers)
             .query(&params)
             .send()
@@ -161,13 +226,13 @@ impl BraveSearchTool {
 
         debug!("Successfully parsed Brave Search response");
         
-        // Format the results
-        let results = match search_response.web {
+        // Format the results, followed by the same results as JSON
+        let results = match &search_response.web {
             Some(web) => {
                 if web.results.is_empty() {
                     "No search results found.".to_string()
                 } else {
-                    web.results
+                    let summary = web.results
                         .iter()
                         .take(count as usize)
                         .map(|result| {
@@ -187,7 +252,9 @@ impl BraveSearchTool {
                             )
                         })
                         .collect::<Vec<_>>()
-                        .join("\n---\n\n")
+                        .join("\n---\n\n");
+                    let structured = serde_json::to_string_pretty(&structured_results(&search_response, count))?;
+                    format!("{}\n\nResults (JSON):\n{}", summary, structured)
                 }
             },
             None => "No web results found".to_string(),
@@ -199,7 +266,7 @@ impl BraveSearchTool {
 
 #[tool(tool_box)]
 impl BraveSearchTool {
-    #[tool(description = "Web search tool powered by Brave Search that retrieves relevant results from across the internet. Use this to find current information and facts from the web, research topics with multiple sources, verify claims, discover recent news and trends, or find specific websites and resources.")]
+    #[tool(description = "Web search tool powered by Brave Search that retrieves relevant results from across the internet. Use this to find current information and facts from the web, research topics with multiple sources, verify claims, discover recent news and trends, or find specific websites and resources. Results are followed by a JSON list of {title, url, description} for picking a URL to fetch.")]
     pub async fn brave_search(
         &self,
         #[tool(aggr)] params: BraveSearchParams
@@ -208,7 +275,24 @@ impl BraveSearchTool {
         info!("Brave Search tool called for query: {}", params.query);
         
         // Execute search and handle errors
-        match self.execute_search(&params.query, params.count).await {
+        match self.execute_search(&params.query, params.count, None).await {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Search error: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "Web search tool (alias of brave_search, results come from Brave Search). Use this to find current information and facts from the web. Supports 'num' results and 'start' for paging.")]
+    pub async fn google_search(
+        &self,
+        #[tool(aggr)] params: GoogleSearchParams
+    ) -> String {
+        info!("google_search (Brave alias) called for query: {}", params.query);
+
+        let offset = start_to_offset(params.start, params.num);
+        match self.execute_search(&params.query, params.num, offset).await {
             Ok(content) => content,
             Err(e) => {
                 error!("Search error: {}", e);
@@ -217,3 +301,99 @@ impl BraveSearchTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_start_to_offset() {
+        assert_eq!(start_to_offset(None, 10), None);
+        assert_eq!(start_to_offset(Some(1), 10), None);
+        assert_eq!(start_to_offset(Some(11), 10), Some(1));
+        assert_eq!(start_to_offset(Some(500), 10), Some(9));
+    }
+
+    #[tokio::test]
+    async fn test_google_search_delegates_to_brave() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("q", "rust async"))
+            .and(query_param("count", "5"))
+            .and(query_param("offset", "1"))
+            .and(header("X-Subscription-Token", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "type": "search",
+                "web": {
+                    "type": "search",
+                    "family_friendly": true,
+                    "results": [{
+                        "title": "Async Rust",
+                        "url": "https://example.com/async",
+                        "description": "An introduction",
+                        "family_friendly": true,
+                        "is_source_local": false,
+                        "is_source_both": false
+                    }]
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tool = BraveSearchTool::with_base_url(server.uri()).with_api_key("test-key");
+        let output = tool
+            .google_search(GoogleSearchParams { query: "rust async".into(), num: 5, start: Some(6) })
+            .await;
+
+        assert!(output.contains("Title: Async Rust"), "{}", output);
+        assert!(output.contains("URL: https://example.com/async"));
+    }
+
+    #[tokio::test]
+    async fn test_results_are_also_returned_as_json() {
+        let server = MockServer::start().await;
+        let result = |title: &str, url: &str, description: Option<&str>| {
+            serde_json::json!({
+                "title": title,
+                "url": url,
+                "description": description,
+                "page_age": "2024-01-01",
+                "family_friendly": true,
+                "is_source_local": false,
+                "is_source_both": false
+            })
+        };
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "type": "search",
+                "web": {
+                    "type": "search",
+                    "family_friendly": true,
+                    "results": [
+                        result("The Rust Book", "https://doc.rust-lang.org/book/", Some("Learn Rust")),
+                        result("Rust by Example", "https://doc.rust-lang.org/rust-by-example/", None),
+                        result("Too many", "https://example.com/", None)
+                    ]
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let tool = BraveSearchTool::with_base_url(server.uri()).with_api_key("test-key");
+        let output = tool.brave_search(BraveSearchParams { query: "rust".into(), count: 2 }).await;
+
+        assert!(output.starts_with("Title: The Rust Book\nURL: https://doc.rust-lang.org/book/"), "{}", output);
+        let (_, json) = output.split_once("Results (JSON):\n").expect("structured results");
+        let parsed: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                serde_json::json!({"title": "The Rust Book", "url": "https://doc.rust-lang.org/book/", "description": "Learn Rust"}),
+                serde_json::json!({"title": "Rust by Example", "url": "https://doc.rust-lang.org/rust-by-example/", "description": null}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeSet;
+use tracing::info;
+
+/// Environment variable restricting the bash tool to the listed commands, e.g. `ls,cat,grep`
+pub const BASH_ALLOWLIST_ENV: &str = "MCP_TOOLS_BASH_ALLOWLIST";
+
+/// The only commands the bash tool may run. Every command of a script (each part of a
+/// pipeline or list) must be allowed, and anything that could run other code - command
+/// substitution, redirections, subshells - is rejected outright.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandAllowlist {
+    commands: BTreeSet<String>,
+}
+
+impl CommandAllowlist {
+    /// Read `MCP_TOOLS_BASH_ALLOWLIST`; unset or blank means any command may run
+    pub fn from_env() -> Option<Self> {
+        let allowlist = Self::parse(std::env::var(BASH_ALLOWLIST_ENV).ok().as_deref());
+        if let Some(allowlist) = &allowlist {
+            info!("Bash tool limited to commands: {:?}", allowlist.commands);
+        }
+        allowlist
+    }
+
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        let commands: BTreeSet<String> = value
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        (!commands.is_empty()).then_some(Self { commands })
+    }
+
+    /// Ok if every command in `script` is allowed; otherwise why it was rejected
+    pub fn check(&self, script: &str) -> Result<(), String> {
+        let commands = simple_commands(script)?;
+        if commands.is_empty() {
+            return Err("no command given".to_string());
+        }
+        for words in &commands {
+            if !self.commands.contains(&words[0]) {
+                return Err(format!(
+                    "'{}' is not an allowed command (allowed: {})",
+                    words[0],
+                    self.commands.iter().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split a shell script into its simple commands (the words between `|`, `||`, `&`, `&&`,
+/// `;` and newlines), honouring quotes and backslash escapes. Errors on syntax whose effect
+/// can't be judged from the command names alone.
+fn simple_commands(script: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut commands = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = script.chars().peekable();
+
+    let end_word = |words: &mut Vec<String>, word: &mut String, in_word: &mut bool| {
+        if *in_word {
+            words.push(std::mem::take(word));
+            *in_word = false;
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some('`') => return Err("command substitution is not allowed".to_string()),
+                        Some('$') if chars.peek() == Some(&'(') => {
+                            return Err("command substitution is not allowed".to_string())
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some('\n') | None => {}
+                    Some(c) => word.push(c),
+                }
+            }
+            '#' if !in_word => {
+                // A comment runs to the end of the line
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            ' ' | '\t' => end_word(&mut words, &mut word, &mut in_word),
+            '\n' | ';' | '|' | '&' => {
+                if matches!(c, '|' | '&') && chars.peek() == Some(&c) {
+                    chars.next();
+                }
+                if c == '&' && chars.peek() == Some(&'>') {
+                    return Err("redirections are not allowed".to_string());
+                }
+                end_word(&mut words, &mut word, &mut in_word);
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            '`' => return Err("command substitution is not allowed".to_string()),
+            '$' if chars.peek() == Some(&'(') => return Err("command substitution is not allowed".to_string()),
+            '<' | '>' => return Err("redirections are not allowed".to_string()),
+            '(' | ')' => return Err("subshells are not allowed".to_string()),
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    end_word(&mut words, &mut word, &mut in_word);
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist() -> CommandAllowlist {
+        CommandAllowlist::parse(Some("ls, cat,grep")).unwrap()
+    }
+
+    #[test]
+    fn test_allowed_commands_pass() {
+        let allowlist = allowlist();
+        assert_eq!(allowlist.check("ls -la /tmp"), Ok(()));
+        // Every stage of a pipeline or list is checked; operators inside quotes are just text
+        assert_eq!(allowlist.check("cat notes.txt | grep 'a|b;c' && ls"), Ok(()));
+        assert_eq!(allowlist.check("grep \"x\\\"y\" file # rm -rf /"), Ok(()));
+        assert!(CommandAllowlist::parse(Some(" , ")).is_none());
+    }
+
+    #[test]
+    fn test_denied_commands_are_rejected() {
+        let allowlist = allowlist();
+        let err = allowlist.check("rm -rf build").unwrap_err();
+        assert_eq!(err, "'rm' is not an allowed command (allowed: cat, grep, ls)");
+
+        // A prefix or a later command can't smuggle anything in
+        assert!(allowlist.check("ls; rm -rf build").unwrap_err().starts_with("'rm'"));
+        assert!(allowlist.check("ls\ncurl example.com").unwrap_err().starts_with("'curl'"));
+        assert!(allowlist.check("lsblk").is_err());
+        assert!(allowlist.check("X=1 ls").is_err());
+        assert!(allowlist.check("cat $(which rm)").unwrap_err().contains("substitution"));
+        assert!(allowlist.check("cat \"`id`\"").unwrap_err().contains("substitution"));
+        assert!(allowlist.check("cat secrets > /tmp/out").unwrap_err().contains("redirection"));
+        assert!(allowlist.check("(rm x)").unwrap_err().contains("subshell"));
+        assert!(allowlist.check("cat 'open").unwrap_err().contains("unterminated"));
+        assert!(allowlist.check("   ").is_err());
+    }
+}
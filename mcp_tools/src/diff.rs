@@ -0,0 +1,301 @@
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+// Import rmcp SDK components
+use rmcp::tool;
+
+/// Context lines shown around each change when the caller doesn't say
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Above this many comparisons the changed region is shown as one replacement rather than
+/// working out the smallest diff
+const MAX_LCS_CELLS: usize = 16_000_000;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DiffParams {
+    #[serde(default)]
+    #[schemars(description = "Path of the original file. Provide either 'old_path' or 'old_text'.")]
+    pub old_path: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Original text, instead of a file")]
+    pub old_text: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Path of the changed file. Provide either 'new_path' or 'new_text'.")]
+    pub new_path: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Changed text, instead of a file")]
+    pub new_text: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Unchanged lines to show around each change. Defaults to 3.")]
+    pub context_lines: Option<usize>,
+
+    #[serde(default)]
+    #[schemars(description = "Treat lines that differ only in whitespace as equal. Defaults to false.")]
+    pub ignore_whitespace: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One step of the edit script, with the old and new line it starts at
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    op: Op,
+    old: usize,
+    new: usize,
+}
+
+/// Unified diff of `old` against `new`, labelled with the given names. Empty if the inputs
+/// have no differences.
+pub fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str, context: usize, ignore_whitespace: bool) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let steps = edit_script(&old_lines, &new_lines, ignore_whitespace);
+    let changes: Vec<usize> = steps.iter().enumerate().filter(|(_, s)| s.op != Op::Equal).map(|(i, _)| i).collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Each change with its context, merging hunks whose context touches or overlaps
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(steps.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in hunks {
+        let hunk = &steps[start..end];
+        let old_count = hunk.iter().filter(|s| s.op != Op::Insert).count();
+        let new_count = hunk.iter().filter(|s| s.op != Op::Delete).count();
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk[0].old, old_count),
+            hunk_range(hunk[0].new, new_count)
+        ));
+        for step in hunk {
+            match step.op {
+                Op::Equal => diff.push_str(&format!(" {}\n", old_lines[step.old])),
+                Op::Delete => diff.push_str(&format!("-{}\n", old_lines[step.old])),
+                Op::Insert => diff.push_str(&format!("+{}\n", new_lines[step.new])),
+            }
+        }
+    }
+    diff
+}
+
+/// A hunk's line range as `start,count`, 1-based; an empty range names the line before it
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// The shortest edit script turning `old` into `new`, from their longest common subsequence.
+/// Deletions come before insertions where a block of lines was replaced.
+fn edit_script(old: &[&str], new: &[&str], ignore_whitespace: bool) -> Vec<Step> {
+    let normalize = |line: &str| -> String {
+        if ignore_whitespace {
+            line.split_whitespace().collect()
+        } else {
+            line.to_string()
+        }
+    };
+    let old_keys: Vec<String> = old.iter().map(|&line| normalize(line)).collect();
+    let new_keys: Vec<String> = new.iter().map(|&line| normalize(line)).collect();
+
+    // Unchanged lines at either end don't need the LCS table
+    let prefix = old_keys.iter().zip(&new_keys).take_while(|(a, b)| a == b).count();
+    let suffix = old_keys[prefix..]
+        .iter()
+        .rev()
+        .zip(new_keys[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old_keys[prefix..old.len() - suffix];
+    let new_mid = &new_keys[prefix..new.len() - suffix];
+
+    let mut steps = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    let mut push = |op: Op, i: &mut usize, j: &mut usize| {
+        steps.push(Step { op, old: *i, new: *j });
+        match op {
+            Op::Equal => {
+                *i += 1;
+                *j += 1;
+            }
+            Op::Delete => *i += 1,
+            Op::Insert => *j += 1,
+        }
+    };
+
+    for _ in 0..prefix {
+        push(Op::Equal, &mut i, &mut j);
+    }
+    if (old_mid.len() + 1) * (new_mid.len() + 1) > MAX_LCS_CELLS {
+        debug!("Diff of {}x{} changed lines is too large to align; showing it as a replacement", old_mid.len(), new_mid.len());
+        for _ in old_mid {
+            push(Op::Delete, &mut i, &mut j);
+        }
+        for _ in new_mid {
+            push(Op::Insert, &mut i, &mut j);
+        }
+    } else {
+        let (n, m) = (old_mid.len(), new_mid.len());
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for a in (0..n).rev() {
+            for b in (0..m).rev() {
+                lcs[a][b] = if old_mid[a] == new_mid[b] { lcs[a + 1][b + 1] + 1 } else { lcs[a + 1][b].max(lcs[a][b + 1]) };
+            }
+        }
+        let (mut a, mut b) = (0, 0);
+        while a < n || b < m {
+            if a < n && b < m && old_mid[a] == new_mid[b] {
+                push(Op::Equal, &mut i, &mut j);
+                a += 1;
+                b += 1;
+            } else if a < n && (b == m || lcs[a + 1][b] >= lcs[a][b + 1]) {
+                push(Op::Delete, &mut i, &mut j);
+                a += 1;
+            } else {
+                push(Op::Insert, &mut i, &mut j);
+                b += 1;
+            }
+        }
+    }
+    for _ in 0..suffix {
+        push(Op::Equal, &mut i, &mut j);
+    }
+    steps
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffTool;
+
+impl DiffTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// One side of the comparison: its label and contents
+    async fn read_side(path: &Option<String>, text: &Option<String>, side: &str) -> Result<(String, String)> {
+        match (path, text) {
+            (Some(path), None) => {
+                let contents = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to read '{}': {}", path, e))?;
+                Ok((path.clone(), contents))
+            }
+            (None, Some(text)) => Ok((side.to_string(), text.clone())),
+            _ => Err(anyhow!("Provide exactly one of '{side}_path' or '{side}_text'")),
+        }
+    }
+
+    async fn diff_internal(&self, params: DiffParams) -> Result<String> {
+        let (old_label, old) = Self::read_side(&params.old_path, &params.old_text, "old").await?;
+        let (new_label, new) = Self::read_side(&params.new_path, &params.new_text, "new").await?;
+        let context = params.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES);
+
+        let diff = unified_diff(&old_label, &new_label, &old, &new, context, params.ignore_whitespace);
+        if diff.is_empty() {
+            return Ok("No differences.".to_string());
+        }
+        Ok(diff)
+    }
+
+    #[tool(description = "Compares two files or two pieces of text and returns a unified diff. Give each side as a path ('old_path'/'new_path') or text ('old_text'/'new_text'). Options: 'context_lines' (default 3) and 'ignore_whitespace'.")]
+    pub async fn diff(
+        &self,
+        #[tool(aggr)] params: DiffParams,
+    ) -> String {
+        match self.diff_internal(params).await {
+            Ok(output) => output,
+            Err(e) => {
+                error!("diff failed: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_params(old: &str, new: &str) -> DiffParams {
+        DiffParams {
+            old_path: None,
+            old_text: Some(old.to_string()),
+            new_path: None,
+            new_text: Some(new.to_string()),
+            context_lines: None,
+            ignore_whitespace: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_inputs_have_no_diff() {
+        assert_eq!(unified_diff("a", "b", "one\ntwo\n", "one\ntwo\n", 3, false), "");
+        assert_eq!(DiffTool::new().diff(text_params("same", "same")).await, "No differences.");
+        // Whitespace-only changes count only when asked to
+        assert_eq!(unified_diff("a", "b", "fn main() {\n", "fn  main()  {\n", 3, true), "");
+        assert!(!unified_diff("a", "b", "fn main() {\n", "fn  main()  {\n", 3, false).is_empty());
+    }
+
+    #[test]
+    fn test_changed_lines_make_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\neleven\n";
+        assert_eq!(
+            unified_diff("old.txt", "new.txt", old, new, 1, false),
+            "--- old.txt\n+++ new.txt\n@@ -2,3 +2,3 @@\n 2\n-3\n+three\n 4\n@@ -10 +10,2 @@\n 10\n+eleven\n"
+        );
+        // With more context the two changes share one hunk
+        let diff = unified_diff("old.txt", "new.txt", old, new, 4, false);
+        assert_eq!(diff.matches("@@ -").count(), 1, "{}", diff);
+        assert!(diff.contains("@@ -1,10 +1,11 @@\n"), "{}", diff);
+
+        assert_eq!(unified_diff("a", "b", "", "x\n", 3, false), "--- a\n+++ b\n@@ -0,0 +1 @@\n+x\n");
+    }
+
+    #[tokio::test]
+    async fn test_diff_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let old_path = dir.path().join("old.rs");
+        let new_path = dir.path().join("new.rs");
+        std::fs::write(&old_path, "let x = 1;\nlet y = 2;\n").unwrap();
+        std::fs::write(&new_path, "let x = 1;\nlet y = 3;\n").unwrap();
+
+        let params = DiffParams {
+            old_path: Some(old_path.to_str().unwrap().to_string()),
+            old_text: None,
+            new_path: Some(new_path.to_str().unwrap().to_string()),
+            new_text: None,
+            context_lines: Some(0),
+            ignore_whitespace: false,
+        };
+        let output = DiffTool::new().diff(params).await;
+        assert!(output.ends_with("@@ -2 +2 @@\n-let y = 2;\n+let y = 3;\n"), "{}", output);
+        assert!(output.starts_with(&format!("--- {}\n", old_path.display())), "{}", output);
+
+        // A side given both ways is ambiguous
+        let both = DiffParams { old_path: Some("/nonexistent".to_string()), ..text_params("x", "y") };
+        assert!(DiffTool::new().diff(both).await.starts_with("Error: Provide exactly one of 'old_path' or 'old_text'"));
+    }
+}
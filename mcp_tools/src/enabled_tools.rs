@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Environment variable holding a comma-separated list of tools to expose
+pub const ENABLED_TOOLS_ENV: &str = "MCP_TOOLS_ENABLED";
+
+/// Names that enable a whole family of tools at once
+const TOOL_GROUPS: &[(&str, &[&str])] = &[
+    ("long_running_task", &["start_task", "get_status", "list_tasks", "stop_task", "restart_task", "clear_tasks"]),
+    ("netlify", &["netlify", "netlify_help"]),
+    ("fs", &["read_file", "write_file", "list_dir", "stat"]),
+];
+
+/// Which tools the server should list and accept calls for
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnabledTools {
+    All,
+    Only(HashSet<String>),
+}
+
+impl EnabledTools {
+    /// Read `MCP_TOOLS_ENABLED`; unset or blank means every tool is enabled
+    pub fn from_env() -> Self {
+        let enabled = Self::parse(std::env::var(ENABLED_TOOLS_ENV).ok().as_deref());
+        info!("Enabled tools: {:?}", enabled);
+        enabled
+    }
+
+    pub fn parse(value: Option<&str>) -> Self {
+        let names: HashSet<String> = value
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .flat_map(|name| match TOOL_GROUPS.iter().find(|(group, _)| *group == name) {
+                Some((_, tools)) => tools.iter().map(|t| t.to_string()).collect(),
+                None => vec![name],
+            })
+            .collect();
+
+        if names.is_empty() {
+            EnabledTools::All
+        } else {
+            EnabledTools::Only(names)
+        }
+    }
+
+    pub fn is_enabled(&self, tool_name: &str) -> bool {
+        match self {
+            EnabledTools::All => true,
+            EnabledTools::Only(names) => names.contains(tool_name),
+        }
+    }
+
+    /// Log any requested names that don't match a registered tool (likely typos)
+    pub fn warn_unknown<'a>(&self, registered: impl IntoIterator<Item = &'a str>) {
+        if let EnabledTools::Only(names) = self {
+            let registered: HashSet<&str> = registered.into_iter().collect();
+            for name in names.iter().filter(|n| !registered.contains(n.as_str())) {
+                warn!("{} names unknown tool '{}'", ENABLED_TOOLS_ENV, name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unset_or_blank_enables_all() {
+        assert_eq!(EnabledTools::parse(None), EnabledTools::All);
+        assert_eq!(EnabledTools::parse(Some(" , ")), EnabledTools::All);
+    }
+
+    #[test]
+    fn test_only_named_tools_enabled() {
+        let enabled = EnabledTools::parse(Some("bash, Brave_Search"));
+        assert!(enabled.is_enabled("bash"));
+        assert!(enabled.is_enabled("brave_search"));
+        assert!(!enabled.is_enabled("aider"));
+    }
+
+    #[test]
+    fn test_group_names_expand() {
+        let enabled = EnabledTools::parse(Some("long_running_task"));
+        assert!(enabled.is_enabled("start_task"));
+        assert!(enabled.is_enabled("clear_tasks"));
+        assert!(!enabled.is_enabled("bash"));
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+use tracing::{error, info};
+
+// Import rmcp SDK components
+use rmcp::tool;
+
+/// Root directory all file operations are confined to (defaults to the working directory)
+pub const SANDBOX_ROOT_ENV: &str = "FS_SANDBOX_ROOT";
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ReadFileParams {
+    #[schemars(description = "Path relative to the sandbox root (absolute paths must be inside it)")]
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WriteFileParams {
+    #[schemars(description = "Path relative to the sandbox root (absolute paths must be inside it)")]
+    pub path: String,
+
+    #[schemars(description = "File content, encoded as given by 'encoding'")]
+    pub content: String,
+
+    #[serde(default)]
+    #[schemars(description = "'utf8' (default) or 'base64' for binary content")]
+    pub encoding: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Create missing parent directories. Defaults to false.")]
+    pub create_dirs: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ListDirParams {
+    #[serde(default = "default_dir")]
+    #[schemars(description = "Directory relative to the sandbox root. Defaults to the root itself.")]
+    pub path: String,
+}
+
+fn default_dir() -> String {
+    ".".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StatParams {
+    #[schemars(description = "Path relative to the sandbox root (absolute paths must be inside it)")]
+    pub path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FsTool {
+    root: PathBuf,
+}
+
+impl Default for FsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsTool {
+    /// Sandbox root from `FS_SANDBOX_ROOT`, or the current directory
+    pub fn new() -> Self {
+        let root = std::env::var_os(SANDBOX_ROOT_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        Self::with_root(root)
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve `path` to a location inside the sandbox. `..` components are refused outright,
+    /// and the deepest existing ancestor is canonicalized so symlinks can't point outside.
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let root = self.root.canonicalize()
+            .map_err(|e| anyhow!("Sandbox root {} is not accessible: {}", self.root.display(), e))?;
+        let requested = Path::new(path);
+        if requested.components().any(|c| matches!(c, Component::ParentDir)) {
+            return Err(anyhow!("Path '{}' may not contain '..'", path));
+        }
+        let joined = if requested.is_absolute() { requested.to_path_buf() } else { root.join(requested) };
+
+        // Split into the part that exists (may contain symlinks) and a not-yet-created tail.
+        // symlink_metadata counts dangling symlinks as existing, so canonicalize rejects them.
+        let mut existing = joined.as_path();
+        let mut tail = Vec::new();
+        while existing.symlink_metadata().is_err() {
+            tail.push(existing.file_name().ok_or_else(|| anyhow!("Invalid path '{}'", path))?);
+            existing = existing.parent().ok_or_else(|| anyhow!("Invalid path '{}'", path))?;
+        }
+        let mut resolved = existing.canonicalize()
+            .map_err(|e| anyhow!("Failed to resolve '{}': {}", path, e))?;
+        if !resolved.starts_with(&root) {
+            return Err(anyhow!("Path '{}' is outside the sandbox", path));
+        }
+        for part in tail.into_iter().rev() {
+            resolved.push(part);
+        }
+        Ok(resolved)
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        let root = self.root.canonicalize().unwrap_or_else(|_| self.root.clone());
+        path.strip_prefix(&root).unwrap_or(path).display().to_string()
+    }
+
+    async fn read(&self, params: ReadFileParams) -> Result<Value> {
+        let path = self.resolve(&params.path)?;
+        let bytes = tokio::fs::read(&path).await.map_err(|e| anyhow!("Failed to read '{}': {}", params.path, e))?;
+        let size = bytes.len();
+        Ok(match String::from_utf8(bytes) {
+            Ok(text) => json!({ "path": self.relative(&path), "size": size, "encoding": "utf8", "content": text }),
+            Err(e) => json!({
+                "path": self.relative(&path),
+                "size": size,
+                "encoding": "base64",
+                "content": BASE64.encode(e.into_bytes()),
+            }),
+        })
+    }
+
+    async fn write(&self, params: WriteFileParams) -> Result<Value> {
+        let path = self.resolve(&params.path)?;
+        let bytes = match params.encoding.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("utf8") | Some("utf-8") => params.content.into_bytes(),
+            Some("base64") => BASE64.decode(params.content.trim()).map_err(|e| anyhow!("Invalid base64 content: {}", e))?,
+            Some(other) => return Err(anyhow!("Unsupported encoding '{}'. Use 'utf8' or 'base64'.", other)),
+        };
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                if !params.create_dirs {
+                    return Err(anyhow!("Parent directory of '{}' does not exist (set create_dirs to create it)", params.path));
+                }
+                tokio::fs::create_dir_all(parent).await?;
+            }
+        }
+        tokio::fs::write(&path, &bytes).await.map_err(|e| anyhow!("Failed to write '{}': {}", params.path, e))?;
+        info!("Wrote {} bytes to {}", bytes.len(), path.display());
+        Ok(json!({ "path": self.relative(&path), "bytes_written": bytes.len() }))
+    }
+
+    async fn list(&self, params: ListDirParams) -> Result<Value> {
+        let path = self.resolve(&params.path)?;
+        let mut reader = tokio::fs::read_dir(&path).await.map_err(|e| anyhow!("Failed to list '{}': {}", params.path, e))?;
+        let mut entries = Vec::new();
+        while let Some(entry) = reader.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            let size = if file_type.is_file() { entry.metadata().await.map(|m| m.len()).ok() } else { None };
+            entries.push(json!({
+                "name": entry.file_name().to_string_lossy(),
+                "kind": kind_of(&file_type),
+                "size": size,
+            }));
+        }
+        entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        Ok(json!({ "path": self.relative(&path), "entries": entries }))
+    }
+
+    async fn stat_path(&self, params: StatParams) -> Result<Value> {
+        let path = self.resolve(&params.path)?;
+        let metadata = tokio::fs::symlink_metadata(&path).await.map_err(|e| anyhow!("Failed to stat '{}': {}", params.path, e))?;
+        let modified = metadata.modified().ok().map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+        Ok(json!({
+            "path": self.relative(&path),
+            "kind": kind_of(&metadata.file_type()),
+            "size": metadata.len(),
+            "modified": modified,
+            "readonly": metadata.permissions().readonly(),
+        }))
+    }
+
+    fn respond(result: Result<Value>, operation: &str) -> String {
+        match result {
+            Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|e| format!("Error: {}", e)),
+            Err(e) => {
+                error!("{} failed: {}", operation, e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+fn kind_of(file_type: &std::fs::FileType) -> &'static str {
+    if file_type.is_dir() {
+        "dir"
+    } else if file_type.is_symlink() {
+        "symlink"
+    } else {
+        "file"
+    }
+}
+
+#[tool(tool_box)]
+impl FsTool {
+    #[tool(description = "Reads a file inside the sandbox. Returns JSON with 'content' as UTF-8 text, or base64 (with encoding 'base64') for binary files.")]
+    pub async fn read_file(
+        &self,
+        #[tool(aggr)] params: ReadFileParams,
+    ) -> String {
+        Self::respond(self.read(params).await, "read_file")
+    }
+
+    #[tool(description = "Writes a file inside the sandbox, replacing any existing content. Use encoding 'base64' for binary data.")]
+    pub async fn write_file(
+        &self,
+        #[tool(aggr)] params: WriteFileParams,
+    ) -> String {
+        Self::respond(self.write(params).await, "write_file")
+    }
+
+    #[tool(description = "Lists a directory inside the sandbox. Returns JSON entries with name, kind (file/dir/symlink) and size.")]
+    pub async fn list_dir(
+        &self,
+        #[tool(aggr)] params: ListDirParams,
+    ) -> String {
+        Self::respond(self.list(params).await, "list_dir")
+    }
+
+    #[tool(description = "Returns metadata (kind, size, modified time, readonly) for a path inside the sandbox.")]
+    pub async fn stat(
+        &self,
+        #[tool(aggr)] params: StatParams,
+    ) -> String {
+        Self::respond(self.stat_path(params).await, "stat")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(output: String) -> Value {
+        serde_json::from_str(&output).unwrap_or_else(|_| panic!("not JSON: {}", output))
+    }
+
+    #[tokio::test]
+    async fn test_write_read_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = FsTool::with_root(dir.path());
+
+        let written = parse(tool.write_file(WriteFileParams {
+            path: "notes/a.txt".into(),
+            content: "line 1\n\"quoted\" $HOME\n".into(),
+            encoding: None,
+            create_dirs: true,
+        }).await);
+        assert_eq!(written["bytes_written"], 22);
+
+        let read = parse(tool.read_file(ReadFileParams { path: "notes/a.txt".into() }).await);
+        assert_eq!(read["encoding"], "utf8");
+        assert_eq!(read["content"], "line 1\n\"quoted\" $HOME\n");
+
+        let listing = parse(tool.list_dir(ListDirParams { path: "notes".into() }).await);
+        assert_eq!(listing["entries"], json!([{ "name": "a.txt", "kind": "file", "size": 22 }]));
+    }
+
+    #[tokio::test]
+    async fn test_binary_round_trip_uses_base64() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = FsTool::with_root(dir.path());
+        let bytes = [0u8, 159, 146, 150, 255];
+
+        tool.write_file(WriteFileParams {
+            path: "blob.bin".into(),
+            content: BASE64.encode(bytes),
+            encoding: Some("base64".into()),
+            create_dirs: false,
+        }).await;
+
+        let read = parse(tool.read_file(ReadFileParams { path: "blob.bin".into() }).await);
+        assert_eq!(read["encoding"], "base64");
+        assert_eq!(BASE64.decode(read["content"].as_str().unwrap()).unwrap(), bytes);
+    }
+
+    #[tokio::test]
+    async fn test_traversal_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = dir.path().join("sandbox");
+        std::fs::create_dir(&sandbox).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(dir.path(), sandbox.join("escape")).unwrap();
+        let tool = FsTool::with_root(&sandbox);
+
+        let dotdot = tool.read_file(ReadFileParams { path: "../secret.txt".into() }).await;
+        assert!(dotdot.contains("may not contain '..'"), "{}", dotdot);
+
+        let symlink = tool.read_file(ReadFileParams { path: "escape/secret.txt".into() }).await;
+        assert!(symlink.contains("outside the sandbox"), "{}", symlink);
+
+        let absolute = tool.stat(StatParams { path: dir.path().join("secret.txt").display().to_string() }).await;
+        assert!(absolute.contains("outside the sandbox"), "{}", absolute);
+
+        let write = tool.write_file(WriteFileParams {
+            path: "escape/new.txt".into(),
+            content: "x".into(),
+            encoding: None,
+            create_dirs: false,
+        }).await;
+        assert!(write.contains("outside the sandbox"), "{}", write);
+        assert!(!dir.path().join("new.txt").exists());
+    }
+}
@@ -0,0 +1,340 @@
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::{debug, error, info};
+
+// Import rmcp SDK components
+use rmcp::tool;
+
+/// Field separator used in git format strings; never appears in commit metadata
+const SEP: char = '\u{1f}';
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GitParams {
+    #[schemars(description = "One of: status, diff, log, commit, branch")]
+    pub operation: String,
+
+    #[serde(default)]
+    #[schemars(description = "Repository directory, inside the server's configured repo root. Defaults to the root itself.")]
+    pub repo_path: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "diff: show staged changes instead of unstaged ones")]
+    pub staged: bool,
+
+    #[serde(default)]
+    #[schemars(description = "log: number of commits to return (default 10)")]
+    pub max_count: Option<usize>,
+
+    #[serde(default)]
+    #[schemars(description = "commit: the commit message")]
+    pub message: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "commit: paths to stage before committing. Empty stages all changes.")]
+    pub paths: Vec<String>,
+
+    #[serde(default)]
+    #[schemars(description = "branch: name of a branch to create (omit to list branches)")]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Must be true for operations that modify the repository (commit, creating a branch)")]
+    pub allow_write: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct GitTool {
+    repo_root: PathBuf,
+}
+
+impl Default for GitTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitTool {
+    /// Use `GIT_REPO_ROOT` if set, otherwise the current directory
+    pub fn new() -> Self {
+        let root = std::env::var("GIT_REPO_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        Self::with_root(root)
+    }
+
+    pub fn with_root(repo_root: impl Into<PathBuf>) -> Self {
+        Self { repo_root: repo_root.into() }
+    }
+
+    /// The repository `repo_path` names, which must resolve (after `..` and symlinks) to the
+    /// repo root or a directory under it. Relative paths are taken from the root.
+    fn resolve_repo(&self, repo_path: Option<&str>) -> Result<PathBuf> {
+        let root = self.repo_root.canonicalize()
+            .map_err(|e| anyhow!("Repo root {} is not accessible: {}", self.repo_root.display(), e))?;
+        let Some(repo_path) = repo_path else { return Ok(root) };
+        let repo = root.join(Path::new(repo_path)).canonicalize()
+            .map_err(|e| anyhow!("Repository path '{}' is not accessible: {}", repo_path, e))?;
+        if !repo.starts_with(&root) {
+            return Err(anyhow!("Repository path '{}' is outside the repo root {}", repo_path, root.display()));
+        }
+        Ok(repo)
+    }
+
+    async fn git(&self, repo: &PathBuf, args: &[&str]) -> Result<String> {
+        debug!("Running git {:?} in {}", args, repo.display());
+        let output = Command::new("git").arg("-C").arg(repo).args(args).output().await
+            .map_err(|e| anyhow!("Failed to run git: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    async fn status(&self, repo: &PathBuf) -> Result<Value> {
+        let output = self.git(repo, &["status", "--porcelain=v1", "--branch"]).await?;
+        let mut branch = None;
+        let mut files = Vec::new();
+        for line in output.lines() {
+            if let Some(header) = line.strip_prefix("## ") {
+                // "main...origin/main [ahead 1]" or "No commits yet on main"
+                let name = header.strip_prefix("No commits yet on ").unwrap_or(header);
+                branch = name.split("...").next().map(|b| b.split(' ').next().unwrap_or(b).to_string());
+            } else if line.len() > 3 {
+                files.push(json!({
+                    "index": line[0..1].trim(),
+                    "worktree": line[1..2].trim(),
+                    "path": &line[3..],
+                }));
+            }
+        }
+        Ok(json!({ "branch": branch, "clean": files.is_empty(), "files": files }))
+    }
+
+    async fn diff(&self, repo: &PathBuf, staged: bool) -> Result<Value> {
+        let args: &[&str] = if staged { &["diff", "--cached"] } else { &["diff"] };
+        let diff = self.git(repo, args).await?;
+        let mut stat_args = args.to_vec();
+        stat_args.push("--numstat");
+        let stat = self.git(repo, &stat_args).await?;
+        let files: Vec<Value> = stat
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '\t');
+                let added = parts.next()?;
+                let removed = parts.next()?;
+                let path = parts.next()?;
+                Some(json!({ "path": path, "added": added.parse::<u64>().ok(), "removed": removed.parse::<u64>().ok() }))
+            })
+            .collect();
+        Ok(json!({ "staged": staged, "files": files, "diff": diff }))
+    }
+
+    async fn log(&self, repo: &PathBuf, max_count: usize) -> Result<Value> {
+        let format = format!("--pretty=format:%H{0}%an{0}%aI{0}%s", SEP);
+        let count = format!("--max-count={}", max_count);
+        let output = self.git(repo, &["log", &count, &format]).await?;
+        let commits: Vec<Value> = output
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.splitn(4, SEP).collect();
+                (fields.len() == 4).then(|| json!({
+                    "hash": fields[0],
+                    "author": fields[1],
+                    "date": fields[2],
+                    "subject": fields[3],
+                }))
+            })
+            .collect();
+        Ok(json!({ "commits": commits }))
+    }
+
+    async fn commit(&self, repo: &PathBuf, message: &str, paths: &[String]) -> Result<Value> {
+        if paths.is_empty() {
+            self.git(repo, &["add", "--all"]).await?;
+        } else {
+            let mut args = vec!["add", "--"];
+            args.extend(paths.iter().map(String::as_str));
+            self.git(repo, &args).await?;
+        }
+        self.git(repo, &["commit", "-m", message]).await?;
+        let hash = self.git(repo, &["rev-parse", "HEAD"]).await?.trim().to_string();
+        info!("Created commit {} in {}", hash, repo.display());
+        Ok(json!({ "hash": hash, "message": message }))
+    }
+
+    async fn branch(&self, repo: &PathBuf, create: Option<&str>) -> Result<Value> {
+        if let Some(name) = create {
+            // git would read it as an option
+            if name.starts_with('-') {
+                return Err(anyhow!("Invalid branch name '{}': must not start with '-'", name));
+            }
+            self.git(repo, &["branch", "--", name]).await?;
+            info!("Created branch {} in {}", name, repo.display());
+        }
+        let format = format!("--format=%(HEAD){}%(refname:short)", SEP);
+        let output = self.git(repo, &["branch", &format]).await?;
+        let mut current = None;
+        let mut branches = Vec::new();
+        for line in output.lines() {
+            if let Some((head, name)) = line.split_once(SEP) {
+                if head == "*" {
+                    current = Some(name.to_string());
+                }
+                branches.push(name.to_string());
+            }
+        }
+        Ok(json!({ "current": current, "branches": branches, "created": create }))
+    }
+
+    async fn run(&self, params: GitParams) -> Result<Value> {
+        let repo = self.resolve_repo(params.repo_path.as_deref())?;
+        let require_write = |what: &str| -> Result<()> {
+            if params.allow_write {
+                Ok(())
+            } else {
+                Err(anyhow!("{} modifies the repository; set allow_write to true to proceed", what))
+            }
+        };
+
+        match params.operation.trim().to_lowercase().as_str() {
+            "status" => self.status(&repo).await,
+            "diff" => self.diff(&repo, params.staged).await,
+            "log" => self.log(&repo, params.max_count.unwrap_or(10)).await,
+            "commit" => {
+                require_write("commit")?;
+                let message = params.message.as_deref().filter(|m| !m.trim().is_empty())
+                    .ok_or_else(|| anyhow!("commit requires a non-empty 'message'"))?;
+                self.commit(&repo, message, &params.paths).await
+            }
+            "branch" => {
+                if params.name.is_some() {
+                    require_write("Creating a branch")?;
+                }
+                self.branch(&repo, params.name.as_deref()).await
+            }
+            other => Err(anyhow!("Unknown git operation '{}'. Use status, diff, log, commit or branch.", other)),
+        }
+    }
+
+    #[tool(description = "Git repository operations returning JSON. operation: 'status' (branch and changed files), 'diff' (unstaged, or staged with staged=true), 'log' (recent commits, max_count), 'branch' (list, or create with name), 'commit' (stage paths or everything, then commit with message). Write operations require allow_write=true.")]
+    pub async fn git_integration(
+        &self,
+        #[tool(aggr)] params: GitParams,
+    ) -> String {
+        info!("Git tool called with operation: {}", params.operation);
+        match self.run(params).await {
+            Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|e| format!("Error: {}", e)),
+            Err(e) => {
+                error!("Git tool error: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command as StdCommand;
+
+    fn init_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "-q", "-b", "main"][..],
+            &["config", "user.name", "Test"],
+            &["config", "user.email", "test@example.com"],
+        ] {
+            assert!(StdCommand::new("git").arg("-C").arg(dir.path()).args(args).status().unwrap().success());
+        }
+        dir
+    }
+
+    fn params(operation: &str) -> GitParams {
+        serde_json::from_value(json!({ "operation": operation })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_status_lists_untracked_file() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+
+        let status = GitTool::with_root(dir.path()).run(params("status")).await.unwrap();
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["clean"], false);
+        assert_eq!(status["files"], json!([{ "index": "?", "worktree": "?", "path": "a.txt" }]));
+    }
+
+    #[tokio::test]
+    async fn test_commit_requires_allow_write() {
+        let dir = init_repo();
+        std::fs::write(dir.path().join("a.txt"), "hello\n").unwrap();
+        let tool = GitTool::with_root(dir.path());
+
+        let mut commit = params("commit");
+        commit.message = Some("Add a.txt".into());
+        let err = tool.run(commit).await.unwrap_err();
+        assert!(err.to_string().contains("allow_write"));
+
+        let mut commit = params("commit");
+        commit.message = Some("Add a.txt".into());
+        commit.allow_write = true;
+        let result = tool.run(commit).await.unwrap();
+        assert_eq!(result["hash"].as_str().unwrap().len(), 40);
+
+        let status = tool.run(params("status")).await.unwrap();
+        assert_eq!(status["clean"], true);
+
+        let log = tool.run(params("log")).await.unwrap();
+        assert_eq!(log["commits"][0]["subject"], "Add a.txt");
+        assert_eq!(log["commits"][0]["hash"], result["hash"]);
+    }
+
+    #[tokio::test]
+    async fn test_repo_path_must_stay_under_root() {
+        let dir = init_repo();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let tool = GitTool::with_root(dir.path());
+
+        let mut inside = params("status");
+        inside.repo_path = Some("sub".into());
+        assert!(tool.run(inside).await.is_ok());
+
+        let mut escaping = params("status");
+        escaping.repo_path = Some(format!("sub/../../{}", outside.path().file_name().unwrap().to_string_lossy()));
+        let err = tool.run(escaping).await.unwrap_err();
+        assert!(err.to_string().contains("outside the repo root"), "{}", err);
+
+        let mut absolute = params("status");
+        absolute.repo_path = Some(outside.path().display().to_string());
+        assert!(tool.run(absolute).await.unwrap_err().to_string().contains("outside the repo root"));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+            let mut linked = params("status");
+            linked.repo_path = Some("link".into());
+            assert!(tool.run(linked).await.unwrap_err().to_string().contains("outside the repo root"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_branch_name_cannot_be_an_option() {
+        let dir = init_repo();
+        let tool = GitTool::with_root(dir.path());
+
+        let mut branch = params("branch");
+        branch.name = Some("--delete".into());
+        branch.allow_write = true;
+        let err = tool.run(branch).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid branch name '--delete': must not start with '-'");
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::{Client, Method, Url};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+// Import rmcp SDK components
+use rmcp::tool;
+
+/// Comma-separated hosts the tool may contact, e.g. `api.github.com,*.example.com`
+pub const ALLOWED_HOSTS_ENV: &str = "HTTP_REQUEST_ALLOWED_HOSTS";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Redirects followed before giving up, as reqwest's default policy does
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HttpRequestParams {
+    #[serde(default = "default_method")]
+    #[schemars(description = "HTTP method (GET, POST, PUT, PATCH, DELETE, HEAD). Defaults to GET.")]
+    pub method: String,
+
+    #[schemars(description = "Absolute http(s) URL. The host must be in the server's allowlist.")]
+    pub url: String,
+
+    #[serde(default)]
+    #[schemars(description = "Request headers as an object of name -> value")]
+    pub headers: BTreeMap<String, String>,
+
+    #[serde(default)]
+    #[schemars(description = "Optional request body, sent as-is")]
+    pub body: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Request timeout in seconds. Defaults to 30.")]
+    pub timeout_secs: Option<u64>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Structured response returned to the model as JSON
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// Exact match, or `*.example.com` matching any subdomain of example.com
+fn host_allowed(allowed_hosts: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    allowed_hosts.iter().any(|allowed| match allowed.strip_prefix("*.") {
+        Some(domain) => host.ends_with(&format!(".{}", domain)),
+        None => *allowed == host,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpRequestTool {
+    client: Client,
+    allowed_hosts: Vec<String>,
+}
+
+impl Default for HttpRequestTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpRequestTool {
+    /// Allowed hosts come from `HTTP_REQUEST_ALLOWED_HOSTS`; if unset, every request is refused
+    pub fn new() -> Self {
+        let hosts = std::env::var(ALLOWED_HOSTS_ENV).unwrap_or_default();
+        Self::with_allowed_hosts(hosts.split(',').map(str::to_string).collect())
+    }
+
+    pub fn with_allowed_hosts(hosts: Vec<String>) -> Self {
+        let allowed_hosts: Vec<String> = hosts
+            .into_iter()
+            .map(|h| h.trim().to_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        // Every redirect hop is checked too, so an allowed host can't send us anywhere else
+        let redirect_hosts = allowed_hosts.clone();
+        let policy = Policy::custom(move |attempt: Attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error(anyhow!("Too many redirects"));
+            }
+            match attempt.url().host_str() {
+                Some(host) if host_allowed(&redirect_hosts, host) => attempt.follow(),
+                host => {
+                    let error = anyhow!(
+                        "Redirect to host '{}' is not allowed. Permitted hosts are configured via {}",
+                        host.unwrap_or_default(),
+                        ALLOWED_HOSTS_ENV
+                    );
+                    attempt.error(error)
+                }
+            }
+        });
+        // Fails only where `Client::new` would panic too (no TLS backend)
+        let client = Client::builder().redirect(policy).build().expect("failed to build HTTP client");
+        Self { client, allowed_hosts }
+    }
+
+    fn is_host_allowed(&self, host: &str) -> bool {
+        host_allowed(&self.allowed_hosts, host)
+    }
+
+    async fn send(&self, params: HttpRequestParams) -> Result<HttpResponse> {
+        let url = Url::parse(&params.url).map_err(|e| anyhow!("Invalid URL '{}': {}", params.url, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("Only http and https URLs are supported"));
+        }
+        let host = url.host_str().ok_or_else(|| anyhow!("URL has no host"))?;
+        if !self.is_host_allowed(host) {
+            return Err(anyhow!(
+                "Host '{}' is not allowed. Permitted hosts are configured via {}",
+                host,
+                ALLOWED_HOSTS_ENV
+            ));
+        }
+
+        let method = Method::from_bytes(params.method.trim().to_uppercase().as_bytes())
+            .map_err(|_| anyhow!("Invalid HTTP method '{}'", params.method))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &params.headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| anyhow!("Invalid header name '{}': {}", name, e))?;
+            let value = HeaderValue::from_str(value).map_err(|e| anyhow!("Invalid value for header '{}': {}", name, e))?;
+            headers.insert(name, value);
+        }
+
+        let timeout = Duration::from_secs(params.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+        let mut request = self.client.request(method.clone(), url).headers(headers).timeout(timeout);
+        if let Some(body) = params.body {
+            request = request.body(body);
+        }
+
+        debug!("Sending {} {}", method, params.url);
+        let response = request.send().await.map_err(|e| match std::error::Error::source(&e) {
+            // The redirect policy's own message says which host was refused
+            Some(reason) if e.is_redirect() => anyhow!("Request failed: {}", reason),
+            _ => anyhow!("Request failed: {}", e),
+        })?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+        let body = response.text().await.map_err(|e| anyhow!("Failed to read response body: {}", e))?;
+
+        Ok(HttpResponse { status, headers, body })
+    }
+
+    #[tool(description = "Makes an HTTP request and returns JSON with the response 'status', 'headers' and 'body'. Use instead of curl for calling APIs. Only hosts on the server's allowlist can be contacted.")]
+    pub async fn http_request(
+        &self,
+        #[tool(aggr)] params: HttpRequestParams,
+    ) -> String {
+        info!("HTTP request tool called: {} {}", params.method, params.url);
+        match self.send(params).await {
+            Ok(response) => serde_json::to_string_pretty(&response).unwrap_or_else(|e| format!("Error: {}", e)),
+            Err(e) => {
+                error!("HTTP request failed: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn params(method: &str, url: String) -> HttpRequestParams {
+        HttpRequestParams {
+            method: method.to_string(),
+            url,
+            headers: BTreeMap::new(),
+            body: None,
+            timeout_secs: None,
+        }
+    }
+
+    async fn call(tool: &HttpRequestTool, params: HttpRequestParams) -> HttpResponse {
+        let output = tool.http_request(params).await;
+        serde_json::from_str(&output).unwrap_or_else(|_| panic!("not a response: {}", output))
+    }
+
+    #[tokio::test]
+    async fn test_get_with_header_round_trip() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/items"))
+            .and(header("x-request-id", "abc"))
+            .respond_with(ResponseTemplate::new(200).insert_header("x-echo", "abc").set_body_string("[1,2]"))
+            .mount(&server)
+            .await;
+
+        let tool = HttpRequestTool::with_allowed_hosts(vec!["127.0.0.1".into()]);
+        let mut request = params("get", format!("{}/items", server.uri()));
+        request.headers.insert("x-request-id".into(), "abc".into());
+        let response = call(&tool, request).await;
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.headers.get("x-echo").map(String::as_str), Some("abc"));
+        assert_eq!(response.body, "[1,2]");
+    }
+
+    #[tokio::test]
+    async fn test_post_body_and_error_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/items"))
+            .and(header("content-type", "application/json"))
+            .and(body_string(r#"{"name":"x"}"#))
+            .respond_with(ResponseTemplate::new(422).set_body_string("invalid"))
+            .mount(&server)
+            .await;
+
+        let tool = HttpRequestTool::with_allowed_hosts(vec!["127.0.0.1".into()]);
+        let mut request = params("POST", format!("{}/items", server.uri()));
+        request.headers.insert("content-type".into(), "application/json".into());
+        request.body = Some(r#"{"name":"x"}"#.into());
+        let response = call(&tool, request).await;
+
+        // Non-2xx statuses are returned, not treated as errors
+        assert_eq!(response.status, 422);
+        assert_eq!(response.body, "invalid");
+    }
+
+    #[tokio::test]
+    async fn test_host_not_in_allowlist_is_refused() {
+        let tool = HttpRequestTool::with_allowed_hosts(vec!["*.example.com".into()]);
+        assert!(tool.is_host_allowed("api.example.com"));
+        assert!(!tool.is_host_allowed("example.com.evil.net"));
+
+        let output = tool.http_request(params("GET", "http://127.0.0.1:1/".into())).await;
+        assert!(output.starts_with("Error: Host '127.0.0.1' is not allowed"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_disallowed_host_is_refused() {
+        let internal = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("secret"))
+            .mount(&internal)
+            .await;
+        let allowed = MockServer::start().await;
+        // Same machine, but named by a host that isn't on the allowlist
+        let target = format!("http://localhost:{}/metadata", internal.address().port());
+        Mock::given(path("/elsewhere"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", target.as_str()))
+            .mount(&allowed)
+            .await;
+        Mock::given(path("/moved"))
+            .respond_with(ResponseTemplate::new(302).insert_header("location", "/items"))
+            .mount(&allowed)
+            .await;
+        Mock::given(path("/items"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[1]"))
+            .mount(&allowed)
+            .await;
+
+        let tool = HttpRequestTool::with_allowed_hosts(vec!["127.0.0.1".into()]);
+        let output = tool.http_request(params("GET", format!("{}/elsewhere", allowed.uri()))).await;
+        assert_eq!(
+            output,
+            format!("Error: Request failed: Redirect to host 'localhost' is not allowed. Permitted hosts are configured via {}", ALLOWED_HOSTS_ENV)
+        );
+        assert!(internal.received_requests().await.unwrap().is_empty());
+
+        // Redirects within allowed hosts are still followed
+        let response = call(&tool, params("GET", format!("{}/moved", allowed.uri()))).await;
+        assert_eq!((response.status, response.body.as_str()), (200, "[1]"));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod process_html;
 pub mod bash;
+pub mod command_allowlist;
 pub mod brave_search;
 pub mod scraping_bee;
 pub mod gmail_integration;
@@ -11,3 +12,12 @@ pub mod planner;
 pub mod netlify;
 pub mod supabase;
 pub mod interactive_terminal;
+pub mod regex_replace;
+pub mod diff;
+pub mod git_integration;
+pub mod enabled_tools;
+pub mod rate_limit;
+pub mod logging;
+pub mod http_request;
+pub mod fs_tool;
+pub mod progress;
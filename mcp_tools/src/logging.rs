@@ -0,0 +1,120 @@
+// stdout carries the JSON-RPC protocol, so nothing configured here may ever write to it.
+// Logs go to a file, to stderr, or nowhere.
+
+use std::path::{Path, PathBuf};
+use tracing::{info, warn, Level};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Where log output ended up
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogTarget {
+    /// Logging to a file in this directory
+    File(PathBuf),
+    /// The log directory couldn't be used; logging to stderr instead
+    Stderr { reason: String },
+    /// Neither a file nor stderr is safe to use; logs are dropped
+    Discarded { reason: String },
+}
+
+/// `LOG_DIR` if set, otherwise `~/Developer/mcp/logs`
+pub fn default_log_dir() -> Option<PathBuf> {
+    std::env::var_os("LOG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join("Developer/mcp/logs")))
+}
+
+/// Open a non-blocking file writer in `log_dir`, falling back to stderr if the directory
+/// is missing or can't be created/written. Never panics.
+pub fn open_log_writer(log_dir: Option<&Path>) -> (BoxMakeWriter, Option<WorkerGuard>, LogTarget) {
+    let Some(log_dir) = log_dir else {
+        return stderr_writer("no log directory configured".to_string());
+    };
+
+    let appender = tracing_appender::rolling::Builder::new()
+        .rotation(tracing_appender::rolling::Rotation::NEVER)
+        .filename_prefix("mcp-server")
+        .filename_suffix("log")
+        .build(log_dir);
+
+    match appender {
+        Ok(appender) => {
+            let (non_blocking, guard) = tracing_appender::non_blocking(appender);
+            (BoxMakeWriter::new(non_blocking), Some(guard), LogTarget::File(log_dir.to_path_buf()))
+        }
+        Err(e) => stderr_writer(format!("failed to open log directory {}: {}", log_dir.display(), e)),
+    }
+}
+
+fn stderr_writer(reason: String) -> (BoxMakeWriter, Option<WorkerGuard>, LogTarget) {
+    if stderr_is_stdout() {
+        // e.g. launched with `2>&1`: writing to stderr would corrupt the protocol stream
+        let reason = format!("{}; stderr is redirected to stdout", reason);
+        return (BoxMakeWriter::new(std::io::sink), None, LogTarget::Discarded { reason });
+    }
+    (BoxMakeWriter::new(std::io::stderr), None, LogTarget::Stderr { reason })
+}
+
+/// Whether file descriptors 1 and 2 refer to the same file/pipe
+fn stderr_is_stdout() -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata("/dev/fd/1"), std::fs::metadata("/dev/fd/2")) {
+        (Ok(out), Ok(err)) => out.dev() == err.dev() && out.ino() == err.ino(),
+        _ => false,
+    }
+}
+
+/// Install the global tracing subscriber. Keep the returned guard alive for the
+/// lifetime of the process so buffered file output gets flushed.
+pub fn init_logging() -> Option<WorkerGuard> {
+    let (writer, guard, target) = open_log_writer(default_log_dir().as_deref());
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::from_default_env()
+                .add_directive(Level::DEBUG.into())
+                .add_directive("mcp_tools=debug".parse().unwrap()),
+        )
+        .with_writer(writer)
+        .with_thread_ids(true)
+        .with_file(true)
+        .with_line_number(true)
+        .with_target(true)
+        .init();
+
+    match &target {
+        LogTarget::File(dir) => info!("Logging to {}", dir.display()),
+        LogTarget::Stderr { reason } => warn!("Logging to stderr: {}", reason),
+        LogTarget::Discarded { .. } => {} // Nowhere to report it
+    }
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writable_dir_logs_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("logs");
+        let (_writer, guard, target) = open_log_writer(Some(&log_dir));
+        assert_eq!(target, LogTarget::File(log_dir.clone()));
+        assert!(guard.is_some());
+        assert!(log_dir.is_dir());
+    }
+
+    #[test]
+    fn test_invalid_dir_falls_back_to_stderr() {
+        // A regular file can't be used as a log directory
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let (_writer, guard, target) = open_log_writer(Some(file.path()));
+        assert!(
+            matches!(target, LogTarget::Stderr { .. } | LogTarget::Discarded { .. }),
+            "got {:?}",
+            target
+        );
+        assert!(guard.is_none());
+    }
+}
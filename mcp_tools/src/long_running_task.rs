@@ -11,7 +11,11 @@ use tokio_util::codec::{FramedRead, LinesCodec};
 use tracing::{debug, info, error, warn}; // Added warn here
 use schemars::JsonSchema;
 
+use crate::progress::ProgressTracker;
+
 // Import SDK components
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use rmcp::service::{Peer, RoleServer};
 use rmcp::tool;
 
 #[derive(Clone, Debug)]
@@ -37,6 +41,40 @@ pub struct TaskState {
     /// Store the process ID, skip serialization as it's runtime-specific
     #[serde(skip)]
     pub pid: Option<u32>,
+    /// When the task was created (RFC 3339)
+    #[serde(default)]
+    pub started_at: Option<String>,
+    /// Exit code of the process, once it has exited normally
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// Directory the command was started in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    /// The task this one re-ran, if it was started by `restart_task`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restarted_from: Option<String>,
+}
+
+/// Machine-readable task summary returned by `list_tasks` with `format: "json"`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaskSummary {
+    pub id: String,
+    pub status: String,
+    pub command: String,
+    pub started_at: Option<String>,
+    pub exit_code: Option<i32>,
+}
+
+impl From<&TaskState> for TaskSummary {
+    fn from(task: &TaskState) -> Self {
+        Self {
+            id: task.task_id.clone(),
+            status: format!("{:?}", task.status).to_lowercase(), // Same names the status filter accepts
+            command: task.command.clone(),
+            started_at: task.started_at.clone(),
+            exit_code: task.exit_code,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -85,8 +123,32 @@ impl LongRunningTaskManager {
         Ok(())
     }
 
-    /// Spawns a background task that reads partial stdout/stderr
-    pub async fn spawn_task(&self, command: &str, reason: &str) -> Result<String> {
+    /// Spawns a background task that reads partial stdout/stderr. If `on_complete` is given,
+    /// that client is sent a `notifications/message` once the process exits.
+    pub async fn spawn_task(&self, command: &str, reason: &str, on_complete: Option<Peer<RoleServer>>) -> Result<String> {
+        let working_dir = std::env::current_dir().ok().map(|dir| dir.display().to_string());
+        self.spawn(command, reason, working_dir, None, on_complete).await
+    }
+
+    /// Run a finished task's command again as a new task, in the directory the original ran in
+    pub async fn restart_task(&self, task_id: &str, on_complete: Option<Peer<RoleServer>>) -> Result<String> {
+        let original = self.get_task_status(task_id).await?;
+        if matches!(original.status, TaskStatus::Created | TaskStatus::Running) {
+            return Err(anyhow!("Task {} is still running; stop it before restarting", task_id));
+        }
+        info!("Restarting task {} ({})", task_id, original.command);
+        self.spawn(&original.command, &original.reason, original.working_dir, Some(task_id.to_string()), on_complete)
+            .await
+    }
+
+    async fn spawn(
+        &self,
+        command: &str,
+        reason: &str,
+        working_dir: Option<String>,
+        restarted_from: Option<String>,
+        on_complete: Option<Peer<RoleServer>>,
+    ) -> Result<String> {
         let task_id = format!("task-{}", uuid::Uuid::new_v4());
         let task_id_clone = task_id.clone();
         let mut state = TaskState {
@@ -97,6 +159,10 @@ impl LongRunningTaskManager {
             stderr: String::new(),
             reason: reason.to_string(),
             pid: None, // Initialize PID as None
+            started_at: Some(chrono::Utc::now().to_rfc3339()),
+            exit_code: None,
+            working_dir,
+            restarted_from,
         };
 
         // Insert initial record in the tasks map
@@ -127,12 +193,15 @@ impl LongRunningTaskManager {
             // Append ' &' to the command to make the shell background it.
             let background_command = format!("{} &", state.command);
             info!("Executing background command: bash -c '{}'", background_command);
-            let child = Command::new("bash")
-                .arg("-c")
+            let mut bash = Command::new("bash");
+            bash.arg("-c")
                 .arg(&background_command) // Use the modified command string
                 .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn();
+                .stderr(Stdio::piped());
+            if let Some(dir) = &state.working_dir {
+                bash.current_dir(dir);
+            }
+            let child = bash.spawn();
 
             match child {
                 Ok(mut child) => {
@@ -228,6 +297,7 @@ impl LongRunningTaskManager {
                     // Wait on final exit
                     match child.wait().await {
                         Ok(status) => {
+                            state.exit_code = status.code();
                             if status.success() {
                                 state.status = TaskStatus::Ended;
                             } else {
@@ -257,7 +327,8 @@ impl LongRunningTaskManager {
                 let mut guard = manager_clone.tasks_in_memory.lock().await;
                 if let Some(ts) = guard.get_mut(&task_id) {
                     // Update only the status field of the existing TaskState
-                    ts.status = state.status; // Use the final status determined above (Ended or Error)
+                    ts.status = state.status.clone(); // Use the final status determined above (Ended or Error)
+                    ts.exit_code = state.exit_code;
                 } else {
                     // This case might happen if the task was cleared concurrently.
                     error!("Task {} not found in map for final status update after process exit.", task_id);
@@ -267,6 +338,9 @@ impl LongRunningTaskManager {
             if let Err(e) = manager_clone.save().await {
                  error!("Failed to save final task state for {}: {}", task_id, e);
             }
+            if let Some(peer) = on_complete {
+                notify_completion(&peer, &state).await;
+            }
             info!("Task {} monitoring task finished.", task_id);
         });
 
@@ -301,14 +375,41 @@ impl LongRunningTaskManager {
     }
 }
 
-/// A helper function to retrieve the last `n` lines from a string.
-fn last_n_lines(s: &str, n: usize) -> String {
+/// Tell the client that started a task that it has finished
+async fn notify_completion(peer: &Peer<RoleServer>, task: &TaskState) {
+    let level = match task.status {
+        TaskStatus::Ended => LoggingLevel::Info,
+        _ => LoggingLevel::Error,
+    };
+    let params = LoggingMessageNotificationParam {
+        level,
+        logger: Some("long_running_task".to_string()),
+        data: serde_json::json!({
+            "message": format!("Task {} finished with status {:?}", task.task_id, task.status),
+            "task": TaskSummary::from(task),
+        }),
+    };
+    if let Err(e) = peer.notify_logging_message(params).await {
+        warn!("Failed to send completion notification for task {}: {}", task.task_id, e);
+    }
+}
+
+/// `count` lines of `s` starting at line `offset` (0-based), or its last `count` lines if no
+/// offset is given, together with a label saying which lines they are.
+fn page_lines(s: &str, offset: Option<usize>, count: usize) -> (String, String) {
     let lines: Vec<&str> = s.lines().collect();
-    if lines.len() > n {
-        lines[lines.len() - n..].join("\n")
+    let total = lines.len();
+    let start = match offset {
+        Some(offset) => offset.min(total),
+        None => total.saturating_sub(count),
+    };
+    let end = (start + count).min(total);
+    let label = if start == end {
+        format!("no lines shown of {}", total)
     } else {
-        s.to_string()
-    }
+        format!("lines {}-{} of {}", start + 1, end, total)
+    };
+    (lines[start..end].join("\n"), label)
 }
 
 // Parameter structs for SDK-based tools
@@ -319,6 +420,10 @@ pub struct StartTaskParams {
     
     #[schemars(description = "A human-friendly reason or rationale for creating this task")]
     pub reason: String,
+
+    #[serde(default)]
+    #[schemars(description = "Notify the client when the task ends or fails, instead of it having to poll 'get_status'. Defaults to false.")]
+    pub on_complete: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -326,9 +431,17 @@ pub struct GetStatusParams {
     #[schemars(description = "The ID of the task to retrieve status for")]
     pub task_id: String,
     
-    #[serde(default = "default_lines")]
+    #[serde(default = "default_lines", alias = "lines")]
     #[schemars(description = "How many trailing lines from stdout/stderr to return. Defaults to 100.")]
-    pub lines: usize,
+    pub tail_lines: usize,
+
+    #[serde(default)]
+    #[schemars(description = "Page through the output from the start instead: the 0-based line to start at. Returns 'limit' lines from there.")]
+    pub offset: Option<usize>,
+
+    #[serde(default)]
+    #[schemars(description = "How many lines to return from 'offset'. Defaults to 100.")]
+    pub limit: Option<usize>,
 }
 
 fn default_lines() -> usize {
@@ -340,12 +453,72 @@ pub struct ListTasksParams {
     #[serde(default)] // Default to empty string if omitted
     #[schemars(description = "Optional filter for tasks (created, running, ended, error, stopped). Leave empty to list all.")]
     pub status: String, // Changed from Option<String>
+
+    #[serde(default)]
+    #[schemars(description = "Output format: 'text' (default) or 'json' for an array of {id, status, command, started_at, exit_code}")]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct StopTaskParams {
     #[schemars(description = "The ID of the running task to stop")]
     pub task_id: String,
+
+    #[serde(default)]
+    #[schemars(description = "Signal to stop it with: SIGTERM (default), SIGINT, SIGQUIT or SIGKILL. SIGHUP, SIGUSR1 and SIGUSR2 are only delivered; the task is not stopped or killed.")]
+    pub signal: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Seconds to wait for the task to exit before killing it with SIGKILL. Defaults to 10.")]
+    pub grace_period_secs: Option<u64>,
+}
+
+/// How long a stopped task gets to exit before it is sent SIGKILL
+const DEFAULT_STOP_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Signals `stop_task` may send
+const STOP_SIGNALS: &[nix::sys::signal::Signal] = {
+    use nix::sys::signal::Signal::*;
+    &[SIGTERM, SIGINT, SIGHUP, SIGQUIT, SIGUSR1, SIGUSR2, SIGKILL]
+};
+
+/// Whether `signal` asks a process to exit, as opposed to e.g. SIGHUP, which many servers
+/// take as a request to reload. Only these mark a task stopped and end in SIGKILL.
+fn is_termination_signal(signal: nix::sys::signal::Signal) -> bool {
+    use nix::sys::signal::Signal::*;
+    matches!(signal, SIGTERM | SIGINT | SIGQUIT | SIGKILL)
+}
+
+/// When process `pid` started, in clock ticks since boot (`/proc/<pid>/stat` field 22).
+/// Tells a process apart from a later one given the same PID. None if it isn't running.
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after the parenthesised command name start at field 3
+    stat.rsplit(')').next()?.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// One of `STOP_SIGNALS` by name, with or without the `SIG` prefix (`int`, `SIGINT`)
+fn parse_stop_signal(name: &str) -> Result<nix::sys::signal::Signal> {
+    let name = name.trim().to_uppercase();
+    let name = if name.starts_with("SIG") { name } else { format!("SIG{}", name) };
+    STOP_SIGNALS
+        .iter()
+        .copied()
+        .find(|signal| signal.as_str() == name)
+        .ok_or_else(|| {
+            let known: Vec<&str> = STOP_SIGNALS.iter().map(|signal| signal.as_str()).collect();
+            anyhow!("Unsupported signal '{}' (use one of {})", name, known.join(", "))
+        })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RestartTaskParams {
+    #[schemars(description = "The ID of the ended, failed or stopped task whose command to run again")]
+    pub task_id: String,
+
+    #[serde(default)]
+    #[schemars(description = "Notify the client when the new task ends or fails. Defaults to false.")]
+    pub on_complete: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -368,29 +541,73 @@ impl LongRunningTaskTool {
         }
     }
     
+    /// Create the tool and finish loading persisted tasks before returning, so the
+    /// first `list_tasks` call already sees them. Load failures are logged, not fatal.
+    pub async fn new_loaded(manager_path: &str) -> Self {
+        let tool = Self::new(manager_path);
+        match tool.load_persistent_tasks().await {
+            Ok(()) => info!("Loaded {} persisted tasks", tool.manager.lock().await.tasks_in_memory.lock().await.len()),
+            Err(e) => error!("Failed to load persistent tasks: {}", e),
+        }
+        tool
+    }
+
     pub async fn load_persistent_tasks(&self) -> Result<()> {
         let manager = self.manager.lock().await;
         manager.load_persistent_tasks().await
     }
+
+    /// Write the current state of all tasks to the persistence file (used on shutdown)
+    pub async fn persist(&self) -> Result<()> {
+        let manager = self.manager.lock().await;
+        manager.save().await
+    }
     
     // Helper method to perform start_task operation
-    async fn spawn_task_internal(&self, command_string: String, reason: String) -> Result<String> {
+    async fn spawn_task_internal(&self, command_string: String, reason: String, on_complete: bool) -> Result<String> {
+        let peer = Self::completion_peer(on_complete);
         let manager = self.manager.lock().await;
-        let task_id = manager.spawn_task(&command_string, &reason).await?;
+        let task_id = manager.spawn_task(&command_string, &reason, peer).await?;
         
         Ok(task_id)
     }
+
+    // The client making this call, if it asked to hear when the task is done
+    fn completion_peer(on_complete: bool) -> Option<Peer<RoleServer>> {
+        if !on_complete {
+            return None;
+        }
+        let peer = ProgressTracker::current().map(|tracker| tracker.peer().clone());
+        if peer.is_none() {
+            warn!("Task started outside a client request; nobody to notify on completion");
+        }
+        peer
+    }
     
     // Helper method to get task status
-    async fn get_status_internal(&self, task_id: &str, lines: usize) -> Result<(TaskState, String, String)> {
+    async fn get_status_internal(&self, params: &GetStatusParams) -> Result<String> {
         let manager = self.manager.lock().await;
-        let state = manager.get_task_status(task_id).await?;
-        
-        // Get only the last N lines of stdout/stderr
-        let stdout_short = last_n_lines(&state.stdout, lines);
-        let stderr_short = last_n_lines(&state.stderr, lines);
-        
-        Ok((state.clone(), stdout_short, stderr_short))
+        let state = manager.get_task_status(&params.task_id).await?;
+
+        // Only the requested page (by default the tail) of stdout/stderr
+        let count = match params.offset {
+            Some(_) => params.limit.unwrap_or_else(default_lines),
+            None => params.tail_lines,
+        };
+        let (stdout, stdout_label) = page_lines(&state.stdout, params.offset, count);
+        let (stderr, stderr_label) = page_lines(&state.stderr, params.offset, count);
+
+        Ok(format!(
+            "Task ID: {}\nStatus: {:?}\nReason: {}\nCommand: {}\n\n=== STDOUT ({}) ===\n{}\n\n=== STDERR ({}) ===\n{}",
+            params.task_id,
+            state.status,
+            state.reason,
+            state.command,
+            stdout_label,
+            stdout,
+            stderr_label,
+            stderr
+        ))
     }
     
     // Helper method to list tasks
@@ -427,8 +644,9 @@ impl LongRunningTaskTool {
         manager.list_tasks(filter_status).await
     }
 
-    // Helper method to stop a task
-    async fn stop_task_internal(&self, task_id: &str) -> Result<String> {
+    // Helper method to stop a task: send it `signal`, then SIGKILL if it is still alive
+    // after `grace_period`. Signals that don't ask a process to exit are only delivered.
+    async fn stop_task_internal(&self, task_id: &str, signal: nix::sys::signal::Signal, grace_period: std::time::Duration) -> Result<String> {
         use nix::sys::signal::{kill, Signal};
         use nix::unistd::Pid;
 
@@ -444,19 +662,59 @@ impl LongRunningTaskTool {
                 if let Some(pid_val) = task.pid {
                     info!("Attempting to stop task {} (PID: {})", task_id, pid_val);
                     let pid = Pid::from_raw(pid_val as i32);
-                    match kill(pid, Signal::SIGTERM) { // Send SIGTERM first for graceful shutdown
+                    // Taken before signalling, so the SIGKILL below can't reach a new process reusing the PID
+                    let started = process_start_time(pid_val);
+                    match kill(pid, signal) { // The requested signal first, for a graceful shutdown
+                        Ok(_) if !is_termination_signal(signal) => {
+                            info!("Sent {} to task {} (PID: {})", signal, task_id, pid_val);
+                            Ok(format!("Sent {} to task {}. It is still running.", signal, task_id))
+                        }
                         Ok(_) => {
                             task.status = TaskStatus::Stopped;
-                            task.stderr.push_str("\n[Task manually stopped via SIGTERM]");
-                            info!("Sent SIGTERM to task {} (PID: {})", task_id, pid_val);
+                            task.stderr.push_str(&format!("\n[Task manually stopped via {}]", signal));
+                            info!("Sent {} to task {} (PID: {})", signal, task_id, pid_val);
                             // Drop the lock before saving
                             drop(tasks_guard);
                             let _ = manager.save().await;
-                            Ok(format!("Stop signal (SIGTERM) sent to task {}. Status set to Stopped.", task_id))
+                            if signal == Signal::SIGKILL {
+                                return Ok(format!("Stop signal (SIGKILL) sent to task {}. Status set to Stopped.", task_id));
+                            }
+
+                            // Kill it if it hasn't exited by the end of the grace period. Once it
+                            // exits its status changes from Stopped, and its PID may belong to
+                            // another process, so both are checked first.
+                            let task_id_for_kill = task_id.to_string();
+                            let manager_for_kill = self.manager.clone();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(grace_period).await;
+                                let still_stopping = {
+                                    let manager = manager_for_kill.lock().await;
+                                    let tasks = manager.tasks_in_memory.lock().await;
+                                    tasks.get(&task_id_for_kill).is_some_and(|task| task.status == TaskStatus::Stopped && task.pid == Some(pid_val))
+                                };
+                                if !still_stopping || started.is_none() || process_start_time(pid_val) != started {
+                                    debug!("Task {} (PID: {}) exited within {:?} of {}", task_id_for_kill, pid_val, grace_period, signal);
+                                    return;
+                                }
+                                warn!("Task {} (PID: {}) still running {:?} after {}; sending SIGKILL", task_id_for_kill, pid_val, grace_period, signal);
+                                if let Err(e) = kill(pid, Signal::SIGKILL) {
+                                    error!("Failed to send SIGKILL to task {} (PID: {}): {}", task_id_for_kill, pid_val, e);
+                                }
+                            });
+                            Ok(format!(
+                                "Stop signal ({}) sent to task {}. Status set to Stopped. It will be killed with SIGKILL if still running after {}s.",
+                                signal,
+                                task_id,
+                                grace_period.as_secs()
+                            ))
+                        }
+                        Err(e) if !is_termination_signal(signal) => {
+                            error!("Failed to send {} to task {} (PID: {}): {}", signal, task_id, pid_val, e);
+                            Err(anyhow!("Failed to send {} to process {}: {}", signal, pid_val, e))
                         }
                         Err(e) => {
-                            error!("Failed to send SIGTERM to task {} (PID: {}): {}. Attempting SIGKILL.", task_id, pid_val, e);
-                            // If SIGTERM fails (e.g., process doesn't exist anymore), try SIGKILL
+                            error!("Failed to send {} to task {} (PID: {}): {}. Attempting SIGKILL.", signal, task_id, pid_val, e);
+                            // If the signal fails (e.g., process doesn't exist anymore), try SIGKILL
                             match kill(pid, Signal::SIGKILL) {
                                 Ok(_) => {
                                     task.status = TaskStatus::Stopped;
@@ -475,7 +733,7 @@ impl LongRunningTaskTool {
                                      task.stderr.push_str(&format!("\n[Failed to stop task: {}]", e2));
                                      drop(tasks_guard);
                                      let _ = manager.save().await;
-                                     Err(anyhow!("Failed to send SIGTERM or SIGKILL to process {}: {}", pid_val, e2))
+                                     Err(anyhow!("Failed to send {} or SIGKILL to process {}: {}", signal, pid_val, e2))
                                 }
                             }
                         }
@@ -524,7 +782,7 @@ impl LongRunningTaskTool {
             };
 
             if is_running {
-                match self.stop_task_internal(task_id).await {
+                match self.stop_task_internal(task_id, nix::sys::signal::Signal::SIGTERM, DEFAULT_STOP_GRACE_PERIOD).await {
                     Ok(_) => {
                         stopped_count += 1;
                     }
@@ -568,14 +826,14 @@ impl LongRunningTaskTool {
 
 #[tool(tool_box)]
 impl LongRunningTaskTool {
-    #[tool(description = "Start a new long-running shell task. Use this for any shell command that might take longer than 1 minute to complete, or for tasks that need to run in the background while other tools are used. The task runs asynchronously, continues after this conversation ends, and its status/output can be checked later using 'get_status' or 'list_tasks'.")]
+    #[tool(description = "Start a new long-running shell task. Use this for any shell command that might take longer than 1 minute to complete, or for tasks that need to run in the background while other tools are used. The task runs asynchronously, continues after this conversation ends, and its status/output can be checked later using 'get_status' or 'list_tasks'. Set 'on_complete' to be notified when it finishes.")]
     pub async fn start_task(
         &self,
         #[tool(aggr)] params: StartTaskParams
     ) -> String {
         info!("Starting long-running task: {}", params.command_string);
         
-        match self.spawn_task_internal(params.command_string.clone(), params.reason.clone()).await {
+        match self.spawn_task_internal(params.command_string.clone(), params.reason.clone(), params.on_complete).await {
             Ok(task_id) => {
                 format!("Task started with ID: {}\nReason: {}", task_id, params.reason)
             }
@@ -586,27 +844,15 @@ impl LongRunningTaskTool {
         }
     }
     
-    #[tool(description = "Get the status and output of a long-running task. This will show if the task is still running and display its stdout/stderr.")]
+    #[tool(description = "Get the status and output of a long-running task. This will show if the task is still running and display the last 'tail_lines' (default 100) lines of its stdout/stderr. For long output, page through it from the start with 'offset' and 'limit'.")]
     pub async fn get_status(
         &self,
         #[tool(aggr)] params: GetStatusParams
     ) -> String {
         info!("Getting status for task ID: {}", params.task_id);
         
-        match self.get_status_internal(&params.task_id, params.lines).await {
-            Ok((state, stdout, stderr)) => {
-                format!(
-                    "Task ID: {}\nStatus: {:?}\nReason: {}\nCommand: {}\n\n=== STDOUT (last {} lines) ===\n{}\n\n=== STDERR (last {} lines) ===\n{}",
-                    params.task_id,
-                    state.status,
-                    state.reason,
-                    state.command,
-                    params.lines,
-                    stdout,
-                    params.lines,
-                    stderr
-                )
-            }
+        match self.get_status_internal(&params).await {
+            Ok(status) => status,
             Err(e) => {
                 error!("Failed to get task status: {}", e);
                 format!("Error getting task status: {}", e)
@@ -622,8 +868,23 @@ impl LongRunningTaskTool {
         // Log the filter string directly from params
         info!("Listing tasks with filter: '{}'", params.status);
 
+        let as_json = match params.format.as_deref().map(|f| f.trim().to_lowercase()) {
+            None => false,
+            Some(f) if f.is_empty() || f == "text" => false,
+            Some(f) if f == "json" => true,
+            Some(f) => return format!("Error: unsupported format '{}'. Use 'text' or 'json'.", f),
+        };
+
         // Pass the String directly to the internal helper
-        let tasks = self.list_tasks_internal(params.status).await;
+        let mut tasks = self.list_tasks_internal(params.status).await;
+
+        if as_json {
+            // Oldest first so indices are stable between calls
+            tasks.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.task_id.cmp(&b.task_id)));
+            let summaries: Vec<TaskSummary> = tasks.iter().map(TaskSummary::from).collect();
+            return serde_json::to_string_pretty(&summaries)
+                .unwrap_or_else(|e| format!("Error serializing tasks: {}", e));
+        }
 
         if tasks.is_empty() {
             return "No tasks found.".to_string();
@@ -634,27 +895,38 @@ impl LongRunningTaskTool {
         
         for task in tasks {
             result.push_str(&format!(
-                "Task ID: {}\nStatus: {:?}\nReason: {}\nCommand: {}\nStdout: {} bytes, Stderr: {} bytes\n\n",
+                "Task ID: {}\nStatus: {:?}\nReason: {}\nCommand: {}\n",
                 task.task_id,
                 task.status,
                 task.reason,
                 task.command,
-                task.stdout.len(),
-                task.stderr.len()
             ));
+            if let Some(original) = &task.restarted_from {
+                result.push_str(&format!("Restarted from: {}\n", original));
+            }
+            result.push_str(&format!("Stdout: {} bytes, Stderr: {} bytes\n\n", task.stdout.len(), task.stderr.len()));
         }
         
         result
     }
 
-    #[tool(description = "Stop a currently running background task. This attempts to gracefully terminate the process using SIGTERM, falling back to SIGKILL if necessary. Use this to cancel tasks that are no longer needed or are running indefinitely.")]
+    #[tool(description = "Stop a currently running background task. This attempts to gracefully terminate the process using SIGTERM (or the given 'signal', e.g. SIGINT), falling back to SIGKILL if it is still running after 'grace_period_secs' (default 10). Use this to cancel tasks that are no longer needed or are running indefinitely.")]
     pub async fn stop_task(
         &self,
         #[tool(aggr)] params: StopTaskParams
     ) -> String {
         info!("Attempting to stop task ID: {}", params.task_id);
 
-        match self.stop_task_internal(&params.task_id).await {
+        let signal = match params.signal.as_deref().map(parse_stop_signal).transpose() {
+            Ok(signal) => signal.unwrap_or(nix::sys::signal::Signal::SIGTERM),
+            Err(e) => return format!("Error stopping task {}: {}", params.task_id, e),
+        };
+        let grace_period = params
+            .grace_period_secs
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(DEFAULT_STOP_GRACE_PERIOD);
+
+        match self.stop_task_internal(&params.task_id, signal, grace_period).await {
             Ok(message) => {
                 info!("Stop task result for {}: {}", params.task_id, message);
                 message
@@ -666,6 +938,24 @@ impl LongRunningTaskTool {
         }
     }
 
+    #[tool(description = "Run the command of an ended, failed or stopped task again as a new task, in the same working directory. The new task records which task it restarted.")]
+    pub async fn restart_task(
+        &self,
+        #[tool(aggr)] params: RestartTaskParams
+    ) -> String {
+        info!("Restarting task ID: {}", params.task_id);
+
+        let peer = Self::completion_peer(params.on_complete);
+        let manager = self.manager.lock().await;
+        match manager.restart_task(&params.task_id, peer).await {
+            Ok(task_id) => format!("Task {} restarted as new task with ID: {}", params.task_id, task_id),
+            Err(e) => {
+                error!("Failed to restart task {}: {}", params.task_id, e);
+                format!("Error restarting task {}: {}", params.task_id, e)
+            }
+        }
+    }
+
     #[tool(description = "Stops all currently running tasks and removes ALL tasks (running, completed, errored, etc.) from the manager's memory and persistence file. Use with caution, as this permanently deletes task history.")]
     pub async fn clear_tasks(
         &self,
@@ -685,3 +975,350 @@ impl LongRunningTaskTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_persisted_tasks_visible_on_first_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        let persisted = serde_json::json!({
+            "task-persisted": {
+                "task_id": "task-persisted",
+                "command": "sleep 1",
+                "status": "Ended",
+                "reason": "from a previous run"
+            }
+        });
+        std::fs::write(&path, persisted.to_string()).unwrap();
+
+        // Absolute paths are used as-is by the manager
+        let tool = LongRunningTaskTool::new_loaded(path.to_str().unwrap()).await;
+        let listing = tool.list_tasks(ListTasksParams { status: String::new(), format: None }).await;
+
+        assert!(listing.contains("Found 1 tasks"), "unexpected listing: {}", listing);
+        assert!(listing.contains("task-persisted"));
+    }
+
+    #[tokio::test]
+    async fn test_new_loaded_tolerates_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let tool = LongRunningTaskTool::new_loaded(path.to_str().unwrap()).await;
+        let listing = tool.list_tasks(ListTasksParams { status: String::new(), format: None }).await;
+        assert_eq!(listing, "No tasks found.");
+    }
+
+    fn task(id: &str, status: TaskStatus, started_at: &str, exit_code: Option<i32>) -> TaskState {
+        TaskState {
+            task_id: id.to_string(),
+            command: format!("run {}", id),
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            reason: String::new(),
+            pid: None,
+            started_at: Some(started_at.to_string()),
+            exit_code,
+            working_dir: None,
+            restarted_from: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_json_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        {
+            let manager = tool.manager.lock().await;
+            let mut tasks = manager.tasks_in_memory.lock().await;
+            tasks.insert("task-b".into(), task("task-b", TaskStatus::Running, "2024-01-02T00:00:00+00:00", None));
+            tasks.insert("task-a".into(), task("task-a", TaskStatus::Error, "2024-01-01T00:00:00+00:00", Some(2)));
+        }
+
+        let output = tool
+            .list_tasks(ListTasksParams { status: String::new(), format: Some("json".into()) })
+            .await;
+        let parsed: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(
+            parsed,
+            serde_json::json!([
+                {
+                    "id": "task-a",
+                    "status": "error",
+                    "command": "run task-a",
+                    "started_at": "2024-01-01T00:00:00+00:00",
+                    "exit_code": 2
+                },
+                {
+                    "id": "task-b",
+                    "status": "running",
+                    "command": "run task-b",
+                    "started_at": "2024-01-02T00:00:00+00:00",
+                    "exit_code": null
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_status_pages_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        {
+            let manager = tool.manager.lock().await;
+            let mut tasks = manager.tasks_in_memory.lock().await;
+            let mut build = task("task-build", TaskStatus::Ended, "2024-01-01T00:00:00+00:00", Some(0));
+            build.stdout = (1..=10).map(|i| format!("line {}\n", i)).collect();
+            tasks.insert("task-build".into(), build);
+        }
+        let status = |params: serde_json::Value| {
+            let params: GetStatusParams = serde_json::from_value(params).unwrap();
+            tool.get_status(params)
+        };
+
+        let output = status(serde_json::json!({"task_id": "task-build", "tail_lines": 3})).await;
+        assert!(output.contains("=== STDOUT (lines 8-10 of 10) ===\nline 8\nline 9\nline 10\n"), "{}", output);
+        assert!(!output.contains("line 7"), "{}", output);
+        assert!(output.contains("=== STDERR (no lines shown of 0) ==="), "{}", output);
+
+        // The old parameter name still works, and the default is the whole of short output
+        let output = status(serde_json::json!({"task_id": "task-build", "lines": 2})).await;
+        assert!(output.contains("(lines 9-10 of 10)"), "{}", output);
+        let output = status(serde_json::json!({"task_id": "task-build"})).await;
+        assert!(output.contains("(lines 1-10 of 10)"), "{}", output);
+
+        let output = status(serde_json::json!({"task_id": "task-build", "offset": 2, "limit": 2})).await;
+        assert!(output.contains("=== STDOUT (lines 3-4 of 10) ===\nline 3\nline 4\n"), "{}", output);
+    }
+
+    #[derive(Clone)]
+    struct TaskServer {
+        tool: LongRunningTaskTool,
+    }
+
+    impl rmcp::ServerHandler for TaskServer {
+        async fn call_tool(
+            &self,
+            request: rmcp::model::CallToolRequestParam,
+            context: rmcp::service::RequestContext<RoleServer>,
+        ) -> Result<rmcp::model::CallToolResult, rmcp::Error> {
+            let params: StartTaskParams = serde_json::from_value(request.arguments.unwrap_or_default().into()).unwrap();
+            let output = ProgressTracker::for_request(&context, &crate::progress::ProgressTokens::new()).scope(self.tool.start_task(params)).await;
+            Ok(rmcp::model::CallToolResult::success(vec![rmcp::model::Content::text(output)]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completed_task_notifies_client() {
+        use rmcp::ServiceExt;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let running = TaskServer { tool }.serve(server).await.unwrap();
+            let _ = running.waiting().await;
+        });
+
+        let (read_half, mut write_half) = tokio::io::split(client);
+        let mut lines = BufReader::new(read_half).lines();
+        for message in [
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": "2024-11-05", "capabilities": {},
+                "clientInfo": { "name": "test", "version": "0.0.0" } } }),
+            serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "start_task",
+                "arguments": { "command_string": "true", "reason": "test", "on_complete": true } } }),
+        ] {
+            write_half.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+
+        // The task may finish before or after the tool call is answered
+        let (task_id, notification) = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            let (mut task_id, mut notification) = (None, None);
+            while task_id.is_none() || notification.is_none() {
+                let line = lines.next_line().await.unwrap().expect("server closed the connection");
+                let message: serde_json::Value = serde_json::from_str(&line).unwrap();
+                if message["id"] == 2 {
+                    let text = message["result"]["content"][0]["text"].as_str().unwrap();
+                    task_id = text.lines().next().and_then(|l| l.strip_prefix("Task started with ID: ")).map(str::to_string);
+                } else if message["method"] == "notifications/message" {
+                    notification = Some(message);
+                }
+            }
+            (task_id.unwrap(), notification.unwrap())
+        })
+        .await
+        .expect("no completion notification");
+
+        assert_eq!(notification["params"]["level"], "info");
+        assert_eq!(notification["params"]["logger"], "long_running_task");
+        assert_eq!(notification["params"]["data"]["task"]["id"], task_id.as_str());
+        assert_eq!(notification["params"]["data"]["task"]["status"], "ended");
+        assert_eq!(notification["params"]["data"]["task"]["command"], "true");
+    }
+
+    #[tokio::test]
+    async fn test_stop_task_sends_requested_signal_before_kill() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        let marker = dir.path().join("signal");
+        // Shuts down cleanly on SIGINT
+        let graceful = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("trap 'echo INT > {}; exit 0' INT; while true; do sleep 0.1; done", marker.display()))
+            .spawn()
+            .unwrap();
+        // Ignores SIGTERM, so only the fallback kill stops it
+        let stubborn = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; while true; do sleep 0.1; done")
+            .spawn()
+            .unwrap();
+        {
+            let manager = tool.manager.lock().await;
+            let mut tasks = manager.tasks_in_memory.lock().await;
+            for (id, child) in [("task-graceful", &graceful), ("task-stubborn", &stubborn)] {
+                let mut running = task(id, TaskStatus::Running, "2024-01-01T00:00:00+00:00", None);
+                running.pid = Some(child.id());
+                tasks.insert(id.into(), running);
+            }
+        }
+        // Let the shells install their traps
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let wait = |mut child: std::process::Child| tokio::task::spawn_blocking(move || child.wait().unwrap());
+
+        let stop = |task_id: &str, signal: Option<&str>| StopTaskParams {
+            task_id: task_id.to_string(),
+            signal: signal.map(str::to_string),
+            grace_period_secs: Some(1),
+        };
+        let output = tool.stop_task(stop("task-graceful", Some("int"))).await;
+        assert!(output.starts_with("Stop signal (SIGINT) sent to task task-graceful"), "{}", output);
+        let status = wait(graceful).await.unwrap();
+        assert_eq!(status.code(), Some(0), "killed instead of exiting on SIGINT: {:?}", status);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "INT\n");
+
+        let output = tool.stop_task(stop("task-stubborn", None)).await;
+        assert!(output.starts_with("Stop signal (SIGTERM) sent"), "{}", output);
+        let status = wait(stubborn).await.unwrap();
+        assert_eq!(status.signal(), Some(nix::sys::signal::Signal::SIGKILL as i32));
+
+        let output = tool.stop_task(stop("task-graceful", Some("SIGFOO"))).await;
+        assert!(output.contains("Unsupported signal 'SIGFOO'"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn test_hangup_is_delivered_without_stopping_the_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        let marker = dir.path().join("signal");
+        // Reloads on SIGHUP and keeps running
+        let mut server = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("trap 'echo HUP >> {}' HUP; while true; do sleep 0.1; done", marker.display()))
+            .spawn()
+            .unwrap();
+        {
+            let manager = tool.manager.lock().await;
+            let mut tasks = manager.tasks_in_memory.lock().await;
+            let mut running = task("task-server", TaskStatus::Running, "2024-01-01T00:00:00+00:00", None);
+            running.pid = Some(server.id());
+            tasks.insert("task-server".into(), running);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let output = tool.stop_task(StopTaskParams {
+            task_id: "task-server".to_string(),
+            signal: Some("hup".to_string()),
+            grace_period_secs: Some(0),
+        }).await;
+        assert_eq!(output, "Sent SIGHUP to task task-server. It is still running.");
+        // Well past the grace period it is neither stopped nor killed
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "HUP\n");
+        assert!(server.try_wait().unwrap().is_none(), "SIGHUP escalated to a kill");
+        {
+            let manager = tool.manager.lock().await;
+            assert_eq!(manager.tasks_in_memory.lock().await["task-server"].status, TaskStatus::Running);
+        }
+        server.kill().unwrap();
+        server.wait().unwrap();
+    }
+
+    #[test]
+    fn test_process_start_time_identifies_the_process() {
+        let started = process_start_time(std::process::id()).expect("this process is running");
+        assert_eq!(process_start_time(std::process::id()), Some(started));
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert_eq!(process_start_time(pid), None);
+    }
+
+    #[tokio::test]
+    async fn test_restart_failed_task_reruns_its_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        let work_dir = dir.path().canonicalize().unwrap().display().to_string();
+        {
+            let manager = tool.manager.lock().await;
+            let mut tasks = manager.tasks_in_memory.lock().await;
+            let mut failed = task("task-failed", TaskStatus::Error, "2024-01-01T00:00:00+00:00", Some(1));
+            failed.command = "pwd".to_string();
+            failed.working_dir = Some(work_dir.clone());
+            tasks.insert("task-failed".into(), failed);
+        }
+
+        let output = tool.restart_task(RestartTaskParams { task_id: "task-failed".into(), on_complete: false }).await;
+        let new_id = output
+            .strip_prefix("Task task-failed restarted as new task with ID: ")
+            .unwrap_or_else(|| panic!("unexpected output: {}", output))
+            .to_string();
+        let restarted = tool.manager.lock().await.get_task_status(&new_id).await.unwrap();
+        assert_ne!(restarted.status, TaskStatus::Error);
+        assert_eq!(restarted.command, "pwd");
+        assert_eq!(restarted.restarted_from.as_deref(), Some("task-failed"));
+
+        // It runs in the original's directory
+        let stdout = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let state = tool.manager.lock().await.get_task_status(&new_id).await.unwrap();
+                if !state.stdout.is_empty() {
+                    return state.stdout;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("restarted task produced no output");
+        assert_eq!(stdout.trim(), work_dir);
+
+        // The original is untouched, and unknown or running tasks can't be restarted
+        let original = tool.manager.lock().await.get_task_status("task-failed").await.unwrap();
+        assert_eq!(original.status, TaskStatus::Error);
+        let output = tool.restart_task(RestartTaskParams { task_id: "task-missing".into(), on_complete: false }).await;
+        assert_eq!(output, "Error restarting task task-missing: Task not found: task-missing");
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_rejects_unknown_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LongRunningTaskTool::new(dir.path().join("tasks.json").to_str().unwrap());
+        let output = tool
+            .list_tasks(ListTasksParams { status: String::new(), format: Some("xml".into()) })
+            .await;
+        assert!(output.starts_with("Error: unsupported format"));
+    }
+}
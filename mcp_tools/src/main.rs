@@ -1,11 +1,13 @@
 // Keep only the necessary imports
-use tracing::{error, info, Level};
-use tracing_appender;
-use tracing_subscriber::{self, EnvFilter};
+use tracing::{error, info, warn};
+use tokio_util::sync::CancellationToken;
 
 // Import necessary rmcp components
 use rmcp::{
-    model::ServerInfo, // Needed for ServerHandler implementation
+    model::{CallToolRequestParam, CallToolResult, ListToolsResult, PaginatedRequestParam, ServerInfo}, // Needed for ServerHandler implementation
+    handler::server::tool::ToolCallContext,
+    service::{RequestContext, RoleServer},
+    Error as McpError,
     tool,              // The tool attribute macro
     transport::stdio,  // For standard I/O transport
     ServerHandler,     // Trait for server handlers
@@ -15,13 +17,21 @@ use rmcp::{
 // Import local modules needed
 use mcp_tools::bash::{BashParams, BashTool}; // Import BashParams too
 use mcp_tools::scraping_bee::{ScrapingBeeTool, ScrapingBeeParams};
-use mcp_tools::brave_search::{BraveSearchTool, BraveSearchParams};
+use mcp_tools::brave_search::{BraveSearchTool, BraveSearchParams, GoogleSearchParams};
 use mcp_tools::long_running_task::{
-    LongRunningTaskTool, StartTaskParams, GetStatusParams, ListTasksParams, StopTaskParams, ClearTasksParams // Added ClearTasksParams
+    LongRunningTaskTool, StartTaskParams, GetStatusParams, ListTasksParams, StopTaskParams, RestartTaskParams, ClearTasksParams // Added ClearTasksParams
 };
 use mcp_tools::aider::{AiderTool, AiderParams};
 use mcp_tools::mermaid_chart::{MermaidChartTool, MermaidChartParams};
 use mcp_tools::netlify::{NetlifyTool, NetlifyParams, NetlifyHelpParams};
+use mcp_tools::regex_replace::{RegexReplaceTool, RegexReplaceParams};
+use mcp_tools::diff::{DiffTool, DiffParams};
+use mcp_tools::git_integration::{GitTool, GitParams};
+use mcp_tools::http_request::{HttpRequestTool, HttpRequestParams};
+use mcp_tools::fs_tool::{FsTool, ReadFileParams, WriteFileParams, ListDirParams, StatParams};
+use mcp_tools::enabled_tools::EnabledTools;
+use mcp_tools::rate_limit::RateLimiter;
+use mcp_tools::progress::{ProgressTokens, ProgressTracker};
 // use mcp_tools::supabase::{SupabaseTool, SupabaseParams, SupabaseHelpParams};
 // use mcp_tools::interactive_terminal::{ // Disabled interactive terminal imports
 //     InteractiveTerminalTool, StartTerminalParams, RunInTerminalParams, GetOutputParams, StopTerminalParams
@@ -35,31 +45,8 @@ use mcp_tools::netlify::{NetlifyTool, NetlifyParams, NetlifyHelpParams};
 
 #[tokio::main]
 async fn main() {
-    // Set up file appender
-    let log_dir = std::env::var("LOG_DIR")
-        .unwrap_or_else(|_| format!("{}/Developer/mcp/logs", dirs::home_dir().unwrap().display()));
-    let file_appender = tracing_appender::rolling::Builder::new()
-        .rotation(tracing_appender::rolling::Rotation::NEVER)
-        .filename_prefix("mcp-server")
-        .filename_suffix("log")
-        .build(log_dir)
-        .expect("Failed to create log directory");
-
-    // Initialize the tracing subscriber with both stdout and file output
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive(Level::DEBUG.into())
-                .add_directive("mcp_tools=debug".parse().unwrap()),
-        )
-        .with_writer(non_blocking)
-        .with_thread_ids(true)
-        .with_file(true)
-        .with_line_number(true)
-        .with_target(true)
-        .init();
+    // Log to LOG_DIR (or ~/Developer/mcp/logs), falling back to stderr if it's unusable
+    let log_guard = mcp_tools::logging::init_logging();
 
     info!("Starting MCP server (SDK)...");
     info!("RUST_LOG environment: {:?}", std::env::var("RUST_LOG"));
@@ -67,12 +54,6 @@ async fn main() {
     info!("Current directory: {:?}", std::env::current_dir().unwrap_or_default());
     info!("Process ID: {}", std::process::id());
 
-    // TODO: Re-integrate LongRunningTaskManager when LongRunningTaskTool is converted to SDK
-    // let my_manager = LongRunningTaskManager::new("tasks.json".to_string());
-    // if let Err(err) = my_manager.load_persistent_tasks().await {
-    //     error!("Failed to load tasks: {}", err);
-    // }
-
     // --- New SDK Server Structure ---
     #[derive(Debug, Clone)]
     struct McpToolServer {
@@ -83,6 +64,14 @@ async fn main() {
         aider_tool: AiderTool,
         mermaid_chart_tool: MermaidChartTool,
         netlify_tool: NetlifyTool,
+        regex_replace_tool: RegexReplaceTool,
+        diff_tool: DiffTool,
+        git_tool: GitTool,
+        http_request_tool: HttpRequestTool,
+        fs_tool: FsTool,
+        enabled_tools: EnabledTools, // From MCP_TOOLS_ENABLED; gates list_tools/call_tool
+        rate_limiter: RateLimiter, // From MCP_TOOLS_RATE_LIMITS; throttles runaway bash/aider loops
+        progress_tokens: ProgressTokens, // Noted by the transport, since rmcp drops `_meta`
         // supabase_tool: SupabaseTool,
         // interactive_terminal_tool: InteractiveTerminalTool, // Disabled interactive terminal field
         // planner_tool: PlannerTool,
@@ -91,18 +80,10 @@ async fn main() {
     }
 
     impl McpToolServer {
-        fn new() -> Self {
-            // Create the long-running task manager
-            let task_tool = LongRunningTaskTool::new("tasks.json");
-            let task_tool_clone = task_tool.clone();
-            
-            // Try to load any existing tasks
-            tokio::spawn(async move {
-                if let Err(e) = task_tool_clone.load_persistent_tasks().await {
-                    error!("Failed to load persistent tasks: {}", e);
-                }
-            });
-            
+        async fn new() -> Self {
+            // Load persisted tasks before serving so the first list_tasks reflects them
+            let task_tool = LongRunningTaskTool::new_loaded("tasks.json").await;
+
             Self {
                 bash_tool: BashTool::new(),
                 scraping_tool: ScrapingBeeTool::new(),
@@ -111,6 +92,14 @@ async fn main() {
                 aider_tool: AiderTool::new(),
                 mermaid_chart_tool: MermaidChartTool::new(),
                 netlify_tool: NetlifyTool::new(),
+                regex_replace_tool: RegexReplaceTool::new(),
+                diff_tool: DiffTool::new(),
+                git_tool: GitTool::new(),
+                http_request_tool: HttpRequestTool::new(),
+                fs_tool: FsTool::new(),
+                enabled_tools: EnabledTools::from_env(),
+                rate_limiter: RateLimiter::from_env(),
+                progress_tokens: ProgressTokens::new(),
                 // supabase_tool: SupabaseTool::new(),
                 // interactive_terminal_tool: InteractiveTerminalTool::new(), // Disabled interactive terminal instantiation
                 // planner_tool: PlannerTool::new(),
@@ -144,7 +133,7 @@ async fn main() {
         }
         
         // Brave search tool implementation
-        #[tool(description = "Web search tool powered by Brave Search that retrieves relevant results from across the internet. Use this to find current information and facts from the web, research topics with multiple sources, verify claims, discover recent news and trends, or find specific websites and resources.")]
+        #[tool(description = "Web search tool powered by Brave Search that retrieves relevant results from across the internet. Use this to find current information and facts from the web, research topics with multiple sources, verify claims, discover recent news and trends, or find specific websites and resources. Results are followed by a JSON list of {title, url, description} for picking a URL to fetch.")]
         async fn brave_search(
             &self,
             #[tool(aggr)] params: BraveSearchParams,
@@ -152,9 +141,19 @@ async fn main() {
             // Delegate to BraveSearchTool's implementation
             self.brave_search_tool.brave_search(params).await
         }
+
+        // google_search is an alias backed by Brave Search (configs still enable it by that name)
+        #[tool(description = "Web search tool (alias of brave_search, results come from Brave Search). Use this to find current information and facts from the web. Supports 'num' results and 'start' for paging.")]
+        async fn google_search(
+            &self,
+            #[tool(aggr)] params: GoogleSearchParams,
+        ) -> String {
+            // Delegate to BraveSearchTool's implementation
+            self.brave_search_tool.google_search(params).await
+        }
         
         // Long-running task tools
-        #[tool(description = "Start a new long-running shell task. Use this for any shell command that might take longer than 1 minute to complete, or for tasks that need to run in the background while other tools are used. The task runs asynchronously, continues after this conversation ends, and its status/output can be checked later using 'get_status' or 'list_tasks'.")]
+        #[tool(description = "Start a new long-running shell task. Use this for any shell command that might take longer than 1 minute to complete, or for tasks that need to run in the background while other tools are used. The task runs asynchronously, continues after this conversation ends, and its status/output can be checked later using 'get_status' or 'list_tasks'. Set 'on_complete' to be notified when it finishes.")]
         async fn start_task(
             &self,
             #[tool(aggr)] params: StartTaskParams,
@@ -163,7 +162,7 @@ async fn main() {
             self.long_running_task_tool.start_task(params).await
         }
         
-        #[tool(description = "Get the status and output of a long-running task. This will show if the task is still running and display its stdout/stderr.")]
+        #[tool(description = "Get the status and output of a long-running task. This will show if the task is still running and display the last 'tail_lines' (default 100) lines of its stdout/stderr. For long output, page through it from the start with 'offset' and 'limit'.")]
         async fn get_status(
             &self,
             #[tool(aggr)] params: GetStatusParams,
@@ -181,7 +180,7 @@ async fn main() {
             self.long_running_task_tool.list_tasks(params).await
         }
 
-        #[tool(description = "Stop a currently running background task. This attempts to gracefully terminate the process using SIGTERM, falling back to SIGKILL if necessary. Use this to cancel tasks that are no longer needed or are running indefinitely.")]
+        #[tool(description = "Stop a currently running background task. This attempts to gracefully terminate the process using SIGTERM (or the given 'signal', e.g. SIGINT), falling back to SIGKILL if it is still running after 'grace_period_secs' (default 10). Use this to cancel tasks that are no longer needed or are running indefinitely.")]
         async fn stop_task(
             &self,
             #[tool(aggr)] params: StopTaskParams,
@@ -190,6 +189,15 @@ async fn main() {
             self.long_running_task_tool.stop_task(params).await
         }
 
+        #[tool(description = "Run the command of an ended, failed or stopped task again as a new task, in the same working directory. The new task records which task it restarted.")]
+        async fn restart_task(
+            &self,
+            #[tool(aggr)] params: RestartTaskParams,
+        ) -> String {
+            // Delegate to LongRunningTaskTool's implementation
+            self.long_running_task_tool.restart_task(params).await
+        }
+
         #[tool(description = "Stops all currently running tasks and removes ALL tasks (running, completed, errored, etc.) from the manager's memory and persistence file. Use with caution, as this permanently deletes task history.")]
         async fn clear_tasks(
             &self,
@@ -214,7 +222,7 @@ async fn main() {
         async fn mermaid_chart(
             &self,
             #[tool(aggr)] params: MermaidChartParams,
-        ) -> String {
+        ) -> Result<CallToolResult, McpError> {
             // Delegate to MermaidChartTool's implementation
             self.mermaid_chart_tool.mermaid_chart(params).await
         }
@@ -237,6 +245,83 @@ async fn main() {
             // Delegate to NetlifyTool's implementation
             self.netlify_tool.netlify_help(params).await
         }
+
+        // Regex replace tool implementation
+        #[tool(description = "Performs a regex search-and-replace in a file (edited in place) or on provided text. Replaces the first match unless 'global' is true. Set 'dry_run' to preview the change as a diff without writing.")]
+        async fn regex_replace(
+            &self,
+            #[tool(aggr)] params: RegexReplaceParams,
+        ) -> String {
+            // Delegate to RegexReplaceTool's implementation
+            self.regex_replace_tool.regex_replace(params).await
+        }
+
+        // Diff tool implementation
+        #[tool(description = "Compares two files or two pieces of text and returns a unified diff. Give each side as a path ('old_path'/'new_path') or text ('old_text'/'new_text'). Options: 'context_lines' (default 3) and 'ignore_whitespace'.")]
+        async fn diff(
+            &self,
+            #[tool(aggr)] params: DiffParams,
+        ) -> String {
+            // Delegate to DiffTool's implementation
+            self.diff_tool.diff(params).await
+        }
+
+        // Git tool implementation
+        #[tool(description = "Git repository operations returning JSON. operation: 'status' (branch and changed files), 'diff' (unstaged, or staged with staged=true), 'log' (recent commits, max_count), 'branch' (list, or create with name), 'commit' (stage paths or everything, then commit with message). Write operations require allow_write=true.")]
+        async fn git_integration(
+            &self,
+            #[tool(aggr)] params: GitParams,
+        ) -> String {
+            // Delegate to GitTool's implementation
+            self.git_tool.git_integration(params).await
+        }
+
+        // HTTP request tool implementation
+        #[tool(description = "Makes an HTTP request and returns JSON with the response 'status', 'headers' and 'body'. Use instead of curl for calling APIs. Only hosts on the server's allowlist can be contacted.")]
+        async fn http_request(
+            &self,
+            #[tool(aggr)] params: HttpRequestParams,
+        ) -> String {
+            // Delegate to HttpRequestTool's implementation
+            self.http_request_tool.http_request(params).await
+        }
+
+        // Filesystem tool implementations (confined to FS_SANDBOX_ROOT)
+        #[tool(description = "Reads a file inside the sandbox. Returns JSON with 'content' as UTF-8 text, or base64 (with encoding 'base64') for binary files.")]
+        async fn read_file(
+            &self,
+            #[tool(aggr)] params: ReadFileParams,
+        ) -> String {
+            // Delegate to FsTool's implementation
+            self.fs_tool.read_file(params).await
+        }
+
+        #[tool(description = "Writes a file inside the sandbox, replacing any existing content. Use encoding 'base64' for binary data.")]
+        async fn write_file(
+            &self,
+            #[tool(aggr)] params: WriteFileParams,
+        ) -> String {
+            // Delegate to FsTool's implementation
+            self.fs_tool.write_file(params).await
+        }
+
+        #[tool(description = "Lists a directory inside the sandbox. Returns JSON entries with name, kind (file/dir/symlink) and size.")]
+        async fn list_dir(
+            &self,
+            #[tool(aggr)] params: ListDirParams,
+        ) -> String {
+            // Delegate to FsTool's implementation
+            self.fs_tool.list_dir(params).await
+        }
+
+        #[tool(description = "Returns metadata (kind, size, modified time, readonly) for a path inside the sandbox.")]
+        async fn stat(
+            &self,
+            #[tool(aggr)] params: StatParams,
+        ) -> String {
+            // Delegate to FsTool's implementation
+            self.fs_tool.stat(params).await
+        }
  
         // Supabase tool implementations
         // #[tool(description = "Executes authenticated Supabase CLI commands. Provide the command arguments *after* 'supabase' (e.g., 'projects list', 'functions deploy my-func'). Authentication is handled automatically.\n\nEssential Supabase CLI Commands:\nInitialize & Local Dev: supabase init creates config files, then supabase start launches local services.\nDatabase Development: Create migrations with supabase migration new name or generate them from changes with supabase db diff -f name.\nLocal Testing: Check service status with supabase status, reset database with supabase db reset, and stop services with supabase stop.\nRemote Connection: Authenticate with supabase login, link to project with supabase link --project-ref YOUR_REF, and pull remote schema with supabase db pull.\nDeployment: Push migrations to production with supabase db push (use --dry-run to preview changes).\nEdge Functions: Create with supabase functions new name, serve locally with supabase functions serve, and deploy with supabase functions deploy name.\nType Generation: Generate TypeScript types with supabase gen types typescript --linked > types/supabase.ts.\nProduction Management: Add secrets with supabase secrets set KEY=VALUE, manage database with supabase db lint for errors, and use supabase db diff to check drift between environments.")]
@@ -377,9 +462,51 @@ async fn main() {
     }
 
     // Implement ServerHandler for the server struct
-    // The #[tool(tool_box)] macro can automatically implement this based on the tools defined above
-    #[tool(tool_box)]
+    // list_tools/call_tool are written out (rather than generated by #[tool(tool_box)])
+    // so that only tools enabled via MCP_TOOLS_ENABLED are exposed
     impl ServerHandler for McpToolServer {
+        async fn list_tools(
+            &self,
+            _request: PaginatedRequestParam,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<ListToolsResult, McpError> {
+            let mut tools: Vec<_> = Self::tool_box()
+                .list()
+                .into_iter()
+                .filter(|tool| self.enabled_tools.is_enabled(&tool.name))
+                .collect();
+            tools.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(ListToolsResult { next_cursor: None, tools })
+        }
+
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParam,
+            context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, McpError> {
+            if !self.enabled_tools.is_enabled(&request.name) {
+                return Err(McpError::invalid_params(format!("tool '{}' is not enabled", request.name), None));
+            }
+            if let Some(throttled) = self.rate_limiter.throttle(&request.name) {
+                return Ok(throttled);
+            }
+            // Tools can report progress for this request via mcp_tools::progress::report_step
+            let tracker = ProgressTracker::for_request(&context, &self.progress_tokens);
+            // Cancelled when the client sends notifications/cancelled for this request;
+            // dropping the tool's future then kills any process it started
+            let cancelled = context.ct.clone();
+            let name = request.name.clone();
+            let context = ToolCallContext::new(self, request, context);
+            tokio::select! {
+                result = tracker.scope(Self::tool_box().call(context)) => result,
+                _ = cancelled.cancelled() => {
+                    info!("Tool call '{}' cancelled by the client", name);
+                    Err(McpError::internal_error(format!("tool call '{}' was cancelled", name), None))
+                }
+            }
+        }
+
+
         // Override get_info for custom server details
         fn get_info(&self) -> ServerInfo {
             // Create the ServerInfo struct with the correct fields
@@ -394,12 +521,22 @@ async fn main() {
     // --- End New SDK Server Structure ---
 
     info!("Setting up tools with rmcp SDK...");
-    let mcp_server = McpToolServer::new();
+    let mcp_server = McpToolServer::new().await;
+    mcp_server.enabled_tools.warn_unknown(McpToolServer::tool_box().map.keys().map(|name| name.as_ref()));
     info!("McpToolServer created with tools.");
 
+    // Kept so tasks can be flushed to disk once the server stops
+    let task_tool = mcp_server.long_running_task_tool.clone();
+
+    // Cancelled on SIGTERM/SIGINT; the server then stops reading requests
+    let shutdown = CancellationToken::new();
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
+
     // Serve the McpToolServer instance
     info!("Initializing RMCP server...");
-    let server = match mcp_server.serve(stdio()).await {
+    let (stdin, stdout) = stdio();
+    let transport = mcp_server.progress_tokens.transport(stdin, stdout);
+    let server = match mcp_server.serve_with_ct(transport, shutdown.clone()).await {
         Ok(s) => {
             info!("RMCP server started successfully.");
             s
@@ -410,11 +547,39 @@ async fn main() {
         }
     };
 
-    // Keep the server running
+    // Keep the server running until the client closes stdin or we get a shutdown signal
     info!("Server is running, waiting for requests...");
-    if let Err(e) = server.waiting().await {
-        error!("Server encountered an error while running: {}", e);
+    match server.waiting().await {
+        Ok(reason) => info!("Server stopped: {:?}", reason),
+        Err(e) => error!("Server encountered an error while running: {}", e),
+    }
+
+    // No more requests will be handled; make sure task state survives the restart
+    if let Err(e) = task_tool.persist().await {
+        error!("Failed to persist tasks on shutdown: {}", e);
     }
 
     info!("MCP server shutdown complete.");
+    if shutdown.is_cancelled() {
+        // Stopped by a signal with stdin still open: tokio reads stdin on a blocking thread,
+        // and the runtime would wait for that read to finish before exiting. Flush the log first.
+        drop(log_guard);
+        std::process::exit(0);
+    }
+}
+
+/// Cancel `token` on SIGTERM or SIGINT
+async fn cancel_on_signal(token: CancellationToken) {
+    let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            return;
+        }
+    };
+    tokio::select! {
+        _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT, shutting down"),
+    }
+    token.cancel();
 }
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::env;
@@ -10,8 +11,103 @@ use reqwest::Client;
 use schemars::JsonSchema;
 
 // Import rmcp SDK components
+use rmcp::model::{CallToolResult, Content};
 use rmcp::tool;
 
+/// A mermaid.ink-compatible renderer (`<url>/<base64url source>` returns a PNG), e.g.
+/// `https://mermaid.ink/img`. Charts are only rendered if this is set, since rendering
+/// sends the diagram source to that service.
+pub const RENDER_URL_ENV: &str = "MERMAID_RENDER_URL";
+
+/// Diagram declarations a chart may start with
+const DIAGRAM_TYPES: &[&str] = &[
+    "graph", "flowchart", "sequenceDiagram", "classDiagram", "stateDiagram", "stateDiagram-v2",
+    "erDiagram", "journey", "gantt", "pie", "quadrantChart", "requirementDiagram", "gitGraph",
+    "mindmap", "timeline", "sankey-beta", "xychart-beta", "block-beta", "C4Context", "C4Container",
+    "C4Component", "C4Dynamic", "C4Deployment",
+];
+
+/// Keywords that open a block closed by a line reading `end`
+const BLOCK_KEYWORDS: &[&str] = &["subgraph", "loop", "alt", "opt", "par", "critical", "break", "rect", "box"];
+
+/// A lightweight syntax check for generated Mermaid: the diagram must start with a known
+/// diagram type, every block (`subgraph`, `loop`, `alt`, ...) must be closed by `end`, and
+/// in flowcharts the node shapes' brackets and quotes must balance on each line. Not a full
+/// parser, but it catches the usual ways model output goes wrong before it reaches a renderer.
+pub fn validate_mermaid(diagram: &str) -> Result<()> {
+    let mut lines = diagram
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with("%%"));
+
+    let (first_number, first) = lines.next().ok_or_else(|| anyhow!("the diagram is empty"))?;
+    let declared = first.split_whitespace().next().unwrap_or_default();
+    if !DIAGRAM_TYPES.contains(&declared) {
+        return Err(anyhow!(
+            "line {}: expected a diagram type such as 'flowchart TD' or 'sequenceDiagram', found '{}'",
+            first_number, first
+        ));
+    }
+
+    // Other diagram types use braces across lines (class bodies) or as markers (ER cardinality)
+    let flowchart = declared == "graph" || declared == "flowchart";
+    let mut open_blocks: Vec<(usize, &str)> = Vec::new();
+    for (number, line) in lines {
+        if flowchart {
+            check_brackets(line).map_err(|e| anyhow!("line {}: {}", number, e))?;
+        }
+
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        if keyword == "end" {
+            if open_blocks.pop().is_none() {
+                return Err(anyhow!("line {}: 'end' without an open block", number));
+            }
+        } else if let Some(block) = BLOCK_KEYWORDS.iter().find(|block| **block == keyword) {
+            open_blocks.push((number, block));
+        }
+    }
+    if let Some((number, block)) = open_blocks.pop() {
+        return Err(anyhow!("line {}: '{}' block is never closed with 'end'", number, block));
+    }
+    Ok(())
+}
+
+/// Brackets outside quoted labels must nest properly, and quotes must be closed.
+/// `>` right after a node id opens the asymmetric shape `A>label]`; elsewhere it is an arrow.
+fn check_brackets(line: &str) -> std::result::Result<(), String> {
+    let mut stack = Vec::new();
+    let mut in_quotes = false;
+    let mut previous = ' ';
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => {}
+            '[' | '(' | '{' => stack.push(c),
+            '>' if previous.is_alphanumeric() || previous == '_' => stack.push(c),
+            ']' | ')' | '}' => {
+                let expected: &[char] = match c {
+                    ']' => &['[', '>'],
+                    ')' => &['('],
+                    _ => &['{'],
+                };
+                if !stack.pop().is_some_and(|open| expected.contains(&open)) {
+                    return Err(format!("unexpected '{}'", c));
+                }
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    if in_quotes {
+        return Err("unterminated '\"'".to_string());
+    }
+    match stack.pop() {
+        Some(open) => Err(format!("unclosed '{}'", open)),
+        None => Ok(()),
+    }
+}
+
 /// Call the Gemini API to generate content
 async fn call_gemini_api(prompt: &str) -> Result<String> {
     // Get the API key from environment
@@ -109,14 +205,75 @@ pub struct MermaidChartParams {
     #[serde(default)]
     #[schemars(description = "Optional: Additional instructions for the chart generation. Leave empty for none.")]
     pub prompt: String, // Changed from Option<String>
+
+    #[serde(default)]
+    #[schemars(description = "Optional: 'image' to also return the chart rendered as a PNG (for clients that can view images, and only if rendering is enabled), or 'text' for the Mermaid source only. Defaults to 'text'.")]
+    pub content_type: String,
 }
 
 #[derive(Debug, Clone)]
-pub struct MermaidChartTool;
+pub struct MermaidChartTool {
+    render_url: Option<String>, // None: image output is off
+}
 
 impl MermaidChartTool {
+    /// Renders images with the renderer in `MERMAID_RENDER_URL`, if it is set
     pub fn new() -> Self {
-        Self
+        Self { render_url: env::var(RENDER_URL_ENV).ok().filter(|url| !url.trim().is_empty()) }
+    }
+
+    /// Render image output with this mermaid.ink-compatible renderer
+    pub fn with_render_url(render_url: impl Into<String>) -> Self {
+        Self { render_url: Some(render_url.into()) }
+    }
+
+    /// Fetch the diagram rendered as a PNG
+    async fn render_png(&self, render_url: &str, diagram: &str) -> Result<Vec<u8>> {
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(diagram);
+        let url = format!("{}/{}?type=png", render_url.trim_end_matches('/'), encoded);
+        let response = Client::new().get(&url).send().await
+            .map_err(|e| anyhow!("Failed to reach the Mermaid renderer: {}", e))?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Mermaid renderer returned {}", response.status()));
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// The tool result for a generated diagram: its source, preceded by the rendered
+    /// image when one was asked for and rendering is on. Otherwise, or if rendering fails,
+    /// the source comes with a note saying why there is no image.
+    /// Diagrams that fail `validate_mermaid` are reported as an error with the source attached.
+    async fn chart_result(&self, diagram: &str, content_type: &str) -> CallToolResult {
+        let source = Content::text(format!("```mermaid\n{}\n```", diagram));
+        if let Err(e) = validate_mermaid(diagram) {
+            error!("Generated Mermaid chart is invalid: {}", e);
+            return CallToolResult::error(vec![
+                Content::text(format!("Generated Mermaid chart has a syntax error: {}", e)),
+                source,
+            ]);
+        }
+        if !content_type.trim().eq_ignore_ascii_case("image") {
+            return CallToolResult::success(vec![source]);
+        }
+        let Some(render_url) = &self.render_url else {
+            return CallToolResult::success(vec![
+                source,
+                Content::text(format!("(Image rendering is off; set {} to enable it)", RENDER_URL_ENV)),
+            ]);
+        };
+        match self.render_png(render_url, diagram).await {
+            Ok(png) => {
+                let data = base64::engine::general_purpose::STANDARD.encode(png);
+                CallToolResult::success(vec![Content::image(data, "image/png"), source])
+            }
+            Err(e) => {
+                error!("Could not render Mermaid chart as an image: {}", e);
+                CallToolResult::success(vec![
+                    source,
+                    Content::text(format!("(Image rendering failed: {})", e)),
+                ])
+            }
+        }
     }
     
     // Helper method to generate the mermaid chart
@@ -214,22 +371,109 @@ impl MermaidChartTool {
     pub async fn mermaid_chart(
         &self,
         #[tool(aggr)] params: MermaidChartParams
-    ) -> String {
+    ) -> Result<CallToolResult, rmcp::Error> {
         // Log the number of files based on splitting the input string
         let file_count = params.files.split_whitespace().count();
         // Log the chart type string directly
         info!("Generating Mermaid chart for {} files (from input string '{}') with chart type: '{}'",
               file_count, params.files, if params.chart_type.is_empty() { "flowchart (default)" } else { &params.chart_type });
 
+        let content_type = params.content_type.clone();
         match self.generate_chart(params).await {
-            Ok(diagram) => {
-                // Format the diagram with a Mermaid code block
-                format!("```mermaid\n{}\n```", diagram)
-            },
+            Ok(diagram) => Ok(self.chart_result(&diagram, &content_type).await),
             Err(e) => {
                 error!("Error generating Mermaid chart: {}", e);
-                format!("Error generating Mermaid chart: {}", e)
+                Ok(CallToolResult::success(vec![Content::text(format!("Error generating Mermaid chart: {}", e))]))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::RawContent;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_content_type_selects_text_or_image() {
+        let diagram = "graph TD; A-->B";
+        let server = MockServer::start().await;
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(diagram);
+        Mock::given(method("GET"))
+            .and(path(format!("/img/{}", encoded)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"\x89PNG".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let tool = MermaidChartTool::with_render_url(format!("{}/img", server.uri()));
+
+        // What a host sends for a model without vision: source only, no render request
+        let text = tool.chart_result(diagram, "text").await;
+        assert_eq!(text.content.len(), 1);
+        assert!(matches!(&text.content[0].raw, RawContent::Text(t) if t.text.contains(diagram)));
+
+        let image = tool.chart_result(diagram, "image").await;
+        assert_eq!(image.content.len(), 2);
+        match &image.content[0].raw {
+            RawContent::Image(img) => {
+                assert_eq!(img.mime_type, "image/png");
+                assert_eq!(base64::engine::general_purpose::STANDARD.decode(&img.data).unwrap(), b"\x89PNG");
             }
+            other => panic!("expected an image, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_rendering_unless_configured() {
+        let tool = MermaidChartTool { render_url: None };
+        let result = tool.chart_result("graph TD; A-->B", "image").await;
+        assert_eq!(result.content.len(), 2);
+        assert!(matches!(&result.content[0].raw, RawContent::Text(t) if t.text.contains("graph TD; A-->B")));
+        assert!(matches!(&result.content[1].raw, RawContent::Text(t) if t.text == "(Image rendering is off; set MERMAID_RENDER_URL to enable it)"));
+    }
+
+    #[test]
+    fn test_validate_mermaid() {
+        let valid = "%% generated\nflowchart TD\n  subgraph core\n    A[\"Start (here)\"] --> B{Ok?}\n  end\n  B -->|yes| C((Done))";
+        assert!(validate_mermaid(valid).is_ok());
+        assert!(validate_mermaid("sequenceDiagram\n  loop Every minute\n    A->>B: ping\n  end").is_ok());
+        assert!(validate_mermaid("graph LR\n  A>Flag] --> B").is_ok());
+        assert!(validate_mermaid("erDiagram\n  CUSTOMER ||--o{ ORDER : places").is_ok());
+        assert!(validate_mermaid("classDiagram\n  class Tool {\n    +call()\n  }").is_ok());
+
+        let cases = [
+            ("", "the diagram is empty"),
+            ("Here is your diagram:\ngraph TD", "line 1: expected a diagram type"),
+            ("graph TD\n  A[Start --> B", "line 2: unclosed '['"),
+            ("graph TD\n  A(Start] --> B", "line 2: unexpected ']'"),
+            ("graph TD\n  A[\"Start] --> B", "line 2: unterminated '\"'"),
+            ("graph TD\n  subgraph one\n  A --> B", "line 2: 'subgraph' block is never closed"),
+            ("sequenceDiagram\n  A->>B: hi\n  end", "line 3: 'end' without an open block"),
+        ];
+        for (diagram, expected) in cases {
+            let err = validate_mermaid(diagram).unwrap_err().to_string();
+            assert!(err.starts_with(expected), "{:?}: {}", diagram, err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_chart_is_an_error_not_an_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"\x89PNG".to_vec()))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let tool = MermaidChartTool::with_render_url(format!("{}/img", server.uri()));
+
+        let result = tool.chart_result("graph TD\n  A[Start --> B", "image").await;
+        assert_eq!(result.is_error, Some(true));
+        match &result.content[0].raw {
+            RawContent::Text(t) => assert_eq!(t.text, "Generated Mermaid chart has a syntax error: line 2: unclosed '['"),
+            other => panic!("expected an error message, got {:?}", other),
         }
+        assert!(matches!(&result.content[1].raw, RawContent::Text(t) if t.text.contains("A[Start --> B")));
     }
 }
@@ -2,9 +2,18 @@ use anyhow::{anyhow, Result};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::env;
-use std::process::Command;
+use std::process::Stdio;
+use std::time::Duration;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use tokio::process::Command;
 use tracing::{debug, error, warn};
 
+use crate::progress::{stream_output, ProgressTracker};
+
+/// How long a Netlify command may run when the caller doesn't say; deploys can take minutes
+const DEFAULT_TIMEOUT_SECS: u64 = 600;
+
 // Import the tool macro
 use rmcp::tool;
 
@@ -17,6 +26,9 @@ pub struct NetlifyParams {
     #[serde(default = "default_cwd")]
     #[schemars(description = "The working directory for the command (defaults to current dir)")]
     pub cwd: String,
+    #[serde(default)]
+    #[schemars(description = "Optional: seconds to let the command run before it is stopped (defaults to 600). Raise it for large deploys.")]
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
@@ -50,11 +62,18 @@ struct NetlifyExecutionResult {
 // --- Tool Struct and Implementation ---
 
 #[derive(Debug, Clone)]
-pub struct NetlifyTool;
+pub struct NetlifyTool {
+    program: String,
+}
 
 impl NetlifyTool {
     pub fn new() -> Self {
-        Self
+        Self::with_program("netlify")
+    }
+
+    /// Run `program` in place of the `netlify` CLI
+    pub fn with_program(program: impl Into<String>) -> Self {
+        Self { program: program.into() }
     }
 
     // Helper function to execute netlify commands
@@ -63,6 +82,7 @@ impl NetlifyTool {
         command_str: &str, // The full command string including subcommands and flags
         cwd: &str,
         append_auth: bool, // Flag to control appending --auth
+        timeout: Duration,
     ) -> Result<NetlifyExecutionResult> {
         let token = if append_auth {
             env::var("NETLIFY_AUTH_TOKEN").map_err(|_| {
@@ -79,7 +99,7 @@ impl NetlifyTool {
             command_str.to_string()
         };
 
-        debug!("Executing Netlify command: netlify {}", full_command);
+        debug!("Executing Netlify command: {} {}", self.program, command_str);
         debug!("Working directory: {}", cwd);
 
         let cwd_path = std::path::PathBuf::from(cwd);
@@ -97,17 +117,48 @@ impl NetlifyTool {
         // Let's try splitting for now, assuming simple command structures.
         // Alternatively, we could require the user to pass args correctly separated.
         // Let's stick to the bash approach for simplicity and robustness with complex args.
-        let output = Command::new("sh") // Use sh -c to handle complex args/quotes
+        // The command gets its own process group so a timeout can stop everything it started.
+        let mut child = Command::new("sh") // Use sh -c to handle complex args/quotes
             .arg("-c")
-            .arg(format!("netlify {}", full_command)) // Prepend netlify here
+            .arg(format!("{} {}", self.program, full_command))
             .current_dir(&cwd_path)
-            .output()?;
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // Stream output as it arrives, so a long deploy shows signs of life
+        let tracker = ProgressTracker::current();
+        let stdout = tokio::spawn(stream_output(child.stdout.take(), "netlify stdout", tracker.clone()));
+        let stderr = tokio::spawn(stream_output(child.stderr.take(), "netlify stderr", tracker));
+
+        let status = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                if let Some(pid) = child.id() {
+                    if let Err(e) = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+                        warn!("Failed to stop timed-out Netlify command: {}", e);
+                    }
+                }
+                let _ = child.wait().await;
+                let partial = stdout.await.unwrap_or_default();
+                error!("Netlify command '{}' timed out after {}s", command_str, timeout.as_secs());
+                return Err(anyhow!(
+                    "'netlify {}' timed out after {}s (pass a larger timeout_secs for long deploys). Output so far:\n{}",
+                    command_str,
+                    timeout.as_secs(),
+                    partial
+                ));
+            }
+        };
 
         let result = NetlifyExecutionResult {
-            success: output.status.success(),
-            status: output.status.code().unwrap_or(-1),
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            success: status.success(),
+            status: status.code().unwrap_or(-1),
+            stdout: stdout.await.unwrap_or_default(),
+            stderr: stderr.await.unwrap_or_default(),
         };
 
         if !result.success {
@@ -129,12 +180,17 @@ impl NetlifyTool {
     ) -> String {
         debug!("Executing netlify tool with params: {:?}", params);
 
-        match self.execute_netlify_command(&params.command_args, &params.cwd, true).await {
+        let timeout = Duration::from_secs(params.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+        match self.execute_netlify_command(&params.command_args, &params.cwd, true, timeout).await {
             Ok(result) => {
-                format!(
+                let summary = format!(
                     "Netlify command completed with status {}\n\nSTDOUT:\n{}\n\nSTDERR:\n{}",
                     result.status, result.stdout, result.stderr
-                )
+                );
+                match deploy_url(&result.stdout) {
+                    Some(url) => format!("DEPLOY URL: {}\n\n{}", url, summary),
+                    None => summary,
+                }
             }
             Err(e) => {
                 let error_message = format!("Failed to execute netlify command: {}", e);
@@ -159,7 +215,7 @@ impl NetlifyTool {
         };
 
         // Execute without appending auth token
-        match self.execute_netlify_command(&command_to_run, &params.cwd, false).await {
+        match self.execute_netlify_command(&command_to_run, &params.cwd, false, Duration::from_secs(DEFAULT_TIMEOUT_SECS)).await {
              Ok(result) => {
                 // Help usually goes to stdout
                 format!(
@@ -175,3 +231,52 @@ impl NetlifyTool {
         }
     }
 }
+
+/// The URL a `deploy` printed: the production or draft website URL, else the unique deploy URL
+fn deploy_url(stdout: &str) -> Option<String> {
+    let url_after = |label: &str| {
+        stdout
+            .lines()
+            .filter(|line| line.contains(label))
+            .find_map(|line| line.split_whitespace().find(|word| word.starts_with("https://")))
+            .map(str::to_string)
+    };
+    ["Website URL", "Website draft URL", "Unique deploy URL"].iter().find_map(|label| url_after(label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_long_command_respects_timeout() {
+        let tool = NetlifyTool::with_program("sh -c 'echo building; sleep 30'");
+        let started = Instant::now();
+        let err = tool
+            .execute_netlify_command("", &default_cwd(), false, Duration::from_secs(1))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(started.elapsed() < Duration::from_secs(10), "took {:?}", started.elapsed());
+        assert!(err.contains("timed out after 1s"), "{}", err);
+        assert!(err.contains("building"), "partial output should be kept: {}", err);
+
+        let tool = NetlifyTool::with_program("echo");
+        let result = tool.execute_netlify_command("deploy", &default_cwd(), false, Duration::from_secs(10)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.stdout, "deploy\n");
+    }
+
+    #[test]
+    fn test_deploy_url_is_found() {
+        let stdout = "Deploying to main site URL...\n\
+            Unique deploy URL: https://abc123--site.netlify.app\n\
+            Website URL:       https://site.netlify.app\n";
+        assert_eq!(deploy_url(stdout).as_deref(), Some("https://site.netlify.app"));
+
+        let draft = "Website draft URL: https://abc--site.netlify.app\n";
+        assert_eq!(deploy_url(draft).as_deref(), Some("https://abc--site.netlify.app"));
+        assert_eq!(deploy_url("Site list:\n  my-site\n"), None);
+    }
+}
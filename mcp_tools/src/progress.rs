@@ -0,0 +1,274 @@
+use futures::{Sink, Stream, StreamExt};
+use rmcp::model::{
+    ClientJsonRpcMessage, LoggingLevel, LoggingMessageNotificationParam, ProgressNotificationParam, ProgressToken, RequestId,
+    ServerJsonRpcMessage,
+};
+use rmcp::service::{Peer, RequestContext, RoleServer};
+use rmcp::transport::io::{from_async_read, from_async_write};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use tracing::{debug, warn};
+
+tokio::task_local! {
+    static CURRENT: ProgressTracker;
+}
+
+/// The `_meta.progressToken` of each `tools/call` the client sent, by request id.
+///
+/// rmcp 0.1.5 drops `_meta` when it parses a request, so the server's transport notes the
+/// tokens on the way in (see `transport`) and `ProgressTracker::for_request` collects them.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressTokens {
+    tokens: Arc<Mutex<HashMap<RequestId, ProgressToken>>>,
+}
+
+impl ProgressTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A transport reading requests from `reader` and writing to `writer` (e.g. stdio) that
+    /// notes each tool call's progress token
+    pub fn transport<R, W>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> (
+        impl Sink<ServerJsonRpcMessage, Error = std::io::Error> + Send + 'static,
+        impl Stream<Item = ClientJsonRpcMessage> + Send + 'static,
+    )
+    where
+        R: AsyncRead + Send + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        let tokens = self.clone();
+        let incoming = from_async_read::<Value, _>(reader).filter_map(move |message| {
+            tokens.note(&message);
+            let parsed = match serde_json::from_value::<ClientJsonRpcMessage>(message) {
+                Ok(parsed) => Some(parsed),
+                Err(e) => {
+                    warn!("Ignoring a message that isn't valid MCP: {}", e);
+                    None
+                }
+            };
+            futures::future::ready(parsed)
+        });
+        (from_async_write(writer), incoming)
+    }
+
+    /// Remember the progress token of `message` if it is a tool call that has one
+    fn note(&self, message: &Value) {
+        if message.get("method").and_then(Value::as_str) != Some("tools/call") {
+            return;
+        }
+        let id = message.get("id").cloned().and_then(|id| serde_json::from_value::<RequestId>(id).ok());
+        let token = message
+            .pointer("/params/_meta/progressToken")
+            .cloned()
+            .and_then(|token| serde_json::from_value::<ProgressToken>(token).ok());
+        if let (Some(id), Some(token)) = (id, token) {
+            self.tokens.lock().unwrap().insert(id, token);
+        }
+    }
+
+    /// The progress token the client sent with request `id`, if any. Each is handed out once.
+    pub fn take(&self, id: &RequestId) -> Option<ProgressToken> {
+        self.tokens.lock().unwrap().remove(id)
+    }
+}
+
+/// Sends `notifications/progress` for the tool call it was created for, under the progress
+/// token the client sent with it. Without a token no progress is sent; output lines still are.
+#[derive(Clone)]
+pub struct ProgressTracker {
+    peer: Peer<RoleServer>,
+    token: Option<ProgressToken>,
+    progress: Arc<AtomicU32>,
+}
+
+impl ProgressTracker {
+    pub fn new(peer: Peer<RoleServer>, token: Option<ProgressToken>) -> Self {
+        Self {
+            peer,
+            token,
+            progress: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Tracker tied to the request being handled, using the progress token (if any) that
+    /// `tokens` noted for it
+    pub fn for_request(context: &RequestContext<RoleServer>, tokens: &ProgressTokens) -> Self {
+        Self::new(context.peer.clone(), tokens.take(&context.id))
+    }
+
+    /// The client the tool call came from
+    pub fn peer(&self) -> &Peer<RoleServer> {
+        &self.peer
+    }
+
+    /// The tracker for the tool call running on this task, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|tracker| tracker.clone()).ok()
+    }
+
+    /// Run `future` with this tracker available to `ProgressTracker::current`
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Advance progress by one step and notify the client
+    pub async fn advance(&self, total: Option<u32>) {
+        let progress = self.progress.fetch_add(1, Ordering::SeqCst) + 1;
+        self.send(progress, total).await;
+    }
+
+    /// Set progress to an absolute value and notify the client
+    pub async fn update(&self, progress: u32, total: Option<u32>) {
+        self.progress.store(progress, Ordering::SeqCst);
+        self.send(progress, total).await;
+    }
+
+    async fn send(&self, progress: u32, total: Option<u32>) {
+        // The client didn't ask for progress
+        let Some(token) = &self.token else { return };
+        let params = ProgressNotificationParam {
+            progress_token: token.clone(),
+            progress,
+            total,
+        };
+        if let Err(e) = self.peer.notify_progress(params).await {
+            warn!("Failed to send progress notification: {}", e);
+        }
+    }
+
+    /// Send one line of the tool's output to the client as a `notifications/message` log
+    /// message, so the client can show it while the call is still running
+    pub async fn log_line(&self, logger: &str, line: &str) {
+        let params = LoggingMessageNotificationParam {
+            level: LoggingLevel::Info,
+            logger: Some(logger.to_string()),
+            data: Value::String(line.to_string()),
+        };
+        if let Err(e) = self.peer.notify_logging_message(params).await {
+            warn!("Failed to send output line to the client: {}", e);
+        }
+    }
+}
+
+/// Report one step of progress from inside a tool. Does nothing outside a tool call.
+pub async fn report_step(total: Option<u32>) {
+    if let Some(tracker) = ProgressTracker::current() {
+        tracker.advance(total).await;
+    }
+}
+
+/// Read a command's output pipe to the end, passing each line to the client as it arrives
+/// (as a log message from `logger`, and a step of progress) and returning everything read
+pub async fn stream_output(pipe: Option<impl AsyncRead + Unpin>, logger: &str, tracker: Option<ProgressTracker>) -> String {
+    let Some(pipe) = pipe else { return String::new() };
+    let mut reader = BufReader::new(pipe);
+    let mut output = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => break,
+            Ok(_) => {
+                output.extend_from_slice(&line);
+                let text = String::from_utf8_lossy(&line);
+                let text = text.trim_end_matches(['\r', '\n']);
+                debug!("{}: {}", logger, text);
+                if let Some(tracker) = &tracker {
+                    tracker.log_line(logger, text).await;
+                    tracker.advance(None).await;
+                }
+            }
+            Err(e) => {
+                warn!("Stopped reading {}: {}", logger, e);
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&output).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{CallToolRequestParam, CallToolResult};
+    use rmcp::{Error as McpError, ServerHandler, ServiceExt};
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[derive(Clone)]
+    struct StepsServer {
+        tokens: ProgressTokens,
+    }
+
+    impl ServerHandler for StepsServer {
+        async fn call_tool(
+            &self,
+            _request: CallToolRequestParam,
+            context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, McpError> {
+            ProgressTracker::for_request(&context, &self.tokens)
+                .scope(async {
+                    for _ in 0..3 {
+                        report_step(Some(3)).await;
+                    }
+                    Ok(CallToolResult::success(vec![]))
+                })
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_only_under_the_client_token() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let steps = StepsServer { tokens: ProgressTokens::new() };
+            let (read_half, write_half) = tokio::io::split(server);
+            let transport = steps.tokens.transport(read_half, write_half);
+            let running = steps.serve(transport).await.unwrap();
+            let _ = running.waiting().await;
+        });
+
+        let (read_half, mut write_half) = tokio::io::split(client);
+        let mut lines = BufReader::new(read_half).lines();
+        for message in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": "2024-11-05", "capabilities": {},
+                "clientInfo": { "name": "test", "version": "0.0.0" } } }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "steps", "_meta": { "progressToken": "steps-token" } } }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "steps" } }),
+        ] {
+            write_half.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+
+        let mut progress = Vec::new();
+        let mut answered = 0;
+        while answered < 2 {
+            let line = lines.next_line().await.unwrap().expect("server closed the connection");
+            let message: Value = serde_json::from_str(&line).unwrap();
+            if message["method"] == "notifications/progress" {
+                progress.push((message["params"]["progressToken"].clone(), message["params"]["progress"].as_u64().unwrap()));
+            } else if message["id"] == 2 || message["id"] == 3 {
+                answered += 1;
+            }
+        }
+        // The call that sent no token gets no progress
+        assert_eq!(progress, vec![(json!("steps-token"), 1), (json!("steps-token"), 2), (json!("steps-token"), 3)]);
+    }
+
+    #[tokio::test]
+    async fn test_report_step_outside_tool_call_is_noop() {
+        assert!(ProgressTracker::current().is_none());
+        report_step(None).await;
+    }
+}
@@ -0,0 +1,162 @@
+use rmcp::model::{CallToolResult, Content};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Environment variable overriding per-tool rate limits, e.g. `bash=30/min,aider=off`
+pub const RATE_LIMITS_ENV: &str = "MCP_TOOLS_RATE_LIMITS";
+
+/// Limits applied when the environment doesn't say otherwise: tools that spawn processes
+const DEFAULT_LIMITS: &[(&str, u32, Duration)] = &[
+    ("bash", 60, Duration::from_secs(60)),
+    ("aider", 10, Duration::from_secs(60)),
+];
+
+/// At most `calls` calls per `per`, allowing bursts of up to `calls`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub calls: u32,
+    pub per: Duration,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-tool token buckets that throttle runaway call loops
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limits: HashMap<String, RateLimit>,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    /// Defaults plus any overrides from `MCP_TOOLS_RATE_LIMITS`
+    pub fn from_env() -> Self {
+        let limiter = Self::parse(std::env::var(RATE_LIMITS_ENV).ok().as_deref());
+        info!("Tool rate limits: {:?}", limiter.limits);
+        limiter
+    }
+
+    /// Parse comma-separated `tool=calls/unit` entries (unit: s, min or h) on top of the defaults.
+    /// `tool=off` removes a tool's limit. Malformed entries are skipped with a warning.
+    pub fn parse(value: Option<&str>) -> Self {
+        let mut limits: HashMap<String, RateLimit> = DEFAULT_LIMITS
+            .iter()
+            .map(|(tool, calls, per)| (tool.to_string(), RateLimit { calls: *calls, per: *per }))
+            .collect();
+
+        for entry in value.unwrap_or_default().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((tool, limit)) = entry.split_once('=') else {
+                warn!("{}: ignoring '{}' (expected tool=calls/unit)", RATE_LIMITS_ENV, entry);
+                continue;
+            };
+            let tool = tool.trim().to_lowercase();
+            let limit = limit.trim();
+            if limit.eq_ignore_ascii_case("off") {
+                limits.remove(&tool);
+                continue;
+            }
+            match parse_limit(limit) {
+                Some(limit) => {
+                    limits.insert(tool, limit);
+                }
+                None => warn!("{}: ignoring '{}' (expected tool=calls/unit)", RATE_LIMITS_ENV, entry),
+            }
+        }
+
+        Self { limits, buckets: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Take a token for `tool`. On failure returns how long until the next call is allowed.
+    pub fn check(&self, tool: &str) -> Result<(), Duration> {
+        self.check_at(tool, Instant::now())
+    }
+
+    fn check_at(&self, tool: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.limits.get(tool) else { return Ok(()) };
+        let refill_per_sec = limit.calls as f64 / limit.per.as_secs_f64();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(tool.to_string())
+            .or_insert_with(|| Bucket { tokens: limit.calls as f64, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(limit.calls as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_sec))
+        }
+    }
+
+    /// The error result to return instead of running `tool`, if it is over its limit
+    pub fn throttle(&self, tool: &str) -> Option<CallToolResult> {
+        let retry_after = self.check(tool).err()?;
+        warn!("Rate limit exceeded for tool '{}'; retry in {:.1}s", tool, retry_after.as_secs_f64());
+        Some(CallToolResult::error(vec![Content::text(format!(
+            "Rate limit exceeded for tool '{}'. Try again in {:.1} seconds.",
+            tool,
+            retry_after.as_secs_f64()
+        ))]))
+    }
+}
+
+fn parse_limit(limit: &str) -> Option<RateLimit> {
+    let (calls, unit) = limit.split_once('/')?;
+    let calls: u32 = calls.trim().parse().ok().filter(|c| *c > 0)?;
+    let per = match unit.trim().to_lowercase().as_str() {
+        "s" | "sec" | "second" => Duration::from_secs(1),
+        "m" | "min" | "minute" => Duration::from_secs(60),
+        "h" | "hour" => Duration::from_secs(3600),
+        _ => return None,
+    };
+    Some(RateLimit { calls, per })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides_defaults() {
+        let limiter = RateLimiter::parse(Some("bash=5/s, aider=off, git_integration=2/min, bogus"));
+        assert_eq!(limiter.limits["bash"], RateLimit { calls: 5, per: Duration::from_secs(1) });
+        assert!(!limiter.limits.contains_key("aider"));
+        assert_eq!(limiter.limits["git_integration"], RateLimit { calls: 2, per: Duration::from_secs(60) });
+
+        let defaults = RateLimiter::parse(None);
+        assert_eq!(defaults.limits["aider"], RateLimit { calls: 10, per: Duration::from_secs(60) });
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::parse(Some("bash=2/s"));
+        let start = Instant::now();
+        assert!(limiter.check_at("bash", start).is_ok());
+        assert!(limiter.check_at("bash", start).is_ok());
+        let wait = limiter.check_at("bash", start).unwrap_err();
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500), "{:?}", wait);
+
+        assert!(limiter.check_at("bash", start + Duration::from_millis(500)).is_ok());
+        // Unlimited tools are never throttled
+        assert!((0..100).all(|_| limiter.check_at("brave_search", start).is_ok()));
+    }
+
+    #[test]
+    fn test_exceeding_limit_returns_error_result() {
+        let limiter = RateLimiter::parse(Some("aider=1/h"));
+        assert!(limiter.throttle("aider").is_none());
+
+        let result = limiter.throttle("aider").expect("second call should be throttled");
+        assert_eq!(result.is_error, Some(true));
+        let text = serde_json::to_value(&result.content).unwrap()[0]["text"].as_str().unwrap().to_string();
+        assert!(text.contains("Rate limit exceeded for tool 'aider'"), "{}", text);
+    }
+}
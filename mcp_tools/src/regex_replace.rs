@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+
+// Import rmcp SDK components
+use rmcp::tool;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RegexReplaceParams {
+    #[serde(default)]
+    #[schemars(description = "Path of the file to edit in place. Provide either 'path' or 'text'.")]
+    pub path: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Text to run the substitution on instead of a file. The result is returned.")]
+    pub text: Option<String>,
+
+    #[schemars(description = "Regular expression to search for (Rust regex syntax)")]
+    pub pattern: String,
+
+    #[schemars(description = "Replacement text. Capture groups can be referenced as $1 or ${name}.")]
+    pub replacement: String,
+
+    #[serde(default)]
+    #[schemars(description = "Replace every match instead of only the first. Defaults to false.")]
+    pub global: bool,
+
+    #[serde(default)]
+    #[schemars(description = "Return a diff of the change without writing the file. Defaults to false.")]
+    pub dry_run: bool,
+}
+
+/// Result of applying a substitution to some text
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    pub output: String,
+    pub count: usize,
+}
+
+/// Apply `pattern` -> `replacement` to `input`, either the first match or all of them
+pub fn apply_replacement(input: &str, pattern: &str, replacement: &str, global: bool) -> Result<Replacement> {
+    let re = Regex::new(pattern).map_err(|e| anyhow!("Invalid regex '{}': {}", pattern, e))?;
+    let matches = re.find_iter(input).count();
+    let count = if global { matches } else { matches.min(1) };
+    let output = if global {
+        re.replace_all(input, replacement).into_owned()
+    } else {
+        re.replace(input, replacement).into_owned()
+    };
+    Ok(Replacement { output, count })
+}
+
+/// Line diff between `before` and `after`. Unchanged leading and trailing lines are
+/// trimmed; what remains is shown as a single hunk, or line by line when the line
+/// count didn't change.
+pub fn line_diff(label: &str, before: &str, after: &str) -> String {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    let mut diff = format!("--- {}\n+++ {}\n", label, label);
+
+    if old.len() == new.len() {
+        for (i, (o, n)) in old.iter().zip(&new).enumerate() {
+            if o != n {
+                diff.push_str(&format!("@@ line {} @@\n-{}\n+{}\n", i + 1, o, n));
+            }
+        }
+        return diff;
+    }
+
+    let prefix = old.iter().zip(&new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    diff.push_str(&format!("@@ line {} @@\n", prefix + 1));
+    for line in &old[prefix..old.len() - suffix] {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in &new[prefix..new.len() - suffix] {
+        diff.push_str(&format!("+{}\n", line));
+    }
+    diff
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RegexReplaceTool;
+
+impl RegexReplaceTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    async fn regex_replace_internal(&self, params: RegexReplaceParams) -> Result<String> {
+        let (label, input) = match (&params.path, &params.text) {
+            (Some(path), None) => (
+                path.clone(),
+                tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to read '{}': {}", path, e))?,
+            ),
+            (None, Some(text)) => ("text".to_string(), text.clone()),
+            _ => return Err(anyhow!("Provide exactly one of 'path' or 'text'")),
+        };
+
+        let result = apply_replacement(&input, &params.pattern, &params.replacement, params.global)?;
+        debug!("Pattern '{}' replaced {} match(es) in {}", params.pattern, result.count, label);
+
+        if result.count == 0 {
+            return Ok(format!("No matches for pattern '{}' in {}.", params.pattern, label));
+        }
+
+        if params.dry_run {
+            return Ok(format!(
+                "Dry run: {} replacement(s) in {}\n\n{}",
+                result.count,
+                label,
+                line_diff(&label, &input, &result.output)
+            ));
+        }
+
+        match &params.path {
+            Some(path) => {
+                tokio::fs::write(path, &result.output)
+                    .await
+                    .map_err(|e| anyhow!("Failed to write '{}': {}", path, e))?;
+                info!("Wrote {} replacement(s) to {}", result.count, path);
+                Ok(format!("Replaced {} match(es) in {}.", result.count, path))
+            }
+            None => Ok(result.output),
+        }
+    }
+
+    #[tool(description = "Performs a regex search-and-replace in a file (edited in place) or on provided text. Replaces the first match unless 'global' is true. Set 'dry_run' to preview the change as a diff without writing.")]
+    pub async fn regex_replace(
+        &self,
+        #[tool(aggr)] params: RegexReplaceParams,
+    ) -> String {
+        match self.regex_replace_internal(params).await {
+            Ok(output) => output,
+            Err(e) => {
+                error!("regex_replace failed: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(path: Option<String>, text: Option<&str>, global: bool, dry_run: bool) -> RegexReplaceParams {
+        RegexReplaceParams {
+            path,
+            text: text.map(str::to_string),
+            pattern: r"foo(\d)".to_string(),
+            replacement: "bar$1".to_string(),
+            global,
+            dry_run,
+        }
+    }
+
+    #[test]
+    fn test_single_vs_global_replace() {
+        let single = apply_replacement("foo1 foo2", r"foo(\d)", "bar$1", false).unwrap();
+        assert_eq!(single, Replacement { output: "bar1 foo2".into(), count: 1 });
+
+        let global = apply_replacement("foo1 foo2", r"foo(\d)", "bar$1", true).unwrap();
+        assert_eq!(global, Replacement { output: "bar1 bar2".into(), count: 2 });
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(apply_replacement("x", "(", "y", true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_diff_and_leaves_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.txt");
+        std::fs::write(&path, "keep\nfoo1\nfoo2\n").unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let output = RegexReplaceTool::new()
+            .regex_replace(params(Some(path_str.clone()), None, true, true))
+            .await;
+
+        assert!(output.starts_with("Dry run: 2 replacement(s)"), "{}", output);
+        assert!(output.contains("@@ line 2 @@\n-foo1\n+bar1\n@@ line 3 @@\n-foo2\n+bar2\n"), "{}", output);
+        assert!(!output.contains("-keep"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep\nfoo1\nfoo2\n");
+    }
+
+    #[tokio::test]
+    async fn test_file_is_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input.txt");
+        std::fs::write(&path, "foo1 foo2").unwrap();
+
+        let output = RegexReplaceTool::new()
+            .regex_replace(params(Some(path.to_str().unwrap().to_string()), None, false, false))
+            .await;
+
+        assert_eq!(output, format!("Replaced 1 match(es) in {}.", path.display()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bar1 foo2");
+    }
+
+    #[tokio::test]
+    async fn test_text_input_and_missing_source() {
+        let tool = RegexReplaceTool::new();
+        assert_eq!(tool.regex_replace(params(None, Some("foo1 foo2"), true, false)).await, "bar1 bar2");
+        assert!(tool.regex_replace(params(None, None, true, false)).await.starts_with("Error:"));
+    }
+
+    #[test]
+    fn test_line_diff_with_changed_line_count() {
+        let diff = line_diff("f", "a\nb\nc\n", "a\nx\ny\nc\n");
+        assert_eq!(diff, "--- f\n+++ f\n@@ line 2 @@\n-b\n+x\n+y\n");
+    }
+}
@@ -4,10 +4,20 @@ use serde::{Serialize, Deserialize};
 use tracing::{info, error, debug}; // Removed warn
 use schemars::JsonSchema;
 use std::env;
+use std::time::Duration;
 
 // Import SDK components
 use rmcp::tool; // Removed unused ServerHandler, model::ServerInfo
 
+/// Environment variable setting how many times a request that failed with 429 or a 5xx is
+/// retried before giving up
+pub const MAX_RETRIES_ENV: &str = "SCRAPINGBEE_MAX_RETRIES";
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Wait before the first retry; doubled for each one after
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 pub enum ScrapingBeeResponse {
     Text(String),
@@ -29,25 +39,64 @@ fn default_render_js() -> bool {
     true
 }
 
+/// Rate limiting and server errors are worth another try; anything else (404, bad API key,
+/// ...) will fail the same way again
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
 // Define the ScrapingBee tool
 #[derive(Debug, Clone)]
 pub struct ScrapingBeeTool {
     // The tool won't store the client directly
     // Instead, it will create a client when needed
+    base_url: String,
+    api_key: Option<String>, // Read from the env on each request if not given
+    max_retries: u32,
+    retry_delay: Duration,
 }
 
 impl ScrapingBeeTool {
     pub fn new() -> Self {
-        Self {}
+        Self::with_base_url("https://app.scrapingbee.com/api/v1/")
+    }
+
+    /// Point the tool at a different endpoint (e.g. a mock server)
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        let max_retries = match env::var(MAX_RETRIES_ENV) {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                error!("Invalid {} '{}', using {}", MAX_RETRIES_ENV, value, DEFAULT_MAX_RETRIES);
+                DEFAULT_MAX_RETRIES
+            }),
+            Err(_) => DEFAULT_MAX_RETRIES,
+        };
+        Self { base_url: base_url.into(), api_key: None, max_retries, retry_delay: DEFAULT_RETRY_DELAY }
+    }
+
+    /// Use `api_key` instead of reading `SCRAPINGBEE_API_KEY`
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Retry a request that failed with 429 or a 5xx up to `max_retries` times, waiting
+    /// `retry_delay` before the first retry and twice as long before each one after
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
     }
     
     // Helper method to create a properly configured client
     async fn execute_scraping(&self, url: &str, render_js: bool) -> Result<String> {
         info!("Starting ScrapingBee request for URL: {} (render_js: {})", url, render_js);
         
-        // Get API key from environment
-        let api_key = env::var("SCRAPINGBEE_API_KEY")
-            .map_err(|_| anyhow!("SCRAPINGBEE_API_KEY environment variable must be set"))?;
+        // Get API key from environment unless one was given
+        let api_key = match &self.api_key {
+            Some(key) => key.clone(),
+            None => env::var("SCRAPINGBEE_API_KEY")
+                .map_err(|_| anyhow!("SCRAPINGBEE_API_KEY environment variable must be set"))?,
+        };
         
         // Create a client with a 20-second timeout
         let client = reqwest::Client::builder()
@@ -67,7 +116,7 @@ impl ScrapingBeeTool {
         
         // Build the request
         let request = client
-            .get("https://app.scrapingbee.com/api/v1/")
+            .get(&self.base_url)
             .headers(headers)
             .query(&[
                 ("api_key", api_key.as_str()),
@@ -79,34 +128,51 @@ impl ScrapingBeeTool {
                 ("timeout", &timeout.to_string()),
             ]);
             
-        debug!("Sending request to ScrapingBee API");
-        
-        // Execute the request
-        let response = request.send().await
-            .map_err(|e| {
-                error!("Failed to send request to ScrapingBee: {}", e);
-                
-                if e.is_timeout() {
-                    error!("Request to ScrapingBee timed out");
-                    anyhow!("Request to ScrapingBee timed out after 20 seconds")
-                } else if e.is_connect() {
-                    error!("Connection error to ScrapingBee API");
-                    anyhow!("Failed to connect to ScrapingBee API: {}", e)
-                } else {
-                    anyhow!("ScrapingBee request failed: {}", e)
-                }
-            })?;
-            
-        let status = response.status();
-        debug!("Received response with status: {}", status);
-        
-        // Check if successful
-        if !status.is_success() {
+        let mut attempt = 0;
+        let response = loop {
+            debug!("Sending request to ScrapingBee API (attempt {})", attempt + 1);
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("ScrapingBee request could not be cloned for sending"))?;
+
+            // Execute the request
+            let response = attempt_request.send().await
+                .map_err(|e| {
+                    error!("Failed to send request to ScrapingBee: {}", e);
+
+                    if e.is_timeout() {
+                        error!("Request to ScrapingBee timed out");
+                        anyhow!("Request to ScrapingBee timed out after 20 seconds")
+                    } else if e.is_connect() {
+                        error!("Connection error to ScrapingBee API");
+                        anyhow!("Failed to connect to ScrapingBee API: {}", e)
+                    } else {
+                        anyhow!("ScrapingBee request failed: {}", e)
+                    }
+                })?;
+
+            let status = response.status();
+            debug!("Received response with status: {}", status);
+
+            // Check if successful
+            if status.is_success() {
+                break response;
+            }
+            if is_retryable(status) && attempt < self.max_retries {
+                let delay = self.retry_delay * 2u32.saturating_pow(attempt);
+                info!("ScrapingBee returned {}; retrying in {:?} ({}/{})", status, delay, attempt + 1, self.max_retries);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
             let error_text = response.text().await?;
             error!("ScrapingBee API request failed with status: {}", status);
             error!("Error response: {}", error_text);
+            if attempt > 0 {
+                return Err(anyhow!("ScrapingBee API failed after {} retries: {} - {}", attempt, status, error_text));
+            }
             return Err(anyhow!("ScrapingBee API failed: {} - {}", status, error_text));
-        }
+        };
         
         // Process the response based on content type
         let content_type = response.headers()
@@ -175,3 +241,61 @@ impl ServerHandler for ScrapingBeeTool {
 
 // Old ScrapingBeeClient struct and related functions have been refactored
 // into ScrapingBeeTool and its implementation above.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn params(url: &str) -> ScrapingBeeParams {
+        ScrapingBeeParams { url: url.to_string(), render_js: false }
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let server = MockServer::start().await;
+        // Mocks are tried in the order they were mounted, so the 503s come first
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("busy"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(query_param("url", "https://example.com/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("Page text"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tool = ScrapingBeeTool::with_base_url(server.uri()).with_api_key("test-key").with_retries(2, Duration::from_millis(1));
+        let output = tool.scrape_url(params("https://example.com/page")).await;
+        assert!(output.contains("Page text"), "{}", output);
+
+        // With fewer retries than failures the last error is reported
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("busy"))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let tool = ScrapingBeeTool::with_base_url(server.uri()).with_api_key("test-key").with_retries(1, Duration::from_millis(1));
+        let output = tool.scrape_url(params("https://example.com/page")).await;
+        assert_eq!(output, "Error: ScrapingBee API failed after 1 retries: 503 Service Unavailable - busy");
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("no such page"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tool = ScrapingBeeTool::with_base_url(server.uri()).with_api_key("test-key").with_retries(3, Duration::from_millis(1));
+        let output = tool.scrape_url(params("https://example.com/missing")).await;
+        assert_eq!(output, "Error: ScrapingBee API failed: 404 Not Found - no such page");
+    }
+}
@@ -0,0 +1,265 @@
+//! End-to-end tests that run the `mcp_tools` binary and speak JSON-RPC over its stdio.
+
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+struct ToolsServer {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+    home_dir: tempfile::TempDir,
+}
+
+impl ToolsServer {
+    /// Spawn the server with a throwaway HOME and LOG_DIR plus any extra env vars
+    async fn spawn(env: &[(&str, &str)]) -> Self {
+        let home = tempfile::tempdir().unwrap();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mcp_tools"));
+        command
+            .env("HOME", home.path())
+            .env("LOG_DIR", home.path().join("logs"))
+            .env_remove("MCP_TOOLS_ENABLED")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        for (key, value) in env {
+            command.env(key, value);
+        }
+        let mut child = command.spawn().expect("failed to start mcp_tools");
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        let mut server = Self { child, stdin, stdout, next_id: 1, home_dir: home };
+        server.initialize().await;
+        server
+    }
+
+    async fn send(&mut self, message: Value) {
+        let stdin = self.stdin.as_mut().expect("stdin already closed");
+        stdin.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        stdin.flush().await.unwrap();
+    }
+
+    /// Next line on stdout, which must be a JSON-RPC message
+    async fn read(&mut self) -> Value {
+        let line = tokio::time::timeout(Duration::from_secs(10), self.stdout.next_line())
+            .await
+            .expect("timed out waiting for server output")
+            .unwrap()
+            .expect("server closed stdout");
+        serde_json::from_str(&line).unwrap_or_else(|e| panic!("non-JSON line on stdout ({}): {}", e, line))
+    }
+
+    async fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await;
+        let response = self.read().await;
+        assert_eq!(response["id"], id, "unexpected response: {}", response);
+        response
+    }
+
+    async fn initialize(&mut self) {
+        let response = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "clientInfo": { "name": "test", "version": "0.0.0" }
+                }),
+            )
+            .await;
+        assert!(response.get("result").is_some(), "initialize failed: {}", response);
+        self.send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+    }
+
+    fn home(&self) -> &Path {
+        self.home_dir.path()
+    }
+
+    async fn wait_for_exit(&mut self) -> ExitStatus {
+        tokio::time::timeout(Duration::from_secs(10), self.child.wait())
+            .await
+            .expect("server did not exit")
+            .unwrap()
+    }
+
+    /// Start a task that stays running (and so unsaved) for a while; returns its ID
+    async fn start_slow_task(&mut self) -> String {
+        let response = self
+            .request(
+                "tools/call",
+                json!({ "name": "start_task", "arguments": { "command_string": "sleep 30; true", "reason": "test" } }),
+            )
+            .await;
+        let text = response["result"]["content"][0]["text"].as_str().unwrap().to_string();
+        text.lines()
+            .next()
+            .and_then(|line| line.strip_prefix("Task started with ID: "))
+            .unwrap_or_else(|| panic!("unexpected start_task output: {}", text))
+            .to_string()
+    }
+
+    fn persisted_tasks(&self) -> Value {
+        let data = std::fs::read_to_string(self.home().join("tasks.json")).expect("tasks.json was not written");
+        serde_json::from_str(&data).unwrap()
+    }
+
+    async fn tool_names(&mut self) -> Vec<String> {
+        let response = self.request("tools/list", json!({})).await;
+        response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn test_enabled_tools_env_limits_tools_list() {
+    let mut server = ToolsServer::spawn(&[("MCP_TOOLS_ENABLED", "bash")]).await;
+    assert_eq!(server.tool_names().await, vec!["bash"]);
+
+    let response = server
+        .request("tools/call", json!({ "name": "aider", "arguments": { "message": "hi" } }))
+        .await;
+    assert!(response.get("error").is_some(), "disabled tool was callable: {}", response);
+}
+
+#[tokio::test]
+async fn test_all_tools_listed_by_default() {
+    let mut server = ToolsServer::spawn(&[]).await;
+    let names = server.tool_names().await;
+    assert!(names.contains(&"bash".to_string()));
+    assert!(names.contains(&"start_task".to_string()));
+    assert!(names.len() > 5);
+    server.child.kill().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_closing_stdin_persists_tasks_and_exits() {
+    let mut server = ToolsServer::spawn(&[]).await;
+    let task_id = server.start_slow_task().await;
+
+    server.stdin = None; // Client hangs up
+    assert!(server.wait_for_exit().await.success());
+    assert!(server.persisted_tasks().get(&task_id).is_some());
+}
+
+#[tokio::test]
+async fn test_sigterm_persists_tasks_and_exits() {
+    let mut server = ToolsServer::spawn(&[]).await;
+    let task_id = server.start_slow_task().await;
+
+    let pid = nix::unistd::Pid::from_raw(server.child.id().unwrap() as i32);
+    nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGTERM).unwrap();
+    assert!(server.wait_for_exit().await.success());
+    assert_eq!(server.persisted_tasks()[&task_id]["status"], "Running");
+}
+
+#[tokio::test]
+async fn test_bash_output_is_streamed_as_log_messages() {
+    let mut server = ToolsServer::spawn(&[("MCP_TOOLS_ENABLED", "bash")]).await;
+    server
+        .send(json!({ "jsonrpc": "2.0", "id": 10, "method": "tools/call",
+            "params": { "name": "bash", "arguments": { "command": "echo one; echo two >&2; echo three" } } }))
+        .await;
+
+    let mut streamed = Vec::new();
+    let response = loop {
+        let message = server.read().await;
+        if message["method"] == "notifications/message" {
+            streamed.push((message["params"]["logger"].clone(), message["params"]["data"].clone()));
+        } else if message["id"] == 10 {
+            break message;
+        }
+    };
+    let stdout: Vec<_> = streamed.iter().filter(|(logger, _)| logger == "bash stdout").map(|(_, line)| line.clone()).collect();
+    assert_eq!(stdout, vec![json!("one"), json!("three")]);
+    assert!(streamed.contains(&(json!("bash stderr"), json!("two"))), "{:?}", streamed);
+    let text = response["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("STDOUT:\none\nthree\n"), "{}", text);
+}
+
+/// Run a short session with verbose logging and collect every line the server writes to stdout
+async fn collect_stdout(program: &str, args: &[&str], log_dir: &Path, home: &Path) -> Vec<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .env("HOME", home)
+        .env("LOG_DIR", log_dir)
+        .env("RUST_LOG", "trace")
+        .env_remove("MCP_TOOLS_ENABLED")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    for message in [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2024-11-05", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "0.0.0" } } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+            "params": { "name": "bash", "arguments": { "command": "echo hi" } } }),
+    ] {
+        stdin.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+    }
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut lines = Vec::new();
+    while lines.len() < 3 {
+        match tokio::time::timeout(Duration::from_secs(10), stdout.next_line()).await {
+            Ok(Ok(Some(line))) => lines.push(line),
+            _ => break,
+        }
+    }
+    drop(stdin);
+    let _ = tokio::time::timeout(Duration::from_secs(10), child.wait()).await;
+    while let Ok(Some(line)) = stdout.next_line().await {
+        lines.push(line);
+    }
+    lines
+}
+
+fn assert_only_json_rpc(lines: &[String]) {
+    assert!(lines.len() >= 3, "expected three responses, got {:?}", lines);
+    for line in lines {
+        let message: Value = serde_json::from_str(line).unwrap_or_else(|_| panic!("non-protocol line on stdout: {}", line));
+        assert_eq!(message["jsonrpc"], "2.0", "unexpected stdout line: {}", line);
+    }
+}
+
+#[tokio::test]
+async fn test_no_log_lines_on_stdout() {
+    let home = tempfile::tempdir().unwrap();
+    // A regular file as LOG_DIR forces the stderr fallback
+    let bad_log_dir = tempfile::NamedTempFile::new().unwrap();
+    let lines = collect_stdout(env!("CARGO_BIN_EXE_mcp_tools"), &[], bad_log_dir.path(), home.path()).await;
+    assert_only_json_rpc(&lines);
+}
+
+#[tokio::test]
+async fn test_no_log_lines_on_stdout_when_stderr_is_merged() {
+    let home = tempfile::tempdir().unwrap();
+    let bad_log_dir = tempfile::NamedTempFile::new().unwrap();
+    // Equivalent of `mcp_tools 2>&1`
+    let lines = collect_stdout(
+        "sh",
+        &["-c", "exec \"$0\" 2>&1", env!("CARGO_BIN_EXE_mcp_tools")],
+        bad_log_dir.path(),
+        home.path(),
+    )
+    .await;
+    assert_only_json_rpc(&lines);
+}